@@ -0,0 +1,49 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! How expensive different expressions are, over the same tree.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use findutils::bench::TreeShape;
+use findutils_bench::{run_find, temp_tree};
+
+const EXPRESSIONS: &[&[&str]] = &[
+    &["-name", "*.rs"],
+    &["-iname", "*.RS"],
+    &["-path", "*/d1/*"],
+    &["-regex", ".*/f[0-9]*\\.c"],
+    &["-type", "f"],
+    &["-empty"],
+    &["-type", "d", "-empty"],
+    &["-size", "+0"],
+    &["-mtime", "-1"],
+    &["-perm", "-u+r"],
+    &["-newer", "."],
+    &["-name", "*.o", "-o", "-name", "*.c", "-o", "-name", "*.txt"],
+    &["-type", "f", "-printf", "%p %s %m %TY\\n"],
+];
+
+fn matchers(c: &mut Criterion) {
+    let shape = TreeShape {
+        depth: 3,
+        fanout: 8,
+        files_per_dir: 10,
+    };
+    let tree = temp_tree(&shape);
+
+    let mut group = c.benchmark_group("matchers");
+    group.throughput(Throughput::Elements(shape.entries() as u64));
+    for &args in EXPRESSIONS {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(args.join(" ")),
+            args,
+            |b, args| b.iter(|| run_find(&tree, args)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, matchers);
+criterion_main!(benches);
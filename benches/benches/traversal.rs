@@ -0,0 +1,44 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! How fast we walk trees of different shapes, with an expression that does
+//! as little as possible.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use findutils::bench::TreeShape;
+use findutils_bench::{run_find, temp_tree};
+
+fn traversal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("traversal");
+    for (name, shape) in [
+        ("deep", TreeShape::deep(200)),
+        ("wide", TreeShape::wide(10_000)),
+        (
+            "bushy",
+            TreeShape {
+                depth: 4,
+                fanout: 6,
+                files_per_dir: 5,
+            },
+        ),
+    ] {
+        let tree = temp_tree(&shape);
+        group.throughput(Throughput::Elements(shape.entries() as u64));
+        for args in [
+            &["-true"][..],
+            &["-print"],
+            &["-depth", "-print"],
+            &["-sorted", "-print"],
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, args.join(" ")), args, |b, args| {
+                b.iter(|| run_find(&tree, args))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, traversal);
+criterion_main!(benches);
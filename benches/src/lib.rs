@@ -0,0 +1,29 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Shared setup for the benchmarks.
+
+use findutils::bench::{generate_tree, SinkDependencies, TreeShape};
+use findutils::find::matchers::build_top_level_matcher;
+use findutils::find::{search, Config};
+use tempfile::TempDir;
+
+/// Creates a temporary directory holding a tree of the given shape.
+pub fn temp_tree(shape: &TreeShape) -> TempDir {
+    let temp_dir = tempfile::Builder::new()
+        .prefix("findutils-bench")
+        .tempdir()
+        .expect("failed to create a temporary directory");
+    generate_tree(temp_dir.path(), shape).expect("failed to generate the tree");
+    temp_dir
+}
+
+/// Runs find over `root` with the given expression, throwing away the output.
+pub fn run_find(root: &TempDir, args: &[&str]) -> i32 {
+    let mut config = Config::default();
+    let matcher = build_top_level_matcher(args, &mut config).expect("invalid expression");
+    let root = root.path().to_str().expect("non-UTF-8 temporary directory");
+    search(&[root], &*matcher, &config, &SinkDependencies::new())
+}
@@ -0,0 +1,160 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Random find command lines.
+
+use crate::rng::Rng;
+
+/// How deeply operators nest.
+const MAX_DEPTH: usize = 3;
+
+const GLOBS: &[&str] = &[
+    "*", "a*", "*.txt", "*.TXT", "?", "[ab]*", "*c", ".*", "*b*", "x y", "[!a-z]*", "*.*.*",
+];
+
+const PATH_GLOBS: &[&str] = &["*", "./a*", "*/b*", "*/*/*", "./x y/*", "*.md"];
+
+const REGEXES: &[&str] = &[
+    ".*",
+    ".*\\.txt",
+    ".*/[a-c].*",
+    "\\./a.*",
+    ".*[0-9]",
+    ".*/\\..*",
+];
+
+const MODES: &[&str] = &[
+    "644",
+    "-644",
+    "/111",
+    "-u+x",
+    "/g+w,o+w",
+    "u=rw,go=r",
+    "-4000",
+    "/o=w",
+    "-1000",
+    "755",
+];
+
+const NUMBERS: &[&str] = &["0", "1", "2", "+0", "+1", "-1", "-2", "+2", "3"];
+
+const SIZES: &[&str] = &[
+    "0", "-1", "+1", "1", "+10k", "-2k", "1k", "+511c", "512c", "-1025c", "+1M", "2", "-3b",
+];
+
+const DAYS: &[&str] = &["0", "1", "-1", "+1", "+2", "-3", "+20", "-400", "+399"];
+
+const MINUTES: &[&str] = &["-5", "+5", "-100", "+1000", "-3000", "+10000", "-1000000"];
+
+const FORMATS: &[&str] = &[
+    "%p %y %s %m %d\\n",
+    "%P|%f|%h\\n",
+    "%k %b %n %Y\\n",
+    "[%-10f] %3d %#m\\n",
+];
+
+/// Generates the arguments to find after the program name: maybe a
+/// symlink option and some other options, the starting point, and an
+/// expression. `paths` are the files in the tree, for primaries like
+/// -newer that take one.
+pub fn generate(rng: &mut Rng, paths: &[String]) -> Vec<String> {
+    let mut args = vec![];
+    if rng.chance(30) {
+        args.push(rng.pick(&["-H", "-L", "-P"]).to_string());
+    }
+    args.push(String::from("."));
+    // Options go first, so GNU find doesn't warn about them
+    if rng.chance(30) {
+        args.extend(["-maxdepth".into(), rng.below(4).to_string()]);
+    }
+    if rng.chance(20) {
+        args.extend(["-mindepth".into(), rng.below(3).to_string()]);
+    }
+    let depth_first = rng.chance(20);
+    if depth_first {
+        args.push("-depth".into());
+    }
+
+    expression(rng, paths, depth_first, 0, &mut args);
+    match rng.below(4) {
+        0 => args.push("-print".into()),
+        1 => args.extend(["-printf".into(), rng.pick(FORMATS).to_string()]),
+        // The default -print
+        _ => {}
+    }
+    args
+}
+
+fn expression(
+    rng: &mut Rng,
+    paths: &[String],
+    depth_first: bool,
+    depth: usize,
+    args: &mut Vec<String>,
+) {
+    if depth >= MAX_DEPTH || rng.chance(40) {
+        primary(rng, paths, depth_first, args);
+        return;
+    }
+    match rng.below(6) {
+        0 => {
+            args.push(rng.pick(&["!", "-not"]).to_string());
+            expression(rng, paths, depth_first, depth + 1, args);
+        }
+        1 => {
+            args.push("(".into());
+            expression(rng, paths, depth_first, depth + 1, args);
+            args.push(")".into());
+        }
+        n => {
+            expression(rng, paths, depth_first, depth + 1, args);
+            match n {
+                2 => args.push(rng.pick(&["-a", "-and"]).to_string()),
+                3 => args.push(rng.pick(&["-o", "-or"]).to_string()),
+                4 if rng.chance(20) => args.push(",".into()),
+                // An implicit -a
+                _ => {}
+            }
+            expression(rng, paths, depth_first, depth + 1, args);
+        }
+    }
+}
+
+fn primary(rng: &mut Rng, paths: &[String], depth_first: bool, args: &mut Vec<String>) {
+    let mut push = |name: &str, arg: Option<&str>| {
+        args.push(name.to_owned());
+        args.extend(arg.map(str::to_owned));
+    };
+    match rng.below(20) {
+        0 => push(rng.pick(&["-name", "-iname"]), Some(rng.pick(GLOBS))),
+        1 => push(
+            rng.pick(&["-path", "-ipath", "-wholename"]),
+            Some(rng.pick(PATH_GLOBS)),
+        ),
+        2 => push(rng.pick(&["-regex", "-iregex"]), Some(rng.pick(REGEXES))),
+        3 => push(
+            rng.pick(&["-type", "-xtype"]),
+            Some(rng.pick(&["f", "d", "l"])),
+        ),
+        4 => push("-size", Some(rng.pick(SIZES))),
+        5 => push(rng.pick(&["-empty", "-true", "-false"]), None),
+        6 => push("-perm", Some(rng.pick(MODES))),
+        7 => push(rng.pick(&["-mtime", "-ctime"]), Some(rng.pick(DAYS))),
+        8 => push("-mmin", Some(rng.pick(MINUTES))),
+        9 => push(
+            rng.pick(&["-newer", "-samefile"]),
+            Some(paths[rng.below(paths.len())].as_str()),
+        ),
+        10 => push("-links", Some(rng.pick(NUMBERS))),
+        11 => push("-lname", Some(rng.pick(GLOBS))),
+        12 => push(rng.pick(&["-readable", "-writable", "-executable"]), None),
+        // -prune does nothing with -depth, and GNU find warns about it
+        13 if !depth_first => push("-prune", None),
+        14 => push("-uid", Some(rng.pick(&["0", "+0", "-1"]))),
+        15 => push(rng.pick(&["-nouser", "-nogroup"]), None),
+        16 => push("-user", Some("root")),
+        _ => push("-name", Some(rng.pick(GLOBS))),
+    }
+}
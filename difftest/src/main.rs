@@ -0,0 +1,224 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Differential testing against GNU find: generates random directory trees
+//! and find command lines, runs both finds on them, and reports where they
+//! behave differently. See DEVELOPMENT.md for how to run it.
+
+mod expr;
+mod rng;
+mod tree;
+
+use std::collections::BTreeSet;
+use std::env;
+use std::ffi::OsString;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command, Output};
+
+use rng::Rng;
+
+const USAGE: &str = "\
+Usage: findutils-difftest [options]
+
+Runs GNU find and ours on random trees and expressions, and reports any
+differences in their output, errors or exit status.
+
+Options:
+  --runs N     how many cases to try (100)
+  --seed N     the seed for the first case (random); case i uses seed N+i,
+               so --seed S --runs 1 repeats the case with seed S
+  --gnu PATH   GNU find (find on the PATH)
+  --ours PATH  our find (../target/debug/find)
+  --ordered    also compare the order of the output
+  --keep       keep the trees for cases with differences
+";
+
+struct Options {
+    runs: u64,
+    seed: u64,
+    gnu: OsString,
+    ours: PathBuf,
+    ordered: bool,
+    keep: bool,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        runs: 100,
+        seed: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        gnu: OsString::from("find"),
+        ours: Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/debug/find"),
+        ordered: false,
+        keep: false,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing argument to {arg}"));
+        let number = |value: String| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid number '{value}'"))
+        };
+        match arg.as_str() {
+            "--runs" => options.runs = number(value()?)?,
+            "--seed" => options.seed = number(value()?)?,
+            "--gnu" => options.gnu = value()?.into(),
+            "--ours" => options.ours = value()?.into(),
+            "--ordered" => options.ordered = true,
+            "--keep" => options.keep = true,
+            "--help" => {
+                print!("{USAGE}");
+                exit(0);
+            }
+            _ => return Err(format!("unknown option '{arg}'")),
+        }
+    }
+    Ok(options)
+}
+
+/// What one find did.
+struct Run {
+    status: Option<i32>,
+    stdout: Vec<String>,
+    stderr: String,
+}
+
+impl Run {
+    fn new(output: Output, ordered: bool) -> Self {
+        let mut stdout: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_owned)
+            .collect();
+        if !ordered {
+            // Directories can be read in any order
+            stdout.sort();
+        }
+        Self {
+            status: output.status.code(),
+            stdout,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+
+    fn differs(&self, other: &Self) -> bool {
+        self.status != other.status || self.stdout != other.stdout || self.stderr != other.stderr
+    }
+}
+
+fn run(program: &Path, gnu: bool, args: &[String], dir: &Path, ordered: bool) -> Run {
+    let mut command = Command::new(program);
+    if gnu {
+        // So its errors start "find: " like ours
+        command.arg0("find");
+    } else {
+        command.arg("--gnu-errors");
+    }
+    let output = command
+        .args(args)
+        .current_dir(dir)
+        .env("LC_ALL", "C")
+        .env("TZ", "UTC")
+        .env_remove("FIND_OPTIONS")
+        .output()
+        .unwrap_or_else(|e| {
+            eprintln!("Can't run {}: {e}", program.display());
+            exit(2);
+        });
+    Run::new(output, ordered)
+}
+
+/// Quotes a word for a shell, if it needs it.
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./+=%,".contains(c))
+    {
+        word.to_owned()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// Prints how two runs differ.
+fn report(seed: u64, args: &[String], gnu: &Run, ours: &Run, tree: Option<&Path>) {
+    let command: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+    println!("seed {seed}: find {}", command.join(" "));
+    if let Some(tree) = tree {
+        println!("  tree kept in {}", tree.display());
+    }
+    if gnu.status != ours.status {
+        println!(
+            "  exit status: GNU {:?}, ours {:?}",
+            gnu.status, ours.status
+        );
+    }
+    if gnu.stdout != ours.stdout {
+        let gnu_lines: BTreeSet<_> = gnu.stdout.iter().collect();
+        let our_lines: BTreeSet<_> = ours.stdout.iter().collect();
+        for line in gnu_lines.difference(&our_lines) {
+            println!("  only GNU:  {line}");
+        }
+        for line in our_lines.difference(&gnu_lines) {
+            println!("  only ours: {line}");
+        }
+        if gnu_lines == our_lines {
+            println!("  same lines, but in a different order or number");
+        }
+    }
+    if gnu.stderr != ours.stderr {
+        for line in gnu.stderr.lines() {
+            println!("  GNU stderr:  {line}");
+        }
+        for line in ours.stderr.lines() {
+            println!("  our stderr:  {line}");
+        }
+    }
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    let mut differences = 0;
+    for i in 0..options.runs {
+        let seed = options.seed.wrapping_add(i);
+        let mut rng = Rng::new(seed);
+        let dir = tempfile::Builder::new()
+            .prefix("findutils-difftest")
+            .tempdir()
+            .expect("failed to create a temporary directory");
+        let paths = tree::generate(dir.path(), &mut rng).expect("failed to generate a tree");
+        let args = expr::generate(&mut rng, &paths);
+
+        let gnu = run(
+            Path::new(&options.gnu),
+            true,
+            &args,
+            dir.path(),
+            options.ordered,
+        );
+        let ours = run(&options.ours, false, &args, dir.path(), options.ordered);
+
+        if gnu.differs(&ours) {
+            differences += 1;
+            let kept = options.keep.then(|| dir.into_path());
+            report(seed, &args, &gnu, &ours, kept.as_deref());
+        }
+    }
+
+    println!(
+        "{differences} of {} cases differed (seeds {} to {})",
+        options.runs,
+        options.seed,
+        options.seed.wrapping_add(options.runs.saturating_sub(1))
+    );
+    exit(i32::from(differences > 0));
+}
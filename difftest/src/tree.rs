@@ -0,0 +1,112 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Random directory trees to search.
+
+use std::fs::{self, File, Permissions};
+use std::io::{self, Write};
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use filetime::{set_file_mtime, FileTime};
+
+use crate::rng::Rng;
+
+/// How deep the directories go.
+const MAX_DEPTH: usize = 3;
+
+const NAMES: &[&str] = &[
+    "a",
+    "abc",
+    "b.txt",
+    "C.TXT",
+    "readme.md",
+    "x y",
+    ".hidden",
+    "data.tar.gz",
+    "lib.rs",
+    "Makefile",
+    "z9",
+];
+
+const SIZES: &[usize] = &[0, 1, 100, 511, 512, 513, 1024, 1025, 4096, 10_000];
+
+const FILE_MODES: &[u32] = &[0o644, 0o600, 0o755, 0o700, 0o444, 0o640, 0o777, 0o4755];
+
+const DIR_MODES: &[u32] = &[0o755, 0o700, 0o775, 0o1777];
+
+/// How long ago things were modified, in days. The half days keep them away
+/// from the edges of -mtime's 24 hour periods.
+const AGES: &[f64] = &[0.5, 1.5, 2.5, 6.5, 30.5, 400.5];
+
+/// Fills `root`, which must already exist, with a random tree of files,
+/// directories and symbolic links, some of them broken or looping. Returns
+/// the paths of everything in it, starting with `.`, for expressions that
+/// refer to files.
+pub fn generate(root: &Path, rng: &mut Rng) -> io::Result<Vec<String>> {
+    let mut paths = vec![String::from(".")];
+    fill(root, ".", 0, rng, &mut paths)?;
+    Ok(paths)
+}
+
+fn fill(
+    root: &Path,
+    dir: &str,
+    depth: usize,
+    rng: &mut Rng,
+    paths: &mut Vec<String>,
+) -> io::Result<()> {
+    let mut names: Vec<&str> = vec![];
+    for _ in 0..rng.below(6) {
+        let name = rng.pick(NAMES);
+        if names.contains(&name) {
+            continue;
+        }
+        names.push(name);
+        let path = format!("{dir}/{name}");
+        let full = root.join(&path);
+
+        match rng.below(10) {
+            0..=5 => {
+                let mut file = File::create(&full)?;
+                file.write_all(&vec![b'x'; rng.pick(SIZES)])?;
+                fs::set_permissions(&full, Permissions::from_mode(rng.pick(FILE_MODES)))?;
+                set_age(&full, rng)?;
+            }
+            6..=7 if depth < MAX_DEPTH => {
+                fs::create_dir(&full)?;
+                fill(root, &path, depth + 1, rng, paths)?;
+                fs::set_permissions(&full, Permissions::from_mode(rng.pick(DIR_MODES)))?;
+                // After filling it, which changes it
+                set_age(&full, rng)?;
+            }
+            _ => {
+                // Something here already, one that isn't, or an ancestor,
+                // which loops with -L
+                let target = match rng.below(3) {
+                    0 => paths[rng.below(paths.len())].clone(),
+                    1 => String::from("nowhere"),
+                    // The root's parent isn't part of the tree
+                    _ if depth == 0 => String::from("."),
+                    _ => String::from(".."),
+                };
+                let target = match target.strip_prefix("./") {
+                    // Relative to the root
+                    Some(rest) => format!("{}{rest}", "../".repeat(depth)),
+                    None => target,
+                };
+                symlink(target, &full)?;
+            }
+        }
+        paths.push(path);
+    }
+    Ok(())
+}
+
+fn set_age(path: &Path, rng: &mut Rng) -> io::Result<()> {
+    let age = Duration::from_secs_f64(rng.pick(AGES) * 86400.0);
+    set_file_mtime(path, FileTime::from_system_time(SystemTime::now() - age))
+}
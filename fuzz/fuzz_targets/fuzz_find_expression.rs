@@ -0,0 +1,14 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Treat the input as a NUL separated list of arguments
+    let data = String::from_utf8_lossy(data);
+    let args: Vec<&str> = data.split('\0').collect();
+    findutils::fuzzing::find_expression(&args);
+});
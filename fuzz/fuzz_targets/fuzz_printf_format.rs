@@ -0,0 +1,13 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(format) = std::str::from_utf8(data) {
+        findutils::fuzzing::printf_format(format);
+    }
+});
@@ -0,0 +1,18 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // An optional delimiter, then a NUL, then the input itself
+    match data.iter().position(|&b| b == 0) {
+        Some(i) => {
+            let delimiter = String::from_utf8_lossy(&data[..i]);
+            findutils::fuzzing::xargs_input(&data[i + 1..], Some(&delimiter));
+        }
+        None => findutils::fuzzing::xargs_input(data, None),
+    }
+});
@@ -0,0 +1,163 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Helpers for the benchmarks in the `benches` directory. These are only built
+//! with the `bench` feature.
+
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, sink, Sink, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::find::Dependencies;
+
+/// The extensions given to generated files, in turn, so that tests like -name
+/// have something to tell apart.
+const EXTENSIONS: &[&str] = &["txt", "rs", "c", "o"];
+
+/// The shape of a synthetic directory tree.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TreeShape {
+    /// How many levels of directories below the root.
+    pub depth: usize,
+    /// How many subdirectories each directory has, apart from the deepest.
+    pub fanout: usize,
+    /// How many files each directory has.
+    pub files_per_dir: usize,
+}
+
+impl TreeShape {
+    /// A single chain of `depth` directories, each with a few files.
+    #[must_use]
+    pub fn deep(depth: usize) -> Self {
+        Self {
+            depth,
+            fanout: 1,
+            files_per_dir: 4,
+        }
+    }
+
+    /// A single directory holding `files` files.
+    #[must_use]
+    pub fn wide(files: usize) -> Self {
+        Self {
+            depth: 0,
+            fanout: 0,
+            files_per_dir: files,
+        }
+    }
+
+    /// How many entries (files and directories, including the root) a tree of
+    /// this shape has.
+    pub fn entries(&self) -> usize {
+        let mut dirs = 1;
+        let mut level = 1;
+        for _ in 0..self.depth {
+            level *= self.fanout;
+            dirs += level;
+        }
+        dirs * (1 + self.files_per_dir)
+    }
+}
+
+/// Creates a tree of the given shape under `root`, which must already exist.
+/// Directories are named `dN` and files `fN.EXT`, and every other file has a
+/// little content so that -size and -empty have something to do.
+pub fn generate_tree(root: &Path, shape: &TreeShape) -> io::Result<()> {
+    for i in 0..shape.files_per_dir {
+        let ext = EXTENSIONS[i % EXTENSIONS.len()];
+        let mut file = File::create(root.join(format!("f{i}.{ext}")))?;
+        if i % 2 == 1 {
+            writeln!(file, "file {i}")?;
+        }
+    }
+
+    if shape.depth > 0 {
+        let shape = TreeShape {
+            depth: shape.depth - 1,
+            ..*shape
+        };
+        for i in 0..shape.fanout {
+            let dir = root.join(format!("d{i}"));
+            fs::create_dir(&dir)?;
+            generate_tree(&dir, &shape)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Dependencies that throw the output away, so that benchmarks measure find
+/// rather than the terminal.
+pub struct SinkDependencies {
+    output: RefCell<Sink>,
+    error_output: RefCell<Sink>,
+    now: SystemTime,
+}
+
+impl SinkDependencies {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            output: RefCell::new(sink()),
+            error_output: RefCell::new(sink()),
+            now: SystemTime::now(),
+        }
+    }
+}
+
+impl Default for SinkDependencies {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dependencies for SinkDependencies {
+    fn get_output(&self) -> &RefCell<dyn Write> {
+        &self.output
+    }
+
+    fn get_error_output(&self) -> &RefCell<dyn Write> {
+        &self.error_output
+    }
+
+    fn now(&self) -> SystemTime {
+        self.now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder;
+
+    fn count_entries(path: &Path) -> usize {
+        let mut count = 1;
+        if path.is_dir() {
+            for entry in fs::read_dir(path).unwrap() {
+                count += count_entries(&entry.unwrap().path());
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn generated_tree_matches_shape() {
+        for shape in [
+            TreeShape::deep(5),
+            TreeShape::wide(10),
+            TreeShape {
+                depth: 2,
+                fanout: 3,
+                files_per_dir: 2,
+            },
+        ] {
+            let temp_dir = Builder::new().prefix("bench").tempdir().unwrap();
+            generate_tree(temp_dir.path(), &shape).unwrap();
+            assert_eq!(count_entries(temp_dir.path()), shape.entries(), "{shape:?}");
+        }
+    }
+}
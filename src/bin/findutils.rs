@@ -0,0 +1,64 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! A single binary for all the utilities, which picks one based on the name
+//! it was run as (e.g. through a `find` symlink), or else its first argument.
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process;
+
+type UtilMain = fn(&[&str]) -> i32;
+
+fn find_main(args: &[&str]) -> i32 {
+    // Ignore SIGPIPE, as the standalone find does
+    uucore::panic::mute_sigpipe_panic();
+
+    let deps = findutils::find::StandardDependencies::new();
+    findutils::find::find_main(args, &deps)
+}
+
+const UTILS: &[(&str, UtilMain)] = &[("find", find_main), ("xargs", findutils::xargs::xargs_main)];
+
+fn util_main(name: &str) -> Option<UtilMain> {
+    UTILS
+        .iter()
+        .find(|&&(util, _)| util == name)
+        .map(|&(_, main)| main)
+}
+
+fn usage(name: &str) -> ! {
+    eprintln!("Usage: {name} UTILITY [ARGUMENT]...");
+    eprintln!();
+    eprintln!("Currently defined utilities:");
+    for (util, _) in UTILS {
+        eprintln!("    {util}");
+    }
+    process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<String>>();
+    let mut args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+
+    // Run as e.g. find, or find.exe on Windows
+    let binary = args.first().copied().unwrap_or("findutils");
+    let name = Path::new(binary)
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default();
+    if let Some(main) = util_main(name) {
+        process::exit(main(&args));
+    }
+
+    // Run as e.g. findutils find
+    match args.get(1).copied().and_then(util_main) {
+        Some(main) => {
+            args.remove(0);
+            process::exit(main(&args));
+        }
+        None => usage(binary),
+    }
+}
@@ -0,0 +1,522 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Running commands, for both find's -exec and xargs.
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How a command we waited for finished.
+#[derive(Debug)]
+pub(crate) enum Finished {
+    Exited(ExitStatus),
+    /// It ran out of time, and was killed.
+    TimedOut,
+}
+
+/// Why a command couldn't be started, which xargs gives different exit
+/// statuses.
+#[derive(Debug)]
+pub(crate) enum SpawnError {
+    /// There's no such program.
+    NotFound(io::Error),
+    /// There is, but it couldn't be run, e.g. for lack of permission.
+    CannotRun(io::Error),
+}
+
+impl From<io::Error> for SpawnError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::NotFound {
+            Self::NotFound(e)
+        } else {
+            Self::CannotRun(e)
+        }
+    }
+}
+
+impl From<SpawnError> for io::Error {
+    fn from(e: SpawnError) -> Self {
+        match e {
+            SpawnError::NotFound(e) | SpawnError::CannotRun(e) => e,
+        }
+    }
+}
+
+impl Display for SpawnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(e) | Self::CannotRun(e) => e.fmt(f),
+        }
+    }
+}
+
+/// What a command's exit status means.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Success,
+    /// It exited with a non-zero code of its own choosing.
+    Failed(i32),
+    /// It was killed by a signal, on Unix.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Killed(i32),
+    /// It crashed, on Windows, where the closest thing to being killed by a
+    /// signal is exiting with an NTSTATUS error code, e.g. 0xC0000005 for an
+    /// access violation.
+    Crashed(u32),
+    Unknown,
+}
+
+impl Outcome {
+    pub(crate) fn new(status: ExitStatus) -> Self {
+        if status.success() {
+            return Self::Success;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return Self::Killed(signal);
+            }
+        }
+
+        match status.code() {
+            Some(code) if cfg!(windows) && is_ntstatus_error(code as u32) => {
+                Self::Crashed(code as u32)
+            }
+            Some(code) => Self::Failed(code),
+            None => Self::Unknown,
+        }
+    }
+
+    /// Describes how `executable` finished if it didn't exit normally, in
+    /// the same words as GNU find, for each utility to report as its own
+    /// error.
+    pub(crate) fn abnormal_exit(self, executable: &str) -> Option<String> {
+        match self {
+            Self::Killed(signal) => Some(killed_by_signal(executable, signal)),
+            Self::Crashed(code) => Some(format!(
+                "'{executable}' terminated by exception 0x{code:08X}"
+            )),
+            Self::Success | Self::Failed(_) | Self::Unknown => None,
+        }
+    }
+}
+
+/// Describes `executable` being killed by `signal`, like
+/// [Outcome::abnormal_exit].
+pub(crate) fn killed_by_signal(executable: &str, signal: i32) -> String {
+    format!("'{executable}' terminated by signal {signal}")
+}
+
+/// Describes `executable` being killed for running for longer than
+/// `timeout`.
+pub(crate) fn timed_out(executable: &str, timeout: Duration) -> String {
+    format!(
+        "'{executable}' timed out after {}s and was killed",
+        timeout.as_secs_f64()
+    )
+}
+
+/// Whether a Windows exit code is really an NTSTATUS error code rather
+/// than a value the program chose to exit with.
+fn is_ntstatus_error(code: u32) -> bool {
+    code & 0xC000_0000 == 0xC000_0000
+}
+
+/// Shows a command the way xargs -t and find --dry-run do, with each
+/// argument quoted. Any change of directory comes first, and so does the
+/// environment, but only where it differs from the one the command would
+/// inherit, e.g. with --exec-sandbox or xargs --env-var.
+pub(crate) fn describe(command: &Command) -> String {
+    format!("{command:?}")
+}
+
+/// Starts a command, to be waited for with [wait_with_timeout] and the same
+/// `timeout`, with whatever `limits` it should run under.
+pub(crate) fn spawn(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    limits: &Limits,
+) -> Result<Child, SpawnError> {
+    prepare_for_timeout(command, timeout);
+    limits.apply(command);
+    Ok(command.spawn()?)
+}
+
+/// What a command can use of the machine, so that big batch jobs can't take
+/// it over (find's --exec-nice and --exec-rlimit, and xargs' --nice and
+/// --rlimit).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Limits {
+    /// How much to add to the command's niceness.
+    nice: Option<i32>,
+    rlimits: Vec<Rlimit>,
+}
+
+impl Limits {
+    /// Limits that can only be set on Unix, so anywhere else, any at all are
+    /// an error.
+    pub(crate) fn new(nice: Option<i32>, rlimits: Vec<Rlimit>) -> Result<Self, String> {
+        if cfg!(not(unix)) && (nice.is_some() || !rlimits.is_empty()) {
+            return Err("Resource limits are not available on this platform".to_owned());
+        }
+        Ok(Self { nice, rlimits })
+    }
+
+    /// Sets the limits in the child, just before it runs the command.
+    fn apply(&self, command: &mut Command) {
+        #[cfg(unix)]
+        if self.nice.is_some() || !self.rlimits.is_empty() {
+            use std::os::unix::process::CommandExt;
+            use uucore::libc;
+
+            let limits = self.clone();
+            // SAFETY: setrlimit() and nice() are system calls, which don't
+            // allocate or take locks
+            unsafe {
+                command.pre_exec(move || {
+                    for rlimit in &limits.rlimits {
+                        let resource = match rlimit.resource {
+                            Resource::Cpu => libc::RLIMIT_CPU,
+                            Resource::AddressSpace => libc::RLIMIT_AS,
+                            Resource::FileSize => libc::RLIMIT_FSIZE,
+                            Resource::OpenFiles => libc::RLIMIT_NOFILE,
+                            Resource::Core => libc::RLIMIT_CORE,
+                        };
+                        let value = rlimit.value as libc::rlim_t;
+                        let rlim = libc::rlimit {
+                            rlim_cur: value,
+                            rlim_max: value,
+                        };
+                        if libc::setrlimit(resource, &rlim) != 0 {
+                            return Err(io::Error::last_os_error());
+                        }
+                    }
+                    if let Some(nice) = limits.nice {
+                        // -1 is also a niceness, so only errno tells them apart
+                        nix::errno::Errno::clear();
+                        if libc::nice(nice) == -1 && nix::errno::Errno::last_raw() != 0 {
+                            return Err(io::Error::last_os_error());
+                        }
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = command;
+    }
+}
+
+/// Parses the argument to --nice, which like nice(1)'s can be negative to
+/// raise the priority, if we're allowed to.
+pub(crate) fn parse_nice(arg: &str) -> Result<i32, String> {
+    arg.parse().map_err(|_| format!("invalid niceness '{arg}'"))
+}
+
+/// Parses the argument to --rlimit, a comma-separated list of limits like
+/// `cpu=60,as=2G`.
+pub(crate) fn parse_rlimits(arg: &str) -> Result<Vec<Rlimit>, String> {
+    arg.split(',').map(str::parse).collect()
+}
+
+/// The things --rlimit can limit, named like prlimit(1)'s options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(unix), allow(dead_code))]
+enum Resource {
+    /// CPU time, in seconds.
+    Cpu,
+    /// Virtual memory, in bytes.
+    AddressSpace,
+    /// How big a file it can write, in bytes.
+    FileSize,
+    /// How many files it can have open.
+    OpenFiles,
+    /// How big a core dump it can leave, in bytes.
+    Core,
+}
+
+/// A limit on one resource, set as both the soft and the hard limit so that
+/// the command can't raise it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) struct Rlimit {
+    resource: Resource,
+    value: u64,
+}
+
+impl FromStr for Rlimit {
+    type Err = String;
+
+    /// Parses `NAME=VALUE`, where sizes can end in `k`, `M` or `G`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid resource limit '{s}': expected NAME=VALUE, where NAME is cpu, as, \
+                 fsize, nofile or core"
+            )
+        };
+        let (name, value) = s.split_once('=').ok_or_else(invalid)?;
+        let resource = match name {
+            "cpu" => Resource::Cpu,
+            "as" => Resource::AddressSpace,
+            "fsize" => Resource::FileSize,
+            "nofile" => Resource::OpenFiles,
+            "core" => Resource::Core,
+            _ => return Err(invalid()),
+        };
+        let (digits, multiplier) = match value.as_bytes().last() {
+            Some(b'k' | b'K') => (&value[..value.len() - 1], 1 << 10),
+            Some(b'M') => (&value[..value.len() - 1], 1 << 20),
+            Some(b'G') => (&value[..value.len() - 1], 1 << 30),
+            _ => (value, 1),
+        };
+        let value = digits
+            .parse::<u64>()
+            .ok()
+            .filter(|_| multiplier == 1 || resource != Resource::Cpu)
+            .and_then(|n| n.checked_mul(multiplier))
+            .ok_or_else(|| format!("invalid value '{value}' for the {name} limit"))?;
+        Ok(Self { resource, value })
+    }
+}
+
+/// Parses a time limit in seconds, which may have a fractional part. Zero
+/// means there's no limit, as for timeout(1).
+pub(crate) fn parse_timeout(arg: &str) -> Result<Option<Duration>, String> {
+    let invalid = || format!("invalid time interval '{arg}'");
+    let secs: f64 = arg.parse().map_err(|_| invalid())?;
+    if secs == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(secs)
+        .map(Some)
+        .map_err(|_| invalid())
+}
+
+/// Gets a command ready to be [spawn]ed. If there's a time limit, the
+/// command gets its own process group on Unix, so that anything it starts
+/// can be killed along with it. That does mean it can't read from the
+/// terminal.
+fn prepare_for_timeout(command: &mut Command, timeout: Option<Duration>) {
+    #[cfg(unix)]
+    if timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    #[cfg(not(unix))]
+    let _ = (command, timeout);
+}
+
+/// Kills a command [spawn]ed with a time limit, and
+/// everything in its process group.
+fn kill(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+        // The group has the same ID as its leader
+        if killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL).is_ok() {
+            return Ok(());
+        }
+    }
+
+    child.kill()
+}
+
+/// Waits for `child` to exit, killing it if it's still running after
+/// `timeout`.
+pub(crate) fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> io::Result<Finished> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Finished::Exited);
+    };
+
+    let deadline = Instant::now() + timeout;
+    // Poll quickly at first, so short commands aren't held up, then back off
+    let mut interval = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Finished::Exited(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        thread::sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(Duration::from_millis(50));
+    }
+
+    // It may have exited since we last looked, in which case there's
+    // nothing to kill
+    if let Err(e) = kill(child) {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Finished::Exited(status));
+        }
+        return Err(e);
+    }
+    child.wait()?;
+    Ok(Finished::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_ntstatus_errors() {
+        assert!(is_ntstatus_error(0xC000_0005));
+        assert!(is_ntstatus_error(0xC000_013A));
+        assert!(!is_ntstatus_error(0));
+        assert!(!is_ntstatus_error(1));
+        assert!(!is_ntstatus_error(255));
+        assert!(!is_ntstatus_error(0x4000_0000));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn outcomes() {
+        use std::os::unix::process::ExitStatusExt;
+
+        assert_eq!(Outcome::new(ExitStatus::from_raw(0)), Outcome::Success);
+        assert_eq!(
+            Outcome::new(ExitStatus::from_raw(1 << 8)),
+            Outcome::Failed(1)
+        );
+        assert_eq!(
+            Outcome::new(ExitStatus::from_raw(255 << 8)),
+            Outcome::Failed(255)
+        );
+        assert_eq!(Outcome::new(ExitStatus::from_raw(9)), Outcome::Killed(9));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn spawn_errors() {
+        let limits = Limits::default();
+        let err = spawn(&mut Command::new("test_data/nope"), None, &limits).unwrap_err();
+        assert!(matches!(err, SpawnError::NotFound(_)), "{err:?}");
+
+        // A directory can't be run
+        let err = spawn(&mut Command::new("test_data/simple"), None, &limits).unwrap_err();
+        assert!(matches!(err, SpawnError::CannotRun(_)), "{err:?}");
+    }
+
+    #[test]
+    fn parse_timeouts() {
+        assert_eq!(parse_timeout("0"), Ok(None));
+        assert_eq!(parse_timeout("1.5"), Ok(Some(Duration::from_millis(1500))));
+        assert_eq!(parse_timeout("2"), Ok(Some(Duration::from_secs(2))));
+        for arg in ["", "-1", "x", "inf", "NaN"] {
+            assert_eq!(
+                parse_timeout(arg),
+                Err(format!("invalid time interval '{arg}'"))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_limits() {
+        assert_eq!(parse_nice("10"), Ok(10));
+        assert_eq!(parse_nice("-5"), Ok(-5));
+        assert!(parse_nice("x").is_err());
+
+        let rlimit = |resource, value| Rlimit { resource, value };
+        assert_eq!(
+            parse_rlimits("cpu=60,as=2G,fsize=10k,nofile=64,core=0"),
+            Ok(vec![
+                rlimit(Resource::Cpu, 60),
+                rlimit(Resource::AddressSpace, 2 << 30),
+                rlimit(Resource::FileSize, 10 << 10),
+                rlimit(Resource::OpenFiles, 64),
+                rlimit(Resource::Core, 0),
+            ])
+        );
+        for arg in [
+            "",
+            "cpu",
+            "cpu=",
+            "cpu=1k",
+            "mem=1G",
+            "as=-1",
+            "as=1T",
+            "as=99999999999G",
+        ] {
+            assert!(parse_rlimits(arg).is_err(), "{arg}");
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn spawns_with_limits() {
+        let output = |limits: &Limits| {
+            let mut command = Command::new("sh");
+            command
+                .args(["-c", "ulimit -t; ulimit -n; nice"])
+                .stdout(std::process::Stdio::piped());
+            let child = spawn(&mut command, None, limits).unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        let limits = Limits::new(Some(3), parse_rlimits("cpu=7,nofile=64").unwrap()).unwrap();
+        let limited = output(&limits);
+        let mut lines = limited.lines();
+        assert_eq!(lines.next(), Some("7"));
+        assert_eq!(lines.next(), Some("64"));
+        let niceness: i32 = lines.next().unwrap().parse().unwrap();
+        let unlimited = output(&Limits::default());
+        let usual: i32 = unlimited.lines().last().unwrap().parse().unwrap();
+        assert_eq!(niceness, (usual + 3).min(19));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn waits_with_timeout() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("waits_with_timeout")
+            .tempdir()
+            .unwrap();
+        let marker = temp_dir.path().join("marker");
+
+        // What the shell starts should be killed along with it
+        let timeout = Some(Duration::from_millis(100));
+        let mut command = Command::new("sh");
+        command
+            .args(["-c", "(sleep 0.5; touch \"$0\") & sleep 10"])
+            .arg(&marker);
+        let mut child = spawn(&mut command, timeout, &Limits::default()).unwrap();
+        let start = Instant::now();
+        let finished = wait_with_timeout(&mut child, timeout);
+        assert!(matches!(finished, Ok(Finished::TimedOut)), "{finished:?}");
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_secs(1));
+        assert!(!marker.exists());
+
+        let mut child = Command::new("true").spawn().unwrap();
+        let finished = wait_with_timeout(&mut child, Some(Duration::from_secs(10)));
+        assert!(
+            matches!(finished, Ok(Finished::Exited(status)) if status.success()),
+            "{finished:?}"
+        );
+
+        let mut child = Command::new("false").spawn().unwrap();
+        let finished = wait_with_timeout(&mut child, None);
+        assert!(
+            matches!(finished, Ok(Finished::Exited(status)) if !status.success()),
+            "{finished:?}"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The errors find reports before it starts searching.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+use uucore::error::{strip_errno, UError};
+
+/// Why find couldn't start a search: the command line didn't make sense, or
+/// something it names couldn't be used.
+#[derive(Debug)]
+pub enum FindError {
+    /// The expression is malformed, e.g. it has unbalanced parentheses or an
+    /// unknown primary.
+    Parse(String),
+    /// An argument where a primary should be that isn't one.
+    UnknownPrimary {
+        name: String,
+        /// The primary or operator before it, if any.
+        after: Option<String>,
+        /// What was probably meant instead, e.g. `-xdev` for `--xdev`.
+        suggestion: Option<String>,
+    },
+    /// An operator with nothing after it.
+    ExpectedExpression {
+        /// The operator, e.g. `-o`.
+        operator: String,
+    },
+    /// A starting point given along with -files0-from.
+    ExtraOperand(String),
+    /// A primary is missing some of its arguments.
+    MissingArgument {
+        /// The primary, e.g. `-name`.
+        predicate: String,
+    },
+    /// A primary was given an argument it can't use.
+    InvalidArgument {
+        /// The primary, e.g. `-type`.
+        predicate: String,
+        /// The offending argument.
+        value: String,
+        /// What's wrong with it.
+        message: String,
+    },
+    /// A file named on the command line couldn't be read or written.
+    Io {
+        /// What we were doing, usually just the quoted path.
+        context: String,
+        /// The path the context quotes, if there is one.
+        path: Option<String>,
+        source: io::Error,
+    },
+    /// A primary that can't work on this platform.
+    Unsupported(String),
+}
+
+impl FindError {
+    /// Creates an [FindError::InvalidArgument].
+    pub(crate) fn invalid_argument(predicate: &str, value: &str, message: impl Display) -> Self {
+        Self::InvalidArgument {
+            predicate: predicate.to_owned(),
+            value: value.to_owned(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Creates an [FindError::Io] for a problem with `path`.
+    pub(crate) fn io(path: &str, source: io::Error) -> Self {
+        Self::Io {
+            context: format!("'{path}'"),
+            path: Some(path.to_owned()),
+            source,
+        }
+    }
+}
+
+impl Display for FindError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(message) | Self::Unsupported(message) => f.write_str(message),
+            Self::UnknownPrimary {
+                name, suggestion, ..
+            } => {
+                write!(f, "Unrecognized flag: '{name}'")?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean '{suggestion}'?")?;
+                }
+                Ok(())
+            }
+            Self::ExpectedExpression { operator } => {
+                write!(f, "expected an expression after {operator}")
+            }
+            Self::ExtraOperand(operand) => write!(
+                f,
+                "extra operand '{operand}'\nfile operands cannot be combined with -files0-from"
+            ),
+            Self::MissingArgument { predicate } => write!(f, "missing argument to {predicate}"),
+            Self::InvalidArgument { message, .. } => f.write_str(message),
+            Self::Io {
+                context, source, ..
+            } => write!(f, "{context}: {}", strip_errno(source)),
+        }
+    }
+}
+
+impl Error for FindError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Like GNU find, the exit code is 1 for anything that stops the search from
+/// starting.
+impl UError for FindError {}
+
+impl From<&str> for FindError {
+    fn from(message: &str) -> Self {
+        Self::Parse(message.to_owned())
+    }
+}
+
+impl From<String> for FindError {
+    fn from(message: String) -> Self {
+        Self::Parse(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            FindError::MissingArgument {
+                predicate: "-name".to_owned()
+            }
+            .to_string(),
+            "missing argument to -name"
+        );
+        assert_eq!(
+            FindError::invalid_argument("-type", "q", "Unrecognised type argument q").to_string(),
+            "Unrecognised type argument q"
+        );
+        let e = FindError::io("out", io::Error::other("disk full"));
+        assert_eq!(e.to_string(), "'out': disk full");
+        assert!(e.source().is_some());
+        assert_eq!(e.code(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Collecting the errors from a search into one summary at the end, for
+//! --errors=json.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::matchers::WalkError;
+use super::Dependencies;
+
+/// One error, as it appears in the summary.
+struct Record {
+    /// What sort of error it was, e.g. `permission_denied`, or `action` for
+    /// the errors from -exec, -delete and friends.
+    kind: &'static str,
+    path: Option<String>,
+    message: String,
+}
+
+/// The errors seen so far, in the order they happened.
+#[derive(Default)]
+pub(crate) struct ErrorSummary {
+    records: Vec<Record>,
+}
+
+impl ErrorSummary {
+    /// Records an error from reading the file system.
+    pub fn walk_error(&mut self, err: &WalkError) {
+        let kind = if err.is_loop() {
+            "loop"
+        } else {
+            match err.kind() {
+                ErrorKind::PermissionDenied => "permission_denied",
+                ErrorKind::NotFound => "not_found",
+                _ => "io",
+            }
+        };
+        self.records.push(Record {
+            kind,
+            path: err.path().map(|path| path.to_string_lossy().into_owned()),
+            message: err.to_string(),
+        });
+    }
+
+    /// Records a starting point that couldn't be used at all.
+    pub fn starting_point_error(&mut self, message: &str) {
+        self.records.push(Record {
+            kind: "starting_point",
+            path: None,
+            message: message.to_owned(),
+        });
+    }
+
+    /// Records the lines a matcher wrote as errors while acting on `path`.
+    pub fn action_errors(&mut self, path: &Path, output: &[u8]) {
+        let output = String::from_utf8_lossy(output);
+        for line in output.lines().filter(|line| !line.is_empty()) {
+            self.records.push(Record {
+                kind: "action",
+                path: Some(path.to_string_lossy().into_owned()),
+                message: line.to_owned(),
+            });
+        }
+    }
+
+    /// Writes the summary as a single line of JSON: how many errors there
+    /// were of each kind, then each of them in turn.
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut kinds = BTreeMap::<&str, usize>::new();
+        for record in &self.records {
+            *kinds.entry(record.kind).or_default() += 1;
+        }
+
+        let mut json = format!("{{\"errors\":{},\"kinds\":{{", self.records.len());
+        for (i, (kind, count)) in kinds.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(json, "{}:{count}", quote(kind)).unwrap();
+        }
+        json.push_str("},\"items\":[");
+        for (i, record) in self.records.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let path = record.path.as_deref().map_or_else(|| "null".into(), quote);
+            write!(
+                json,
+                "{{\"kind\":{},\"path\":{path},\"message\":{}}}",
+                quote(record.kind),
+                quote(&record.message)
+            )
+            .unwrap();
+        }
+        json.push_str("]}");
+        writeln!(out, "{json}")
+    }
+}
+
+/// Quotes a string for JSON.
+fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Dependencies that keep what matchers write as errors, so that it can go
+/// in the summary rather than straight to stderr.
+pub(crate) struct Collecting<'a> {
+    deps: &'a dyn Dependencies,
+    errors: RefCell<Vec<u8>>,
+}
+
+impl<'a> Collecting<'a> {
+    pub fn new(deps: &'a dyn Dependencies) -> Self {
+        Self {
+            deps,
+            errors: RefCell::default(),
+        }
+    }
+
+    /// Takes what's been written as errors since the last time.
+    pub fn take_errors(&self) -> Vec<u8> {
+        std::mem::take(&mut self.errors.borrow_mut())
+    }
+}
+
+impl Dependencies for Collecting<'_> {
+    fn get_output(&self) -> &RefCell<dyn Write> {
+        self.deps.get_output()
+    }
+
+    fn get_error_output(&self) -> &RefCell<dyn Write> {
+        &self.errors
+    }
+
+    fn now(&self) -> SystemTime {
+        self.deps.now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find::tests::FakeDependencies;
+
+    #[test]
+    #[cfg(unix)]
+    fn summarizes_errors() {
+        let mut summary = ErrorSummary::default();
+        let mut out = vec![];
+        summary.write_json(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"errors\":0,\"kinds\":{},\"items\":[]}\n"
+        );
+
+        let denied = io::Error::from_raw_os_error(uucore::libc::EACCES);
+        summary.walk_error(&WalkError::for_path(&denied, "a/\"b\"", 1));
+        summary.action_errors(Path::new("c\td"), b"Failed to delete c\td\nagain\n");
+        summary.starting_point_error("invalid zero-length file name");
+        let mut out = vec![];
+        summary.write_json(&mut out).unwrap();
+        let denied = quote(&format!("a/\"b\": {denied}"));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{{\"errors\":4,\"kinds\":{{\"action\":2,\"permission_denied\":1,\
+                 \"starting_point\":1}},\"items\":[\
+                 {{\"kind\":\"permission_denied\",\"path\":\"a/\\\"b\\\"\",\"message\":{denied}}},\
+                 {{\"kind\":\"action\",\"path\":\"c\\td\",\"message\":\"Failed to delete c\\td\"}},\
+                 {{\"kind\":\"action\",\"path\":\"c\\td\",\"message\":\"again\"}},\
+                 {{\"kind\":\"starting_point\",\"path\":null,\
+                 \"message\":\"invalid zero-length file name\"}}]}}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn quotes_json() {
+        assert_eq!(quote("plain"), "\"plain\"");
+        assert_eq!(quote("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(quote("\n\u{1b}"), "\"\\n\\u001b\"");
+    }
+
+    #[test]
+    fn collects_error_output() {
+        let deps = FakeDependencies::new();
+        let collecting = Collecting::new(&deps);
+        writeln!(collecting.get_output().borrow_mut(), "out").unwrap();
+        writeln!(collecting.get_error_output().borrow_mut(), "err").unwrap();
+        assert_eq!(deps.get_output_as_string(), "out\n");
+        assert_eq!(deps.get_error_output_as_string(), "");
+        assert_eq!(collecting.take_errors(), b"err\n");
+        assert!(collecting.take_errors().is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! --expr-file, which reads part of the command line from a file so long
+//! expressions can be kept, commented, somewhere other than a shell history.
+
+use std::fs;
+use std::io::{stdin, Read};
+
+use super::matchers::skip_primary;
+use super::FindError;
+use crate::words::split_words;
+
+/// Replaces each `--expr-file FILE` (or `--expr-file=FILE`) in `args` with the
+/// words read from `FILE`, or standard input if it's `-`. The words aren't
+/// expanded again, so an expression file can't name another one, and an
+/// `--expr-file` that's the argument of a primary, like `-exec echo
+/// --expr-file ;`, is left alone.
+pub(crate) fn expand_expr_files(args: &[&str]) -> Result<Vec<String>, FindError> {
+    let mut expanded = vec![];
+    let mut i = 0;
+    while i < args.len() {
+        let (file, next) = match args[i].strip_prefix("--expr-file") {
+            Some("") => match args.get(i + 1) {
+                Some(file) => (*file, i + 2),
+                None => {
+                    return Err(FindError::MissingArgument {
+                        predicate: args[i].to_owned(),
+                    })
+                }
+            },
+            Some(rest) if rest.starts_with('=') => (&rest[1..], i + 1),
+            _ => {
+                let end = skip_primary(args, i).unwrap_or(i + 1);
+                expanded.extend(args[i..end].iter().map(|arg| (*arg).to_owned()));
+                i = end;
+                continue;
+            }
+        };
+
+        let name = if file == "-" {
+            "(standard input)"
+        } else {
+            file
+        };
+        let mut text = String::new();
+        let result = if file == "-" {
+            stdin().lock().read_to_string(&mut text).map(|_| ())
+        } else {
+            fs::read_to_string(file).map(|contents| text = contents)
+        };
+        result.map_err(|source| FindError::io(name, source))?;
+
+        let words = split_words(&text).map_err(|(line, message)| {
+            FindError::invalid_argument("--expr-file", file, format!("'{name}':{line}: {message}"))
+        })?;
+        expanded.extend(words);
+        i = next;
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn expand() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "-name '*.rs' # Rust\n-o -name \"*.md\"").unwrap();
+        let path = file.path().to_string_lossy();
+
+        assert_eq!(
+            expand_expr_files(&[".", "-type", "f", "--expr-file", &path, "-print"]).unwrap(),
+            [".", "-type", "f", "-name", "*.rs", "-o", "-name", "*.md", "-print"]
+        );
+        assert_eq!(
+            expand_expr_files(&["(", &format!("--expr-file={path}"), ")"]).unwrap(),
+            ["(", "-name", "*.rs", "-o", "-name", "*.md", ")"]
+        );
+        // Arguments are left alone
+        assert_eq!(
+            expand_expr_files(&["-exec", "echo", "--expr-file", &path, ";"]).unwrap(),
+            ["-exec", "echo", "--expr-file", &path, ";"]
+        );
+        assert_eq!(
+            expand_expr_files(&["-name", "--expr-file"]).unwrap(),
+            ["-name", "--expr-file"]
+        );
+
+        assert!(matches!(
+            expand_expr_files(&["--expr-file"]),
+            Err(FindError::MissingArgument { .. })
+        ));
+        assert!(matches!(
+            expand_expr_files(&["--expr-file", "/no/such/file"]),
+            Err(FindError::Io { .. })
+        ));
+
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "-name\n'abc").unwrap();
+        let path = file.path().to_string_lossy();
+        let err = expand_expr_files(&["--expr-file", &path]).unwrap_err();
+        assert_eq!(err.to_string(), format!("'{path}':2: unterminated ' quote"));
+    }
+}
@@ -0,0 +1,370 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! --gnu-errors, which words and quotes error messages exactly like GNU find,
+//! for scripts that look for particular ones.
+
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, stderr, Write};
+use std::path::Path;
+
+use uucore::error::strip_errno;
+
+use super::matchers::WalkError;
+use super::FindError;
+
+/// The primaries whose numeric argument GNU find complains about with
+/// "invalid argument `X' to `-P'".
+const NUMERIC: &[&str] = &[
+    "-amin", "-atime", "-cmin", "-ctime", "-gid", "-inum", "-links", "-mmin", "-mtime", "-uid",
+];
+
+/// The regular expression types GNU find lists when it doesn't know one.
+const REGEX_TYPES: &[&str] = &[
+    "findutils-default",
+    "ed",
+    "emacs",
+    "gnu-awk",
+    "grep",
+    "posix-awk",
+    "awk",
+    "posix-basic",
+    "posix-egrep",
+    "egrep",
+    "posix-extended",
+    "posix-minimal-basic",
+    "sed",
+];
+
+/// Quotes file names and arguments the way gnulib's `locale_quoting_style`
+/// does, which is what GNU find uses: ‘like this’ in a UTF-8 locale, 'like
+/// this' otherwise, with backslash escapes for anything unprintable.
+pub(crate) struct Quoter {
+    utf8: bool,
+}
+
+impl Quoter {
+    /// Picks the quotes for the locale in LC_ALL, LC_CTYPE or LANG.
+    pub(crate) fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        Self {
+            utf8: locale.contains("utf-8") || locale.contains("utf8"),
+        }
+    }
+
+    pub(crate) fn quote(&self, s: impl AsRef<OsStr>) -> String {
+        let (open, close) = if self.utf8 {
+            ('\u{2018}', '\u{2019}')
+        } else {
+            ('\'', '\'')
+        };
+        let mut quoted = String::from(open);
+        let bytes = s.as_ref().as_encoded_bytes();
+        if self.utf8 {
+            for chunk in bytes.utf8_chunks() {
+                for c in chunk.valid().chars() {
+                    escape(c, close, &mut quoted);
+                }
+                chunk.invalid().iter().for_each(|b| octal(*b, &mut quoted));
+            }
+        } else {
+            for &b in bytes {
+                if b.is_ascii() {
+                    escape(char::from(b), close, &mut quoted);
+                } else {
+                    octal(b, &mut quoted);
+                }
+            }
+        }
+        quoted.push(close);
+        quoted
+    }
+}
+
+/// Adds a character to a quoted string, escaped if need be.
+fn escape(c: char, close: char, quoted: &mut String) {
+    let escaped = match c {
+        '\x07' => 'a',
+        '\x08' => 'b',
+        '\x0c' => 'f',
+        '\n' => 'n',
+        '\r' => 'r',
+        '\t' => 't',
+        '\x0b' => 'v',
+        '\\' => '\\',
+        c if c == close => c,
+        c if c.is_control() => {
+            let mut buf = [0; 4];
+            c.encode_utf8(&mut buf)
+                .bytes()
+                .for_each(|b| octal(b, quoted));
+            return;
+        }
+        c => {
+            quoted.push(c);
+            return;
+        }
+    };
+    quoted.push('\\');
+    quoted.push(escaped);
+}
+
+fn octal(b: u8, quoted: &mut String) {
+    quoted.push_str(&format!("\\{b:03o}"));
+}
+
+/// Words an error from before the search like GNU find would.
+pub(crate) fn find_error(e: &FindError) -> String {
+    let q = Quoter::from_env();
+    match e {
+        FindError::UnknownPrimary { name, .. } if name.starts_with('-') => {
+            format!("unknown predicate `{name}'")
+        }
+        FindError::UnknownPrimary { name, after, .. } => {
+            let mut message = format!("paths must precede expression: `{name}'");
+            // Most likely a wildcard the shell expanded
+            if let Some(after) = after.as_ref().filter(|_| Path::new(name).exists()) {
+                message += &format!("\npossible unquoted pattern after predicate `{after}'?");
+            }
+            message
+        }
+        FindError::ExpectedExpression { operator } => {
+            format!("expected an expression after '{operator}'")
+        }
+        FindError::ExtraOperand(operand) => format!(
+            "extra operand {}\nfile operands cannot be combined with -files0-from",
+            q.quote(operand)
+        ),
+        FindError::MissingArgument { predicate } => format!("missing argument to `{predicate}'"),
+        FindError::InvalidArgument {
+            predicate, value, ..
+        } => invalid_argument(&q, predicate, value).unwrap_or_else(|| e.to_string()),
+        FindError::Io {
+            context,
+            path: Some(path),
+            source,
+        } => format!(
+            "{}: {}",
+            context.replacen(&format!("'{path}'"), &q.quote(path), 1),
+            strip_errno(source)
+        ),
+        _ => e.to_string(),
+    }
+}
+
+/// Words GNU find's complaint about an argument, for the primaries where it's
+/// different from ours.
+fn invalid_argument(q: &Quoter, predicate: &str, value: &str) -> Option<String> {
+    let message = match predicate {
+        "-maxdepth" | "-mindepth" => format!(
+            "Expected a positive decimal integer argument to {predicate}, but got {}",
+            q.quote(value)
+        ),
+        "-type" | "-xtype" => return type_error(predicate, value),
+        "-size" => match value.chars().last() {
+            None => String::from("invalid null argument to -size"),
+            Some(c) if !c.is_ascii_digit() && !"bcwkMG".contains(c) => {
+                format!("invalid -size type `{c}'")
+            }
+            _ => format!("invalid argument `{value}' to `{predicate}'"),
+        },
+        "-user" if !value.is_empty() => {
+            format!("{} is not the name of a known user", q.quote(value))
+        }
+        "-group" if value.is_empty() => {
+            String::from("argument to -group is empty, but should be a group name")
+        }
+        "-group" => format!("{} is not the name of an existing group", q.quote(value)),
+        "-perm" => format!("invalid mode {}", q.quote(value)),
+        "-regextype" => format!(
+            "Unknown regular expression type {}; valid types are {}.",
+            q.quote(value),
+            REGEX_TYPES
+                .iter()
+                .map(|t| q.quote(t))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        "-newerat" | "-newerBt" | "-newerct" | "-newermt" => format!(
+            "I cannot figure out how to interpret {} as a date or time",
+            q.quote(value)
+        ),
+        _ if NUMERIC.contains(&predicate) => {
+            format!("invalid argument `{value}' to `{predicate}'")
+        }
+        _ => return None,
+    };
+    Some(message)
+}
+
+/// GNU find checks the letters in a -type list one at a time, and says
+/// what's wrong with the first bad one. It's `None` for lists that are only
+/// wrong because we can't handle more than one type.
+fn type_error(predicate: &str, types: &str) -> Option<String> {
+    if types.is_empty() {
+        return Some(format!(
+            "Arguments to {predicate} should contain at least one letter"
+        ));
+    }
+    let mut seen = vec![];
+    let mut chars = types.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == 'D' {
+            return Some(format!(
+                "{predicate} D is not supported because Solaris doors are not supported on \
+                 the platform find was compiled on."
+            ));
+        }
+        if !"bcdpfls".contains(c) {
+            return Some(format!("Unknown argument to {predicate}: {c}"));
+        }
+        if seen.contains(&c) {
+            return Some(format!(
+                "Duplicate file type '{c}' in the argument list to {predicate}."
+            ));
+        }
+        seen.push(c);
+        match chars.next() {
+            None => {}
+            Some(',') if chars.peek().is_none() => {
+                return Some(format!(
+                    "Last file type in list argument to {predicate} is missing, i.e., list is \
+                     ending on: ','"
+                ));
+            }
+            Some(',') => {}
+            Some(_) => {
+                return Some(format!(
+                    "Must separate multiple arguments to {predicate} using: ','"
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// Words an error from during the search like GNU find would.
+pub(crate) fn walk_error(e: &WalkError) -> String {
+    let q = Quoter::from_env();
+    if let (Some(path), Some(ancestor)) = (e.path(), e.ancestor()) {
+        return format!(
+            "File system loop detected; {} is part of the same file system loop as {}.",
+            q.quote(path),
+            q.quote(ancestor)
+        );
+    }
+
+    let ioe = strip_errno(&io::Error::from(e));
+    match e.path() {
+        Some(path) => format!("{}: {ioe}", q.quote(path)),
+        None => ioe,
+    }
+}
+
+/// Prints an error like GNU find does, with each line starting `find: `.
+pub(crate) fn print(message: &str) {
+    let mut stderr = stderr().lock();
+    for line in message.lines() {
+        writeln!(stderr, "find: {line}").unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const C: Quoter = Quoter { utf8: false };
+    const UTF8: Quoter = Quoter { utf8: true };
+
+    #[test]
+    fn quote() {
+        assert_eq!(C.quote("it's"), r"'it\'s'");
+        assert_eq!(UTF8.quote("it's"), "‘it's’");
+        assert_eq!(C.quote("a\nb"), r"'a\nb'");
+        assert_eq!(C.quote(r"a\b"), r"'a\\b'");
+        assert_eq!(C.quote("\x01"), r"'\001'");
+        assert_eq!(C.quote("é"), r"'\303\251'");
+        assert_eq!(UTF8.quote("é"), "‘é’");
+        assert_eq!(UTF8.quote("‘x’"), "‘‘x\\’’");
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            assert_eq!(UTF8.quote(OsStr::from_bytes(b"a\xffb")), r"‘a\377b’");
+        }
+    }
+
+    #[test]
+    fn type_errors() {
+        assert_eq!(
+            type_error("-type", "q").as_deref(),
+            Some("Unknown argument to -type: q")
+        );
+        assert_eq!(
+            type_error("-type", "f,q").as_deref(),
+            Some("Unknown argument to -type: q")
+        );
+        assert_eq!(
+            type_error("-xtype", "ff").as_deref(),
+            Some("Must separate multiple arguments to -xtype using: ','")
+        );
+        assert_eq!(
+            type_error("-type", "f,f").as_deref(),
+            Some("Duplicate file type 'f' in the argument list to -type.")
+        );
+        assert!(type_error("-type", "f,")
+            .unwrap()
+            .starts_with("Last file type"));
+        assert!(type_error("-type", "").is_some());
+        assert_eq!(type_error("-type", "f,d"), None);
+    }
+
+    #[test]
+    fn find_errors() {
+        let unknown = |name: &str| FindError::UnknownPrimary {
+            name: name.to_owned(),
+            after: Some("-name".to_owned()),
+            suggestion: None,
+        };
+        assert_eq!(find_error(&unknown("-foo")), "unknown predicate `-foo'");
+        assert_eq!(
+            find_error(&unknown("foo")),
+            "paths must precede expression: `foo'"
+        );
+        assert_eq!(
+            find_error(&unknown("src")),
+            "paths must precede expression: `src'\n\
+             possible unquoted pattern after predicate `-name'?"
+        );
+        assert_eq!(
+            find_error(&FindError::MissingArgument {
+                predicate: "-exec".to_owned()
+            }),
+            "missing argument to `-exec'"
+        );
+        assert_eq!(
+            find_error(&FindError::invalid_argument("-mtime", "x", "bad")),
+            "invalid argument `x' to `-mtime'"
+        );
+        assert_eq!(
+            find_error(&FindError::invalid_argument("-size", "3q", "bad")),
+            "invalid -size type `q'"
+        );
+        // Anything GNU find doesn't have is left alone
+        assert_eq!(
+            find_error(&FindError::invalid_argument("-sparse", "x", "bad")),
+            "bad"
+        );
+        assert_eq!(
+            find_error(&FindError::Parse("you have too many ')'".to_owned())),
+            "you have too many ')'"
+        );
+    }
+}
@@ -4,6 +4,7 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+#[cfg(not(unix))]
 use faccess::PathExt;
 
 use super::{Matcher, MatcherIO, WalkEntry};
@@ -16,6 +17,34 @@ pub enum AccessMatcher {
 }
 
 impl Matcher for AccessMatcher {
+    /// Asks the OS whether the test would pass, using faccessat() with the
+    /// effective user and group IDs, so that ACLs, read-only mounts and root's
+    /// special powers are all taken into account.
+    #[cfg(unix)]
+    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+        use nix::fcntl::AtFlags;
+        use nix::unistd::{faccessat, AccessFlags};
+
+        let mode = match self {
+            Self::Readable => AccessFlags::R_OK,
+            Self::Writable => AccessFlags::W_OK,
+            Self::Executable => AccessFlags::X_OK,
+        };
+
+        #[cfg(not(target_os = "android"))]
+        let flags = AtFlags::AT_EACCESS;
+        #[cfg(target_os = "android")]
+        let flags = AtFlags::empty();
+
+        match file_info.parent_fd() {
+            Some(fd) => faccessat(Some(fd), file_info.file_name(), mode, flags).is_ok(),
+            None => faccessat(None, file_info.path(), mode, flags).is_ok(),
+        }
+    }
+
+    /// On Windows this uses AccessCheck() against the file's security
+    /// descriptor.
+    #[cfg(not(unix))]
     fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
         let path = file_info.path();
 
@@ -55,4 +84,34 @@ mod tests {
             "file should not be executable"
         );
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn access_matcher_uses_effective_ids() {
+        use crate::find::walk::Walker;
+        use std::fs::{set_permissions, File, Permissions};
+        use std::os::unix::fs::PermissionsExt;
+        use tempfile::Builder;
+
+        let temp_dir = Builder::new().prefix("access_matcher").tempdir().unwrap();
+        let path = temp_dir.path().join("file");
+        File::create(&path).unwrap();
+        set_permissions(&path, Permissions::from_mode(0o100)).unwrap();
+
+        let deps = FakeDependencies::new();
+        let root = nix::unistd::geteuid().is_root();
+        for file_info in Walker::new(temp_dir.path()).min_depth(1) {
+            let file_info = file_info.unwrap();
+            // The permission bits don't tell the whole story for root
+            assert_eq!(
+                AccessMatcher::Readable.matches(&file_info, &mut deps.new_matcher_io()),
+                root
+            );
+            assert_eq!(
+                AccessMatcher::Writable.matches(&file_info, &mut deps.new_matcher_io()),
+                root
+            );
+            assert!(AccessMatcher::Executable.matches(&file_info, &mut deps.new_matcher_io()));
+        }
+    }
 }
@@ -0,0 +1,73 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// The destination for `-log FILE`, which records every action with side
+/// effects that find carries out.
+///
+/// Each action is written as a single tab-separated line, like
+///
+/// ```text
+/// 2024-01-02T03:04:05.678Z<TAB>delete<TAB>"./some/file"<TAB>ok
+/// ```
+///
+/// i.e. the time (UTC, RFC 3339), the action, the quoted path and the result.
+/// Paths are quoted and escaped so that tabs or newlines in file names can't
+/// break up a record.
+pub struct ActionLog {
+    file: RefCell<File>,
+}
+
+impl ActionLog {
+    pub fn new(file: File) -> Self {
+        Self {
+            file: RefCell::new(file),
+        }
+    }
+
+    /// Records that `action` was carried out on `path`, with the given result.
+    pub fn record(&self, action: &str, path: &Path, result: &str) -> io::Result<()> {
+        let line = format_record(SystemTime::now(), action, path, result);
+        let mut file = self.file.borrow_mut();
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
+
+fn format_record(time: SystemTime, action: &str, path: &Path, result: &str) -> String {
+    let time = DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Millis, true);
+    format!(
+        "{time}\t{action}\t{:?}\t{}\n",
+        path.to_string_lossy(),
+        result.escape_debug()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_format_record() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(
+            format_record(time, "delete", Path::new("./a/b"), "ok"),
+            "2023-11-14T22:13:20.123Z\tdelete\t\"./a/b\"\tok\n"
+        );
+        assert_eq!(
+            format_record(time, "exec", Path::new("./tab\there\n"), "exit status 1"),
+            "2023-11-14T22:13:20.123Z\texec\t\"./tab\\there\\n\"\texit status 1\n"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! -cap [CAPS], which finds files with Linux file capabilities, like the ones
+//! setcap gives to ping.
+
+use std::error::Error;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use uucore::libc;
+
+use super::{Matcher, MatcherIO, WalkEntry};
+
+/// The capabilities Linux knows about, in order, without their `cap_` prefix.
+const NAMES: &[&str] = &[
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+/// The revisions of `struct vfs_cap_data`, in the top byte of its first word.
+const REVISION_MASK: u32 = 0xff00_0000;
+const REVISION_1: u32 = 0x0100_0000;
+const REVISION_2: u32 = 0x0200_0000;
+/// Like revision 2, with the root user ID of a user namespace on the end.
+const REVISION_3: u32 = 0x0300_0000;
+
+/// This matcher checks the `security.capability` extended attribute that
+/// holds a file's capabilities. With no capabilities given it matches any
+/// file that has some, otherwise only files that have all of them, i.e.
+/// they're in its permitted or inheritable set, which is what getcap shows.
+pub struct CapabilityMatcher {
+    /// A bit for each capability we need, or none for any at all.
+    required: u64,
+}
+
+impl CapabilityMatcher {
+    /// Creates a matcher for a comma-separated list of capability names like
+    /// `cap_net_raw,cap_setuid`, ignoring case, or any capabilities if there's
+    /// no list.
+    pub fn new(names: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let mut required = 0;
+        for name in names.into_iter().flat_map(|names| names.split(',')) {
+            let lower = name.to_ascii_lowercase();
+            let bare = lower.strip_prefix("cap_").unwrap_or(&lower);
+            let bit = NAMES
+                .iter()
+                .position(|known| *known == bare)
+                .ok_or_else(|| format!("unknown capability '{name}'"))?;
+            required |= 1 << bit;
+        }
+        Ok(Self { required })
+    }
+}
+
+impl Matcher for CapabilityMatcher {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        // Symbolic links can't have capabilities of their own
+        if file_info.file_type().is_symlink() {
+            return false;
+        }
+        match read_capabilities(file_info.path()) {
+            Ok(Some(caps)) if self.required == 0 => caps != 0,
+            Ok(Some(caps)) => caps & self.required == self.required,
+            Ok(None) => false,
+            Err(e) => {
+                writeln!(
+                    matcher_io.error_output(),
+                    "Error getting capabilities for {}: {}",
+                    file_info.path().to_string_lossy(),
+                    e
+                )
+                .unwrap();
+                false
+            }
+        }
+    }
+}
+
+/// Reads a file's capabilities, with a bit for each one it has, or `None` if
+/// it has none (or the file system doesn't support them).
+fn read_capabilities(path: &Path) -> io::Result<Option<u64>> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    // Big enough for revision 3
+    let mut data = [0u8; 24];
+    // SAFETY: both names are NUL-terminated, and the buffer is as big as
+    // we say it is
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            c"security.capability".as_ptr(),
+            data.as_mut_ptr().cast(),
+            data.len(),
+        )
+    };
+    if len < 0 {
+        let e = io::Error::last_os_error();
+        return match e.raw_os_error() {
+            Some(libc::ENODATA | libc::ENOTSUP) => Ok(None),
+            _ => Err(e),
+        };
+    }
+    Ok(decode(&data[..len as usize]))
+}
+
+/// Decodes a `struct vfs_cap_data`, which is a little-endian magic number
+/// giving its revision, then the permitted and inheritable sets for each
+/// 32 capabilities.
+fn decode(data: &[u8]) -> Option<u64> {
+    let word = |i: usize| {
+        let bytes = data.get(4 * i..4 * i + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let words = match word(0)? & REVISION_MASK {
+        REVISION_1 => 1,
+        REVISION_2 | REVISION_3 => 2,
+        _ => return None,
+    };
+    let mut caps = 0;
+    for i in 0..words {
+        let permitted = word(1 + 2 * i)?;
+        let inheritable = word(2 + 2 * i)?;
+        caps |= u64::from(permitted | inheritable) << (32 * i);
+    }
+    Some(caps)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::find::matchers::tests::get_dir_entry_for;
+    use crate::find::tests::FakeDependencies;
+
+    /// Encodes capabilities as revision 2 data, which is what setcap writes.
+    fn encode(permitted: u64, inheritable: u64) -> Vec<u8> {
+        let mut data = REVISION_2.to_le_bytes().to_vec();
+        for i in 0..2 {
+            data.extend((((permitted >> (32 * i)) & 0xffff_ffff) as u32).to_le_bytes());
+            data.extend((((inheritable >> (32 * i)) & 0xffff_ffff) as u32).to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(NAMES.len(), 41);
+        let required = |names| CapabilityMatcher::new(names).unwrap().required;
+        assert_eq!(required(None), 0);
+        assert_eq!(required(Some("cap_chown")), 1);
+        assert_eq!(required(Some("CAP_NET_RAW,setuid")), 1 << 13 | 1 << 7);
+        assert_eq!(required(Some("cap_checkpoint_restore")), 1 << 40);
+
+        let err = CapabilityMatcher::new(Some("cap_setuid,cap_fly"))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "unknown capability 'cap_fly'");
+        assert!(CapabilityMatcher::new(Some("cap_setuid,")).is_err());
+    }
+
+    #[test]
+    fn decode_revisions() {
+        assert_eq!(decode(&encode(1 << 13, 0)), Some(1 << 13));
+        assert_eq!(decode(&encode(1, 1 << 40)), Some(1 | 1 << 40));
+
+        let mut v1 = (REVISION_1 | 1).to_le_bytes().to_vec();
+        v1.extend(0x80u32.to_le_bytes());
+        v1.extend(0u32.to_le_bytes());
+        assert_eq!(decode(&v1), Some(0x80));
+
+        let mut v3 = (REVISION_3).to_le_bytes().to_vec();
+        v3.extend(encode(0, 1 << 33).into_iter().skip(4));
+        v3.extend(1000u32.to_le_bytes());
+        assert_eq!(decode(&v3), Some(1 << 33));
+
+        assert_eq!(decode(&[]), None);
+        assert_eq!(decode(&encode(1, 0)[..8]), None);
+        assert_eq!(decode(&0x0400_0000u32.to_le_bytes()), None);
+    }
+
+    #[test]
+    fn capability_matcher() {
+        let temp_dir = Builder::new()
+            .prefix("capability_matcher")
+            .tempdir()
+            .unwrap();
+        let path = temp_dir.path().join("ping");
+        File::create(&path).unwrap();
+        let entry = get_dir_entry_for(&temp_dir.path().to_string_lossy(), "ping");
+        let deps = FakeDependencies::new();
+        let any = CapabilityMatcher::new(None).unwrap();
+        let net_raw = CapabilityMatcher::new(Some("cap_net_raw")).unwrap();
+        let both = CapabilityMatcher::new(Some("cap_net_raw,cap_setuid")).unwrap();
+
+        assert!(!any.matches(&entry, &mut deps.new_matcher_io()));
+
+        // Setting capabilities takes privileges we may not have
+        let data = encode(1 << 13, 0);
+        let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        // SAFETY: as in read_capabilities
+        let ret = unsafe {
+            libc::setxattr(
+                c_path.as_ptr(),
+                c"security.capability".as_ptr(),
+                data.as_ptr().cast(),
+                data.len(),
+                0,
+            )
+        };
+        if ret != 0 {
+            return;
+        }
+        assert!(any.matches(&entry, &mut deps.new_matcher_io()));
+        assert!(net_raw.matches(&entry, &mut deps.new_matcher_io()));
+        assert!(!both.matches(&entry, &mut deps.new_matcher_io()));
+    }
+}
@@ -0,0 +1,159 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! -chmod MODE, which changes the permissions of matches without having to
+//! run chmod for each of them.
+
+use std::error::Error;
+use std::fs::{self, Permissions};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+use uucore::mode::{get_umask, parse_numeric, parse_symbolic};
+
+use super::{Matcher, MatcherIO, WalkEntry};
+
+/// This matcher changes the permissions of each file like `chmod MODE` would,
+/// where MODE is octal or symbolic, e.g. `u+x,go-w`. Symbolic links that
+/// aren't followed are left alone, as `chmod -R` does, since changing them
+/// would change what they point to instead. It's true if the file ends up
+/// with the new permissions.
+pub struct ChmodMatcher {
+    mode: String,
+    /// For symbolic modes that don't say who they're for, like `+x`.
+    umask: u32,
+}
+
+impl ChmodMatcher {
+    pub fn new(mode: &str) -> Result<Self, Box<dyn Error>> {
+        let matcher = Self {
+            mode: mode.to_owned(),
+            umask: get_umask(),
+        };
+        // Catch mistakes before the search starts
+        matcher
+            .apply(0, false)
+            .map_err(|e| format!("invalid mode '{mode}': {e}"))?;
+        Ok(matcher)
+    }
+
+    /// Works out the new permissions for a file with the given ones.
+    fn apply(&self, current: u32, is_dir: bool) -> Result<u32, String> {
+        if self.mode.contains(|c: char| c.is_ascii_digit()) {
+            return parse_numeric(current, &self.mode, is_dir);
+        }
+        self.mode.split(',').try_fold(current, |mode, chunk| {
+            parse_symbolic(mode, chunk, self.umask, is_dir)
+        })
+    }
+}
+
+impl Matcher for ChmodMatcher {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let path = file_info.path();
+        let path_str = path.to_string_lossy();
+        if file_info.file_type().is_symlink() {
+            return false;
+        }
+
+        let result = file_info
+            .metadata()
+            .map_err(|e| e.to_string())
+            .and_then(|meta| {
+                let current = meta.mode() & 0o7777;
+                Ok((
+                    current,
+                    self.apply(current, file_info.file_type().is_dir())?,
+                ))
+            });
+        let (current, mode) = match result {
+            Ok(modes) => modes,
+            Err(e) => {
+                matcher_io.set_exit_code(1);
+                writeln!(matcher_io.error_output(), "Failed to chmod {path_str}: {e}").unwrap();
+                return false;
+            }
+        };
+
+        if matcher_io.is_dry_run() {
+            let mut out = matcher_io.deps.get_output().borrow_mut();
+            writeln!(out, "would chmod: {path_str} to {mode:04o}").unwrap();
+            drop(out);
+            matcher_io.log_action("chmod", path, "dry-run");
+            return true;
+        }
+
+        if mode == current {
+            return true;
+        }
+        match fs::set_permissions(path, Permissions::from_mode(mode)) {
+            Ok(()) => {
+                matcher_io.log_action("chmod", path, "ok");
+                true
+            }
+            Err(e) => {
+                matcher_io.log_action("chmod", path, &format!("failed: {e}"));
+                matcher_io.set_exit_code(1);
+                writeln!(matcher_io.error_output(), "Failed to chmod {path_str}: {e}").unwrap();
+                false
+            }
+        }
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::find::matchers::tests::get_dir_entry_for;
+    use crate::find::tests::FakeDependencies;
+
+    #[test]
+    fn modes() {
+        let matcher = |mode| ChmodMatcher {
+            mode: String::from(mode),
+            umask: 0o022,
+        };
+        assert_eq!(matcher("755").apply(0o644, false), Ok(0o755));
+        assert_eq!(matcher("u+x,go-r").apply(0o644, false), Ok(0o700));
+        assert_eq!(matcher("a=r").apply(0o644, false), Ok(0o444));
+        // Without anyone to say who it's for, the umask applies
+        assert_eq!(matcher("+w").apply(0o444, false), Ok(0o644));
+        // X is only for directories, or what's executable already
+        assert_eq!(matcher("a+X").apply(0o644, false), Ok(0o644));
+        assert_eq!(matcher("a+X").apply(0o644, true), Ok(0o755));
+
+        assert!(ChmodMatcher::new("u+q").is_err());
+        assert!(ChmodMatcher::new("778").is_err());
+    }
+
+    #[test]
+    fn chmod_matcher() {
+        let temp_dir = Builder::new().prefix("chmod_matcher").tempdir().unwrap();
+        let path = temp_dir.path().join("file");
+        File::create(&path).unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o600)).unwrap();
+        let entry = get_dir_entry_for(&temp_dir.path().to_string_lossy(), "file");
+        let deps = FakeDependencies::new();
+
+        let matcher = ChmodMatcher::new("g+r").unwrap();
+        let mut matcher_io = deps.new_matcher_io();
+        matcher_io.set_dry_run(true);
+        assert!(matcher.matches(&entry, &mut matcher_io));
+        assert_eq!(
+            deps.get_output_as_string(),
+            format!("would chmod: {} to 0640\n", path.to_string_lossy())
+        );
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o7777, 0o600);
+
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+        assert_eq!(fs::metadata(&path).unwrap().mode() & 0o7777, 0o640);
+    }
+}
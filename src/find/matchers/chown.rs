@@ -0,0 +1,163 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! -chown USER[:GROUP], which changes the owner of matches without having to
+//! run chown for each of them.
+
+use std::error::Error;
+use std::os::unix::fs::{chown, lchown, MetadataExt};
+
+use nix::unistd::{Group, User};
+
+use super::{Matcher, MatcherIO, WalkEntry};
+
+/// This matcher changes the owner and/or group of each file like
+/// `chown -h` would, so symbolic links that aren't followed are changed
+/// themselves. The spec is `USER`, `USER:GROUP`, `USER:` for the user's login
+/// group, or `:GROUP`. It's true if the file ends up with the new owner.
+pub struct ChownMatcher {
+    spec: String,
+    uid: Option<u32>,
+    gid: Option<u32>,
+}
+
+impl ChownMatcher {
+    pub fn new(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let (user, group) = match spec.split_once(':') {
+            Some((user, group)) => (user, Some(group)),
+            None => (spec, None),
+        };
+
+        // Like chown, names take precedence over numeric IDs
+        let mut login_group = None;
+        let uid = if user.is_empty() {
+            None
+        } else {
+            let uid = match User::from_name(user).ok().flatten() {
+                Some(user) => {
+                    login_group = Some(user.gid.as_raw());
+                    Some(user.uid.as_raw())
+                }
+                None => user.parse().ok(),
+            };
+            Some(uid.ok_or_else(|| format!("invalid user: '{user}'"))?)
+        };
+        let gid = match group {
+            None => None,
+            Some("") if uid.is_some() => {
+                Some(login_group.ok_or_else(|| format!("no login group for user '{user}'"))?)
+            }
+            Some(group) => Some(
+                Group::from_name(group)
+                    .ok()
+                    .flatten()
+                    .map(|group| group.gid.as_raw())
+                    .or_else(|| group.parse().ok())
+                    .ok_or_else(|| format!("invalid group: '{group}'"))?,
+            ),
+        };
+        if uid.is_none() && gid.is_none() {
+            return Err(From::from("expected USER[:GROUP] or :GROUP"));
+        }
+
+        Ok(Self {
+            spec: spec.to_owned(),
+            uid,
+            gid,
+        })
+    }
+}
+
+impl Matcher for ChownMatcher {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let path = file_info.path();
+        let path_str = path.to_string_lossy();
+
+        if matcher_io.is_dry_run() {
+            let mut out = matcher_io.deps.get_output().borrow_mut();
+            writeln!(out, "would chown: {path_str} to {}", self.spec).unwrap();
+            drop(out);
+            matcher_io.log_action("chown", path, "dry-run");
+            return true;
+        }
+
+        let unchanged = file_info.metadata().is_ok_and(|meta| {
+            self.uid.is_none_or(|uid| uid == meta.uid())
+                && self.gid.is_none_or(|gid| gid == meta.gid())
+        });
+        if unchanged {
+            return true;
+        }
+
+        let result = if file_info.file_type().is_symlink() {
+            lchown(path, self.uid, self.gid)
+        } else {
+            chown(path, self.uid, self.gid)
+        };
+        match result {
+            Ok(()) => {
+                matcher_io.log_action("chown", path, "ok");
+                true
+            }
+            Err(e) => {
+                matcher_io.log_action("chown", path, &format!("failed: {e}"));
+                matcher_io.set_exit_code(1);
+                writeln!(matcher_io.error_output(), "Failed to chown {path_str}: {e}").unwrap();
+                false
+            }
+        }
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::find::matchers::tests::get_dir_entry_for;
+    use crate::find::tests::FakeDependencies;
+
+    #[test]
+    fn parse_spec() {
+        let root = ChownMatcher::new("root:").unwrap();
+        assert_eq!((root.uid, root.gid), (Some(0), Some(0)));
+        let ids = ChownMatcher::new("12345:678").unwrap();
+        assert_eq!((ids.uid, ids.gid), (Some(12345), Some(678)));
+        let group = ChownMatcher::new(":0").unwrap();
+        assert_eq!((group.uid, group.gid), (None, Some(0)));
+
+        for spec in ["", ":", "no-such-user", "0:no-such-group", "12345:"] {
+            assert!(ChownMatcher::new(spec).is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn chown_matcher() {
+        let temp_dir = Builder::new().prefix("chown_matcher").tempdir().unwrap();
+        let path = temp_dir.path().join("file");
+        File::create(&path).unwrap();
+        let meta = fs::metadata(&path).unwrap();
+        let entry = get_dir_entry_for(&temp_dir.path().to_string_lossy(), "file");
+        let deps = FakeDependencies::new();
+
+        // Anyone can give a file to themselves
+        let spec = format!("{}:{}", meta.uid(), meta.gid());
+        let matcher = ChownMatcher::new(&spec).unwrap();
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+
+        let mut matcher_io = deps.new_matcher_io();
+        matcher_io.set_dry_run(true);
+        assert!(matcher.matches(&entry, &mut matcher_io));
+        assert_eq!(
+            deps.get_output_as_string(),
+            format!("would chown: {} to {spec}\n", path.to_string_lossy())
+        );
+    }
+}
@@ -0,0 +1,192 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Colored paths for -print (--color), using the same LS_COLORS as ls.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::Metadata;
+use std::rc::Rc;
+
+use super::printer::Printer;
+use super::{FileType, Matcher, MatcherIO, WalkEntry};
+
+/// The colors ls uses when LS_COLORS doesn't say otherwise.
+const DEFAULT_COLORS: &str = "di=01;34:ln=01;36:pi=33:so=01;35:do=01;35:bd=01;33:cd=01;33:\
+                              ex=01;32:su=37;41:sg=30;43:st=37;44:ow=34;42:tw=30;42";
+
+/// The colors from LS_COLORS, e.g. `di=01;34:*.tar=01;31`.
+#[derive(Debug, Default)]
+pub struct LsColors {
+    /// SGR sequences for the kinds of file, by their two-letter key.
+    kinds: HashMap<String, String>,
+    /// SGR sequences for file names ending in a suffix, from `*.tar` and the
+    /// like. The suffixes are lowercase, as they're matched ignoring case.
+    suffixes: Vec<(String, String)>,
+}
+
+impl LsColors {
+    /// Reads the colors from the LS_COLORS environment variable, on top of the
+    /// defaults.
+    pub fn from_env() -> Self {
+        let mut colors = Self::parse(DEFAULT_COLORS);
+        if let Ok(spec) = env::var("LS_COLORS") {
+            colors.extend(&spec);
+        }
+        colors
+    }
+
+    /// Parses an LS_COLORS value. Anything we don't understand is ignored,
+    /// like ls does.
+    pub fn parse(spec: &str) -> Self {
+        let mut colors = Self::default();
+        colors.extend(spec);
+        colors
+    }
+
+    fn extend(&mut self, spec: &str) {
+        for (key, value) in spec.split(':').filter_map(|entry| entry.split_once('=')) {
+            if let Some(suffix) = key.strip_prefix('*') {
+                let suffix = suffix.to_lowercase();
+                self.suffixes.retain(|(s, _)| *s != suffix);
+                self.suffixes.push((suffix, value.to_owned()));
+            } else {
+                self.kinds.insert(key.to_owned(), value.to_owned());
+            }
+        }
+    }
+
+    fn kind(&self, key: &str) -> Option<&str> {
+        self.kinds
+            .get(key)
+            .map(String::as_str)
+            .filter(|color| !color.is_empty())
+    }
+
+    /// The longest suffix that `name` ends in, ignoring case.
+    fn suffix(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.suffixes
+            .iter()
+            .filter(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .max_by_key(|(suffix, _)| suffix.len())
+            .map(|(_, color)| color.as_str())
+    }
+
+    /// Picks the color for an entry, the same way ls would.
+    pub fn color(&self, file_info: &WalkEntry) -> Option<&str> {
+        let name = file_info.file_name().to_string_lossy();
+        if file_info.file_type().is_symlink() {
+            return match file_info.target_metadata() {
+                // ln=target colors links like what they point to
+                Ok(meta) if self.kind("ln") == Some("target") => {
+                    self.classify(meta.file_type().into(), Some(meta), &name)
+                }
+                Ok(_) => self.kind("ln"),
+                Err(_) => self.kind("or").or_else(|| self.kind("ln")),
+            }
+            .filter(|&color| color != "target");
+        }
+        self.classify(file_info.file_type(), file_info.metadata().ok(), &name)
+    }
+
+    fn classify(&self, file_type: FileType, meta: Option<&Metadata>, name: &str) -> Option<&str> {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::MetadataExt;
+            meta.map_or(0, MetadataExt::mode)
+        };
+        #[cfg(not(unix))]
+        let mode = {
+            let _ = meta;
+            0
+        };
+        let has = |bits: u32, key: &str| (mode & bits == bits).then(|| self.kind(key)).flatten();
+
+        match file_type {
+            FileType::Directory => has(0o1002, "tw")
+                .or_else(|| has(0o0002, "ow"))
+                .or_else(|| has(0o1000, "st"))
+                .or_else(|| self.kind("di")),
+            FileType::Regular => has(0o4000, "su")
+                .or_else(|| has(0o2000, "sg"))
+                .or_else(|| (mode & 0o111 != 0).then(|| self.kind("ex")).flatten())
+                .or_else(|| self.suffix(name))
+                .or_else(|| self.kind("fi")),
+            FileType::Symlink => self.kind("ln"),
+            FileType::Fifo => self.kind("pi"),
+            FileType::Socket => self.kind("so"),
+            FileType::BlockDevice => self.kind("bd"),
+            FileType::CharDevice => self.kind("cd"),
+            FileType::Unknown => None,
+        }
+    }
+}
+
+/// Decorates a [Printer], coloring each path according to [LsColors].
+pub struct Colorize {
+    printer: Printer,
+    colors: Rc<LsColors>,
+}
+
+impl Colorize {
+    pub fn new(printer: Printer, colors: Rc<LsColors>) -> Self {
+        Self { printer, colors }
+    }
+}
+
+impl Matcher for Colorize {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let path = self.printer.path(file_info);
+        match self.colors.color(file_info) {
+            Some(color) => {
+                let painted = format!("\x1b[{color}m{path}\x1b[0m");
+                self.printer.print_path(file_info, &painted, matcher_io)
+            }
+            None => self.printer.print_path(file_info, &path, matcher_io),
+        }
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find::matchers::printer::PrintDelimiter;
+    use crate::find::matchers::tests::get_dir_entry_for;
+    use crate::find::tests::{fix_up_slashes, FakeDependencies};
+
+    #[test]
+    fn parse_ls_colors() {
+        let colors = LsColors::parse("di=01;34:fi=:*.TAR=01;31:*.tar.gz=01;35:bogus:*.tar=31");
+        assert_eq!(colors.kind("di"), Some("01;34"));
+        // Empty colors mean no color at all
+        assert_eq!(colors.kind("fi"), None);
+        assert_eq!(colors.kind("ln"), None);
+        // Later suffixes win, and the longest match wins
+        assert_eq!(colors.suffix("a.Tar"), Some("31"));
+        assert_eq!(colors.suffix("a.tar.gz"), Some("01;35"));
+        assert_eq!(colors.suffix("a.gz"), None);
+    }
+
+    #[test]
+    fn colorize() {
+        let colors = Rc::new(LsColors::parse("di=01;34:*.c=33"));
+        let dir = get_dir_entry_for("./test_data", "simple");
+        let file = get_dir_entry_for("./test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+
+        let matcher = Colorize::new(Printer::new(PrintDelimiter::Newline, None), colors);
+        assert!(matcher.matches(&dir, &mut deps.new_matcher_io()));
+        assert!(matcher.matches(&file, &mut deps.new_matcher_io()));
+        assert_eq!(
+            deps.get_output_as_string(),
+            fix_up_slashes("\x1b[01;34m./test_data/simple\x1b[0m\n./test_data/simple/abbbc\n")
+        );
+    }
+}
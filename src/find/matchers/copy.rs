@@ -0,0 +1,402 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! -copy-to DIR and -move-to DIR, which put matches in another directory,
+//! keeping their paths relative to the starting point.
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, stderr, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use uucore::fs::FileInformation;
+
+use super::printf::get_starting_point;
+use super::{Matcher, MatcherIO, WalkEntry};
+
+/// What to do when there's already something where a file would go
+/// (--collision).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collision {
+    /// Leave it alone, and report an error.
+    #[default]
+    Error,
+    /// Leave it alone, quietly.
+    Skip,
+    /// Replace it.
+    Overwrite,
+}
+
+impl FromStr for Collision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Self::Error),
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            _ => Err("must be one of 'error', 'skip', 'overwrite'".to_owned()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    Copy,
+    Move,
+}
+
+impl Transfer {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Move => "move",
+        }
+    }
+}
+
+/// What happened to an entry.
+enum Outcome {
+    Done,
+    /// Something was already there, and --collision says to leave it.
+    Skipped,
+    /// A whole directory was moved, so there's nothing left in it to search.
+    MovedTree,
+}
+
+/// This matcher copies or moves each entry into a destination directory,
+/// under the same path relative to its starting point, creating directories
+/// as needed. A starting point that's a directory corresponds to the
+/// destination itself. It's true if the entry was copied or moved.
+pub struct CopyMatcher {
+    transfer: Transfer,
+    dest: PathBuf,
+    collision: Collision,
+    /// The destination, once it exists, so that the search can leave it out.
+    dest_info: RefCell<Option<FileInformation>>,
+    /// Directories whose contents were moved one by one, to be removed once
+    /// the search is over if that left them empty.
+    merged: RefCell<Vec<PathBuf>>,
+}
+
+impl CopyMatcher {
+    pub fn new(transfer: Transfer, dest: impl Into<PathBuf>, collision: Collision) -> Self {
+        Self {
+            transfer,
+            dest: dest.into(),
+            collision,
+            dest_info: RefCell::new(None),
+            merged: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Whether `file_info` is the destination directory.
+    fn is_dest(&self, file_info: &WalkEntry) -> bool {
+        let mut dest_info = self.dest_info.borrow_mut();
+        if dest_info.is_none() {
+            *dest_info = FileInformation::from_path(&self.dest, true).ok();
+        }
+        dest_info.as_ref().is_some_and(|dest_info| {
+            FileInformation::from_path(file_info.path(), file_info.follow())
+                .is_ok_and(|info| info == *dest_info)
+        })
+    }
+
+    /// Works out where an entry goes.
+    fn target(&self, file_info: &WalkEntry) -> PathBuf {
+        let path = file_info.path();
+        let relative = path
+            .strip_prefix(get_starting_point(file_info))
+            .unwrap_or(path);
+        if !relative.as_os_str().is_empty() {
+            self.dest.join(relative)
+        } else if file_info.file_type().is_dir() {
+            self.dest.clone()
+        } else {
+            // A file given as a starting point goes straight in
+            match path.file_name() {
+                Some(name) => self.dest.join(name),
+                None => self.dest.clone(),
+            }
+        }
+    }
+
+    /// Gets ready to put a file at `target`, by creating the directories it
+    /// goes in and dealing with anything that's already there. Returns false
+    /// if it should be skipped.
+    fn make_room(&self, target: &Path) -> io::Result<bool> {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let meta = match fs::symlink_metadata(target) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(true),
+            Err(e) => return Err(e),
+        };
+        match self.collision {
+            Collision::Error => Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                "the destination already exists",
+            )),
+            Collision::Skip => Ok(false),
+            Collision::Overwrite if meta.is_dir() => fs::remove_dir(target).map(|()| true),
+            Collision::Overwrite => fs::remove_file(target).map(|()| true),
+        }
+    }
+
+    /// Makes sure there's a directory at `target` for a directory's contents
+    /// to go in. One that's already there is merged with.
+    fn make_dir(&self, target: &Path) -> io::Result<Outcome> {
+        if fs::symlink_metadata(target).is_ok_and(|meta| meta.is_dir()) {
+            return Ok(Outcome::Done);
+        }
+        if !self.make_room(target)? {
+            return Ok(Outcome::Skipped);
+        }
+        fs::create_dir(target)?;
+        Ok(Outcome::Done)
+    }
+
+    fn copy(&self, file_info: &WalkEntry, target: &Path) -> io::Result<Outcome> {
+        let file_type = file_info.file_type();
+        if file_type.is_dir() {
+            return self.make_dir(target);
+        }
+        if !file_type.is_file() && !file_type.is_symlink() {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "only regular files, directories and symbolic links can be copied",
+            ));
+        }
+        if !self.make_room(target)? {
+            return Ok(Outcome::Skipped);
+        }
+        copy_file(file_info.path(), target, file_type.is_symlink())?;
+        Ok(Outcome::Done)
+    }
+
+    fn rename(&self, file_info: &WalkEntry, target: &Path) -> io::Result<Outcome> {
+        let path = file_info.path();
+        let file_type = file_info.file_type();
+        if file_type.is_dir() && (file_info.depth() == 0 || target.is_dir()) {
+            // The contents are moved one by one, and whether or not they've
+            // gone yet depends on -depth, so the directory is dealt with once
+            // the search is over
+            let outcome = self.make_dir(target)?;
+            if matches!(outcome, Outcome::Done) {
+                self.merged.borrow_mut().push(path.to_path_buf());
+            }
+            return Ok(outcome);
+        }
+
+        if !self.make_room(target)? {
+            return Ok(Outcome::Skipped);
+        }
+        match fs::rename(path, target) {
+            Ok(()) if file_type.is_dir() => Ok(Outcome::MovedTree),
+            Ok(()) => Ok(Outcome::Done),
+            // A directory on another file system is merged like the starting point
+            Err(e) if e.kind() == ErrorKind::CrossesDevices && file_type.is_dir() => {
+                fs::create_dir(target)?;
+                self.merged.borrow_mut().push(path.to_path_buf());
+                Ok(Outcome::Done)
+            }
+            Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+                copy_file(path, target, file_type.is_symlink())?;
+                fs::remove_file(path)?;
+                Ok(Outcome::Done)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Copies a file, or a symbolic link itself rather than what it points to.
+fn copy_file(path: &Path, target: &Path, symlink: bool) -> io::Result<()> {
+    if !symlink {
+        return fs::copy(path, target).map(|_| ());
+    }
+
+    let link = fs::read_link(path)?;
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(link, target);
+    #[cfg(windows)]
+    return if fs::metadata(path).is_ok_and(|meta| meta.is_dir()) {
+        std::os::windows::fs::symlink_dir(link, target)
+    } else {
+        std::os::windows::fs::symlink_file(link, target)
+    };
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (link, target);
+        Err(ErrorKind::Unsupported.into())
+    }
+}
+
+impl Matcher for CopyMatcher {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let path = file_info.path();
+        if file_info.file_type().is_dir() && self.is_dest(file_info) {
+            // What's already been put there shouldn't be put there again
+            matcher_io.mark_current_dir_to_be_skipped();
+            return false;
+        }
+
+        let action = self.transfer.name();
+        let target = self.target(file_info);
+        if matcher_io.is_dry_run() {
+            let mut out = matcher_io.deps.get_output().borrow_mut();
+            writeln!(
+                out,
+                "would {action}: {} -> {}",
+                path.to_string_lossy(),
+                target.to_string_lossy()
+            )
+            .unwrap();
+            drop(out);
+            matcher_io.log_action(action, path, "dry-run");
+            return true;
+        }
+
+        let result = match self.transfer {
+            Transfer::Copy => self.copy(file_info, &target),
+            Transfer::Move => self.rename(file_info, &target),
+        };
+        match result {
+            Ok(Outcome::Done) => {
+                matcher_io.log_action(action, path, "ok");
+                true
+            }
+            Ok(Outcome::Skipped) => {
+                matcher_io.log_action(action, path, "skipped");
+                if file_info.file_type().is_dir() {
+                    matcher_io.mark_current_dir_to_be_skipped();
+                }
+                false
+            }
+            Ok(Outcome::MovedTree) => {
+                matcher_io.log_action(action, path, "ok");
+                matcher_io.mark_current_dir_to_be_skipped();
+                true
+            }
+            Err(e) => {
+                matcher_io.log_action(action, path, &format!("failed: {e}"));
+                matcher_io.set_exit_code(1);
+                writeln!(
+                    matcher_io.error_output(),
+                    "Failed to {action} {} to {}: {e}",
+                    path.to_string_lossy(),
+                    target.to_string_lossy()
+                )
+                .unwrap();
+                false
+            }
+        }
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+
+    fn finished(&self) {
+        let mut merged = self.merged.take();
+        // Subdirectories go before the directories they're in
+        merged.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+        for path in merged {
+            match fs::remove_dir(&path) {
+                Ok(()) => {}
+                // Whatever didn't match is still in it
+                Err(e) if e.kind() == ErrorKind::DirectoryNotEmpty => {}
+                Err(e) => {
+                    writeln!(
+                        &mut stderr(),
+                        "Failed to remove {}: {e}",
+                        path.to_string_lossy()
+                    )
+                    .unwrap();
+                    uucore::error::set_exit_code(1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::find::matchers::tests::get_dir_entry_for;
+    use crate::find::tests::FakeDependencies;
+
+    #[test]
+    fn parse_collision() {
+        assert_eq!("skip".parse(), Ok(Collision::Skip));
+        assert_eq!("overwrite".parse(), Ok(Collision::Overwrite));
+        assert!("clobber".parse::<Collision>().is_err());
+    }
+
+    #[test]
+    fn targets() {
+        let matcher = CopyMatcher::new(Transfer::Copy, "dest", Collision::Error);
+        let target = |root, path| matcher.target(&get_dir_entry_for(root, path));
+        assert_eq!(target("test_data", ""), Path::new("dest"));
+        assert_eq!(
+            target("test_data", "simple/abbbc"),
+            Path::new("dest/simple/abbbc")
+        );
+        assert_eq!(
+            target("test_data/simple/abbbc", ""),
+            Path::new("dest/abbbc")
+        );
+    }
+
+    #[test]
+    fn copy_and_move() {
+        let temp_dir = Builder::new().prefix("copy_and_move").tempdir().unwrap();
+        let root = temp_dir.path().join("root");
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::write(root.join("dir/file"), "file").unwrap();
+        let root = root.to_string_lossy();
+        let deps = FakeDependencies::new();
+
+        let copy = CopyMatcher::new(Transfer::Copy, &dest, Collision::Error);
+        for path in ["", "dir", "dir/file"] {
+            let entry = get_dir_entry_for(&root, path);
+            assert!(copy.matches(&entry, &mut deps.new_matcher_io()), "{path}");
+        }
+        assert_eq!(fs::read_to_string(dest.join("dir/file")).unwrap(), "file");
+
+        // It's there already now
+        let entry = get_dir_entry_for(&root, "dir/file");
+        let mut matcher_io = deps.new_matcher_io();
+        assert!(!copy.matches(&entry, &mut matcher_io));
+        assert_eq!(matcher_io.exit_code(), 1);
+        let skip = CopyMatcher::new(Transfer::Copy, &dest, Collision::Skip);
+        let mut matcher_io = deps.new_matcher_io();
+        assert!(!skip.matches(&entry, &mut matcher_io));
+        assert_eq!(matcher_io.exit_code(), 0);
+
+        // Moving a directory takes everything in it
+        let rename = CopyMatcher::new(Transfer::Move, dest.join("moved"), Collision::Error);
+        let entry = get_dir_entry_for(&root, "dir");
+        let mut matcher_io = deps.new_matcher_io();
+        assert!(rename.matches(&entry, &mut matcher_io));
+        assert!(matcher_io.should_skip_current_dir());
+        assert_eq!(
+            fs::read_to_string(dest.join("moved/dir/file")).unwrap(),
+            "file"
+        );
+        assert!(!Path::new(&*root).join("dir").exists());
+
+        // The destination is left out of the search
+        let entry = get_dir_entry_for(&dest.to_string_lossy(), "");
+        let mut matcher_io = deps.new_matcher_io();
+        assert!(!copy.matches(&entry, &mut matcher_io));
+        assert!(matcher_io.should_skip_current_dir());
+    }
+}
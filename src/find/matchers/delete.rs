@@ -8,7 +8,7 @@
  */
 
 use std::fs;
-use std::io::{self, stderr, Write};
+use std::io;
 
 use super::{Matcher, MatcherIO, WalkEntry};
 
@@ -20,7 +20,23 @@ impl DeleteMatcher {
     }
 
     fn delete(&self, entry: &WalkEntry) -> io::Result<()> {
-        if entry.file_type().is_dir() && !entry.path_is_symlink() {
+        let is_dir = entry.file_type().is_dir() && !entry.path_is_symlink();
+
+        // Remove the entry from the directory we found it in, in case the
+        // path now leads somewhere else
+        #[cfg(unix)]
+        if let Some(fd) = entry.parent_fd() {
+            use nix::unistd::{unlinkat, UnlinkatFlags};
+
+            let flag = if is_dir {
+                UnlinkatFlags::RemoveDir
+            } else {
+                UnlinkatFlags::NoRemoveDir
+            };
+            return Ok(unlinkat(Some(fd), entry.file_name(), flag)?);
+        }
+
+        if is_dir {
             fs::remove_dir(entry.path())
         } else {
             fs::remove_file(entry.path())
@@ -40,11 +56,27 @@ impl Matcher for DeleteMatcher {
             return true;
         }
 
+        if matcher_io.is_dry_run() {
+            let mut out = matcher_io.deps.get_output().borrow_mut();
+            writeln!(out, "would delete: {path_str}").unwrap();
+            drop(out);
+            matcher_io.log_action("delete", path, "dry-run");
+            return true;
+        }
+
         match self.delete(file_info) {
-            Ok(()) => true,
+            Ok(()) => {
+                matcher_io.log_action("delete", path, "ok");
+                true
+            }
             Err(e) => {
+                matcher_io.log_action("delete", path, &format!("failed: {e}"));
                 matcher_io.set_exit_code(1);
-                writeln!(&mut stderr(), "Failed to delete {path_str}: {e}").unwrap();
+                writeln!(
+                    matcher_io.error_output(),
+                    "Failed to delete {path_str}: {e}"
+                )
+                .unwrap();
                 false
             }
         }
@@ -94,4 +126,64 @@ mod tests {
             "DeleteMatcher should actually delete (empty) directories it matches",
         );
     }
+
+    #[test]
+    fn delete_matcher_dry_run() {
+        let matcher = DeleteMatcher::new();
+        let deps = FakeDependencies::new();
+
+        let temp_dir = Builder::new().prefix("test_data").tempdir().unwrap();
+
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+        File::create(temp_dir.path().join("test")).expect("created test file");
+        let test_entry = get_dir_entry_for(&temp_dir_path, "test");
+
+        let mut matcher_io = deps.new_matcher_io();
+        matcher_io.set_dry_run(true);
+        assert!(
+            matcher.matches(&test_entry, &mut matcher_io),
+            "DeleteMatcher should still match in dry-run mode",
+        );
+        assert!(
+            temp_dir.path().join("test").exists(),
+            "DeleteMatcher shouldn't delete anything in dry-run mode",
+        );
+        assert_eq!(
+            deps.get_output_as_string(),
+            format!("would delete: {}\n", test_entry.path().to_string_lossy())
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn delete_matcher_uses_parent_dir() {
+        use crate::find::walk::Walker;
+
+        let matcher = DeleteMatcher::new();
+        let deps = FakeDependencies::new();
+
+        let temp_dir = Builder::new().prefix("test_data").tempdir().unwrap();
+        create_dir(temp_dir.path().join("dir")).unwrap();
+        File::create(temp_dir.path().join("dir/test")).unwrap();
+
+        let entry = Walker::new(temp_dir.path())
+            .map(Result::unwrap)
+            .find(|entry| entry.file_name() == "test")
+            .unwrap();
+
+        // Swap the directory out from under the entry
+        fs::rename(temp_dir.path().join("dir"), temp_dir.path().join("moved")).unwrap();
+        create_dir(temp_dir.path().join("dir")).unwrap();
+        File::create(temp_dir.path().join("dir/test")).unwrap();
+
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+        assert!(
+            !temp_dir.path().join("moved/test").exists(),
+            "DeleteMatcher should delete the file it found",
+        );
+        assert!(
+            temp_dir.path().join("dir/test").exists(),
+            "DeleteMatcher shouldn't delete the file that replaced it",
+        );
+    }
 }
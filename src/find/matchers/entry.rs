@@ -1,5 +1,6 @@
 //! Paths encountered during a walk.
 
+use std::borrow::Cow;
 use std::cell::OnceCell;
 use std::error::Error;
 use std::ffi::OsStr;
@@ -7,20 +8,29 @@ use std::fmt::{self, Display, Formatter};
 use std::fs::{self, Metadata};
 use std::io::{self, ErrorKind};
 #[cfg(unix)]
+use std::os::fd::{AsRawFd, RawFd};
+#[cfg(unix)]
 use std::os::unix::fs::FileTypeExt;
-use std::path::{Path, PathBuf};
-
-use walkdir::DirEntry;
+use std::path::{is_separator, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use super::Follow;
+use crate::find::walk::DirFd;
 
 /// Wrapper for a directory entry.
 #[derive(Debug)]
 enum Entry {
     /// Wraps an explicit path and depth.
     Explicit(PathBuf, usize),
-    /// Wraps a WalkDir entry.
-    WalkDir(DirEntry),
+    /// An entry found by reading a directory, whose type is already known.
+    Child {
+        path: PathBuf,
+        depth: usize,
+        /// The file type, after following symlinks if appropriate.
+        file_type: FileType,
+        /// Whether the directory entry itself is a symlink.
+        is_symlink: bool,
+    },
 }
 
 /// File types.
@@ -91,14 +101,54 @@ pub struct WalkError {
     depth: Option<usize>,
     /// The io::Error::raw_os_error(), if known.
     raw: Option<i32>,
+    /// For file system loops, the ancestor directory that was visited again.
+    ancestor: Option<PathBuf>,
 }
 
 impl WalkError {
+    /// Create an error for an I/O error that occurred on a specific path.
+    pub(crate) fn for_path(e: &io::Error, path: impl Into<PathBuf>, depth: usize) -> Self {
+        Self {
+            path: Some(path.into()),
+            depth: Some(depth),
+            raw: e.raw_os_error(),
+            ancestor: None,
+        }
+    }
+
+    /// Create an error for a directory that is its own ancestor.
+    pub(crate) fn file_system_loop(
+        path: impl Into<PathBuf>,
+        depth: usize,
+        ancestor: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            path: Some(path.into()),
+            depth: Some(depth),
+            raw: None,
+            ancestor: Some(ancestor.into()),
+        }
+    }
+
+    /// Fill in the path and depth, if they aren't already known.
+    pub(crate) fn or_path(mut self, path: &Path, depth: usize) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.to_owned());
+            self.depth = Some(depth);
+        }
+        self
+    }
+
     /// Get the path this error occurred on, if known.
     pub fn path(&self) -> Option<&Path> {
         self.path.as_deref()
     }
 
+    /// Get the directory a file system loop leads back to, if this is one.
+    pub(crate) fn ancestor(&self) -> Option<&Path> {
+        self.ancestor.as_deref()
+    }
+
     /// Get the traversal depth when this error occurred, if known.
     pub fn depth(&self) -> Option<usize> {
         self.depth
@@ -128,6 +178,10 @@ impl WalkError {
 
     /// Check for ErrorKind::FilesystemLoop.
     pub fn is_loop(&self) -> bool {
+        if self.ancestor.is_some() {
+            return true;
+        }
+
         #[cfg(unix)]
         return self.raw == Some(uucore::libc::ELOOP);
 
@@ -138,6 +192,15 @@ impl WalkError {
 
 impl Display for WalkError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        if let (Some(path), Some(ancestor)) = (&self.path, &self.ancestor) {
+            return write!(
+                f,
+                "File system loop detected; '{}' is part of the same file system loop as '{}'.",
+                path.display(),
+                ancestor.display()
+            );
+        }
+
         let ioe = io::Error::from(self);
         if let Some(path) = &self.path {
             write!(f, "{}: {}", path.display(), ioe)
@@ -161,22 +224,7 @@ impl From<&io::Error> for WalkError {
             path: None,
             depth: None,
             raw: e.raw_os_error(),
-        }
-    }
-}
-
-impl From<walkdir::Error> for WalkError {
-    fn from(e: walkdir::Error) -> WalkError {
-        WalkError::from(&e)
-    }
-}
-
-impl From<&walkdir::Error> for WalkError {
-    fn from(e: &walkdir::Error) -> WalkError {
-        WalkError {
-            path: e.path().map(|p| p.to_owned()),
-            depth: Some(e.depth()),
-            raw: e.io_error().and_then(|e| e.raw_os_error()),
+            ancestor: None,
         }
     }
 }
@@ -195,6 +243,19 @@ impl From<&WalkError> for io::Error {
     }
 }
 
+/// The number of times we've asked the OS for metadata, for --stats.
+static STAT_CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// Count a call to stat() (or an equivalent) for --stats.
+pub(crate) fn count_stat_call() {
+    STAT_CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Get the number of stat() calls made so far.
+pub(crate) fn stat_calls() -> u64 {
+    STAT_CALLS.load(Ordering::Relaxed)
+}
+
 /// A path encountered while walking a file system.
 #[derive(Debug)]
 pub struct WalkEntry {
@@ -204,6 +265,12 @@ pub struct WalkEntry {
     follow: Follow,
     /// Cached metadata.
     meta: OnceCell<Result<Metadata, WalkError>>,
+    /// Cached metadata for the link itself, if it differs from meta.
+    link_meta: OnceCell<Result<Metadata, WalkError>>,
+    /// Cached metadata for the link target, if it differs from meta.
+    target_meta: OnceCell<Result<Metadata, WalkError>>,
+    /// The directory this entry was found in, if it's still open.
+    parent: Option<DirFd>,
 }
 
 impl WalkEntry {
@@ -213,54 +280,74 @@ impl WalkEntry {
             inner: Entry::Explicit(path.into(), depth),
             follow,
             meta: OnceCell::new(),
+            link_meta: OnceCell::new(),
+            target_meta: OnceCell::new(),
+            parent: None,
         }
     }
 
-    /// Convert a [walkdir::DirEntry] to a [WalkEntry].  Errors due to broken symbolic links will be
-    /// converted to valid entries, but other errors will be propagated.
-    pub fn from_walkdir(
-        result: walkdir::Result<DirEntry>,
+    /// Create a [WalkEntry] for an entry read from a directory, whose type
+    /// (after following symlinks if appropriate) is already known.
+    pub(crate) fn child(
+        path: PathBuf,
+        depth: usize,
         follow: Follow,
-    ) -> Result<WalkEntry, WalkError> {
-        let result = result.map_err(WalkError::from);
-
-        match result {
-            Ok(entry) => {
-                let ret = if entry.depth() == 0 && follow != Follow::Never {
-                    // DirEntry::file_type() is wrong for root symlinks when follow_root_links is set
-                    Self::new(entry.path(), 0, follow)
-                } else {
-                    Self {
-                        inner: Entry::WalkDir(entry),
-                        follow,
-                        meta: OnceCell::new(),
-                    }
-                };
-                Ok(ret)
-            }
-            Err(e) if e.is_not_found() => {
-                // Detect broken symlinks and replace them with explicit entries
-                if let (Some(path), Some(depth)) = (e.path(), e.depth()) {
-                    if let Ok(meta) = path.symlink_metadata() {
-                        return Ok(WalkEntry {
-                            inner: Entry::Explicit(path.into(), depth),
-                            follow: Follow::Never,
-                            meta: Ok(meta).into(),
-                        });
-                    }
-                }
-
-                Err(e)
-            }
-            Err(e) => Err(e),
+        file_type: FileType,
+        is_symlink: bool,
+    ) -> Self {
+        Self {
+            inner: Entry::Child {
+                path,
+                depth,
+                file_type,
+                is_symlink,
+            },
+            follow,
+            meta: OnceCell::new(),
+            link_meta: OnceCell::new(),
+            target_meta: OnceCell::new(),
+            parent: None,
         }
     }
 
+    /// Create a [WalkEntry] for a broken symlink found while following links.
+    /// It will be treated as the link itself.
+    pub(crate) fn broken_symlink(path: PathBuf, depth: usize) -> Self {
+        Self::child(path, depth, Follow::Never, FileType::Symlink, true)
+    }
+
+    /// Remember metadata that the caller already fetched, to avoid asking for
+    /// it again.
+    pub(crate) fn with_metadata(self, meta: Metadata) -> Self {
+        // The cell is empty, since we own the only reference to self.
+        let _ = self.meta.set(Ok(meta));
+        self
+    }
+
+    /// Remember the open directory this entry was found in.
+    pub(crate) fn with_parent(mut self, parent: DirFd) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Forget the directory this entry was found in, so that it can be closed.
+    pub(crate) fn clear_parent(&mut self) {
+        self.parent = None;
+    }
+
+    /// Get a file descriptor for the directory this entry was found in, if
+    /// it's still open. Operations on [Self::file_name()] relative to this
+    /// descriptor are immune to the directories above being moved around.
+    #[cfg(unix)]
+    pub(crate) fn parent_fd(&self) -> Option<RawFd> {
+        self.parent.as_ref().map(|dir| dir.as_raw_fd())
+    }
+
     /// Get the path to this entry.
     pub fn path(&self) -> &Path {
         match &self.inner {
             Entry::Explicit(path, _) => path.as_path(),
-            Entry::WalkDir(ent) => ent.path(),
+            Entry::Child { path, .. } => path.as_path(),
         }
     }
 
@@ -268,7 +355,7 @@ impl WalkEntry {
     pub fn into_path(self) -> PathBuf {
         match self.inner {
             Entry::Explicit(path, _) => path,
-            Entry::WalkDir(ent) => ent.into_path(),
+            Entry::Child { path, .. } => path,
         }
     }
 
@@ -276,13 +363,41 @@ impl WalkEntry {
     pub fn file_name(&self) -> &OsStr {
         match &self.inner {
             Entry::Explicit(path, _) => {
+                // components() drops a trailing ".", but "dir/." and
+                // "dir/./" are still named "."
+                let bytes = path.as_os_str().as_encoded_bytes();
+                let end = bytes
+                    .iter()
+                    .rposition(|&c| !is_separator(c.into()))
+                    .map_or(bytes.len(), |i| i + 1);
+                let bytes = &bytes[..end];
+                if bytes.ends_with(b"/.") || (cfg!(windows) && bytes.ends_with(b"\\.")) {
+                    return OsStr::new(".");
+                }
+
                 // Path::file_name() only works if the last component is normal
                 path.components()
-                    .last()
+                    .next_back()
                     .map(|c| c.as_os_str())
                     .unwrap_or_else(|| path.as_os_str())
             }
-            Entry::WalkDir(ent) => ent.file_name(),
+            Entry::Child { path, .. } => path.file_name().unwrap_or(path.as_os_str()),
+        }
+    }
+
+    /// Get the name of this entry the way GNU find prints it for %f and
+    /// -execdir: like [Self::file_name()], except that a starting point like
+    /// "dir//" keeps one trailing slash.
+    pub fn printed_name(&self) -> Cow<'_, OsStr> {
+        let name = self.file_name();
+        let path = self.path().as_os_str().as_encoded_bytes();
+        if self.depth() == 0 && name != "/" && path.last().is_some_and(|&c| is_separator(c.into()))
+        {
+            let mut name = name.to_owned();
+            name.push("/");
+            name.into()
+        } else {
+            name.into()
         }
     }
 
@@ -290,7 +405,7 @@ impl WalkEntry {
     pub fn depth(&self) -> usize {
         match &self.inner {
             Entry::Explicit(_, depth) => *depth,
-            Entry::WalkDir(ent) => ent.depth(),
+            Entry::Child { depth, .. } => *depth,
         }
     }
 
@@ -307,9 +422,47 @@ impl WalkEntry {
     /// Get the [Metadata] for this entry, following symbolic links if appropriate.
     /// Multiple calls to this function will cache and re-use the same [Metadata].
     pub fn metadata(&self) -> Result<&Metadata, WalkError> {
-        let result = self.meta.get_or_init(|| match &self.inner {
-            Entry::Explicit(_, _) => Ok(self.get_metadata()?),
-            Entry::WalkDir(ent) => Ok(ent.metadata()?),
+        let result = self.meta.get_or_init(|| self.get_metadata());
+        result.as_ref().map_err(|e| e.clone())
+    }
+
+    /// Get the [Metadata] for this entry itself, like lstat(). Multiple calls
+    /// to this function will cache and re-use the same [Metadata].
+    pub fn link_metadata(&self) -> Result<&Metadata, WalkError> {
+        let same = match &self.inner {
+            _ if !self.follow() => true,
+            Entry::Child { is_symlink, .. } => !is_symlink,
+            Entry::Explicit(_, _) => false,
+        };
+        if same {
+            return self.metadata();
+        }
+
+        let result = self.link_meta.get_or_init(|| {
+            count_stat_call();
+            Ok(self.path().symlink_metadata()?)
+        });
+        result.as_ref().map_err(|e| e.clone())
+    }
+
+    /// Get the [Metadata] for whatever this entry points to, like stat().
+    /// Unlike [Self::metadata()], this fails for broken symbolic links.
+    /// Multiple calls to this function will cache and re-use the same
+    /// [Metadata].
+    pub fn target_metadata(&self) -> Result<&Metadata, WalkError> {
+        let same = if self.follow() {
+            // Only broken links are still seen as links
+            !self.file_type().is_symlink()
+        } else {
+            !self.path_is_symlink()
+        };
+        if same {
+            return self.metadata();
+        }
+
+        let result = self.target_meta.get_or_init(|| {
+            count_stat_call();
+            Ok(self.path().metadata()?)
         });
         result.as_ref().map_err(|e| e.clone())
     }
@@ -321,7 +474,7 @@ impl WalkEntry {
                 .metadata()
                 .map(|m| m.file_type().into())
                 .unwrap_or(FileType::Unknown),
-            Entry::WalkDir(ent) => ent.file_type().into(),
+            Entry::Child { file_type, .. } => *file_type,
         }
     }
 
@@ -329,15 +482,10 @@ impl WalkEntry {
     /// are being followed.
     pub fn path_is_symlink(&self) -> bool {
         match &self.inner {
-            Entry::Explicit(path, _) => {
-                if self.follow() {
-                    path.symlink_metadata()
-                        .is_ok_and(|m| m.file_type().is_symlink())
-                } else {
-                    self.file_type().is_symlink()
-                }
-            }
-            Entry::WalkDir(ent) => ent.path_is_symlink(),
+            Entry::Explicit(_, _) => self
+                .link_metadata()
+                .is_ok_and(|m| m.file_type().is_symlink()),
+            Entry::Child { is_symlink, .. } => *is_symlink,
         }
     }
 }
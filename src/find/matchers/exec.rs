@@ -5,12 +5,17 @@
 // https://opensource.org/licenses/MIT.
 
 use std::error::Error;
-use std::ffi::OsString;
-use std::io::{stderr, Write};
-use std::path::Path;
-use std::process::Command;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, stderr, stdin, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
+use super::response::Affirmative;
 use super::{Matcher, MatcherIO, WalkEntry};
+use crate::exec_common::{
+    describe, spawn, timed_out, wait_with_timeout, Finished, Limits, Outcome,
+};
 
 enum Arg {
     FileArg(Vec<OsString>),
@@ -19,8 +24,84 @@ enum Arg {
 
 pub struct SingleExecMatcher {
     executable: String,
+    program: OsString,
     args: Vec<Arg>,
     exec_in_parent_dir: bool,
+    timeout: Option<Duration>,
+    /// For -ok and -okdir, what counts as the user saying yes.
+    affirmative: Option<Affirmative>,
+    sandbox: Option<Sandbox>,
+    limits: Limits,
+}
+
+/// The environment variables commands always get in a sandbox: enough to
+/// find programs, and to keep the locale and time zone.
+#[cfg(not(windows))]
+const SANDBOX_ENV: &[&str] = &["PATH", "LANG", "TZ"];
+
+/// Windows programs may not even start without SYSTEMROOT.
+#[cfg(windows)]
+const SANDBOX_ENV: &[&str] = &["PATH", "PATHEXT", "SYSTEMROOT", "TZ"];
+
+/// Restrictions on the commands that -exec and friends run (--exec-sandbox).
+#[derive(Clone, Debug, Default)]
+pub struct Sandbox {
+    /// Where -exec and -ok commands are run from, rather than find's own
+    /// working directory. -execdir and -okdir still use the file's.
+    dir: Option<PathBuf>,
+    /// The environment variables to pass on, besides [SANDBOX_ENV] and
+    /// `LC_*` (--exec-keep-env).
+    keep_env: Vec<String>,
+}
+
+impl Sandbox {
+    pub fn new(dir: Option<PathBuf>, keep_env: Vec<String>) -> Self {
+        Self { dir, keep_env }
+    }
+
+    /// Whether a command in the sandbox gets to see an environment variable.
+    fn keeps(&self, name: &OsStr) -> bool {
+        let Some(name) = name.to_str() else {
+            return false;
+        };
+        // Windows doesn't care about the case of environment variables
+        let eq = |kept: &str| {
+            if cfg!(windows) {
+                kept.eq_ignore_ascii_case(name)
+            } else {
+                kept == name
+            }
+        };
+        SANDBOX_ENV.iter().any(|kept| eq(kept))
+            || name.starts_with("LC_")
+            || self.keep_env.iter().any(|kept| eq(kept))
+    }
+
+    fn apply(&self, command: &mut Command, in_parent_dir: bool) {
+        command.env_clear();
+        command.envs(std::env::vars_os().filter(|(name, _)| self.keeps(name)));
+        if let (Some(dir), false) = (&self.dir, in_parent_dir) {
+            command.current_dir(dir);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::process::CommandExt;
+            use uucore::libc::{prctl, PR_SET_NO_NEW_PRIVS};
+
+            // SAFETY: prctl() doesn't allocate or take locks
+            unsafe {
+                command.pre_exec(|| {
+                    // Nothing it runs, like setuid programs, can gain
+                    // privileges it didn't start with
+                    if prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+    }
 }
 
 impl SingleExecMatcher {
@@ -44,20 +125,77 @@ impl SingleExecMatcher {
 
         Ok(Self {
             executable: executable.to_string(),
+            program: resolve_program(executable),
             args: transformed_args,
             exec_in_parent_dir,
+            timeout: None,
+            affirmative: None,
+            sandbox: None,
+            limits: Limits::default(),
         })
     }
+
+    /// Kill the command if it's still running after `timeout`
+    /// (--exec-timeout).
+    #[must_use]
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Ask before running the command each time, reading the answer from
+    /// stdin (-ok and -okdir).
+    #[must_use]
+    pub fn prompt(mut self, prompt: bool) -> Self {
+        self.affirmative = prompt.then(Affirmative::from_locale);
+        self
+    }
+
+    /// Run the command in a sandbox, if given one (--exec-sandbox).
+    #[must_use]
+    pub fn sandbox(mut self, sandbox: Option<Sandbox>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Run the command with a lower priority or resource limits
+    /// (--exec-nice and --exec-rlimit).
+    #[must_use]
+    pub(crate) fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+impl SingleExecMatcher {
+    fn action_name(&self) -> &'static str {
+        match (self.affirmative.is_some(), self.exec_in_parent_dir) {
+            (false, false) => "exec",
+            (false, true) => "execdir",
+            (true, false) => "ok",
+            (true, true) => "okdir",
+        }
+    }
+
+    /// Asks whether to run the command on `path`, the way GNU find does.
+    fn confirm(&self, affirmative: &Affirmative, path: &Path) -> bool {
+        let mut stderr = stderr();
+        let _ = write!(stderr, "< {} ... {} > ? ", self.executable, path.display());
+        let _ = stderr.flush();
+        affirmative.read(&mut stdin().lock()).unwrap_or(false)
+    }
 }
 
 impl Matcher for SingleExecMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        let mut command = Command::new(&self.executable);
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let mut command = Command::new(&self.program);
         let path_to_file = if self.exec_in_parent_dir {
-            if let Some(f) = file_info.path().file_name() {
-                Path::new(".").join(f)
+            // Like GNU find, "./" and the name as %f prints it, except for
+            // the root, which is just "/"
+            if Path::new(file_info.file_name()).has_root() {
+                file_info.path().components().collect()
             } else {
-                Path::new(".").join(file_info.path())
+                Path::new(".").join(file_info.printed_name())
             }
         } else {
             file_info.path().to_path_buf()
@@ -69,24 +207,67 @@ impl Matcher for SingleExecMatcher {
                 Arg::FileArg(ref parts) => command.arg(parts.join(path_to_file.as_os_str())),
             };
         }
+        if let Some(sandbox) = &self.sandbox {
+            sandbox.apply(&mut command, self.exec_in_parent_dir);
+        }
+        if matcher_io.is_dry_run() {
+            // Command's Debug output quotes each argument, and includes the
+            // directory it would be run from for -execdir.
+            if self.exec_in_parent_dir {
+                set_dir_by_path(&mut command, file_info);
+            }
+            let mut out = matcher_io.deps.get_output().borrow_mut();
+            writeln!(out, "would run: {}", describe(&command)).unwrap();
+            drop(out);
+            matcher_io.log_action(self.action_name(), file_info.path(), "dry-run");
+            return true;
+        }
+        if let Some(affirmative) = &self.affirmative {
+            if !self.confirm(affirmative, file_info.path()) {
+                return false;
+            }
+            // The answers come from stdin, so the command can't have it too
+            command.stdin(Stdio::null());
+        }
         if self.exec_in_parent_dir {
-            match file_info.path().parent() {
-                None => {
-                    // Root paths like "/" have no parent.  Run them from the root to match GNU find.
-                    command.current_dir(file_info.path());
-                }
-                Some(parent) if parent == Path::new("") => {
-                    // Paths like "foo" have a parent of "".  Avoid chdir("").
-                }
-                Some(parent) => {
-                    command.current_dir(parent);
+            set_parent_dir(&mut command, file_info);
+        }
+
+        let finished = spawn(&mut command, self.timeout, &self.limits)
+            .map_err(io::Error::from)
+            .and_then(|mut child| wait_with_timeout(&mut child, self.timeout));
+        match finished {
+            Ok(Finished::Exited(status)) => {
+                matcher_io.log_action(self.action_name(), file_info.path(), &status.to_string());
+                if let Some(message) = Outcome::new(status).abnormal_exit(&self.executable) {
+                    writeln!(matcher_io.error_output(), "find: {message}").unwrap();
                 }
+                status.success()
+            }
+            Ok(Finished::TimedOut) => {
+                matcher_io.log_action(self.action_name(), file_info.path(), "timed out");
+                writeln!(
+                    matcher_io.error_output(),
+                    "find: {}",
+                    timed_out(&self.executable, self.timeout.unwrap_or_default())
+                )
+                .unwrap();
+                matcher_io.set_exit_code(1);
+                false
             }
-        }
-        match command.status() {
-            Ok(status) => status.success(),
             Err(e) => {
-                writeln!(&mut stderr(), "Failed to run {}: {}", self.executable, e).unwrap();
+                matcher_io.log_action(
+                    self.action_name(),
+                    file_info.path(),
+                    &format!("failed: {e}"),
+                );
+                writeln!(
+                    matcher_io.error_output(),
+                    "Failed to run {}: {}",
+                    self.executable,
+                    e
+                )
+                .unwrap();
                 false
             }
         }
@@ -97,7 +278,181 @@ impl Matcher for SingleExecMatcher {
     }
 }
 
+/// Runs `command` from the directory containing `file_info`, for -execdir.
+#[cfg(unix)]
+fn set_parent_dir(command: &mut Command, file_info: &WalkEntry) {
+    use std::os::unix::process::CommandExt;
+
+    match file_info.parent_fd() {
+        // Change to the directory we actually found the file in, which the
+        // path might not lead to any more
+        Some(fd) => unsafe {
+            // SAFETY: fchdir() is async-signal-safe, and the descriptor stays
+            // open until after the child has been spawned.
+            command.pre_exec(move || Ok(nix::unistd::fchdir(fd)?));
+        },
+        None => set_dir_by_path(command, file_info),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_parent_dir(command: &mut Command, file_info: &WalkEntry) {
+    set_dir_by_path(command, file_info);
+}
+
+/// Runs `command` from the parent of `file_info`'s path.
+fn set_dir_by_path(command: &mut Command, file_info: &WalkEntry) {
+    if file_info.file_name() == "." {
+        // Path::parent() skips the "." in "dir/.", which is "dir" itself
+        command.current_dir(file_info.path());
+        return;
+    }
+    match file_info.path().parent() {
+        None => {
+            // Root paths like "/" have no parent.  Run them from the root to match GNU find.
+            command.current_dir(file_info.path());
+        }
+        Some(parent) if parent == Path::new("") => {
+            // Paths like "foo" have a parent of "".  Avoid chdir("").
+        }
+        Some(parent) => {
+            command.current_dir(parent);
+        }
+    }
+}
+
+/// Works out which program to run for `executable`.
+///
+/// Windows will only find programs without an extension if they're `.exe`
+/// files, but users expect to be able to run scripts like `foo.bat` or
+/// `foo.cmd` as just `foo`, the same way the shell does. So look the name
+/// up using the extensions listed in `PATHEXT`.
+#[cfg(windows)]
+fn resolve_program(executable: &str) -> OsString {
+    let search_path = std::env::var_os("PATH");
+    let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| DEFAULT_PATHEXT.to_string());
+    resolve_with_pathext(Path::new(executable), search_path.as_deref(), &pathext)
+        .map_or_else(|| OsString::from(executable), PathBuf::into_os_string)
+}
+
+#[cfg(not(windows))]
+fn resolve_program(executable: &str) -> OsString {
+    OsString::from(executable)
+}
+
+/// The value of `PATHEXT` that Windows uses when the variable isn't set.
+#[cfg(any(windows, test))]
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+#[cfg(any(windows, test))]
+fn resolve_with_pathext(
+    executable: &Path,
+    search_path: Option<&OsStr>,
+    pathext: &str,
+) -> Option<PathBuf> {
+    if executable.extension().is_some() {
+        return None;
+    }
+
+    // Bare names are searched for in PATH, anything else is relative to the
+    // current directory (or absolute).
+    let dirs: Vec<PathBuf> = if executable.components().count() == 1 && !executable.has_root() {
+        search_path
+            .map(|p| std::env::split_paths(p).collect())
+            .unwrap_or_default()
+    } else {
+        vec![PathBuf::new()]
+    };
+
+    dirs.iter().find_map(|dir| {
+        pathext
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| {
+                let mut candidate = dir.join(executable).into_os_string();
+                candidate.push(ext);
+                PathBuf::from(candidate)
+            })
+            .find(|candidate| candidate.is_file())
+    })
+}
+
 #[cfg(test)]
-/// No tests here, because we need to call out to an external executable. See
-/// `tests/exec_unit_tests.rs` instead.
-mod tests {}
+/// Most tests live in `tests/exec_unit_tests.rs`, because we need to call out
+/// to an external executable.
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::Builder;
+
+    #[test]
+    fn resolves_scripts_with_pathext() {
+        let temp_dir = Builder::new()
+            .prefix("resolves_scripts_with_pathext")
+            .tempdir()
+            .unwrap();
+        File::create(temp_dir.path().join("script.cmd")).unwrap();
+        File::create(temp_dir.path().join("both.bat")).unwrap();
+        File::create(temp_dir.path().join("both.cmd")).unwrap();
+
+        let search_path = std::env::join_paths([temp_dir.path()]).unwrap();
+        let resolve = |name: &str| {
+            resolve_with_pathext(Path::new(name), Some(&search_path), ".exe;.bat;.cmd")
+        };
+
+        assert_eq!(resolve("script"), Some(temp_dir.path().join("script.cmd")));
+        // The first matching extension wins.
+        assert_eq!(resolve("both"), Some(temp_dir.path().join("both.bat")));
+        assert_eq!(resolve("missing"), None);
+        // Names that already have an extension are left alone.
+        assert_eq!(resolve("script.cmd"), None);
+
+        // Paths aren't looked up in the search path.
+        let path = temp_dir.path().join("script");
+        assert_eq!(
+            resolve_with_pathext(&path, None, DEFAULT_PATHEXT.to_lowercase().as_str()),
+            Some(temp_dir.path().join("script.cmd"))
+        );
+    }
+
+    #[test]
+    fn sandbox_keeps_environment() {
+        let sandbox = Sandbox::new(None, vec!["FOO".to_owned()]);
+        for name in ["PATH", "LANG", "LC_ALL", "LC_TIME", "TZ", "FOO"] {
+            assert!(sandbox.keeps(OsStr::new(name)), "{name}");
+        }
+        for name in ["HOME", "LD_PRELOAD", "FOOBAR", "LC"] {
+            assert!(!sandbox.keeps(OsStr::new(name)), "{name}");
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn execdir_uses_parent_dir() {
+        use crate::find::tests::FakeDependencies;
+        use crate::find::walk::Walker;
+        use std::fs::{create_dir, rename};
+
+        let temp_dir = Builder::new()
+            .prefix("execdir_uses_parent_dir")
+            .tempdir()
+            .unwrap();
+        create_dir(temp_dir.path().join("dir")).unwrap();
+        File::create(temp_dir.path().join("dir/test")).unwrap();
+
+        let entry = Walker::new(temp_dir.path())
+            .map(Result::unwrap)
+            .find(|entry| entry.file_name() == "test")
+            .unwrap();
+
+        // Swap the directory out from under the entry
+        rename(temp_dir.path().join("dir"), temp_dir.path().join("moved")).unwrap();
+        create_dir(temp_dir.path().join("dir")).unwrap();
+
+        let matcher = SingleExecMatcher::new("touch", &["marker"], true).unwrap();
+        let deps = FakeDependencies::new();
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+        assert!(temp_dir.path().join("moved/marker").exists());
+        assert!(!temp_dir.path().join("dir/marker").exists());
+    }
+}
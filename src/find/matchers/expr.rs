@@ -0,0 +1,498 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The parsed form of a find expression.
+//!
+//! The [parser](super::parse) turns the command line into an [Expr], which is
+//! then lowered into the matchers that actually do the work. Keeping the two
+//! apart means the expression can be inspected (or printed, for
+//! -print-expression) before anything is built. It's displayed with every
+//! operator spelled out, and just enough parentheses to preserve the meaning.
+
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+use super::glob::literal_prefix;
+use super::FindError;
+
+/// Tests that don't do anything but look at the file, and options, neither of
+/// which matter for files that nothing else in the expression acts on.
+/// -newerXY is handled separately.
+const PURE: &[&str] = &[
+    "-name",
+    "-iname",
+    "-path",
+    "-ipath",
+    "-wholename",
+    "-iwholename",
+    "-regex",
+    "-iregex",
+    "-lname",
+    "-ilname",
+    "-type",
+    "-xtype",
+    "-size",
+    "-empty",
+    "-perm",
+    "-links",
+    "-inum",
+    "-samefile",
+    "-newer",
+    "-atime",
+    "-ctime",
+    "-mtime",
+    "-amin",
+    "-cmin",
+    "-mmin",
+    "-user",
+    "-group",
+    "-uid",
+    "-gid",
+    "-nouser",
+    "-nogroup",
+    "-readable",
+    "-writable",
+    "-executable",
+    "-fstype",
+    "-true",
+    "-false",
+    "-depth",
+    "-maxdepth",
+    "-mindepth",
+    "-xdev",
+    "-noleaf",
+    "-daystart",
+    "-regextype",
+    "-sorted",
+];
+
+/// A test, action or option, with its arguments, e.g. `-name foo`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Primary {
+    /// The name, including the leading dash.
+    pub name: String,
+    /// The arguments that follow the name.
+    pub args: Vec<String>,
+    /// The indices of the command line arguments this was parsed from. This
+    /// is empty for primaries that were added implicitly.
+    pub span: Range<usize>,
+}
+
+/// A parsed expression.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expr {
+    /// A test, action or option.
+    Primary(Primary),
+    /// `! EXPR`
+    Not(Box<Expr>),
+    /// `EXPR -a EXPR ...`
+    And(Vec<Expr>),
+    /// `EXPR -o EXPR ...`
+    Or(Vec<Expr>),
+    /// `EXPR , EXPR ...`
+    List(Vec<Expr>),
+}
+
+impl Expr {
+    /// Create a primary expression from the arguments that make it up, which
+    /// started at index `start` on the command line.
+    pub fn primary(args: &[&str], start: usize) -> Self {
+        Self::Primary(Primary {
+            name: args[0].to_owned(),
+            args: args[1..].iter().map(|&arg| arg.to_owned()).collect(),
+            span: start..start + args.len(),
+        })
+    }
+
+    /// Add an implicit -print to this expression, which ended at index `end`
+    /// on the command line.
+    pub fn and_print(self, end: usize) -> Self {
+        let print = Self::Primary(Primary {
+            name: "-print".to_owned(),
+            args: vec![],
+            span: end..end,
+        });
+        match self {
+            Self::And(exprs) if exprs.is_empty() => print,
+            expr => Self::And(vec![expr, print]),
+        }
+    }
+
+    /// Whether this expression uses the primary called `name` anywhere.
+    pub fn contains(&self, name: &str) -> bool {
+        match self {
+            Self::Primary(primary) => primary.name == name,
+            Self::Not(expr) => expr.contains(name),
+            Self::And(exprs) | Self::Or(exprs) | Self::List(exprs) => {
+                exprs.iter().any(|expr| expr.contains(name))
+            }
+        }
+    }
+
+    /// The last use of the primary called `name`, if there is one.
+    pub fn find_last(&self, name: &str) -> Option<&Primary> {
+        match self {
+            Self::Primary(primary) => Some(primary).filter(|primary| primary.name == name),
+            Self::Not(expr) => expr.find_last(name),
+            Self::And(exprs) | Self::Or(exprs) | Self::List(exprs) => {
+                exprs.iter().rev().find_map(|expr| expr.find_last(name))
+            }
+        }
+    }
+
+    /// The literal prefixes of the -path patterns that a file's path has to
+    /// start with for this expression to do anything with it, if there are
+    /// any. For example, nothing below `./doc` can match
+    /// `-path './src/*' -print`, so find needn't read it.
+    pub fn path_prefixes(&self) -> Option<Vec<String>> {
+        let prefixes = match self {
+            Self::Primary(primary) if matches!(primary.name.as_str(), "-path" | "-wholename") => {
+                vec![literal_prefix(&primary.args[0])]
+            }
+            // The first -path that everything before it can be skipped for
+            Self::And(exprs) => {
+                let mut prefixes = None;
+                for expr in exprs {
+                    prefixes = expr.path_prefixes();
+                    if prefixes.is_some() || !expr.is_pure() {
+                        break;
+                    }
+                }
+                prefixes?
+            }
+            // Some -path in every branch
+            Self::Or(exprs) if !exprs.is_empty() => exprs
+                .iter()
+                .map(Self::path_prefixes)
+                .collect::<Option<Vec<_>>>()?
+                .concat(),
+            _ => return None,
+        };
+        // Everything starts with an empty prefix
+        if prefixes.iter().any(String::is_empty) {
+            return None;
+        }
+        Some(prefixes)
+    }
+
+    /// Whether this expression only tests files, without acting on them.
+    fn is_pure(&self) -> bool {
+        match self {
+            Self::Primary(primary) => {
+                PURE.contains(&primary.name.as_str()) || primary.name.starts_with("-newer")
+            }
+            Self::Not(expr) => expr.is_pure(),
+            Self::And(exprs) | Self::Or(exprs) | Self::List(exprs) => {
+                exprs.iter().all(Self::is_pure)
+            }
+        }
+    }
+
+    /// How tightly this expression binds, from 0 (`,`) to 3 (primaries).
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::List(_) => 0,
+            Self::Or(_) => 1,
+            Self::And(_) => 2,
+            Self::Not(_) | Self::Primary(_) => 3,
+        }
+    }
+
+    /// Write this expression, in parentheses if it wouldn't bind tightly
+    /// enough otherwise.
+    fn fmt_operand(&self, f: &mut Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "( {self} )")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+
+    fn fmt_operands(&self, f: &mut Formatter<'_>, exprs: &[Expr], operator: &str) -> fmt::Result {
+        if exprs.is_empty() {
+            // An empty expression, which is always true
+            return f.write_str("-true");
+        }
+
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                write!(f, " {operator} ")?;
+            }
+            expr.fmt_operand(f, self.precedence())?;
+        }
+        Ok(())
+    }
+}
+
+/// Quote an argument if needed to make it unambiguous.
+fn fmt_arg(f: &mut Formatter<'_>, arg: &str) -> fmt::Result {
+    let is_plain = |c: char| c.is_alphanumeric() || "_-+=./:,@%^".contains(c);
+    if !arg.is_empty() && arg != "," && arg.chars().all(is_plain) {
+        f.write_str(arg)
+    } else {
+        write!(f, "'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Primary(primary) => {
+                f.write_str(&primary.name)?;
+                for arg in &primary.args {
+                    // Long options like --color=WHEN take their argument
+                    // in the same word, as does -sorted=ORDER
+                    f.write_str(
+                        if primary.name.starts_with("--") || primary.name == "-sorted" {
+                            "="
+                        } else {
+                            " "
+                        },
+                    )?;
+                    fmt_arg(f, arg)?;
+                }
+                Ok(())
+            }
+            Self::Not(expr) => {
+                f.write_str("! ")?;
+                expr.fmt_operand(f, self.precedence())
+            }
+            Self::And(exprs) => self.fmt_operands(f, exprs, "-a"),
+            Self::Or(exprs) => self.fmt_operands(f, exprs, "-o"),
+            Self::List(exprs) => self.fmt_operands(f, exprs, ","),
+        }
+    }
+}
+
+/// Builds an [Expr] from a sequence of expressions and operators, respecting
+/// the precedence rules (`,` binds loosest, then -o, then -a).
+pub struct ExprBuilder {
+    /// A list of -o separated lists of -a separated expressions.
+    list: Vec<Vec<Vec<Expr>>>,
+}
+
+impl ExprBuilder {
+    pub fn new() -> Self {
+        Self {
+            list: vec![vec![vec![]]],
+        }
+    }
+
+    /// Whether the current -a separated list is empty, i.e. there's nothing
+    /// for a binary operator to apply to.
+    fn nothing_before(&self) -> bool {
+        // safe to unwrap: there's always at least one of each
+        self.list.last().unwrap().last().unwrap().is_empty()
+    }
+
+    fn check_operand(&self, arg: &str) -> Result<(), FindError> {
+        if self.nothing_before() {
+            return Err(From::from(format!(
+                "invalid expression; you have used a binary operator \
+                 '{arg}' with nothing before it."
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn new_and_condition(&mut self, expr: Expr) {
+        self.list.last_mut().unwrap().last_mut().unwrap().push(expr);
+    }
+
+    pub fn check_new_and_condition(&self) -> Result<(), FindError> {
+        self.check_operand("-a")
+    }
+
+    pub fn new_or_condition(&mut self, arg: &str) -> Result<(), FindError> {
+        self.check_operand(arg)?;
+        self.list.last_mut().unwrap().push(vec![]);
+        Ok(())
+    }
+
+    pub fn new_list_condition(&mut self) -> Result<(), FindError> {
+        self.check_operand(",")?;
+        self.list.push(vec![vec![]]);
+        Ok(())
+    }
+
+    /// Builds the expression, consuming the builder in the process.
+    pub fn build(self) -> Expr {
+        fn collapse(mut exprs: Vec<Expr>, wrap: fn(Vec<Expr>) -> Expr) -> Expr {
+            if exprs.len() == 1 {
+                exprs.pop().unwrap()
+            } else {
+                wrap(exprs)
+            }
+        }
+
+        let list = self
+            .list
+            .into_iter()
+            .map(|or| {
+                let or = or.into_iter().map(|and| collapse(and, Expr::And)).collect();
+                collapse(or, Expr::Or)
+            })
+            .collect();
+        collapse(list, Expr::List)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse::parse_expression;
+    use super::*;
+
+    fn name(pattern: &str) -> Expr {
+        Expr::primary(&["-name", pattern], 0)
+    }
+
+    #[test]
+    fn display_primaries() {
+        assert_eq!(name("foo").to_string(), "-name foo");
+        assert_eq!(name("*.txt").to_string(), "-name '*.txt'");
+        assert_eq!(name("it's").to_string(), r"-name 'it'\''s'");
+        assert_eq!(name("").to_string(), "-name ''");
+        assert_eq!(name("(").to_string(), "-name '('");
+        assert_eq!(name(",").to_string(), "-name ','");
+        assert_eq!(Expr::primary(&["-print"], 0).to_string(), "-print");
+        assert_eq!(
+            Expr::primary(&["--color", "auto"], 0).to_string(),
+            "--color=auto"
+        );
+    }
+
+    #[test]
+    fn display_precedence() {
+        let expr = Expr::List(vec![
+            Expr::Or(vec![
+                Expr::And(vec![name("a"), Expr::Not(Box::new(name("b")))]),
+                name("c"),
+            ]),
+            Expr::primary(&["-print"], 0),
+        ]);
+        assert_eq!(expr.to_string(), "-name a -a ! -name b -o -name c , -print");
+
+        let expr = Expr::And(vec![
+            Expr::Or(vec![name("a"), name("b")]),
+            Expr::Not(Box::new(Expr::List(vec![name("c"), name("d")]))),
+        ]);
+        assert_eq!(
+            expr.to_string(),
+            "( -name a -o -name b ) -a ! ( -name c , -name d )"
+        );
+    }
+
+    #[test]
+    fn implicit_print() {
+        assert_eq!(Expr::And(vec![]).and_print(0).to_string(), "-print");
+        assert_eq!(
+            Expr::Or(vec![name("a"), name("b")])
+                .and_print(2)
+                .to_string(),
+            "( -name a -o -name b ) -a -print"
+        );
+
+        let Expr::And(exprs) = Expr::primary(&["-true"], 0).and_print(1) else {
+            panic!("expected an -a expression");
+        };
+        let Expr::Primary(print) = &exprs[1] else {
+            panic!("expected a primary");
+        };
+        assert_eq!(print.name, "-print");
+        assert_eq!(print.span, 1..1);
+    }
+
+    #[test]
+    fn path_prefixes() {
+        let prefixes = |args: &str| {
+            let args: Vec<_> = args.split(' ').filter(|a| !a.is_empty()).collect();
+            parse_expression(&args).unwrap().path_prefixes()
+        };
+        let some = |prefixes: &[&str]| Some(prefixes.iter().map(|p| p.to_string()).collect());
+
+        assert_eq!(prefixes("-path ./src/*/tests/*"), some(&["./src/"]));
+        assert_eq!(prefixes("-wholename ./src/*.rs"), some(&["./src/"]));
+        assert_eq!(
+            prefixes("-type f -name *.rs -path ./src/* -print0"),
+            some(&["./src/"])
+        );
+        assert_eq!(
+            prefixes("-path ./a/* -print -o -path ./b* -delete"),
+            some(&["./a/", "./b"])
+        );
+        assert_eq!(
+            prefixes("( -path ./a* -o -path ./b* ) -ls"),
+            some(&["./a", "./b"])
+        );
+        assert_eq!(prefixes("-path ./\\*/*"), some(&["./*/"]));
+        assert_eq!(
+            prefixes("-maxdepth 3 ! -name x -path ./a/*"),
+            some(&["./a/"])
+        );
+
+        // Something might be done with files that don't match
+        for args in [
+            "",
+            "-name *.rs",
+            "-print -path ./a/*",
+            "-name x -prune -o -path ./a/*",
+            "-path ./a/* -o -name b",
+            "! -path ./a/*",
+            "-path ./a/* , -print",
+            "-ipath ./a/*",
+            "-path *.rs",
+            "-path [.]/a/*",
+        ] {
+            assert_eq!(prefixes(args), None, "{args}");
+        }
+    }
+
+    #[test]
+    fn builder() {
+        let mut builder = ExprBuilder::new();
+        builder.new_and_condition(name("a"));
+        builder.new_and_condition(name("b"));
+        builder.new_or_condition("-o").unwrap();
+        builder.new_and_condition(name("c"));
+        builder.new_list_condition().unwrap();
+        builder.new_and_condition(name("d"));
+        assert_eq!(
+            builder.build(),
+            Expr::List(vec![
+                Expr::Or(vec![Expr::And(vec![name("a"), name("b")]), name("c")]),
+                name("d"),
+            ])
+        );
+
+        assert_eq!(ExprBuilder::new().build(), Expr::And(vec![]));
+
+        let mut builder = ExprBuilder::new();
+        assert!(builder.check_new_and_condition().is_err());
+        assert!(builder.new_or_condition("-or").is_err());
+        assert!(builder.new_list_condition().is_err());
+        builder.new_and_condition(name("a"));
+        assert!(builder.check_new_and_condition().is_ok());
+    }
+
+    #[test]
+    fn contains() {
+        let expr = Expr::Or(vec![
+            Expr::Not(Box::new(name("a"))),
+            Expr::List(vec![Expr::primary(&["-prune"], 0), name("b")]),
+        ]);
+        assert!(expr.contains("-name"));
+        assert!(expr.contains("-prune"));
+        assert!(!expr.contains("-delete"));
+        assert_eq!(
+            expr.find_last("-name"),
+            Some(&Primary {
+                name: "-name".to_owned(),
+                args: vec!["b".to_owned()],
+                span: 0..2,
+            })
+        );
+        assert_eq!(expr.find_last("-delete"), None);
+    }
+}
@@ -0,0 +1,96 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Case folding for -iname, -ipath, -ilname and -iregex.
+
+use std::env;
+
+use onig::RegexOptions;
+
+/// How to compare letters when matching case-insensitively.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CaseFolding {
+    /// Fold case according to Unicode, so É matches é and ß matches SS.
+    Unicode,
+    /// Only fold ASCII letters, like GNU find in the C locale.
+    Ascii,
+}
+
+impl CaseFolding {
+    /// Works out the case folding from the environment. We use Unicode case
+    /// folding unless the locale is explicitly set to "C" or "POSIX".
+    pub fn from_env() -> Self {
+        // The first of these that's set determines the locale for LC_CTYPE
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty());
+        Self::from_locale(locale.as_deref())
+    }
+
+    fn from_locale(locale: Option<&str>) -> Self {
+        match locale {
+            Some("C" | "POSIX") => Self::Ascii,
+            _ => Self::Unicode,
+        }
+    }
+
+    /// The regex options that implement this kind of case folding.
+    pub fn regex_options(self) -> RegexOptions {
+        match self {
+            Self::Unicode => RegexOptions::REGEX_OPTION_IGNORECASE,
+            Self::Ascii => {
+                // SAFETY: the onig crate doesn't have a constant for this
+                // option yet, but it only passes the bits through to
+                // Oniguruma, which supports it
+                let ascii = unsafe {
+                    RegexOptions::from_bits_unchecked(onig_sys::ONIG_OPTION_IGNORECASE_IS_ASCII)
+                };
+                RegexOptions::REGEX_OPTION_IGNORECASE | ascii
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use onig::{Regex, Syntax};
+
+    #[test]
+    fn from_locale() {
+        assert_eq!(CaseFolding::from_locale(None), CaseFolding::Unicode);
+        assert_eq!(CaseFolding::from_locale(Some("C")), CaseFolding::Ascii);
+        assert_eq!(CaseFolding::from_locale(Some("POSIX")), CaseFolding::Ascii);
+        assert_eq!(
+            CaseFolding::from_locale(Some("C.UTF-8")),
+            CaseFolding::Unicode
+        );
+        assert_eq!(
+            CaseFolding::from_locale(Some("en_US.UTF-8")),
+            CaseFolding::Unicode
+        );
+    }
+
+    #[test]
+    fn regex_options() {
+        let matches = |folding: CaseFolding, pattern: &str, text: &str| {
+            Regex::with_options(pattern, folding.regex_options(), Syntax::posix_basic())
+                .unwrap()
+                .is_match(text)
+        };
+
+        assert!(matches(CaseFolding::Unicode, "abc", "ABC"));
+        assert!(matches(CaseFolding::Unicode, "é", "É"));
+        assert!(matches(CaseFolding::Unicode, "[é]", "É"));
+        assert!(matches(CaseFolding::Unicode, "straße", "STRASSE"));
+
+        assert!(matches(CaseFolding::Ascii, "abc", "ABC"));
+        assert!(matches(CaseFolding::Ascii, "[a-c]", "B"));
+        assert!(!matches(CaseFolding::Ascii, "é", "É"));
+        assert!(!matches(CaseFolding::Ascii, "[é]", "É"));
+        assert!(!matches(CaseFolding::Ascii, "straße", "STRASSE"));
+    }
+}
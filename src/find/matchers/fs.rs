@@ -3,6 +3,7 @@
 // For the full copyright and license information, please view the LICENSE
 // file that was distributed with this source code.
 use super::{Matcher, MatcherIO, WalkEntry};
+#[cfg(unix)]
 use uucore::error::UResult;
 
 /// The latest mapping from dev_id to fs_type, used for saving mount info reads
@@ -25,11 +26,7 @@ pub struct Cache {
 ///
 /// This is only supported on Unix.
 #[cfg(unix)]
-use std::{
-    cell::RefCell,
-    io::{stderr, Write},
-    path::Path,
-};
+use std::{cell::RefCell, path::Path};
 #[cfg(unix)]
 pub fn get_file_system_type(path: &Path, cache: &RefCell<Option<Cache>>) -> UResult<String> {
     use std::os::unix::fs::MetadataExt;
@@ -91,12 +88,12 @@ impl FileSystemMatcher {
 
 impl Matcher for FileSystemMatcher {
     #[cfg(unix)]
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match get_file_system_type(file_info.path(), &self.cache) {
             Ok(result) => result == self.fs_text,
             Err(_) => {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
                     "Error getting filesystem type for {}",
                     file_info.path().to_string_lossy()
                 )
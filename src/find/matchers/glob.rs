@@ -6,6 +6,8 @@
 
 use onig::{Regex, RegexOptions, Syntax};
 
+use super::fold::CaseFolding;
+
 /// Parse a string as a POSIX Basic Regular Expression.
 fn parse_bre(expr: &str, options: RegexOptions) -> Result<Regex, onig::Error> {
     let bre = Syntax::posix_basic();
@@ -150,6 +152,26 @@ fn glob_to_regex(pattern: &str) -> Option<String> {
     Some(regex)
 }
 
+/// The literal text at the start of a glob, up to its first wildcard, which
+/// everything it matches has to start with.
+pub fn literal_prefix(pattern: &str) -> String {
+    let mut prefix = String::new();
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            // A '[' might not start a bracket expression, but it may as well
+            // be a wildcard
+            '?' | '*' | '[' => break,
+            '\\' => match chars.next() {
+                Some(ch) => prefix.push(ch),
+                None => break,
+            },
+            _ => prefix.push(ch),
+        }
+    }
+    prefix
+}
+
 /// An fnmatch()-style glob matcher.
 pub struct Pattern {
     regex: Option<Regex>,
@@ -159,7 +181,7 @@ impl Pattern {
     /// Parse an fnmatch()-style glob.
     pub fn new(pattern: &str, caseless: bool) -> Self {
         let options = if caseless {
-            RegexOptions::REGEX_OPTION_IGNORECASE
+            CaseFolding::from_env().regex_options()
         } else {
             RegexOptions::REGEX_OPTION_NONE
         };
@@ -227,6 +249,17 @@ mod tests {
         assert_eq!(glob_to_regex(r"foo\"), None);
     }
 
+    #[test]
+    fn literal_prefixes() {
+        assert_eq!(literal_prefix("./src/*/tests/*"), "./src/");
+        assert_eq!(literal_prefix("./src"), "./src");
+        assert_eq!(literal_prefix("*.rs"), "");
+        assert_eq!(literal_prefix("./a?c"), "./a");
+        assert_eq!(literal_prefix("./[ab]/*"), "./");
+        assert_eq!(literal_prefix(r"./\*/\[x]*"), "./*/[x]");
+        assert_eq!(literal_prefix(r"./a\"), "./a");
+    }
+
     #[test]
     fn pattern_matches() {
         assert!(Pattern::new(r"foo*bar", false).matches("foo--bar"));
@@ -3,54 +3,60 @@
 // For the full copyright and license information, please view the LICENSE
 // file that was distributed with this source code.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{ComparableValue, Matcher, MatcherIO, WalkEntry};
 
+#[cfg(windows)]
+use super::sid::Sid;
 #[cfg(unix)]
 use nix::unistd::Group;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 
+/// How files are identified as belonging to a group.
+#[cfg(unix)]
+type Owner = ComparableValue;
+/// Windows doesn't have numeric IDs, so we compare SIDs instead.
+#[cfg(windows)]
+type Owner = Sid;
+
 pub struct GroupMatcher {
-    gid: Option<u32>,
+    gid: Option<Owner>,
 }
 
 impl GroupMatcher {
+    /// Matches files owned by the group `group`. Like GNU find, a group with
+    /// that name takes precedence, but otherwise it can be a numeric ID, with
+    /// an optional + or - prefix as for -gid.
     #[cfg(unix)]
     pub fn from_group_name(group: &str) -> GroupMatcher {
-        // get gid from group name
-        let Ok(group) = Group::from_name(group) else {
-            return GroupMatcher { gid: None };
-        };
-
-        let Some(group) = group else {
-            // This if branch is to determine whether a certain group exists in the system.
-            // If a certain group does not exist in the system,
-            // the result will need to be returned according to
-            // the flag bit of whether to invert the result.
-            return GroupMatcher { gid: None };
-        };
-
-        GroupMatcher {
-            gid: Some(group.gid.as_raw()),
-        }
+        let gid = super::parse_owner(group, |name| {
+            Group::from_name(name)
+                .ok()
+                .flatten()
+                .map(|group| group.gid.as_raw())
+        });
+        GroupMatcher { gid }
     }
 
     #[cfg(unix)]
-    pub fn from_gid(gid: u32) -> GroupMatcher {
+    pub fn from_gid(gid: ComparableValue) -> GroupMatcher {
         GroupMatcher { gid: Some(gid) }
     }
 
+    /// Matches files owned by the group `group`, which can be an account
+    /// name or a SID in string form, like `S-1-5-32-544`.
     #[cfg(windows)]
-    pub fn from_group_name(_group: &str) -> GroupMatcher {
-        GroupMatcher { gid: None }
+    pub fn from_group_name(group: &str) -> GroupMatcher {
+        let gid = Sid::from_account_name(group).or_else(|| Sid::parse(group));
+        GroupMatcher { gid }
     }
 
     #[cfg(windows)]
-    pub fn from_gid(_gid: u32) -> GroupMatcher {
+    pub fn from_gid(_gid: ComparableValue) -> GroupMatcher {
         GroupMatcher { gid: None }
     }
 
-    pub fn gid(&self) -> &Option<u32> {
+    pub fn gid(&self) -> &Option<Owner> {
         &self.gid
     }
 }
@@ -62,19 +68,18 @@ impl Matcher for GroupMatcher {
             return false;
         };
 
-        let file_gid = metadata.gid();
-
         // When matching the -group parameter in find/matcher/mod.rs,
         // it has been judged that the group does not exist and an error is returned.
-        // So use unwarp() directly here.
-        self.gid.unwrap() == file_gid
+        // So use unwrap() directly here.
+        self.gid.as_ref().unwrap().matches(metadata.gid().into())
     }
 
     #[cfg(windows)]
-    fn matches(&self, _file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        // The user group acquisition function for Windows systems is not implemented in MetadataExt,
-        // so it is somewhat difficult to implement it. :(
-        false
+    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+        let Some(gid) = &self.gid else {
+            return false;
+        };
+        Sid::file_group(file_info.path()).is_ok_and(|sid| sid == *gid)
     }
 }
 
@@ -105,8 +110,15 @@ impl Matcher for NoGroupMatcher {
     }
 
     #[cfg(windows)]
-    fn matches(&self, _file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        false
+    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+        if file_info.path().is_symlink() {
+            return false;
+        }
+
+        match Sid::file_group(file_info.path()) {
+            Ok(sid) => !sid.is_known(),
+            Err(_) => true,
+        }
     }
 }
 
@@ -115,7 +127,9 @@ mod tests {
     #[test]
     #[cfg(unix)]
     fn test_group_matcher() {
-        use crate::find::matchers::{group::GroupMatcher, tests::get_dir_entry_for, Matcher};
+        use crate::find::matchers::{
+            group::GroupMatcher, tests::get_dir_entry_for, ComparableValue, Matcher,
+        };
         use crate::find::tests::FakeDependencies;
         use chrono::Local;
         use nix::unistd::{Gid, Group};
@@ -143,7 +157,8 @@ mod tests {
         );
 
         // Testing a non-existent group name
-        let time_string = Local::now().format("%Y%m%d%H%M%S").to_string();
+        // (with a prefix, since numbers are accepted as IDs)
+        let time_string = Local::now().format("no%Y%m%d%H%M%S").to_string();
         let matcher = GroupMatcher::from_group_name(time_string.as_str());
         assert!(
             matcher.gid().is_none(),
@@ -152,7 +167,7 @@ mod tests {
         );
 
         // Testing group id
-        let matcher = GroupMatcher::from_gid(file_gid);
+        let matcher = GroupMatcher::from_gid(ComparableValue::EqualTo(file_gid.into()));
         assert!(
             matcher.gid().is_some(),
             "group id {} should exist",
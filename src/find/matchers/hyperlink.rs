@@ -0,0 +1,104 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Terminal hyperlinks (OSC 8) for --hyperlink, like ls --hyperlink.
+
+use std::fmt::Write;
+use std::path::{self, Path};
+
+/// Turns printed paths into links to `file://` URLs.
+#[derive(Debug, Default)]
+pub struct Hyperlinks {
+    /// The host part of the URLs, so that terminals can tell whether a link
+    /// is to a file on their own machine.
+    host: String,
+}
+
+impl Hyperlinks {
+    pub fn new() -> Self {
+        #[cfg(unix)]
+        let host = nix::unistd::gethostname()
+            .map(|host| host.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        #[cfg(not(unix))]
+        let host = String::new();
+        Self { host }
+    }
+
+    /// Wraps `text` in a link to `path`.
+    pub fn link(&self, path: &Path, text: &str) -> String {
+        let path = path::absolute(path).unwrap_or_else(|_| path.to_owned());
+        format!(
+            "\x1b]8;;file://{}{}\x1b\\{text}\x1b]8;;\x1b\\",
+            self.host,
+            url_path(&path)
+        )
+    }
+}
+
+/// Percent-encodes an absolute path for a URL.
+fn url_path(path: &Path) -> String {
+    #[cfg(unix)]
+    let (path, separator) = {
+        use std::os::unix::ffi::OsStrExt;
+        (path.as_os_str().as_bytes(), b'/')
+    };
+    #[cfg(not(unix))]
+    let path = path.to_string_lossy();
+    #[cfg(not(unix))]
+    let (path, separator) = (path.as_bytes(), b'\\');
+
+    let mut url = String::new();
+    // C:\ becomes /C:/
+    if !path.starts_with(b"/") {
+        url.push('/');
+    }
+    for &byte in path {
+        match byte {
+            b if b == separator => url.push('/'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                url.push(char::from(byte));
+            }
+            b':' if cfg!(windows) => url.push(':'),
+            _ => write!(url, "%{byte:02X}").unwrap(),
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn url_paths() {
+        assert_eq!(url_path(Path::new("/")), "/");
+        assert_eq!(url_path(Path::new("/a b/c%d")), "/a%20b/c%25d");
+        assert_eq!(url_path(Path::new("/é?#")), "/%C3%A9%3F%23");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn url_paths() {
+        assert_eq!(url_path(Path::new(r"C:\")), "/C:/");
+        assert_eq!(url_path(Path::new(r"C:\a b\c%d")), "/C:/a%20b/c%25d");
+    }
+
+    #[test]
+    fn link() {
+        let hyperlinks = Hyperlinks {
+            host: "host".to_owned(),
+        };
+        let path = path::absolute("a").unwrap();
+        assert_eq!(
+            hyperlinks.link(Path::new("a"), "text"),
+            format!(
+                "\x1b]8;;file://host{}\x1b\\text\x1b]8;;\x1b\\",
+                url_path(&path)
+            )
+        );
+    }
+}
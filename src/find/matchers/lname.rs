@@ -4,13 +4,12 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use std::io::{stderr, Write};
 use std::path::PathBuf;
 
 use super::glob::Pattern;
 use super::{Matcher, MatcherIO, WalkEntry};
 
-fn read_link_target(file_info: &WalkEntry) -> Option<PathBuf> {
+fn read_link_target(file_info: &WalkEntry, matcher_io: &MatcherIO) -> Option<PathBuf> {
     match file_info.path().read_link() {
         Ok(target) => Some(target),
         Err(err) => {
@@ -18,7 +17,7 @@ fn read_link_target(file_info: &WalkEntry) -> Option<PathBuf> {
             // shown.
             if err.kind() != std::io::ErrorKind::InvalidInput {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
                     "Error reading target of {}: {}",
                     file_info.path().display(),
                     err
@@ -33,6 +32,9 @@ fn read_link_target(file_info: &WalkEntry) -> Option<PathBuf> {
 
 /// This matcher makes a comparison of the link target against a shell wildcard
 /// pattern. See `glob::Pattern` for details on the exact syntax.
+///
+/// When following symlinks, only broken links are still seen as links, so
+/// nothing else can match.
 pub struct LinkNameMatcher {
     pattern: Pattern,
 }
@@ -45,8 +47,12 @@ impl LinkNameMatcher {
 }
 
 impl Matcher for LinkNameMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        if let Some(target) = read_link_target(file_info) {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        if file_info.follow() && !file_info.file_type().is_symlink() {
+            return false;
+        }
+
+        if let Some(target) = read_link_target(file_info, matcher_io) {
             self.pattern.matches(&target.to_string_lossy())
         } else {
             false
@@ -58,6 +64,8 @@ impl Matcher for LinkNameMatcher {
 mod tests {
     use super::*;
     use crate::find::matchers::tests::get_dir_entry_for;
+    #[cfg(unix)]
+    use crate::find::matchers::{tests::get_dir_entry_follow, Follow};
     use crate::find::tests::FakeDependencies;
 
     use std::io::ErrorKind;
@@ -104,4 +112,37 @@ mod tests {
         let deps = FakeDependencies::new();
         assert!(matcher.matches(&link_f, &mut deps.new_matcher_io()));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn follow_only_matches_broken_links() {
+        create_file_link();
+        for (link, target) in [("link-missing", "missing"), ("link-loop", "link-loop")] {
+            if let Err(e) = symlink(target, format!("test_data/links/{link}")) {
+                assert!(
+                    e.kind() == ErrorKind::AlreadyExists,
+                    "Failed to create sym link: {e:?}"
+                );
+            }
+        }
+
+        let deps = FakeDependencies::new();
+        let matcher = LinkNameMatcher::new("*", false);
+
+        let link_f = get_dir_entry_follow("test_data/links", "link-f", Follow::Always);
+        assert!(!matcher.matches(&link_f, &mut deps.new_matcher_io()));
+
+        let link_missing = get_dir_entry_follow("test_data/links", "link-missing", Follow::Always);
+        assert!(matcher.matches(&link_missing, &mut deps.new_matcher_io()));
+
+        // Following a loop fails without it being a broken link
+        let link_loop = get_dir_entry_follow("test_data/links", "link-loop", Follow::Always);
+        assert!(!matcher.matches(&link_loop, &mut deps.new_matcher_io()));
+
+        // -H only follows the roots
+        let link_f = get_dir_entry_follow("test_data/links", "link-f", Follow::Roots);
+        assert!(matcher.matches(&link_f, &mut deps.new_matcher_io()));
+        let link_loop = get_dir_entry_follow("test_data/links", "link-loop", Follow::Never);
+        assert!(matcher.matches(&link_loop, &mut deps.new_matcher_io()));
+    }
 }
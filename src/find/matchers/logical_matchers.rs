@@ -9,10 +9,9 @@
 //! always-false matchers). The design is strongly tied to the precedence rules
 //! when parsing command-line options (e.g. "-foo -o -bar -baz" is equivalent
 //! to "-foo -o ( -bar -baz )", not "( -foo -o -bar ) -baz").
-use std::error::Error;
 use std::path::Path;
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{FindError, Matcher, MatcherIO, Needs, WalkEntry};
 
 /// This matcher contains a collection of other matchers. A file only matches
 /// if it matches ALL the contained sub-matchers. For sub-matchers that have
@@ -51,6 +50,14 @@ impl Matcher for AndMatcher {
             .any(super::Matcher::has_side_effects)
     }
 
+    fn needs(&self) -> Needs {
+        self.submatchers
+            .iter()
+            .map(super::Matcher::needs)
+            .max()
+            .unwrap_or(Needs::Name)
+    }
+
     fn finished_dir(&self, dir: &Path) {
         for m in &self.submatchers {
             m.finished_dir(dir);
@@ -127,6 +134,14 @@ impl Matcher for OrMatcher {
             .any(super::Matcher::has_side_effects)
     }
 
+    fn needs(&self) -> Needs {
+        self.submatchers
+            .iter()
+            .map(super::Matcher::needs)
+            .max()
+            .unwrap_or(Needs::Name)
+    }
+
     fn finished_dir(&self, dir: &Path) {
         for m in &self.submatchers {
             m.finished_dir(dir);
@@ -153,7 +168,7 @@ impl OrMatcherBuilder {
             .new_and_condition(matcher);
     }
 
-    pub fn new_or_condition(&mut self, arg: &str) -> Result<(), Box<dyn Error>> {
+    pub fn new_or_condition(&mut self, arg: &str) -> Result<(), FindError> {
         if self.submatchers.last().unwrap().submatchers.is_empty() {
             return Err(From::from(format!(
                 "invalid expression; you have used a binary operator \
@@ -222,6 +237,14 @@ impl Matcher for ListMatcher {
             .any(super::Matcher::has_side_effects)
     }
 
+    fn needs(&self) -> Needs {
+        self.submatchers
+            .iter()
+            .map(super::Matcher::needs)
+            .max()
+            .unwrap_or(Needs::Name)
+    }
+
     fn finished_dir(&self, dir: &Path) {
         for m in &self.submatchers {
             m.finished_dir(dir);
@@ -248,26 +271,11 @@ impl ListMatcherBuilder {
             .new_and_condition(matcher);
     }
 
-    pub fn new_or_condition(&mut self, arg: &str) -> Result<(), Box<dyn Error>> {
+    pub fn new_or_condition(&mut self, arg: &str) -> Result<(), FindError> {
         self.submatchers.last_mut().unwrap().new_or_condition(arg)
     }
 
-    pub fn check_new_and_condition(&mut self) -> Result<(), Box<dyn Error>> {
-        {
-            let child_or_matcher = &self.submatchers.last().unwrap();
-            let grandchild_and_matcher = &child_or_matcher.submatchers.last().unwrap();
-
-            if grandchild_and_matcher.submatchers.is_empty() {
-                return Err(From::from(
-                    "invalid expression; you have used a binary operator '-a' \
-                     with nothing before it.",
-                ));
-            }
-        }
-        Ok(())
-    }
-
-    pub fn new_list_condition(&mut self) -> Result<(), Box<dyn Error>> {
+    pub fn new_list_condition(&mut self) -> Result<(), FindError> {
         {
             let child_or_matcher = &self.submatchers.last().unwrap();
             let grandchild_and_matcher = &child_or_matcher.submatchers.last().unwrap();
@@ -313,6 +321,10 @@ impl Matcher for TrueMatcher {
     fn matches(&self, _dir_entry: &WalkEntry, _: &mut MatcherIO) -> bool {
         true
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Name
+    }
 }
 
 /// A simple matcher that never matches.
@@ -322,6 +334,10 @@ impl Matcher for FalseMatcher {
     fn matches(&self, _dir_entry: &WalkEntry, _: &mut MatcherIO) -> bool {
         false
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Name
+    }
 }
 
 /// Matcher that wraps another matcher and inverts matching criteria.
@@ -346,6 +362,10 @@ impl Matcher for NotMatcher {
         self.submatcher.has_side_effects()
     }
 
+    fn needs(&self) -> Needs {
+        self.submatcher.needs()
+    }
+
     fn finished_dir(&self, dir: &Path) {
         self.submatcher.finished_dir(dir);
     }
@@ -6,80 +6,16 @@
 use chrono::DateTime;
 use std::{
     fs::File,
-    io::{stderr, Write},
+    io::{self, Write},
 };
 
 use super::{Matcher, MatcherIO, WalkEntry};
 
+/// Formats a mode like `ls -l` does, including the file type and the setuid,
+/// setgid and sticky bits.
 #[cfg(unix)]
 fn format_permissions(mode: uucore::libc::mode_t) -> String {
-    let file_type = match mode & (uucore::libc::S_IFMT as uucore::libc::mode_t) {
-        uucore::libc::S_IFDIR => "d",
-        uucore::libc::S_IFREG => "-",
-        _ => "?",
-    };
-
-    // S_$$USR means "user permissions"
-    let user_perms = format!(
-        "{}{}{}",
-        if mode & uucore::libc::S_IRUSR != 0 {
-            "r"
-        } else {
-            "-"
-        },
-        if mode & uucore::libc::S_IWUSR != 0 {
-            "w"
-        } else {
-            "-"
-        },
-        if mode & uucore::libc::S_IXUSR != 0 {
-            "x"
-        } else {
-            "-"
-        }
-    );
-
-    // S_$$GRP means "group permissions"
-    let group_perms = format!(
-        "{}{}{}",
-        if mode & uucore::libc::S_IRGRP != 0 {
-            "r"
-        } else {
-            "-"
-        },
-        if mode & uucore::libc::S_IWGRP != 0 {
-            "w"
-        } else {
-            "-"
-        },
-        if mode & uucore::libc::S_IXGRP != 0 {
-            "x"
-        } else {
-            "-"
-        }
-    );
-
-    // S_$$OTH means "other permissions"
-    let other_perms = format!(
-        "{}{}{}",
-        if mode & uucore::libc::S_IROTH != 0 {
-            "r"
-        } else {
-            "-"
-        },
-        if mode & uucore::libc::S_IWOTH != 0 {
-            "w"
-        } else {
-            "-"
-        },
-        if mode & uucore::libc::S_IXOTH != 0 {
-            "x"
-        } else {
-            "-"
-        }
-    );
-
-    format!("{}{}{}{}", file_type, user_perms, group_perms, other_perms)
+    uucore::fs::display_permissions_unix(mode, true)
 }
 
 #[cfg(windows)]
@@ -119,7 +55,7 @@ impl Ls {
     }
 
     #[cfg(unix)]
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write, print_error_message: bool) {
+    fn print(&self, file_info: &WalkEntry, mut out: impl Write) -> io::Result<()> {
         use nix::unistd::{Gid, Group, Uid, User};
         use std::os::unix::fs::{MetadataExt, PermissionsExt};
 
@@ -160,7 +96,7 @@ impl Ls {
         };
         let path = file_info.path().to_string_lossy();
 
-        match writeln!(
+        writeln!(
             out,
             " {:<4} {:>6} {:<10} {:>3} {:<8} {:<8} {:>8} {} {}",
             inode_number,
@@ -172,25 +108,11 @@ impl Ls {
             size,
             last_modified,
             path,
-        ) {
-            Ok(_) => {}
-            Err(e) => {
-                if print_error_message {
-                    writeln!(
-                        &mut stderr(),
-                        "Error writing {:?} for {}",
-                        file_info.path().to_string_lossy(),
-                        e
-                    )
-                    .unwrap();
-                    uucore::error::set_exit_code(1);
-                }
-            }
-        }
+        )
     }
 
     #[cfg(windows)]
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write, print_error_message: bool) {
+    fn print(&self, file_info: &WalkEntry, mut out: impl Write) -> io::Result<()> {
         use std::os::windows::fs::MetadataExt;
 
         let metadata = file_info.metadata().unwrap();
@@ -223,9 +145,9 @@ impl Ls {
         };
         let path = file_info.path().to_string_lossy();
 
-        match write!(
+        writeln!(
             out,
-            " {:<4} {:>6} {:<10} {:>3} {:<8} {:<8} {:>8} {} {}\n",
+            " {:<4} {:>6} {:<10} {:>3} {:<8} {:<8} {:>8} {} {}",
             inode_number,
             number_of_blocks,
             permission,
@@ -235,34 +157,25 @@ impl Ls {
             size,
             last_modified,
             path,
-        ) {
-            Ok(_) => {}
-            Err(e) => {
-                if print_error_message {
-                    writeln!(
-                        &mut stderr(),
-                        "Error writing {:?} for {}",
-                        file_info.path().to_string_lossy(),
-                        e
-                    )
-                    .unwrap();
-                    uucore::error::set_exit_code(1);
-                }
-            }
-        }
+        )
     }
 }
 
 impl Matcher for Ls {
     fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         if let Some(file) = &self.output_file {
-            self.print(file_info, file, true);
+            if let Err(e) = self.print(file_info, file) {
+                writeln!(
+                    matcher_io.error_output(),
+                    "Error writing {:?} for {}",
+                    file_info.path().to_string_lossy(),
+                    e
+                )
+                .unwrap();
+                uucore::error::set_exit_code(1);
+            }
         } else {
-            self.print(
-                file_info,
-                &mut *matcher_io.deps.get_output().borrow_mut(),
-                false,
-            );
+            let _ = self.print(file_info, &mut *matcher_io.deps.get_output().borrow_mut());
         }
         true
     }
@@ -290,5 +203,23 @@ mod tests {
         let mode: uucore::libc::mode_t = 0o100777;
         let expected = "-rwxrwxrwx";
         assert_eq!(format_permissions(mode), expected);
+
+        for (mode, expected) in [
+            (0o104644, "-rwSr--r--"),
+            (0o104755, "-rwsr-xr-x"),
+            (0o102644, "-rw-r-Sr--"),
+            (0o102755, "-rwxr-sr-x"),
+            (0o101776, "-rwxrwxrwT"),
+            (0o041777, "drwxrwxrwt"),
+            (0o106000, "---S--S---"),
+            (0o107777, "-rwsrwsrwt"),
+            (0o020644, "crw-r--r--"),
+            (0o060660, "brw-rw----"),
+            (0o010644, "prw-r--r--"),
+            (0o120777, "lrwxrwxrwx"),
+            (0o140755, "srwxr-xr-x"),
+        ] {
+            assert_eq!(format_permissions(mode), expected, "{mode:o}");
+        }
     }
 }
@@ -5,17 +5,32 @@
 // https://opensource.org/licenses/MIT.
 
 mod access;
+mod action_log;
+#[cfg(target_os = "linux")]
+mod cap;
+#[cfg(unix)]
+mod chmod;
+#[cfg(unix)]
+mod chown;
+mod color;
+mod copy;
 mod delete;
 mod empty;
 mod entry;
+#[doc(hidden)]
 pub mod exec;
+mod expr;
+mod fold;
+#[doc(hidden)]
 pub mod fs;
 mod glob;
 mod group;
+mod hyperlink;
 mod lname;
 mod logical_matchers;
 mod ls;
 mod name;
+mod parse;
 mod path;
 mod perm;
 mod printer;
@@ -23,11 +38,17 @@ mod printf;
 mod prune;
 mod quit;
 mod regex;
+mod response;
 mod samefile;
+#[cfg(windows)]
+mod sid;
 mod size;
 #[cfg(unix)]
 mod stat;
+mod tar;
+#[doc(hidden)]
 pub mod time;
+mod touch;
 mod type_matcher;
 mod user;
 
@@ -35,15 +56,27 @@ use ::regex::Regex;
 use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
 use fs::FileSystemMatcher;
 use ls::Ls;
-use std::fs::{File, Metadata};
-use std::path::Path;
+use std::cell::RefMut;
+use std::fmt::Display;
+use std::fs::{File, Metadata, OpenOptions};
+use std::io::{stdout, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::time::SystemTime;
 use std::{error::Error, str::FromStr};
 
 use self::access::AccessMatcher;
+#[cfg(target_os = "linux")]
+use self::cap::CapabilityMatcher;
+#[cfg(unix)]
+use self::chmod::ChmodMatcher;
+#[cfg(unix)]
+use self::chown::ChownMatcher;
+use self::color::Colorize;
+use self::copy::{CopyMatcher, Transfer};
 use self::delete::DeleteMatcher;
 use self::empty::EmptyMatcher;
-use self::exec::SingleExecMatcher;
+use self::exec::{Sandbox, SingleExecMatcher};
 use self::group::{GroupMatcher, NoGroupMatcher};
 use self::lname::LinkNameMatcher;
 use self::logical_matchers::{
@@ -58,20 +91,36 @@ use self::prune::PruneMatcher;
 use self::quit::QuitMatcher;
 use self::regex::RegexMatcher;
 use self::samefile::SameFileMatcher;
-use self::size::SizeMatcher;
+use self::size::{SizeMatcher, SparseMatcher};
 #[cfg(unix)]
 use self::stat::{InodeMatcher, LinksMatcher};
+use self::tar::Tar;
 use self::time::{
     FileAgeRangeMatcher, FileTimeMatcher, FileTimeType, NewerMatcher, NewerOptionMatcher,
     NewerOptionType, NewerTimeMatcher,
 };
+use self::touch::{TouchMatcher, TouchTimes};
 use self::type_matcher::{TypeMatcher, XtypeMatcher};
 use self::user::{NoUserMatcher, UserMatcher};
 
-use super::{Config, Dependencies};
+use super::sort::SortOrder;
+use super::walk::{Throttle, VisitedDirs};
+use super::{Config, Dependencies, FindError};
+use crate::exec_common::{parse_nice, parse_rlimits, parse_timeout, Limits};
 
 pub use entry::{FileType, WalkEntry, WalkError};
 
+pub(crate) use self::entry::{count_stat_call, stat_calls};
+
+pub(crate) use self::action_log::ActionLog;
+pub(crate) use self::color::LsColors;
+pub(crate) use self::copy::Collision;
+pub(crate) use self::expr::Expr;
+use self::expr::Primary;
+pub(crate) use self::hyperlink::Hyperlinks;
+pub(crate) use self::parse::{is_primary, skip_primary};
+pub(crate) use self::regex::RegexType;
+
 /// Symlink following mode.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Follow {
@@ -93,20 +142,18 @@ impl Follow {
         }
     }
 
-    /// Get metadata for a [WalkEntry].
+    /// Get metadata for a [WalkEntry], as if it had been found with this
+    /// follow mode.
     pub fn metadata(self, entry: &WalkEntry) -> Result<Metadata, WalkError> {
-        if self.follow_at_depth(entry.depth()) == entry.follow() {
-            // Same follow flag, re-use cached metadata
-            entry.metadata().cloned()
-        } else if !entry.follow() && !entry.file_type().is_symlink() {
-            // Not a symlink, re-use cached metadata
-            entry.metadata().cloned()
-        } else if entry.follow() && entry.file_type().is_symlink() {
-            // Broken symlink, re-use cached metadata
-            entry.metadata().cloned()
+        if self.follow_at_depth(entry.depth()) {
+            match entry.target_metadata() {
+                Err(e) if e.is_not_found() => entry.link_metadata(),
+                result => result,
+            }
         } else {
-            self.metadata_at_depth(entry.path(), entry.depth())
+            entry.link_metadata()
         }
+        .cloned()
     }
 
     /// Get metadata for a path from the command line.
@@ -123,6 +170,7 @@ impl Follow {
         let path = path.as_ref();
 
         if self.follow_at_depth(depth) {
+            count_stat_call();
             match path.metadata().map_err(WalkError::from) {
                 Ok(meta) => return Ok(meta),
                 Err(e) if !e.is_not_found() => return Err(e),
@@ -130,6 +178,7 @@ impl Follow {
             }
         }
 
+        count_stat_call();
         Ok(path.symlink_metadata()?)
     }
 }
@@ -140,15 +189,19 @@ pub struct MatcherIO<'a> {
     should_skip_dir: bool,
     exit_code: i32,
     quit: bool,
+    dry_run: bool,
+    action_log: Option<&'a ActionLog>,
     deps: &'a dyn Dependencies,
 }
 
-impl MatcherIO<'_> {
+impl<'a> MatcherIO<'a> {
     pub fn new(deps: &dyn Dependencies) -> MatcherIO<'_> {
         MatcherIO {
             should_skip_dir: false,
             exit_code: 0,
             quit: false,
+            dry_run: false,
+            action_log: None,
             deps,
         }
     }
@@ -171,6 +224,18 @@ impl MatcherIO<'_> {
         self.exit_code
     }
 
+    /// Where to write error messages, which is stderr except in tests.
+    pub fn error_output(&self) -> RefMut<'_, dyn Write> {
+        self.deps.get_error_output().borrow_mut()
+    }
+
+    /// Reports an error with a file that doesn't stop the search, but does
+    /// make find exit with status 1 once it's done.
+    pub fn report_error(&mut self, message: impl Display) {
+        writeln!(self.error_output(), "{message}").unwrap();
+        self.set_exit_code(1);
+    }
+
     pub fn quit(&mut self) {
         self.quit = true;
     }
@@ -180,12 +245,49 @@ impl MatcherIO<'_> {
         self.quit
     }
 
+    /// Makes destructive actions like -delete and -exec report what they
+    /// would do, instead of doing it.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    #[must_use]
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub(crate) fn set_action_log(&mut self, action_log: Option<&'a ActionLog>) {
+        self.action_log = action_log;
+    }
+
+    /// Records an action with side effects in the -log file, if there is one.
+    pub fn log_action(&self, action: &str, path: &Path, result: &str) {
+        if let Some(action_log) = self.action_log {
+            if let Err(e) = action_log.record(action, path, result) {
+                writeln!(self.error_output(), "Error writing to log file: {e}").unwrap();
+                uucore::error::set_exit_code(1);
+            }
+        }
+    }
+
     #[must_use]
     pub fn now(&self) -> SystemTime {
         self.deps.now()
     }
 }
 
+/// How much a matcher needs to know about each file, from the cheapest to the
+/// dearest to find out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Needs {
+    /// Just the path and depth.
+    Name,
+    /// The file type, which reading the directory usually tells us.
+    Type,
+    /// Anything else, which may mean a stat().
+    Metadata,
+}
+
 /// A basic interface that can be used to determine whether a directory entry
 /// is what's being searched for. To a first order approximation, find consists
 /// of building a chain of Matcher objects, and then walking a directory tree,
@@ -212,6 +314,13 @@ pub trait Matcher: 'static {
         false
     }
 
+    /// Returns what the matcher needs to know about each file, so that the
+    /// walker can avoid finding out anything more. Unless a matcher says
+    /// otherwise, it might need any of the file's metadata.
+    fn needs(&self) -> Needs {
+        Needs::Metadata
+    }
+
     /// Notification that find has finished processing a given directory.
     fn finished_dir(&self, _finished_directory: &Path) {}
 
@@ -234,6 +343,10 @@ impl Matcher for Box<dyn Matcher> {
         (**self).has_side_effects()
     }
 
+    fn needs(&self) -> Needs {
+        (**self).needs()
+    }
+
     fn finished_dir(&self, finished_directory: &Path) {
         (**self).finished_dir(finished_directory);
     }
@@ -243,6 +356,8 @@ impl Matcher for Box<dyn Matcher> {
     }
 }
 
+#[doc(hidden)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum ComparableValue {
     MoreThan(u64),
     EqualTo(u64),
@@ -270,45 +385,181 @@ impl ComparableValue {
 
 /// Builds a single `AndMatcher` containing the Matcher objects corresponding
 /// to the passed in predicate arguments.
+///
+/// Errors are [FindError]s, and can be downcast to one to find out more.
 pub fn build_top_level_matcher(
     args: &[&str],
     config: &mut Config,
 ) -> Result<Box<dyn Matcher>, Box<dyn Error>> {
-    let (_, top_level_matcher) = (build_matcher_tree(args, config, 0, false))?;
+    Ok(build_top_level_expression(args, config).map(|(matcher, _)| matcher)?)
+}
+
+/// Like [build_top_level_matcher], but also returns a description of the
+/// parsed expression.
+pub(crate) fn build_top_level_expression(
+    args: &[&str],
+    config: &mut Config,
+) -> Result<(Box<dyn Matcher>, Expr), FindError> {
+    let expr = parse::parse_expression(args)?;
+    if expr.contains("-delete") && expr.contains("-prune") && !expr.contains("-depth") {
+        // -prune does nothing once -delete has turned on -depth, and GNU find
+        // would rather not delete what the user meant to prune
+        return Err(FindError::Parse(
+            "The -delete action automatically turns on -depth, but -prune does nothing \
+             when -depth is in effect.  If you want to carry on anyway, just explicitly \
+             use the -depth option."
+                .to_owned(),
+        ));
+    }
+    if expr.contains("-bfs") && (expr.contains("-depth") || expr.contains("-delete")) {
+        // Post-order traversal has to go depth-first, as each directory comes
+        // after everything in it
+        return Err(FindError::Parse(
+            "-bfs can't be combined with -depth, or -delete, which turns it on".to_owned(),
+        ));
+    }
+    // Like --dry-run, these affect printing wherever they appear, so they have
+    // to be dealt with before anything that prints is built
+    let when = |name| {
+        let when = expr.find_last(name)?.args.first().map(String::as_str);
+        Some(convert_when_arg(name, when))
+    };
+    if let Some(color) = when("--color") {
+        if color? {
+            config.colors = Some(Rc::new(LsColors::from_env()));
+        }
+    }
+    if let Some(hyperlink) = when("--hyperlink") {
+        if hyperlink? {
+            config.hyperlinks = Some(Rc::new(Hyperlinks::new()));
+        }
+    }
+    config.relative = expr.contains("--relative");
+    config.normalize_paths = expr.contains("--normalize-paths");
+    // The same goes for --exec-timeout and any -exec
+    if let Some(timeout) = expr.find_last("--exec-timeout") {
+        let arg = &timeout.args[0];
+        config.exec_timeout = parse_timeout(arg)
+            .map_err(|e| FindError::invalid_argument("--exec-timeout", arg, e))?;
+    }
+    let nice = match expr.find_last("--exec-nice") {
+        Some(nice) => Some(
+            parse_nice(&nice.args[0])
+                .map_err(|e| FindError::invalid_argument("--exec-nice", &nice.args[0], e))?,
+        ),
+        None => None,
+    };
+    let rlimits = match expr.find_last("--exec-rlimit") {
+        Some(rlimit) => parse_rlimits(&rlimit.args[0])
+            .map_err(|e| FindError::invalid_argument("--exec-rlimit", &rlimit.args[0], e))?,
+        None => vec![],
+    };
+    config.exec_limits = Limits::new(nice, rlimits).map_err(FindError::Unsupported)?;
+    if let Some(errors) = expr.find_last("--errors") {
+        let arg = &errors.args[0];
+        config.errors_json = match arg.as_str() {
+            "text" => false,
+            "json" => true,
+            _ => {
+                return Err(FindError::invalid_argument(
+                    "--errors",
+                    arg,
+                    format!("invalid error format '{arg}': expected text or json"),
+                ))
+            }
+        };
+    }
+    if let Some(sandbox) = expr.find_last("--exec-sandbox") {
+        let dir = sandbox.args.first().map(PathBuf::from);
+        if let Some(dir) = &dir {
+            if !dir.is_dir() {
+                return Err(FindError::invalid_argument(
+                    "--exec-sandbox",
+                    &dir.to_string_lossy(),
+                    format!("'{}' is not a directory", dir.display()),
+                ));
+            }
+        }
+        let keep_env = expr
+            .find_last("--exec-keep-env")
+            .map(|keep| keep.args[0].split(',').map(str::to_owned).collect())
+            .unwrap_or_default();
+        config.exec_sandbox = Some(Sandbox::new(dir, keep_env));
+    }
+    // And --collision and any -copy-to or -move-to
+    if let Some(collision) = expr.find_last("--collision") {
+        let arg = &collision.args[0];
+        config.collision = arg
+            .parse()
+            .map_err(|e| FindError::invalid_argument("--collision", arg, e))?;
+    }
+    // A dry run shouldn't even create -tar's archive
+    if expr.contains("--dry-run") {
+        config.dry_run = true;
+    }
+    config.path_prefixes = expr.path_prefixes();
+    let mut regex_type = config.regex_type;
+    let top_level_matcher = build_matcher(&expr, config, &mut regex_type)?;
 
     // if the matcher doesn't have any side-effects, then we default to printing
     if !top_level_matcher.has_side_effects() {
         let mut new_and_matcher = AndMatcherBuilder::new();
         new_and_matcher.new_and_condition(top_level_matcher);
-        new_and_matcher.new_and_condition(Printer::new(PrintDelimiter::Newline, None));
-        return Ok(new_and_matcher.build());
+        new_and_matcher.new_and_condition(build_print(config));
+        return Ok((new_and_matcher.build(), expr.and_print(args.len())));
     }
-    Ok(top_level_matcher)
+    Ok((top_level_matcher, expr))
 }
 
-/// Helper function for `build_matcher_tree`.
-fn are_more_expressions(args: &[&str], index: usize) -> bool {
-    (index < args.len() - 1) && args[index + 1] != ")"
+/// Builds -print, colored and with links if --color and --hyperlink say so.
+fn build_print(config: &Config) -> Box<dyn Matcher> {
+    let printer = Printer::new(PrintDelimiter::Newline, None)
+        .hyperlinks(config.hyperlinks.clone())
+        .relative(config.relative)
+        .xargs_safe(config.xargs_safe);
+    match &config.colors {
+        Some(colors) => Colorize::new(printer, colors.clone()).into_box(),
+        None => printer.into_box(),
+    }
 }
 
-fn convert_arg_to_number(
-    option_name: &str,
-    value_as_string: &str,
-) -> Result<usize, Box<dyn Error>> {
+/// Works out whether an option like `--color[=WHEN]` is on. Without a WHEN,
+/// like ls, it always is.
+fn convert_when_arg(option_name: &str, when: Option<&str>) -> Result<bool, FindError> {
+    match when {
+        None | Some("always" | "yes" | "force") => Ok(true),
+        Some("never" | "no" | "none") => Ok(false),
+        Some("auto" | "tty" | "if-tty") => Ok(stdout().is_terminal()),
+        Some(when) => Err(FindError::invalid_argument(
+            option_name,
+            when,
+            format!(
+                "invalid argument '{when}' for '{option_name}'\n\
+                 Valid arguments are 'always', 'never' and 'auto'"
+            ),
+        )),
+    }
+}
+
+fn convert_arg_to_number(option_name: &str, value_as_string: &str) -> Result<usize, FindError> {
     match value_as_string.parse::<usize>() {
         Ok(val) => Ok(val),
-        _ => Err(From::from(format!(
-            "Expected a positive decimal integer argument to {option_name}, but got \
-             `{value_as_string}'"
-        ))),
+        _ => Err(FindError::invalid_argument(
+            option_name,
+            value_as_string,
+            format!(
+                "Expected a positive decimal integer argument to {option_name}, but got \
+                 `{value_as_string}'"
+            ),
+        )),
     }
 }
 
 fn convert_arg_to_comparable_value(
     option_name: &str,
     value_as_string: &str,
-) -> Result<ComparableValue, Box<dyn Error>> {
-    let re = Regex::new(r"^([+-]?)(\d+)$")?;
+) -> Result<ComparableValue, FindError> {
+    let re = Regex::new(r"^([+-]?)(\d+)$").unwrap();
     if let Some(groups) = re.captures(value_as_string) {
         if let Ok(val) = groups[2].parse::<u64>() {
             return Ok(match &groups[1] {
@@ -318,17 +569,21 @@ fn convert_arg_to_comparable_value(
             });
         }
     }
-    Err(From::from(format!(
-        "Expected a decimal integer (with optional + or - prefix) argument \
-         to {option_name}, but got `{value_as_string}'"
-    )))
+    Err(FindError::invalid_argument(
+        option_name,
+        value_as_string,
+        format!(
+            "Expected a decimal integer (with optional + or - prefix) argument \
+             to {option_name}, but got `{value_as_string}'"
+        ),
+    ))
 }
 
 fn convert_arg_to_comparable_value_and_suffix(
     option_name: &str,
     value_as_string: &str,
-) -> Result<(ComparableValue, String), Box<dyn Error>> {
-    let re = Regex::new(r"([+-]?)(\d+)(.*)$")?;
+) -> Result<(ComparableValue, String), FindError> {
+    let re = Regex::new(r"([+-]?)(\d+)(.*)$").unwrap();
     if let Some(groups) = re.captures(value_as_string) {
         if let Ok(val) = groups[2].parse::<u64>() {
             return Ok((
@@ -341,10 +596,26 @@ fn convert_arg_to_comparable_value_and_suffix(
             ));
         }
     }
-    Err(From::from(format!(
-        "Expected a decimal integer (with optional + or - prefix) and \
-         (optional suffix) argument to {option_name}, but got `{value_as_string}'"
-    )))
+    Err(FindError::invalid_argument(
+        option_name,
+        value_as_string,
+        format!(
+            "Expected a decimal integer (with optional + or - prefix) and \
+             (optional suffix) argument to {option_name}, but got `{value_as_string}'"
+        ),
+    ))
+}
+
+/// Parses the argument to -user or -group. A name that `lookup` knows about
+/// always wins, so a user called "1000" is matched by name, just like GNU
+/// find. Otherwise, numeric IDs are accepted, with an optional + or - prefix
+/// as for -uid and -gid.
+#[cfg(unix)]
+fn parse_owner(name: &str, lookup: impl FnOnce(&str) -> Option<u32>) -> Option<ComparableValue> {
+    match lookup(name) {
+        Some(id) => Some(ComparableValue::EqualTo(id.into())),
+        None => convert_arg_to_comparable_value("", name).ok(),
+    }
 }
 
 /// This is a function that converts a specific string format into a timestamp.
@@ -419,537 +690,452 @@ fn parse_str_to_newer_args(input: &str) -> Option<(String, String)> {
 
 /// Creates a file if it doesn't exist.
 /// If it does exist, it will be overwritten.
-fn get_or_create_file(path: &str) -> Result<File, Box<dyn Error>> {
-    let file = File::create(path)?;
-    Ok(file)
+fn get_or_create_file(path: &str) -> Result<File, FindError> {
+    File::create(path).map_err(|e| FindError::io(path, e))
 }
 
-/// The main "translate command-line args into a matcher" function. Will call
-/// itself recursively if it encounters an opening bracket. A successful return
-/// consists of a tuple containing the new index into the args array to use (if
-/// called recursively) and the resulting matcher.
-fn build_matcher_tree(
-    args: &[&str],
+/// The main "translate a parsed expression into a matcher" function. Will call
+/// itself recursively for compound expressions. The expression is lowered from
+/// left to right, so options that affect later tests (like -daystart) take
+/// effect in the right order.
+fn build_matcher(
+    expr: &Expr,
     config: &mut Config,
-    arg_index: usize,
-    mut expecting_bracket: bool,
-) -> Result<(usize, Box<dyn Matcher>), Box<dyn Error>> {
-    let mut top_level_matcher = ListMatcherBuilder::new();
-
-    let mut regex_type = regex::RegexType::default();
-
-    // can't use getopts for a variety or reasons:
-    // order of arguments is important
-    // arguments can start with + as well as -
-    // multiple-character flags don't start with a double dash
-    let mut i = arg_index;
-    let mut invert_next_matcher = false;
-    while i < args.len() {
-        let possible_submatcher = match args[i] {
-            "-print" => Some(Printer::new(PrintDelimiter::Newline, None).into_box()),
-            "-print0" => Some(Printer::new(PrintDelimiter::Null, None).into_box()),
-            "-printf" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(Printf::new(args[i], None)?.into_box())
+    regex_type: &mut RegexType,
+) -> Result<Box<dyn Matcher>, FindError> {
+    match expr {
+        Expr::Primary(primary) => build_primary(primary, config, regex_type),
+        Expr::Not(expr) => Ok(NotMatcher::new(build_matcher(expr, config, regex_type)?).into_box()),
+        Expr::And(exprs) => {
+            let mut builder = AndMatcherBuilder::new();
+            for expr in exprs {
+                builder.new_and_condition(build_matcher(expr, config, regex_type)?);
             }
-            "-fprint" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-
-                let file = get_or_create_file(args[i])?;
-                Some(Printer::new(PrintDelimiter::Newline, Some(file)).into_box())
-            }
-            "-fprintf" => {
-                if i >= args.len() - 2 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
+            Ok(builder.build())
+        }
+        Expr::Or(exprs) => {
+            let mut builder = ListMatcherBuilder::new();
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    builder.new_or_condition("-o")?;
                 }
-
-                // Action: -fprintf file format
-                // Args + 1: output file path
-                // Args + 2: format string
-                i += 1;
-                let file = get_or_create_file(args[i])?;
-                i += 1;
-                Some(Printf::new(args[i], Some(file))?.into_box())
+                builder.new_and_condition(build_matcher(expr, config, regex_type)?);
             }
-            "-fprint0" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
+            Ok(builder.build())
+        }
+        Expr::List(exprs) => {
+            let mut builder = ListMatcherBuilder::new();
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    builder.new_list_condition()?;
                 }
-                i += 1;
-
-                let file = get_or_create_file(args[i])?;
-                Some(Printer::new(PrintDelimiter::Null, Some(file)).into_box())
+                builder.new_and_condition(build_matcher(expr, config, regex_type)?);
             }
-            "-ls" => Some(Ls::new(None).into_box()),
-            "-fls" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
+            Ok(builder.build())
+        }
+    }
+}
 
-                let file = get_or_create_file(args[i])?;
-                Some(Ls::new(Some(file)).into_box())
-            }
-            "-true" => Some(TrueMatcher.into_box()),
-            "-false" => Some(FalseMatcher.into_box()),
-            "-lname" | "-ilname" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(LinkNameMatcher::new(args[i], args[i - 1].starts_with("-i")).into_box())
-            }
-            "-name" | "-iname" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(NameMatcher::new(args[i], args[i - 1].starts_with("-i")).into_box())
-            }
-            "-path" | "-ipath" | "-wholename" | "-iwholename" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(PathMatcher::new(args[i], args[i - 1].starts_with("-i")).into_box())
-            }
-            "-readable" => Some(AccessMatcher::Readable.into_box()),
-            "-regextype" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                regex_type = regex::RegexType::from_str(args[i])?;
-                Some(TrueMatcher.into_box())
-            }
-            "-regex" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(RegexMatcher::new(regex_type, args[i], false)?.into_box())
-            }
-            "-iregex" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(RegexMatcher::new(regex_type, args[i], true)?.into_box())
-            }
-            "-type" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(TypeMatcher::new(args[i])?.into_box())
-            }
-            "-xtype" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(XtypeMatcher::new(args[i])?.into_box())
-            }
-            "-fstype" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(FileSystemMatcher::new(args[i].to_string()).into_box())
-            }
-            "-delete" => {
-                // -delete implicitly requires -depth
-                config.depth_first = true;
-                Some(DeleteMatcher::new().into_box())
-            }
-            "-newer" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(NewerMatcher::new(args[i], config.follow)?.into_box())
-            }
-            "-mtime" | "-atime" | "-ctime" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let file_time_type = match args[i] {
-                    "-atime" => FileTimeType::Accessed,
-                    "-ctime" => FileTimeType::Changed,
-                    "-mtime" => FileTimeType::Modified,
-                    // This shouldn't be possible. We've already checked the value
-                    // is one of those three values.
-                    _ => unreachable!("Encountered unexpected value {}", args[i]),
-                };
-                let days = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(FileTimeMatcher::new(file_time_type, days, config.today_start).into_box())
-            }
-            "-amin" | "-cmin" | "-mmin" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let file_time_type = match args[i] {
-                    "-amin" => FileTimeType::Accessed,
-                    "-cmin" => FileTimeType::Changed,
-                    "-mmin" => FileTimeType::Modified,
-                    _ => unreachable!("Encountered unexpected value {}", args[i]),
-                };
-                let minutes = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(
-                    FileAgeRangeMatcher::new(file_time_type, minutes, config.today_start)
-                        .into_box(),
-                )
-            }
-            "-size" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let (size, unit) =
-                    convert_arg_to_comparable_value_and_suffix(args[i], args[i + 1])?;
-                i += 1;
-                Some(SizeMatcher::new(size, &unit)?.into_box())
-            }
-            "-empty" => Some(EmptyMatcher::new().into_box()),
-            "-exec" | "-execdir" => {
-                let mut arg_index = i + 1;
-                while arg_index < args.len() && args[arg_index] != ";" {
-                    if args[arg_index - 1] == "{}" && args[arg_index] == "+" {
-                        // MultiExecMatcher isn't written yet
-                        return Err(From::from(format!(
-                            "{} [args...] + isn't supported yet. \
-                             Only {} [args...] ;",
-                            args[i], args[i]
-                        )));
-                    }
-                    arg_index += 1;
-                }
-                if arg_index < i + 2 || arg_index == args.len() {
-                    // at the minimum we need the executable and the ';'
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let expression = args[i];
-                let executable = args[i + 1];
-                let exec_args = &args[i + 2..arg_index];
-                i = arg_index;
-                Some(
-                    SingleExecMatcher::new(executable, exec_args, expression == "-execdir")?
-                        .into_box(),
-                )
-            }
-            #[cfg(unix)]
-            "-inum" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let inum = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(InodeMatcher::new(inum).into_box())
-            }
-            #[cfg(not(unix))]
-            "-inum" => {
-                return Err(From::from(
-                    "Inode numbers are not available on this platform",
+/// Builds the matcher for a single primary. The parser has already checked
+/// that it has the right number of arguments.
+fn build_primary(
+    primary: &Primary,
+    config: &mut Config,
+    regex_type: &mut RegexType,
+) -> Result<Box<dyn Matcher>, FindError> {
+    let name = primary.name.as_str();
+    let args: Vec<&str> = primary.args.iter().map(String::as_str).collect();
+    // For the errors from matchers that check their first argument
+    let invalid = |e: Box<dyn Error>| FindError::invalid_argument(name, args[0], e);
+
+    let matcher = match name {
+        "-print" => build_print(config),
+        "-print0" => Printer::new(PrintDelimiter::Null, None)
+            .hyperlinks(config.hyperlinks.clone())
+            .relative(config.relative)
+            .into_box(),
+        "-printf" => Printf::new(args[0], None)
+            .map_err(invalid)?
+            .hyperlinks(config.hyperlinks.clone())
+            .relative(config.relative)
+            .into_box(),
+        "-fprint" => {
+            let file = get_or_create_file(args[0])?;
+            Printer::new(PrintDelimiter::Newline, Some(file))
+                .relative(config.relative)
+                .xargs_safe(config.xargs_safe)
+                .into_box()
+        }
+        "-fprintf" => {
+            // Action: -fprintf file format
+            // Args + 1: output file path
+            // Args + 2: format string
+            let file = get_or_create_file(args[0])?;
+            Printf::new(args[1], Some(file))
+                .map_err(|e| FindError::invalid_argument(name, args[1], e))?
+                .relative(config.relative)
+                .into_box()
+        }
+        "-fprint0" => {
+            let file = get_or_create_file(args[0])?;
+            Printer::new(PrintDelimiter::Null, Some(file))
+                .relative(config.relative)
+                .into_box()
+        }
+        "-ls" => Ls::new(None).into_box(),
+        "-touch" => TouchMatcher::new(match args.first() {
+            Some(&"-a") => TouchTimes::Access,
+            Some(&"-m") => TouchTimes::Modification,
+            _ => TouchTimes::Both,
+        })
+        .into_box(),
+        #[cfg(target_os = "linux")]
+        "-cap" => CapabilityMatcher::new(args.first().copied())
+            .map_err(invalid)?
+            .into_box(),
+        #[cfg(not(target_os = "linux"))]
+        "-cap" => {
+            return Err(FindError::Unsupported(
+                "File capabilities are only available on Linux".to_owned(),
+            ));
+        }
+        #[cfg(unix)]
+        "-chmod" => ChmodMatcher::new(args[0]).map_err(invalid)?.into_box(),
+        #[cfg(not(unix))]
+        "-chmod" => {
+            return Err(FindError::Unsupported(
+                "Changing permissions is not supported on this platform".to_owned(),
+            ));
+        }
+        #[cfg(unix)]
+        "-chown" => ChownMatcher::new(args[0]).map_err(invalid)?.into_box(),
+        #[cfg(not(unix))]
+        "-chown" => {
+            return Err(FindError::Unsupported(
+                "Changing ownership is not supported on this platform".to_owned(),
+            ));
+        }
+        "-copy-to" => CopyMatcher::new(Transfer::Copy, args[0], config.collision).into_box(),
+        "-move-to" => CopyMatcher::new(Transfer::Move, args[0], config.collision).into_box(),
+        "-tar" => Tar::new(args[0], config.dry_run).into_box(),
+        "-fls" => {
+            let file = get_or_create_file(args[0])?;
+            Ls::new(Some(file)).into_box()
+        }
+        "-true" => TrueMatcher.into_box(),
+        "-false" => FalseMatcher.into_box(),
+        "-lname" | "-ilname" => LinkNameMatcher::new(args[0], name.starts_with("-i")).into_box(),
+        "-name" | "-iname" => NameMatcher::new(args[0], name.starts_with("-i")).into_box(),
+        "-path" | "-ipath" | "-wholename" | "-iwholename" => {
+            PathMatcher::new(args[0], name.starts_with("-i")).into_box()
+        }
+        "-readable" => AccessMatcher::Readable.into_box(),
+        "-regextype" => {
+            *regex_type = RegexType::from_str(args[0])
+                .map_err(|e| FindError::invalid_argument(name, args[0], e))?;
+            TrueMatcher.into_box()
+        }
+        "-regex" => RegexMatcher::new(*regex_type, args[0], false)
+            .map_err(invalid)?
+            .into_box(),
+        "-iregex" => RegexMatcher::new(*regex_type, args[0], true)
+            .map_err(invalid)?
+            .into_box(),
+        "-type" => TypeMatcher::new(args[0]).map_err(invalid)?.into_box(),
+        "-xtype" => XtypeMatcher::new(args[0]).map_err(invalid)?.into_box(),
+        "-fstype" => FileSystemMatcher::new(args[0].to_string()).into_box(),
+        "-delete" => {
+            // -delete implicitly requires -depth
+            config.depth_first = true;
+            DeleteMatcher::new().into_box()
+        }
+        "-newer" => NewerMatcher::new(args[0], config.follow)?.into_box(),
+        "-mtime" | "-atime" | "-ctime" => {
+            let file_time_type = match name {
+                "-atime" => FileTimeType::Accessed,
+                "-ctime" => FileTimeType::Changed,
+                "-mtime" => FileTimeType::Modified,
+                // This shouldn't be possible. We've already checked the value
+                // is one of those three values.
+                _ => unreachable!("Encountered unexpected value {}", name),
+            };
+            let days = convert_arg_to_comparable_value(name, args[0])?;
+            FileTimeMatcher::new(file_time_type, days, config.today_start).into_box()
+        }
+        "-amin" | "-cmin" | "-mmin" => {
+            let file_time_type = match name {
+                "-amin" => FileTimeType::Accessed,
+                "-cmin" => FileTimeType::Changed,
+                "-mmin" => FileTimeType::Modified,
+                _ => unreachable!("Encountered unexpected value {}", name),
+            };
+            let minutes = convert_arg_to_comparable_value(name, args[0])?;
+            FileAgeRangeMatcher::new(file_time_type, minutes, config.today_start).into_box()
+        }
+        "-size" => {
+            let (size, unit) = convert_arg_to_comparable_value_and_suffix(name, args[0])?;
+            SizeMatcher::new(size, &unit).map_err(invalid)?.into_box()
+        }
+        "-sparse" => SparseMatcher::new(args.first().copied())
+            .map_err(invalid)?
+            .into_box(),
+        "-empty" => EmptyMatcher::new().into_box(),
+        "-exec" | "-execdir" | "-ok" | "-okdir" => {
+            // The last argument is the ';'
+            let executable = args[0];
+            let exec_args = &args[1..args.len() - 1];
+            let prompt = name.starts_with("-ok");
+            config.prompts |= prompt;
+            SingleExecMatcher::new(executable, exec_args, name.ends_with("dir"))
+                .map_err(invalid)?
+                .timeout(config.exec_timeout)
+                .sandbox(config.exec_sandbox.clone())
+                .limits(config.exec_limits.clone())
+                .prompt(prompt)
+                .into_box()
+        }
+        #[cfg(unix)]
+        "-inum" => {
+            let inum = convert_arg_to_comparable_value(name, args[0])?;
+            InodeMatcher::new(inum).into_box()
+        }
+        #[cfg(not(unix))]
+        "-inum" => {
+            return Err(FindError::Unsupported(
+                "Inode numbers are not available on this platform".to_owned(),
+            ));
+        }
+        #[cfg(unix)]
+        "-links" => {
+            let inum = convert_arg_to_comparable_value(name, args[0])?;
+            LinksMatcher::new(inum).into_box()
+        }
+        #[cfg(not(unix))]
+        "-links" => {
+            return Err(FindError::Unsupported(
+                "Link counts are not available on this platform".to_owned(),
+            ));
+        }
+        "-samefile" => {
+            let path = args[0];
+            SameFileMatcher::new(path, config.follow)?.into_box()
+        }
+        "-user" => {
+            let user = args[0];
+
+            if user.is_empty() {
+                return Err(FindError::invalid_argument(
+                    name,
+                    user,
+                    "The argument to -user should not be empty",
                 ));
             }
-            #[cfg(unix)]
-            "-links" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                let inum = convert_arg_to_comparable_value(args[i], args[i + 1])?;
-                i += 1;
-                Some(LinksMatcher::new(inum).into_box())
-            }
-            #[cfg(not(unix))]
-            "-links" => {
-                return Err(From::from("Link counts are not available on this platform"));
-            }
-            "-samefile" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                let path = args[i];
-                let matcher = SameFileMatcher::new(path, config.follow)
-                    .map_err(|e| format!("{path}: {e}"))?;
-                Some(matcher.into_box())
-            }
-            "-user" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-
-                let user = args[i + 1];
-
-                if user.is_empty() {
-                    return Err(From::from("The argument to -user should not be empty"));
-                }
 
-                i += 1;
-                let matcher = UserMatcher::from_user_name(user);
-                match matcher.uid() {
-                    Some(_) => Some(matcher.into_box()),
-                    None => {
-                        return Err(From::from(format!(
-                            "{} is not the name of a known user",
-                            user
-                        )))
-                    }
+            let matcher = UserMatcher::from_user_name(user);
+            match matcher.uid() {
+                Some(_) => matcher.into_box(),
+                None => {
+                    return Err(FindError::invalid_argument(
+                        name,
+                        user,
+                        format!("{user} is not the name of a known user"),
+                    ))
                 }
             }
-            "-nouser" => Some(NoUserMatcher {}.into_box()),
-            "-uid" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                // check if the argument is a number
-                let uid = args[i + 1].parse::<u32>();
-                if uid.is_err() {
-                    return Err(From::from(format!("{} is not a number", args[i + 1])));
-                }
-                i += 1;
-                Some(UserMatcher::from_uid(uid.unwrap()).into_box())
+        }
+        "-nouser" => NoUserMatcher {}.into_box(),
+        "-uid" => {
+            let uid = convert_arg_to_comparable_value(name, args[0])?;
+            UserMatcher::from_uid(uid).into_box()
+        }
+        "-group" => {
+            let group = args[0];
+
+            if group.is_empty() {
+                return Err(FindError::invalid_argument(
+                    name,
+                    group,
+                    "Argument to -group is empty, but should be a group name",
+                ));
             }
-            "-group" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-
-                let group = args[i + 1];
 
-                if group.is_empty() {
-                    return Err(From::from(
-                        "Argument to -group is empty, but should be a group name",
-                    ));
-                }
-
-                i += 1;
-                let matcher = GroupMatcher::from_group_name(group);
-                match matcher.gid() {
-                    Some(_) => Some(matcher.into_box()),
-                    None => {
-                        return Err(From::from(format!(
-                            "{} is not the name of an existing group",
-                            group
-                        )))
-                    }
+            let matcher = GroupMatcher::from_group_name(group);
+            match matcher.gid() {
+                Some(_) => matcher.into_box(),
+                None => {
+                    return Err(FindError::invalid_argument(
+                        name,
+                        group,
+                        format!("{group} is not the name of an existing group"),
+                    ))
                 }
             }
-            "-nogroup" => Some(NoGroupMatcher {}.into_box()),
-            "-gid" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                // check if the argument is a number
-                let gid = args[i + 1].parse::<u32>();
-                if gid.is_err() {
-                    return Err(From::from(format!(
-                        "find: invalid argument `{}' to `-gid'",
-                        args[i + 1]
-                    )));
-                }
-                i += 1;
-                Some(GroupMatcher::from_gid(gid.unwrap()).into_box())
-            }
-            "-executable" => Some(AccessMatcher::Executable.into_box()),
-            "-perm" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                i += 1;
-                Some(PermMatcher::new(args[i])?.into_box())
-            }
-            "-prune" => Some(PruneMatcher::new().into_box()),
-            "-quit" => Some(QuitMatcher.into_box()),
-            "-writable" => Some(AccessMatcher::Writable.into_box()),
-            "-not" | "!" => {
-                if !are_more_expressions(args, i) {
-                    return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
-                    )));
-                }
-                invert_next_matcher = !invert_next_matcher;
-                None
-            }
-            "-and" | "-a" => {
-                if !are_more_expressions(args, i) {
-                    return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
-                    )));
-                }
-                top_level_matcher.check_new_and_condition()?;
-                None
-            }
-            "-or" | "-o" => {
-                if !are_more_expressions(args, i) {
-                    return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
-                    )));
-                }
-                top_level_matcher.new_or_condition(args[i])?;
-                None
-            }
-            "," => {
-                if !are_more_expressions(args, i) {
-                    return Err(From::from(format!(
-                        "expected an expression after {}",
-                        args[i]
-                    )));
-                }
-                top_level_matcher.new_list_condition()?;
-                None
-            }
-            "(" => {
-                let (new_arg_index, sub_matcher) = build_matcher_tree(args, config, i + 1, true)?;
-                i = new_arg_index;
-                Some(sub_matcher)
-            }
-            ")" => {
-                if !expecting_bracket {
-                    return Err(From::from("you have too many ')'"));
-                }
-
-                let bracket = args[i - 1];
-                if bracket == "(" {
-                    return Err(From::from(
-                        "invalid expression; empty parentheses are not allowed.",
-                    ));
-                }
+        }
+        "-nogroup" => NoGroupMatcher {}.into_box(),
+        "-gid" => {
+            let gid = convert_arg_to_comparable_value(name, args[0])?;
+            GroupMatcher::from_gid(gid).into_box()
+        }
+        "-executable" => AccessMatcher::Executable.into_box(),
+        "-perm" => PermMatcher::new(args[0]).map_err(invalid)?.into_box(),
+        "-prune" => PruneMatcher::new().into_box(),
+        "-quit" => QuitMatcher.into_box(),
+        "-writable" => AccessMatcher::Writable.into_box(),
+        "-follow" => {
+            // This option affects multiple matchers.
+            // 1. It will use noleaf by default. (but -noleaf No change of behavior)
+            // Unless -L or -H is specified:
+            // 2. changes the behaviour of the -newer predicate.
+            // 3. consideration applies to -newerXY, -anewer and -cnewer
+            // 4. -type predicate will always match against the type of
+            //    the file that a symbolic link points to rather than the link itself.
+            //
+            // 5. causes the -lname and -ilname predicates always to return false.
+            //    (unless they happen to match broken symbolic links)
+            config.follow = Follow::Always;
+            config.no_leaf_dirs = true;
+            TrueMatcher.into_box()
+        }
+        "-daystart" => {
+            config.today_start = true;
+            TrueMatcher.into_box()
+        }
+        "-noleaf" => {
+            // Don't trust directory link counts to tell us when we've
+            // seen all the subdirectories
+            config.no_leaf_dirs = true;
+            TrueMatcher.into_box()
+        }
+        "-depth" => {
+            // TODO add warning if it appears after actual testing criterion
+            config.depth_first = true;
+            TrueMatcher.into_box()
+        }
+        "-bfs" => {
+            config.breadth_first = true;
+            TrueMatcher.into_box()
+        }
+        "-xdev" => {
+            // TODO add warning if it appears after actual testing criterion
+            config.same_file_system = true;
+            TrueMatcher.into_box()
+        }
+        "-sorted" => {
+            // TODO add warning if it appears after actual testing criterion
+            config.sort_order = Some(match args.first() {
+                Some(order) => order
+                    .parse()
+                    .map_err(|e| FindError::invalid_argument(name, order, e))?,
+                None => SortOrder::default(),
+            });
+            TrueMatcher.into_box()
+        }
+        "-log" => {
+            // Append, so that the records from earlier runs are kept
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(args[0])
+                .map_err(|e| FindError::io(args[0], e))?;
+            config.action_log = Some(ActionLog::new(file));
+            TrueMatcher.into_box()
+        }
+        "--stats" => {
+            config.stats = true;
+            TrueMatcher.into_box()
+        }
+        "--progress" => {
+            config.progress = true;
+            TrueMatcher.into_box()
+        }
+        "--dedup-roots" => {
+            config.visited_dirs = Some(VisitedDirs::default());
+            TrueMatcher.into_box()
+        }
+        "-print-expression" => {
+            config.print_expression = true;
+            TrueMatcher.into_box()
+        }
+        // Already dealt with by build_top_level_expression
+        "--color" | "--hyperlink" | "--relative" | "--normalize-paths" | "--exec-timeout"
+        | "--exec-sandbox" | "--exec-keep-env" | "--exec-nice" | "--exec-rlimit"
+        | "--collision" | "--errors" => TrueMatcher.into_box(),
+        "--dry-run" => {
+            // Unlike -depth etc. this only matters once we start matching,
+            // so it doesn't matter where it appears.
+            config.dry_run = true;
+            TrueMatcher.into_box()
+        }
+        "-files0-from" => {
+            config.files0_from = Some(args[0].to_owned());
+            TrueMatcher.into_box()
+        }
+        "--extra-paths-from" => {
+            config.extra_paths_from = Some(args[0].to_owned());
+            TrueMatcher.into_box()
+        }
+        "--throttle" => {
+            let throttle: Throttle = args[0]
+                .parse()
+                .map_err(|e| FindError::invalid_argument(name, args[0], e))?;
+            config.throttle = Some(throttle);
+            TrueMatcher.into_box()
+        }
+        "-maxdepth" => {
+            config.max_depth = convert_arg_to_number(name, args[0])?;
+            TrueMatcher.into_box()
+        }
+        "-mindepth" => {
+            config.min_depth = convert_arg_to_number(name, args[0])?;
+            TrueMatcher.into_box()
+        }
+        // The parser stops at these, so nothing after them is built
+        "-help" => {
+            config.help_requested = true;
+            TrueMatcher.into_box()
+        }
+        "-version" => {
+            config.version_requested = true;
+            TrueMatcher.into_box()
+        }
 
-                return Ok((i, top_level_matcher.build()));
-            }
-            "-follow" => {
-                // This option affects multiple matchers.
-                // 1. It will use noleaf by default. (but -noleaf No change of behavior)
-                // Unless -L or -H is specified:
-                // 2. changes the behaviour of the -newer predicate.
-                // 3. consideration applies to -newerXY, -anewer and -cnewer
-                // 4. -type predicate will always match against the type of
-                //    the file that a symbolic link points to rather than the link itself.
-                //
-                // 5. causes the -lname and -ilname predicates always to return false.
-                //    (unless they happen to match broken symbolic links)
-                config.follow = Follow::Always;
-                config.no_leaf_dirs = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-daystart" => {
-                config.today_start = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-noleaf" => {
-                // No change of behavior
-                config.no_leaf_dirs = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-d" | "-depth" => {
-                // TODO add warning if it appears after actual testing criterion
-                config.depth_first = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-mount" | "-xdev" => {
-                // TODO add warning if it appears after actual testing criterion
-                config.same_file_system = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-sorted" => {
-                // TODO add warning if it appears after actual testing criterion
-                config.sorted_output = true;
-                Some(TrueMatcher.into_box())
-            }
-            "-maxdepth" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                config.max_depth = convert_arg_to_number(args[i], args[i + 1])?;
-                i += 1;
-                Some(TrueMatcher.into_box())
-            }
-            "-mindepth" => {
-                if i >= args.len() - 1 {
-                    return Err(From::from(format!("missing argument to {}", args[i])));
-                }
-                config.min_depth = convert_arg_to_number(args[i], args[i + 1])?;
-                i += 1;
-                Some(TrueMatcher.into_box())
-            }
-            "-help" | "--help" => {
-                config.help_requested = true;
-                None
-            }
-            "-version" | "--version" => {
-                config.version_requested = true;
-                None
+        _ => {
+            // The parser only lets through primaries we know about, so this
+            // must be -newerXY
+            let Some((x_option, y_option)) = parse_str_to_newer_args(name) else {
+                unreachable!("Encountered unexpected value {}", name);
+            };
+            #[cfg(target_os = "linux")]
+            if x_option == "B" {
+                return Err(FindError::Unsupported(
+                    "find: This system does not provide a way to find the birth time of a file."
+                        .to_owned(),
+                ));
             }
-
-            _ => {
-                match parse_str_to_newer_args(args[i]) {
-                    Some((x_option, y_option)) => {
-                        if i >= args.len() - 1 {
-                            return Err(From::from(format!("missing argument to {}", args[i])));
-                        }
-                        #[cfg(target_os = "linux")]
-                        if x_option == "B" {
-                            return Err(From::from("find: This system does not provide a way to find the birth time of a file."));
-                        }
-                        if y_option == "t" {
-                            let time = args[i + 1];
-                            let newer_time_type = NewerOptionType::from_str(x_option.as_str());
-                            // Convert args to unix timestamps. (expressed in numeric types)
-                            let comparable_time = match parse_date_str_to_timestamps(time) {
-                                Some(timestamp) => timestamp,
-                                None => {
-                                    return Err(From::from(format!(
-                                        "find: I cannot figure out how to interpret ‘{}’ as a date or time",
-                                        args[i + 1]
-                                    )))
-                                }
-                            };
-                            i += 1;
-                            Some(NewerTimeMatcher::new(newer_time_type, comparable_time).into_box())
-                        } else {
-                            let file_path = args[i + 1];
-                            i += 1;
-                            Some(NewerOptionMatcher::new(x_option, y_option, file_path)?.into_box())
-                        }
+            if y_option == "t" {
+                let time = args[0];
+                let newer_time_type = NewerOptionType::from_str(x_option.as_str());
+                // Convert args to unix timestamps. (expressed in numeric types)
+                let comparable_time = match parse_date_str_to_timestamps(time) {
+                    Some(timestamp) => timestamp,
+                    None => {
+                        return Err(FindError::invalid_argument(
+                            name,
+                            time,
+                            format!(
+                            "find: I cannot figure out how to interpret ‘{time}’ as a date or time"
+                        ),
+                        ))
                     }
-                    None => return Err(From::from(format!("Unrecognized flag: '{}'", args[i]))),
-                }
-            }
-        };
-        i += 1;
-        if config.help_requested || config.version_requested {
-            // Ignore anything, even invalid expressions, after -help/-version
-            expecting_bracket = false;
-            break;
-        }
-        if let Some(submatcher) = possible_submatcher {
-            if invert_next_matcher {
-                top_level_matcher.new_and_condition(NotMatcher::new(submatcher));
-                invert_next_matcher = false;
+                };
+                NewerTimeMatcher::new(newer_time_type, comparable_time).into_box()
             } else {
-                top_level_matcher.new_and_condition(submatcher);
+                let file_path = args[0];
+                NewerOptionMatcher::new(x_option, y_option, file_path, config.follow)?.into_box()
             }
         }
-    }
-    if expecting_bracket {
-        return Err(From::from(
-            "invalid expression; I was expecting to find a ')' somewhere but \
-             did not see one.",
-        ));
-    }
-    Ok((i, top_level_matcher.build()))
+    };
+    Ok(matcher)
 }
 
 #[cfg(test)]
@@ -981,6 +1167,76 @@ mod tests {
         WalkEntry::new(path, depth, follow)
     }
 
+    #[test]
+    fn build_top_level_matcher_throttle_idle() {
+        // This only records it, for do_find, rather than changing the I/O
+        // priority of whichever process is building the expression (which
+        // would fail where there's no such thing)
+        let mut config = Config::default();
+        build_top_level_matcher(&["--throttle", "idle"], &mut config)
+            .expect("building should succeed");
+        assert_eq!(config.throttle, Some(Throttle::Idle));
+    }
+
+    #[test]
+    fn build_top_level_matcher_regextype_switches() {
+        let abbbc = get_dir_entry_for("./test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+
+        // Each -regextype applies to the regexes after it, up to the next one,
+        // whatever's in between
+        for (args, expected) in [
+            (
+                &[
+                    "-regextype",
+                    "posix-basic",
+                    "-regex",
+                    r".*ab\{3\}c",
+                    "-regextype",
+                    "emacs",
+                    "-regex",
+                    ".*ab+c",
+                ][..],
+                true,
+            ),
+            (&["-regextype", "posix-basic", "-regex", ".*ab+c"], false),
+            (
+                &[
+                    "(",
+                    "-regextype",
+                    "posix-extended",
+                    "-regex",
+                    ".*zzz",
+                    ")",
+                    "-o",
+                    "-regex",
+                    ".*a(b)+c",
+                ],
+                true,
+            ),
+            (
+                &[
+                    "-regextype",
+                    "egrep",
+                    "-regex",
+                    ".*a(b)+c",
+                    "-regextype",
+                    "findutils-default",
+                    "-regex",
+                    ".*a(b)+c",
+                ],
+                false,
+            ),
+        ] {
+            let matcher = build_top_level_matcher(args, &mut Config::default()).unwrap();
+            assert_eq!(
+                matcher.matches(&abbbc, &mut deps.new_matcher_io()),
+                expected,
+                "{args:?}"
+            );
+        }
+    }
+
     #[test]
     fn build_top_level_matcher_name() {
         let abbbc_lower = get_dir_entry_for("./test_data/simple", "abbbc");
@@ -1085,6 +1341,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn build_top_level_matcher_typed_errors() {
+        let error = |args: &[&str]| {
+            let e = build_top_level_matcher(args, &mut Config::default())
+                .err()
+                .expect("building the matcher should fail");
+            *e.downcast::<FindError>()
+                .expect("errors should be FindErrors")
+        };
+
+        assert!(matches!(
+            error(&["-name"]),
+            FindError::MissingArgument { predicate } if predicate == "-name"
+        ));
+        assert!(matches!(
+            error(&["-maxdepth", "x"]),
+            FindError::InvalidArgument { predicate, value, .. }
+                if predicate == "-maxdepth" && value == "x"
+        ));
+        assert!(matches!(
+            error(&["-type", "q"]),
+            FindError::InvalidArgument { predicate, value, .. }
+                if predicate == "-type" && value == "q"
+        ));
+        assert!(matches!(
+            error(&["-printf", "%"]),
+            FindError::InvalidArgument { predicate, value, .. }
+                if predicate == "-printf" && value == "%"
+        ));
+        assert!(matches!(error(&["-true", ")"]), FindError::Parse(_)));
+        assert!(matches!(
+            error(&["-samefile", "test_data/simple/missing"]),
+            FindError::Io { .. }
+        ));
+    }
+
     #[test]
     fn build_top_level_matcher_or_without_expr1() {
         for arg in &["-or", "-o"] {
@@ -1226,6 +1518,186 @@ mod tests {
         );
     }
 
+    #[test]
+    fn build_top_level_matcher_precedence() {
+        let abbbc = get_dir_entry_for("./test_data/simple", "abbbc");
+        // ! binds tightest, then -a, then -o, then ','. The output shows which
+        // side effects happened, and in what order. (Checked against GNU find.)
+        let cases = [
+            ("-printf A -o -printf B", "A", true),
+            ("-false -o -printf B -printf C", "BC", true),
+            ("-printf A -o -printf B -printf C", "A", true),
+            ("-printf A -a -false -o -printf B", "AB", true),
+            ("-false -a -printf A -o -printf B", "B", true),
+            ("! -true -o -printf B", "B", true),
+            ("! ( -false -o -false ) -printf A", "A", true),
+            ("-printf A , -printf B", "AB", true),
+            ("-false , -printf B", "B", true),
+            ("-printf A , -false", "A", false),
+            ("-false -o -printf A , -printf B", "AB", true),
+            (
+                "-printf A -o -printf B , -printf C -o -printf D",
+                "AC",
+                true,
+            ),
+            ("( -printf A , -false ) -o -printf B", "AB", true),
+            (
+                "( -printf A -o -printf B ) , -printf C -printf D",
+                "ACD",
+                true,
+            ),
+            ("-true -a ( -false , -true ) -printf X", "X", true),
+            ("! -printf A , -printf B", "AB", true),
+            ("! ! -printf A -printf B", "AB", true),
+            ("-false -o ! -printf A -o -printf B", "AB", true),
+            (
+                "-printf A -printf B -o -printf C -a -printf D , -printf E",
+                "ABE",
+                true,
+            ),
+            (
+                "( -false -o -printf A ) -a ( -printf B , -false ) -o -printf C",
+                "ABC",
+                true,
+            ),
+        ];
+
+        for (expr, output, result) in cases {
+            let args: Vec<_> = expr.split(' ').collect();
+            let mut config = Config::default();
+            let deps = FakeDependencies::new();
+            let matcher = build_top_level_matcher(&args, &mut config).unwrap();
+            assert_eq!(
+                matcher.matches(&abbbc, &mut deps.new_matcher_io()),
+                result,
+                "{expr}"
+            );
+            assert_eq!(deps.get_output_as_string(), output, "{expr}");
+        }
+    }
+
+    #[test]
+    fn build_top_level_expression_display() {
+        let cases = [
+            ("", "-print"),
+            ("-name a -o -name b", "( -name a -o -name b ) -a -print"),
+            ("-not -name a -and -print0", "! -name a -a -print0"),
+            (
+                "( -type f -o -type d ) -print",
+                "( -type f -o -type d ) -a -print",
+            ),
+            ("! ! -true ! ( -false )", "-true -a ! -false -a -print"),
+            (
+                "-print , -name a -exec echo {} ;",
+                "-print , -name a -a -exec echo '{}' ';'",
+            ),
+        ];
+
+        for (args, expected) in cases {
+            let args: Vec<_> = args.split(' ').filter(|a| !a.is_empty()).collect();
+            let mut config = Config::default();
+            let (_, expr) = build_top_level_expression(&args, &mut config).unwrap();
+            assert_eq!(expr.to_string(), expected, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn build_top_level_matcher_needs() {
+        let cases = [
+            ("", Needs::Name),
+            ("-name a -o -path b/* -print0", Needs::Name),
+            ("-regex a -prune -o -quit", Needs::Type),
+            ("! -type f", Needs::Type),
+            ("-name a -size +1", Needs::Metadata),
+            ("-name a -print -o -xtype l", Needs::Metadata),
+            ("-ls", Needs::Metadata),
+        ];
+
+        for (args, expected) in cases {
+            let args: Vec<_> = args.split(' ').filter(|a| !a.is_empty()).collect();
+            let mut config = Config::default();
+            let matcher = build_top_level_matcher(&args, &mut config).unwrap();
+            assert_eq!(matcher.needs(), expected, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn build_top_level_matcher_delete_prune() {
+        let mut config = Config::default();
+        let args = ["-name", "a", "-prune", "-o", "-delete"];
+        let err = build_top_level_matcher(&args, &mut config).err().unwrap();
+        assert!(err.to_string().contains("-prune does nothing"), "{err}");
+
+        for args in [
+            ["-depth", "-name", "a", "-prune", "-o", "-delete"],
+            ["-name", "a", "-prune", "-o", "-delete", "-d"],
+        ] {
+            let mut config = Config::default();
+            assert!(
+                build_top_level_matcher(&args, &mut config).is_ok(),
+                "{args:?}"
+            );
+            assert!(config.depth_first);
+        }
+    }
+
+    #[test]
+    fn build_top_level_matcher_when_options() {
+        assert!(convert_when_arg("--color", None).unwrap());
+        assert!(convert_when_arg("--color", Some("always")).unwrap());
+        assert!(!convert_when_arg("--color", Some("never")).unwrap());
+
+        let mut config = Config::default();
+        let args = ["--hyperlink=sometimes"];
+        let err = build_top_level_matcher(&args, &mut config).err().unwrap();
+        assert!(
+            err.to_string().contains("'sometimes' for '--hyperlink'"),
+            "{err}"
+        );
+
+        // The last one wins
+        let mut config = Config::default();
+        let args = [
+            "--color",
+            "--hyperlink=never",
+            "--color=never",
+            "--hyperlink",
+        ];
+        build_top_level_matcher(&args, &mut config).unwrap();
+        assert!(config.colors.is_none());
+        assert!(config.hyperlinks.is_some());
+    }
+
+    #[test]
+    fn build_top_level_matcher_consecutive_operators() {
+        for expr in [
+            "-true -a -a -true",
+            "-true -a -o -true",
+            "-true -o -a -true",
+            "-true , , -true",
+            "-true -o , -true",
+            "-true ! -o -true",
+            "-true -not , -true",
+            "! -a -true",
+        ] {
+            let args: Vec<_> = expr.split(' ').collect();
+            let mut config = Config::default();
+            match build_top_level_matcher(&args, &mut config) {
+                Ok(_) => panic!("parsing {expr} should fail"),
+                Err(e) => assert!(
+                    e.to_string().contains("you have used a binary operator"),
+                    "{expr}: {e}"
+                ),
+            }
+        }
+
+        let mut config = Config::default();
+        match build_top_level_matcher(&["-true", "(", "!", ")"], &mut config) {
+            Ok(_) => panic!("parsing '( ! )' should fail"),
+            Err(e) => assert_eq!(e.to_string(), "expected an expression between '!' and ')'"),
+        }
+    }
+
     #[test]
     fn build_top_level_matcher_list_without_expr1() {
         let mut config = Config::default();
@@ -1402,6 +1874,40 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn parse_owner_prefers_names() {
+        let lookup = |name: &str| match name {
+            "root" => Some(0),
+            // A user whose name looks like a number
+            "1000" => Some(5),
+            _ => None,
+        };
+
+        assert_eq!(
+            parse_owner("root", lookup),
+            Some(ComparableValue::EqualTo(0))
+        );
+        assert_eq!(
+            parse_owner("1000", lookup),
+            Some(ComparableValue::EqualTo(5))
+        );
+        assert_eq!(
+            parse_owner("1001", lookup),
+            Some(ComparableValue::EqualTo(1001))
+        );
+        assert_eq!(
+            parse_owner("+100", lookup),
+            Some(ComparableValue::MoreThan(100))
+        );
+        assert_eq!(
+            parse_owner("-100", lookup),
+            Some(ComparableValue::LessThan(100))
+        );
+        assert_eq!(parse_owner("nobody-here", lookup), None);
+        assert_eq!(parse_owner("", lookup), None);
+    }
+
     #[test]
     fn comparable_value_imatches() {
         assert!(
@@ -1680,6 +2186,45 @@ mod tests {
         assert!(config.version_requested);
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn walk_entry_link_and_target_metadata() {
+        use std::fs;
+        use std::os::unix::fs::symlink;
+        use tempfile::Builder;
+
+        let temp_dir = Builder::new().prefix("entry_meta").tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy();
+        File::create(temp_dir.path().join("file")).unwrap();
+        symlink("file", temp_dir.path().join("link")).unwrap();
+        symlink("missing", temp_dir.path().join("broken")).unwrap();
+
+        for follow in [Follow::Never, Follow::Always] {
+            let file = get_dir_entry_follow(&root, "file", follow);
+            assert!(file.link_metadata().unwrap().is_file());
+            assert!(file.target_metadata().unwrap().is_file());
+
+            let link = get_dir_entry_follow(&root, "link", follow);
+            assert!(link.link_metadata().unwrap().is_symlink(), "{follow:?}");
+            assert!(link.target_metadata().unwrap().is_file(), "{follow:?}");
+            assert!(link.path_is_symlink());
+
+            let broken = get_dir_entry_follow(&root, "broken", follow);
+            assert!(broken.link_metadata().unwrap().is_symlink(), "{follow:?}");
+            assert!(broken.target_metadata().unwrap_err().is_not_found());
+            // as if the link were followed, falling back to the link itself
+            assert!(Follow::Always.metadata(&broken).unwrap().is_symlink());
+            assert!(Follow::Never.metadata(&link).unwrap().is_symlink());
+            assert!(Follow::Always.metadata(&link).unwrap().is_file());
+        }
+
+        // The results are cached, even once the file is gone
+        let link = get_dir_entry_follow(&root, "link", Follow::Never);
+        link.target_metadata().unwrap();
+        fs::remove_file(temp_dir.path().join("file")).unwrap();
+        assert!(link.target_metadata().unwrap().is_file());
+    }
+
     #[test]
     fn get_or_create_file_test() {
         use std::fs;
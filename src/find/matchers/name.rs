@@ -5,7 +5,7 @@
 // https://opensource.org/licenses/MIT.
 
 use super::glob::Pattern;
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Matcher, MatcherIO, Needs, WalkEntry};
 
 /// This matcher makes a comparison of the name against a shell wildcard
 /// pattern. See `glob::Pattern` for details on the exact syntax.
@@ -25,6 +25,10 @@ impl Matcher for NameMatcher {
         let name = file_info.file_name().to_string_lossy();
         self.pattern.matches(&name)
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Name
+    }
 }
 
 #[cfg(test)]
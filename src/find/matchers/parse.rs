@@ -0,0 +1,536 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Parses the command line into an [Expr].
+//!
+//! This only deals with the structure of the expression: which arguments
+//! belong to which primary, and how the operators and parentheses combine
+//! them. Whether the arguments make sense is checked later, when the
+//! expression is lowered into matchers.
+
+use super::expr::{Expr, ExprBuilder};
+use super::parse_str_to_newer_args;
+use super::FindError;
+
+/// How many arguments a primary takes.
+enum Arity {
+    /// A fixed number of arguments.
+    Fixed(usize),
+    /// A command line terminated by `;`, for -exec and friends.
+    Command,
+    /// One argument if the next one is a number, otherwise none.
+    OptionalNumber,
+    /// One argument if the next one is one of these flags, otherwise none.
+    OptionalFlag(&'static [&'static str]),
+    /// One argument if the next one starts with this prefix, ignoring case,
+    /// otherwise none.
+    OptionalPrefix(&'static str),
+}
+
+/// A recursive descent parser over the arguments. We can't use getopts for a
+/// variety of reasons: order of arguments is important, arguments can start
+/// with + as well as -, and multiple-character flags don't start with a double
+/// dash.
+struct Parser<'a> {
+    args: &'a [&'a str],
+    /// The index of the next argument to look at.
+    i: usize,
+    /// Set once we see -help or -version, after which we ignore anything, even
+    /// invalid expressions.
+    done: bool,
+    /// The last primary we parsed, as it was spelled, and the index just past
+    /// its arguments.
+    last_primary: Option<(&'a str, usize)>,
+}
+
+/// Parses a whole expression.
+pub fn parse_expression(args: &[&str]) -> Result<Expr, FindError> {
+    let mut parser = Parser {
+        args,
+        i: 0,
+        done: false,
+        last_primary: None,
+    };
+    parser.parse_group(false)
+}
+
+fn are_more_expressions(args: &[&str], index: usize) -> bool {
+    (index < args.len() - 1) && args[index + 1] != ")"
+}
+
+/// Other spellings of primaries, from GNU or BSD find, and the name they're
+/// parsed as. Nothing after the parser needs to know about these.
+const ALIASES: &[(&str, &str)] = &[
+    // BSD
+    ("-d", "-depth"),
+    ("-mount", "-xdev"),
+    // Like du, cp and friends
+    ("--one-file-system", "-xdev"),
+    ("--help", "-help"),
+    ("--version", "-version"),
+];
+
+/// Resolves an alias to the name of the primary it stands for.
+fn canonical_name(name: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canonical)| canonical)
+}
+
+fn arity(name: &str) -> Option<Arity> {
+    let arity = match name {
+        "-print" | "-print0" | "-ls" | "-true" | "-false" | "-readable" | "-delete" | "-empty"
+        | "-nouser" | "-nogroup" | "-executable" | "-prune" | "-quit" | "-writable" | "-follow"
+        | "-daystart" | "-noleaf" | "-depth" | "-bfs" | "-xdev" | "-sorted" | "--stats"
+        | "--progress" | "--dedup-roots" | "-print-expression" | "--dry-run" | "--color"
+        | "--hyperlink" | "--relative" | "--normalize-paths" | "--exec-sandbox" | "-help"
+        | "-version" => Arity::Fixed(0),
+        "-printf" | "-fprint" | "-fprint0" | "-fls" | "-lname" | "-ilname" | "-name" | "-iname"
+        | "-path" | "-ipath" | "-wholename" | "-iwholename" | "-regextype" | "-regex"
+        | "-iregex" | "-type" | "-xtype" | "-fstype" | "-newer" | "-mtime" | "-atime"
+        | "-ctime" | "-amin" | "-cmin" | "-mmin" | "-size" | "-inum" | "-links" | "-samefile"
+        | "-user" | "-uid" | "-group" | "-gid" | "-perm" | "-log" | "-maxdepth" | "-mindepth"
+        | "-files0-from" | "--extra-paths-from" | "--exec-timeout" | "--exec-keep-env"
+        | "--exec-nice" | "--exec-rlimit" | "--errors" | "-tar" | "-copy-to" | "-move-to"
+        | "--collision" | "-chmod" | "-chown" | "--throttle" => Arity::Fixed(1),
+        "-fprintf" => Arity::Fixed(2),
+        "-exec" | "-execdir" | "-ok" | "-okdir" => Arity::Command,
+        "-sparse" => Arity::OptionalNumber,
+        // -touch -a always means the access time, never an -a operator
+        "-touch" => Arity::OptionalFlag(&["-a", "-m"]),
+        "-cap" => Arity::OptionalPrefix("cap_"),
+        _ => match parse_str_to_newer_args(name) {
+            // -newerXY
+            Some(_) => Arity::Fixed(1),
+            None => return None,
+        },
+    };
+    Some(arity)
+}
+
+/// Finds the index just past the arguments of the primary `name` at
+/// `args[start]`, or `None` if there's no such primary.
+fn primary_end(name: &str, args: &[&str], start: usize) -> Result<Option<usize>, FindError> {
+    let end = match arity(name) {
+        Some(Arity::Fixed(n)) => {
+            if start + n >= args.len() {
+                return Err(FindError::MissingArgument {
+                    predicate: name.to_owned(),
+                });
+            }
+            start + n + 1
+        }
+        Some(Arity::Command) => {
+            let mut arg_index = start + 1;
+            while arg_index < args.len() && args[arg_index] != ";" {
+                if args[arg_index - 1] == "{}" && args[arg_index] == "+" {
+                    // MultiExecMatcher isn't written yet
+                    return Err(From::from(format!(
+                        "{name} [args...] + isn't supported yet. \
+                         Only {name} [args...] ;"
+                    )));
+                }
+                arg_index += 1;
+            }
+            if arg_index < start + 2 || arg_index == args.len() {
+                // at the minimum we need the executable and the ';'
+                return Err(FindError::MissingArgument {
+                    predicate: name.to_owned(),
+                });
+            }
+            arg_index + 1
+        }
+        Some(Arity::OptionalNumber) => match args.get(start + 1) {
+            Some(arg) if arg.parse::<f64>().is_ok() => start + 2,
+            _ => start + 1,
+        },
+        Some(Arity::OptionalFlag(flags)) => match args.get(start + 1) {
+            Some(arg) if flags.contains(arg) => start + 2,
+            _ => start + 1,
+        },
+        Some(Arity::OptionalPrefix(prefix)) => match args.get(start + 1) {
+            Some(arg)
+                if arg
+                    .get(..prefix.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(prefix)) =>
+            {
+                start + 2
+            }
+            _ => start + 1,
+        },
+        None => return Ok(None),
+    };
+    Ok(Some(end))
+}
+
+/// Whether `arg` is the name of a primary, like `-print` or `--color=auto`.
+pub(crate) fn is_primary(arg: &str) -> bool {
+    let name = arg.split_once('=').map_or(arg, |(name, _)| name);
+    arity(canonical_name(name)).is_some()
+}
+
+/// Skips over the primary at `args[start]` and its arguments, for looking
+/// through an expression without parsing it. Returns `None` if it isn't a
+/// primary, or its arguments are missing.
+pub(crate) fn skip_primary(args: &[&str], start: usize) -> Option<usize> {
+    primary_end(canonical_name(args[start]), args, start)
+        .ok()
+        .flatten()
+}
+
+impl Parser<'_> {
+    /// Parses a sequence of expressions joined by operators, up to the end of
+    /// the arguments or (if `expecting_bracket`) a closing bracket.
+    fn parse_group(&mut self, expecting_bracket: bool) -> Result<Expr, FindError> {
+        let args = self.args;
+        let mut builder = ExprBuilder::new();
+        let mut invert_next_expr = false;
+        // Whether the last thing we saw was a binary operator, which would need
+        // another expression before the next operator
+        let mut after_operator = false;
+
+        while self.i < args.len() {
+            let i = self.i;
+
+            if matches!(args[i], "-and" | "-a" | "-or" | "-o" | ",")
+                && (after_operator || invert_next_expr)
+            {
+                return Err(From::from(format!(
+                    "invalid expression; you have used a binary operator '{}' \
+                     with nothing before it.",
+                    args[i]
+                )));
+            }
+
+            let expr = match args[i] {
+                "-not" | "!" => {
+                    if args.get(i + 1) == Some(&")") {
+                        return Err(From::from(format!(
+                            "expected an expression between '{}' and ')'",
+                            args[i]
+                        )));
+                    }
+                    if !are_more_expressions(args, i) {
+                        return Err(FindError::ExpectedExpression {
+                            operator: args[i].to_owned(),
+                        });
+                    }
+                    invert_next_expr = !invert_next_expr;
+                    self.i += 1;
+                    continue;
+                }
+                "-and" | "-a" | "-or" | "-o" | "," => {
+                    if !are_more_expressions(args, i) {
+                        return Err(FindError::ExpectedExpression {
+                            operator: args[i].to_owned(),
+                        });
+                    }
+                    match args[i] {
+                        "-and" | "-a" => builder.check_new_and_condition()?,
+                        "," => builder.new_list_condition()?,
+                        _ => builder.new_or_condition(args[i])?,
+                    }
+                    after_operator = true;
+                    self.i += 1;
+                    continue;
+                }
+                "(" => {
+                    self.i += 1;
+                    let expr = self.parse_group(true)?;
+                    // Skip the ')'
+                    self.i += 1;
+                    expr
+                }
+                ")" => {
+                    if !expecting_bracket {
+                        return Err(From::from("you have too many ')'"));
+                    }
+
+                    if args[i - 1] == "(" {
+                        return Err(From::from(
+                            "invalid expression; empty parentheses are not allowed.",
+                        ));
+                    }
+
+                    return Ok(builder.build());
+                }
+                _ => self.parse_primary()?,
+            };
+
+            after_operator = false;
+            if invert_next_expr {
+                builder.new_and_condition(Expr::Not(Box::new(expr)));
+                invert_next_expr = false;
+            } else {
+                builder.new_and_condition(expr);
+            }
+
+            if self.done {
+                return Ok(builder.build());
+            }
+        }
+
+        if expecting_bracket {
+            return Err(From::from(
+                "invalid expression; I was expecting to find a ')' somewhere but \
+                 did not see one.",
+            ));
+        }
+        Ok(builder.build())
+    }
+
+    /// Parses a single primary and its arguments.
+    fn parse_primary(&mut self) -> Result<Expr, FindError> {
+        let args = self.args;
+        let start = self.i;
+        // Like ls, --color=WHEN and --hyperlink=WHEN take an optional
+        // argument in the same word, and so do -sorted=ORDER and
+        // --exec-sandbox=DIR. --errors=FORMAT can be written either way.
+        if let Some((name, arg)) = args[start].split_once('=') {
+            if matches!(
+                name,
+                "--color" | "--hyperlink" | "-sorted" | "--exec-sandbox" | "--errors"
+            ) {
+                self.i += 1;
+                self.last_primary = Some((args[start], self.i));
+                return Ok(Expr::primary(&[name, arg], start));
+            }
+        }
+        let name = canonical_name(args[start]);
+        let Some(end) = primary_end(name, args, start)? else {
+            return Err(self.unrecognized());
+        };
+
+        if matches!(name, "-help" | "-version") {
+            self.done = true;
+        }
+
+        self.i = end;
+        self.last_primary = Some((args[start], end));
+        // Aliases are stored under the name they stand for
+        let mut primary = args[start..end].to_vec();
+        primary[0] = name;
+        Ok(Expr::primary(&primary, start))
+    }
+
+    /// Explains why the current argument isn't a primary we know about,
+    /// pointing out the likely mistake where there is one.
+    fn unrecognized(&self) -> FindError {
+        let name = self.args[self.i];
+        let after = match self.last_primary {
+            Some((primary, end)) if end == self.i => Some(primary),
+            _ => self.i.checked_sub(1).map(|i| self.args[i]),
+        }
+        .map(str::to_owned);
+
+        if self.last_primary == Some(("-d", self.i)) && name.parse::<usize>().is_ok() {
+            // BSD's -d is -depth, not a depth limit
+            return From::from(format!(
+                "-d is an alias for -depth and takes no argument; use -maxdepth {name} \
+                 to limit the depth of the search"
+            ));
+        }
+
+        // BSD options, which aren't primaries
+        let bsd_primary = match name {
+            "-s" => Some("-sorted"),
+            "-x" => Some("-xdev"),
+            "-E" => Some("-regextype posix-extended"),
+            _ => None,
+        };
+        if let Some(primary) = bsd_primary {
+            return From::from(format!(
+                "{name} is an option that must come before the starting points; use \
+                 {primary} in the expression"
+            ));
+        }
+
+        if let Some(single) = name.strip_prefix('-').filter(|s| s.starts_with('-')) {
+            // e.g. --xdev rather than -xdev
+            if arity(canonical_name(single)).is_some() {
+                return FindError::UnknownPrimary {
+                    name: name.to_owned(),
+                    after,
+                    suggestion: Some(single.to_owned()),
+                };
+            }
+        }
+
+        FindError::UnknownPrimary {
+            name: name.to_owned(),
+            after,
+            suggestion: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primary(args: &[&str], start: usize) -> Expr {
+        Expr::primary(args, start)
+    }
+
+    #[test]
+    fn parse_primaries() {
+        let args = ["-name", "foo", "-print", "-fprintf", "out", "%p"];
+        assert_eq!(
+            parse_expression(&args).unwrap(),
+            Expr::And(vec![
+                primary(&["-name", "foo"], 0),
+                primary(&["-print"], 2),
+                primary(&["-fprintf", "out", "%p"], 3),
+            ])
+        );
+
+        let args = ["-exec", "echo", "(", "{}", ";", "-newermt", "today"];
+        assert_eq!(
+            parse_expression(&args).unwrap(),
+            Expr::And(vec![
+                primary(&["-exec", "echo", "(", "{}", ";"], 0),
+                primary(&["-newermt", "today"], 5),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_optional_number() {
+        let args = ["-sparse", "0.5", "-sparse", "-print", "-sparse"];
+        assert_eq!(
+            parse_expression(&args).unwrap(),
+            Expr::And(vec![
+                primary(&["-sparse", "0.5"], 0),
+                primary(&["-sparse"], 2),
+                primary(&["-print"], 3),
+                primary(&["-sparse"], 4),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_optional_flag() {
+        let args = ["-touch", "-a", "-touch", "-print", "-touch", "-m", "-touch"];
+        assert_eq!(
+            parse_expression(&args).unwrap(),
+            Expr::And(vec![
+                primary(&["-touch", "-a"], 0),
+                primary(&["-touch"], 2),
+                primary(&["-print"], 3),
+                primary(&["-touch", "-m"], 4),
+                primary(&["-touch"], 6),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_optional_prefix() {
+        let args = ["-cap", "CAP_SETUID,cap_net_raw", "-cap", "-print", "-cap"];
+        assert_eq!(
+            parse_expression(&args).unwrap(),
+            Expr::And(vec![
+                primary(&["-cap", "CAP_SETUID,cap_net_raw"], 0),
+                primary(&["-cap"], 2),
+                primary(&["-print"], 3),
+                primary(&["-cap"], 4),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_spans() {
+        let args = ["(", "-name", "a", "-o", "!", "-type", "d", ")", "-print"];
+        let Expr::And(exprs) = parse_expression(&args).unwrap() else {
+            panic!("expected an -a expression");
+        };
+        let Expr::Or(alternatives) = &exprs[0] else {
+            panic!("expected an -o expression");
+        };
+        let Expr::Not(not) = &alternatives[1] else {
+            panic!("expected a ! expression");
+        };
+        let Expr::Primary(ty) = &**not else {
+            panic!("expected a primary");
+        };
+        assert_eq!(ty.name, "-type");
+        assert_eq!(ty.args, ["d"]);
+        assert_eq!(ty.span, 5..7);
+        assert_eq!(args[ty.span.clone()], ["-type", "d"]);
+        assert_eq!(exprs[1], primary(&["-print"], 8));
+    }
+
+    #[test]
+    fn parse_stops_at_help() {
+        let args = ["(", "-true", "-help", ")", ")", "-bogus"];
+        assert_eq!(
+            parse_expression(&args).unwrap(),
+            Expr::And(vec![primary(&["-true"], 1), primary(&["-help"], 2)])
+        );
+    }
+
+    #[test]
+    fn parse_aliases() {
+        let args = ["-d", "-mount", "--one-file-system", "-noleaf"];
+        let Expr::And(exprs) = parse_expression(&args).unwrap() else {
+            panic!("expected an -a expression");
+        };
+        let names: Vec<_> = exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::Primary(primary) => primary.name.as_str(),
+                _ => panic!("expected a primary"),
+            })
+            .collect();
+        assert_eq!(names, ["-depth", "-xdev", "-xdev", "-noleaf"]);
+
+        assert_eq!(
+            parse_expression(&["--color=never", "--color"]).unwrap(),
+            Expr::And(vec![
+                primary(&["--color", "never"], 0),
+                primary(&["--color"], 1)
+            ])
+        );
+
+        assert_eq!(
+            parse_expression(&["--help", "-bogus"]).unwrap(),
+            primary(&["-help"], 0)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (args, error) in [
+            (&["-name"][..], "missing argument to -name"),
+            (&["-fprintf", "out"], "missing argument to -fprintf"),
+            (&["-exec", ";"], "missing argument to -exec"),
+            (&["-exec", "echo"], "missing argument to -exec"),
+            (&["-bogus"], "Unrecognized flag: '-bogus'"),
+            (&["-true", ")"], "you have too many ')'"),
+            (
+                &["-d", "3"],
+                "-d is an alias for -depth and takes no argument; use -maxdepth 3 \
+                 to limit the depth of the search",
+            ),
+            (&["-name", "-d", "3"], "Unrecognized flag: '3'"),
+            (&["-d", "x"], "Unrecognized flag: 'x'"),
+            (
+                &["--xdev"],
+                "Unrecognized flag: '--xdev'; did you mean '-xdev'?",
+            ),
+            (
+                &["-true", "--mount"],
+                "Unrecognized flag: '--mount'; did you mean '-mount'?",
+            ),
+            (&["--bogus"], "Unrecognized flag: '--bogus'"),
+        ] {
+            assert_eq!(
+                parse_expression(args).unwrap_err().to_string(),
+                error,
+                "{args:?}"
+            );
+        }
+    }
+}
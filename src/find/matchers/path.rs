@@ -5,7 +5,7 @@
 // https://opensource.org/licenses/MIT.
 
 use super::glob::Pattern;
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Matcher, MatcherIO, Needs, WalkEntry};
 
 /// This matcher makes a comparison of the path against a shell wildcard
 /// pattern. See `glob::Pattern` for details on the exact syntax.
@@ -25,6 +25,10 @@ impl Matcher for PathMatcher {
         let path = file_info.path().to_string_lossy();
         self.pattern.matches(&path)
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Name
+    }
 }
 
 #[cfg(test)]
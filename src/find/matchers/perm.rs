@@ -9,7 +9,6 @@
 //! even try.
 
 use std::error::Error;
-use std::io::{stderr, Write};
 #[cfg(unix)]
 use uucore::mode::{parse_numeric, parse_symbolic};
 
@@ -40,7 +39,7 @@ impl ComparisonType {
 
 #[cfg(unix)]
 mod parsing {
-    use super::{parse_numeric, parse_symbolic, ComparisonType, Error};
+    use super::{parse_numeric, parse_symbolic, ComparisonType};
 
     pub fn split_comparison_type(pattern: &str) -> (ComparisonType, &str) {
         let mut chars = pattern.chars();
@@ -52,17 +51,18 @@ mod parsing {
         }
     }
 
-    pub fn parse_mode(pattern: &str, for_dir: bool) -> Result<u32, Box<dyn Error>> {
-        let mode = if pattern.contains(|c: char| c.is_ascii_digit()) {
-            parse_numeric(0, pattern, for_dir)?
+    /// Works out the bits a mode stands for, starting from no bits at all.
+    /// Symbolic modes are a comma-separated list of clauses like chmod's,
+    /// e.g. `u=rwX,go+r-w`, where `X` only means `x` for directories, or if
+    /// an earlier clause added an `x` bit.
+    pub fn parse_mode(pattern: &str, for_dir: bool) -> Result<u32, String> {
+        if pattern.contains(|c: char| c.is_ascii_digit()) {
+            parse_numeric(0, pattern, for_dir)
         } else {
-            let mut mode = 0;
-            for chunk in pattern.split(',') {
-                mode = parse_symbolic(mode, chunk, 0, for_dir)?;
-            }
-            mode
-        };
-        Ok(mode)
+            pattern
+                .split(',')
+                .try_fold(0, |mode, clause| parse_symbolic(mode, clause, 0, for_dir))
+        }
     }
 }
 
@@ -79,10 +79,11 @@ pub struct PermMatcher {}
 
 impl PermMatcher {
     #[cfg(unix)]
-    pub fn new(pattern: &str) -> Result<Self, Box<dyn Error>> {
-        let (comparison_type, pattern) = parsing::split_comparison_type(pattern);
-        let file_pattern = parsing::parse_mode(pattern, false)?;
-        let dir_pattern = parsing::parse_mode(pattern, false)?;
+    pub fn new(arg: &str) -> Result<Self, Box<dyn Error>> {
+        let (comparison_type, pattern) = parsing::split_comparison_type(arg);
+        let invalid = |e| format!("invalid mode '{arg}': {e}");
+        let file_pattern = parsing::parse_mode(pattern, false).map_err(invalid)?;
+        let dir_pattern = parsing::parse_mode(pattern, true).map_err(invalid)?;
         Ok(Self {
             comparison_type,
             file_pattern,
@@ -100,7 +101,7 @@ impl PermMatcher {
 
 impl Matcher for PermMatcher {
     #[cfg(unix)]
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         use std::os::unix::fs::PermissionsExt;
         match file_info.metadata() {
             Ok(metadata) => {
@@ -114,7 +115,7 @@ impl Matcher for PermMatcher {
             }
             Err(e) => {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
                     "Error getting permissions for {}: {}",
                     file_info.path().to_string_lossy(),
                     e
@@ -126,13 +127,13 @@ impl Matcher for PermMatcher {
     }
 
     #[cfg(not(unix))]
-    fn matches(&self, _dummy_file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, _dummy_file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         writeln!(
-            &mut stderr(),
+            matcher_io.error_output(),
             "Permission matching not available on this platform!"
         )
         .unwrap();
-        return false;
+        false
     }
 }
 
@@ -147,10 +148,21 @@ mod tests {
 
     #[track_caller]
     fn assert_parse(pattern: &str, comparison_type: ComparisonType, mode: u32) {
+        assert_parse_dir(pattern, comparison_type, mode, mode);
+    }
+
+    /// For modes that mean something different for directories.
+    #[track_caller]
+    fn assert_parse_dir(
+        pattern: &str,
+        comparison_type: ComparisonType,
+        file_mode: u32,
+        dir_mode: u32,
+    ) {
         let matcher = PermMatcher::new(pattern).unwrap();
         assert_eq!(matcher.comparison_type, comparison_type);
-        assert_eq!(matcher.file_pattern, mode);
-        assert_eq!(matcher.dir_pattern, mode);
+        assert_eq!(matcher.file_pattern, file_mode, "{pattern} for files");
+        assert_eq!(matcher.dir_pattern, dir_mode, "{pattern} for directories");
     }
 
     #[test]
@@ -215,6 +227,43 @@ mod tests {
         assert_parse("/o=t", AnyOf, 0o1000);
     }
 
+    #[test]
+    fn parsing_human_readable_conditional_execute() {
+        // X is x for directories, or if something is executable already
+        assert_parse_dir("u=rwX", Exact, 0o600, 0o700);
+        assert_parse_dir("-a+X", AtLeast, 0o000, 0o111);
+        assert_parse_dir("/u=rw,go=rX", AnyOf, 0o644, 0o655);
+        assert_parse("u=x,a+X", Exact, 0o111);
+        // Only earlier clauses count
+        assert_parse("u+x,go+X", Exact, 0o111);
+        assert_parse_dir("go+X,u+x", Exact, 0o100, 0o111);
+        assert_parse_dir("u+X,u+x", Exact, 0o100, 0o100);
+    }
+
+    #[test]
+    fn parsing_human_readable_multiple_operators() {
+        assert_parse("u+r-w", Exact, 0o400);
+        assert_parse("u=rw+x", Exact, 0o700);
+        assert_parse("a=rwx-w", Exact, 0o555);
+        assert_parse("a=rwx,o-t", Exact, 0o777);
+        assert_parse("u=rw,g=u-w,o=g", Exact, 0o644);
+        assert_parse("u=rwx,go=u-w", Exact, 0o755);
+        assert_parse("a=r,+t", Exact, 0o1444);
+        // a covers the special bits too
+        assert_parse("+t,a=r", Exact, 0o444);
+        assert_parse("u=r,g+w,o=,a-x", Exact, 0o420);
+        // An operator with no permissions is allowed, like in chmod
+        assert_parse("u=rwx,g+", Exact, 0o700);
+        assert_parse("a=", Exact, 0o000);
+    }
+
+    #[test]
+    fn parsing_human_readable_set_id_bits_for_directories() {
+        // As for chmod, = leaves the set-ID bits of directories alone
+        assert_parse_dir("g+s,g=rx", Exact, 0o050, 0o2050);
+        assert_parse("u+s,u-s", Exact, 0o000);
+    }
+
     #[test]
     fn parsing_fails() {
         PermMatcher::new("urwx,g=rx,o+r").expect_err("missing equals should fail");
@@ -223,10 +272,20 @@ mod tests {
         PermMatcher::new("u_rwx,g=rx,o+r")
             .expect_err("invalid category/permission separator should fail");
         PermMatcher::new("77777777777777").expect_err("overflowing octal value should fail");
-
-        // FIXME: uucore::mode shouldn't accept this
-        // PermMatcher::new("u=rwxg=rx,o+r")
-        //     .expect_err("missing comma should fail");
+        PermMatcher::new("u=rwxg=rx,o+r").expect_err("missing comma should fail");
+        PermMatcher::new("u=ur").expect_err("copying and bits should fail");
+        PermMatcher::new("u=rw,").expect_err("trailing comma should fail");
+        PermMatcher::new(",u=rw").expect_err("leading comma should fail");
+        PermMatcher::new("ug").expect_err("missing operator should fail");
+        PermMatcher::new("").expect_err("empty mode should fail");
+        PermMatcher::new("u*r").expect_err("invalid operator should fail");
+        PermMatcher::new("8").expect_err("non-octal digit should fail");
+
+        let err = PermMatcher::new("-u+q").unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid mode '-u+q': "),
+            "{err}"
+        );
     }
 
     #[test]
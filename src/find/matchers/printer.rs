@@ -4,10 +4,14 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{stderr, Write};
+use std::io::Write;
+use std::rc::Rc;
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::hyperlink::Hyperlinks;
+use super::printf::relative_path;
+use super::{Matcher, MatcherIO, Needs, WalkEntry};
 
 pub enum PrintDelimiter {
     Newline,
@@ -27,58 +31,132 @@ impl std::fmt::Display for PrintDelimiter {
 pub struct Printer {
     delimiter: PrintDelimiter,
     output_file: Option<File>,
+    hyperlinks: Option<Rc<Hyperlinks>>,
+    relative: bool,
+    xargs_safe: bool,
 }
 
+/// The characters that xargs splits arguments at or unquotes, unless it's
+/// given -0.
+#[cfg(not(windows))]
+const XARGS_UNSAFE: &[char] = &[' ', '\t', '\n', '\'', '"', '\\'];
+/// Leaving out backslashes, which every path has on Windows.
+#[cfg(windows)]
+const XARGS_UNSAFE: &[char] = &[' ', '\t', '\n', '\'', '"'];
+
 impl Printer {
     pub fn new(delimiter: PrintDelimiter, output_file: Option<File>) -> Self {
         Self {
             delimiter,
             output_file,
+            hyperlinks: None,
+            relative: false,
+            xargs_safe: false,
         }
     }
 
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write, print_error_message: bool) {
-        match write!(
-            out,
-            "{}{}",
-            file_info.path().to_string_lossy(),
-            self.delimiter
-        ) {
-            Ok(_) => {}
-            Err(e) => {
-                if print_error_message {
-                    writeln!(
-                        &mut stderr(),
-                        "Error writing {:?} for {}",
-                        file_info.path().to_string_lossy(),
-                        e
-                    )
-                    .unwrap();
-                    uucore::error::set_exit_code(1);
-                }
-            }
+    /// Skip paths that xargs would split up, with a warning, like BSD find's
+    /// -X.
+    #[must_use]
+    pub(crate) fn xargs_safe(mut self, xargs_safe: bool) -> Self {
+        self.xargs_safe = xargs_safe;
+        self
+    }
+
+    /// Print each path as a link to the file (--hyperlink).
+    #[must_use]
+    pub(crate) fn hyperlinks(mut self, hyperlinks: Option<Rc<Hyperlinks>>) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// Print paths relative to their starting points (--relative).
+    #[must_use]
+    pub(crate) fn relative(mut self, relative: bool) -> Self {
+        self.relative = relative;
+        self
+    }
+
+    /// The path to print for an entry.
+    pub(crate) fn path<'a>(&self, file_info: &'a WalkEntry) -> Cow<'a, str> {
+        if self.relative {
+            relative_path(file_info)
+        } else {
+            file_info.path().to_string_lossy()
         }
-        out.flush().unwrap();
     }
-}
 
-impl Matcher for Printer {
-    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
-        if let Some(file) = &self.output_file {
-            self.print(file_info, file, true);
+    fn print(&self, file_info: &WalkEntry, path: &str, mut out: impl Write) -> std::io::Result<()> {
+        match &self.hyperlinks {
+            Some(hyperlinks) => write!(
+                out,
+                "{}{}",
+                hyperlinks.link(file_info.path(), path),
+                self.delimiter
+            ),
+            None => write!(out, "{}{}", path, self.delimiter),
+        }
+    }
+
+    /// Prints `path` in place of the entry's own path, for decorators like
+    /// [super::color::Colorize].
+    pub(crate) fn print_path(
+        &self,
+        file_info: &WalkEntry,
+        path: &str,
+        matcher_io: &mut MatcherIO,
+    ) -> bool {
+        // What's printed is what xargs would see, without any colours
+        let printed = self.path(file_info);
+        if self.xargs_safe && printed.contains(XARGS_UNSAFE) {
+            writeln!(
+                matcher_io.error_output(),
+                "Skipping {printed}: xargs would split it up"
+            )
+            .unwrap();
+            matcher_io.set_exit_code(1);
+            return false;
+        }
+        let result = if let Some(file) = &self.output_file {
+            let result = self.print(file_info, path, file);
+            if let Err(e) = &result {
+                writeln!(
+                    matcher_io.error_output(),
+                    "Error writing {:?} for {}",
+                    file_info.path().to_string_lossy(),
+                    e
+                )
+                .unwrap();
+                uucore::error::set_exit_code(1);
+            }
+            result
         } else {
             self.print(
                 file_info,
+                path,
                 &mut *matcher_io.deps.get_output().borrow_mut(),
-                false,
-            );
+            )
+        };
+        match result {
+            Ok(()) => matcher_io.log_action("print", file_info.path(), "ok"),
+            Err(e) => matcher_io.log_action("print", file_info.path(), &format!("failed: {e}")),
         }
         true
     }
+}
+
+impl Matcher for Printer {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        self.print_path(file_info, &self.path(file_info), matcher_io)
+    }
 
     fn has_side_effects(&self) -> bool {
         true
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Name
+    }
 }
 
 #[cfg(test)]
@@ -114,6 +192,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn prints_relative() {
+        let root = get_dir_entry_for("./test_data/simple", "");
+        let abbbc = get_dir_entry_for("./test_data/simple", "subdir/ABBBC");
+
+        let matcher = Printer::new(PrintDelimiter::Newline, None).relative(true);
+        let deps = FakeDependencies::new();
+        assert!(matcher.matches(&root, &mut deps.new_matcher_io()));
+        assert!(matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+        assert_eq!(
+            fix_up_slashes(".\nsubdir/ABBBC\n"),
+            deps.get_output_as_string()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn skips_xargs_unsafe_relative() {
+        let dir = tempfile::Builder::new()
+            .prefix("skips xargs unsafe relative")
+            .tempdir()
+            .unwrap();
+        std::fs::create_dir(dir.path().join("a b")).unwrap();
+        let root = dir.path().to_string_lossy();
+        let matcher = Printer::new(PrintDelimiter::Newline, None)
+            .relative(true)
+            .xargs_safe(true);
+
+        // Only the part of the path that's printed counts
+        let deps = FakeDependencies::new();
+        for path in ["", "a b"] {
+            let entry = get_dir_entry_for(&root, path);
+            let mut matcher_io = deps.new_matcher_io();
+            matcher.matches(&entry, &mut matcher_io);
+        }
+        assert_eq!(deps.get_output_as_string(), ".\n");
+        assert_eq!(
+            deps.get_error_output_as_string(),
+            "Skipping a b: xargs would split it up\n"
+        );
+    }
+
+    #[test]
+    fn prints_hyperlinks() {
+        let abbbc = get_dir_entry_for("./test_data/simple", "abbbc");
+        let hyperlinks = Rc::new(Hyperlinks::new());
+
+        let matcher =
+            Printer::new(PrintDelimiter::Newline, None).hyperlinks(Some(hyperlinks.clone()));
+        let deps = FakeDependencies::new();
+        assert!(matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+        let path = fix_up_slashes("./test_data/simple/abbbc");
+        assert_eq!(
+            format!("{}\n", hyperlinks.link(abbbc.path(), &path)),
+            deps.get_output_as_string()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn skips_xargs_unsafe() {
+        let dir = tempfile::Builder::new()
+            .prefix("skips_xargs_unsafe")
+            .tempdir()
+            .unwrap();
+        let root = dir.path().to_string_lossy();
+        let matcher = Printer::new(PrintDelimiter::Newline, None).xargs_safe(true);
+        for name in ["x y", "it's", "back\\slash", "tab\tbed"] {
+            File::create(dir.path().join(name)).unwrap();
+            let entry = get_dir_entry_for(&root, name);
+            let deps = FakeDependencies::new();
+            let mut matcher_io = deps.new_matcher_io();
+            assert!(!matcher.matches(&entry, &mut matcher_io), "{name}");
+            assert_eq!(matcher_io.exit_code(), 1, "{name}");
+            assert!(deps.get_output_as_string().is_empty());
+            assert!(deps
+                .get_error_output_as_string()
+                .ends_with(&format!("{name}: xargs would split it up\n")));
+        }
+
+        let abbbc = get_dir_entry_for("./test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+        let mut matcher_io = deps.new_matcher_io();
+        assert!(matcher.matches(&abbbc, &mut matcher_io));
+        assert_eq!(matcher_io.exit_code(), 0);
+        assert_eq!(
+            fix_up_slashes("./test_data/simple/abbbc\n"),
+            deps.get_output_as_string()
+        );
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn prints_error_message() {
@@ -124,11 +293,15 @@ mod tests {
         let deps = FakeDependencies::new();
 
         assert!(matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+        assert_eq!(uucore::error::get_exit_code(), 1);
 
         // Reset the exit code global variable in case we run another test after this one
         // See https://github.com/uutils/coreutils/issues/5777
         uucore::error::set_exit_code(0);
 
         assert!(deps.get_output_as_string().is_empty());
+        assert!(deps
+            .get_error_output_as_string()
+            .starts_with("Error writing \"./test_data/simple/abbbc\" for "));
     }
 }
@@ -6,13 +6,17 @@
 
 use std::error::Error;
 use std::fs::{self, File};
-use std::path::Path;
+use std::io;
+use std::path::{is_separator, Path};
+use std::rc::Rc;
 use std::time::SystemTime;
 use std::{borrow::Cow, io::Write};
 
 use chrono::{format::StrftimeItems, DateTime, Local};
 
-use super::{FileType, Matcher, MatcherIO, WalkEntry, WalkError};
+use super::hyperlink::Hyperlinks;
+use super::size::allocated_size;
+use super::{FileType, Matcher, MatcherIO, WalkEntry};
 
 #[cfg(unix)]
 use std::os::unix::prelude::MetadataExt;
@@ -112,6 +116,34 @@ enum FormatDirective {
     User { as_name: bool },
     // %y, %Y
     Type { follow_links: bool },
+    // %{ext}
+    Extension,
+    // %{linkdepth}
+    LinkDepth,
+    // %{xattr:NAME}
+    Xattr(String),
+}
+
+/// Makes the directive for a %{KEY:ARG} from ARG, or "" if it doesn't take
+/// one.
+type MakeDirective = fn(&str) -> FormatDirective;
+
+/// The %{KEY} and %{KEY:ARG} directives, which are extensions: each key,
+/// whether it takes an argument, and the directive it stands for.
+const EXTENDED_DIRECTIVES: &[(&str, bool, MakeDirective)] = &[
+    ("ext", false, |_| FormatDirective::Extension),
+    ("linkdepth", false, |_| FormatDirective::LinkDepth),
+    ("xattr", true, |name| {
+        FormatDirective::Xattr(name.to_owned())
+    }),
+];
+
+impl FormatDirective {
+    /// Whether this prints the entry's own name or path, rather than
+    /// something about it.
+    fn names_entry(&self) -> bool {
+        matches!(self, Self::Basename | Self::Path { .. })
+    }
 }
 
 /// A component in a full format string.
@@ -139,16 +171,16 @@ impl FormatStringParser<'_> {
     }
 
     fn peek(&self, count: usize) -> Result<&str, Box<dyn Error>> {
-        if self.string.len() < count {
-            return Err("Unexpected EOF".into());
-        }
-
-        Ok(&self.string[0..count])
+        // This also fails if count would split a multi-byte character, which
+        // is fine since we only peek at ASCII sequences
+        self.string
+            .get(0..count)
+            .ok_or_else(|| "Unexpected EOF".into())
     }
 
     fn advance_one(&mut self) -> Result<char, Box<dyn Error>> {
         let c = self.front()?;
-        self.string = &self.string[1..];
+        self.string = &self.string[c.len_utf8()..];
         Ok(c)
     }
 
@@ -201,7 +233,7 @@ impl FormatStringParser<'_> {
         }
     }
 
-    fn parse_format_width(&mut self) -> Option<usize> {
+    fn parse_format_width(&mut self) -> Result<Option<usize>, Box<dyn Error>> {
         let start = self.string;
         let mut digits = 0;
 
@@ -212,11 +244,15 @@ impl FormatStringParser<'_> {
         }
 
         if digits > 0 {
-            // safe to unwrap: we already know all the digits are valid due to
-            // the above checks.
-            Some((start[0..digits]).parse().unwrap())
+            // All the digits are valid due to the above checks, but there may
+            // be too many of them.
+            let width = &start[0..digits];
+            match width.parse() {
+                Ok(width) => Ok(Some(width)),
+                Err(_) => Err(format!("Field width too large: {width}").into()),
+            }
         } else {
-            None
+            Ok(None)
         }
     }
 
@@ -239,6 +275,31 @@ impl FormatStringParser<'_> {
         }
     }
 
+    fn parse_extended_directive(&mut self) -> Result<FormatDirective, Box<dyn Error>> {
+        let string = self.string;
+        let Some(end) = string.find('}') else {
+            return Err(format!("Unterminated format directive: %{{{string}").into());
+        };
+        let spec = &string[..end];
+        self.string = &string[end + 1..];
+
+        let (key, arg) = match spec.split_once(':') {
+            Some((key, arg)) => (key, Some(arg)),
+            None => (spec, None),
+        };
+        let Some((_, takes_arg, directive)) =
+            EXTENDED_DIRECTIVES.iter().find(|(name, ..)| *name == key)
+        else {
+            return Err(format!("Unknown format directive: %{{{spec}}}").into());
+        };
+        match (takes_arg, arg) {
+            (true, Some(arg)) if !arg.is_empty() => Ok(directive(arg)),
+            (true, _) => Err(format!("Missing argument: %{{{key}:...}}").into()),
+            (false, None) => Ok(directive("")),
+            (false, Some(_)) => Err(format!("Unexpected argument: %{{{spec}}}").into()),
+        }
+    }
+
     fn parse_format_specifier(&mut self) -> Result<FormatComponent, Box<dyn Error>> {
         let mut justify = Justify::Right;
         loop {
@@ -252,7 +313,7 @@ impl FormatStringParser<'_> {
             self.advance_one().unwrap();
         }
 
-        let width = self.parse_format_width();
+        let width = self.parse_format_width()?;
 
         let first = self.advance_one()?;
         if first == '%' {
@@ -297,6 +358,7 @@ impl FormatStringParser<'_> {
                 follow_links: false,
             },
             'Y' => FormatDirective::Type { follow_links: true },
+            '{' => self.parse_extended_directive()?,
             // TODO: %Z
             _ => return Ok(FormatComponent::Literal(first.to_string())),
         };
@@ -349,7 +411,29 @@ impl FormatString {
     }
 }
 
-fn get_starting_point(file_info: &WalkEntry) -> &Path {
+/// Gets the volume serial number, file index and link count, which Windows
+/// leaves out of [fs::Metadata], for %D, %i and %n.
+#[cfg(windows)]
+fn file_information(file_info: &WalkEntry) -> std::io::Result<winapi_util::file::Information> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT,
+    };
+
+    // Directories can only be opened with backup semantics. Like lstat(),
+    // open the link itself unless it's being followed, or is broken.
+    let mut flags = FILE_FLAG_BACKUP_SEMANTICS;
+    if !file_info.follow() || file_info.file_type().is_symlink() {
+        flags |= FILE_FLAG_OPEN_REPARSE_POINT;
+    }
+    let file = fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(flags)
+        .open(file_info.path())?;
+    winapi_util::file::information(&file)
+}
+
+pub(super) fn get_starting_point(file_info: &WalkEntry) -> &Path {
     file_info
         .path()
         .ancestors()
@@ -359,6 +443,133 @@ fn get_starting_point(file_info: &WalkEntry) -> &Path {
         .unwrap()
 }
 
+/// The path of a file relative to its starting point, for %P.
+fn strip_starting_point(file_info: &WalkEntry) -> Cow<'_, str> {
+    file_info
+        .path()
+        .strip_prefix(get_starting_point(file_info))
+        // safe to unwrap: the prefix is derived *from* the path to begin
+        // with, so it cannot be invalid.
+        .unwrap()
+        .to_string_lossy()
+}
+
+/// Like %P, except that the starting point itself is ".", for --relative.
+pub(super) fn relative_path(file_info: &WalkEntry) -> Cow<'_, str> {
+    if file_info.depth() == 0 {
+        ".".into()
+    } else {
+        strip_starting_point(file_info)
+    }
+}
+
+/// The leading directories of a path, for %h. GNU find's behavior for this is
+/// a bit...odd:
+/// - Both the root directory and the paths immediately underneath return an
+///   empty string
+/// - Any path without any slashes (i.e. relative to cwd) returns "."
+/// - "." also returns "."
+/// - ".." returns "." (???)
+/// - Trailing slashes are ignored if there are leading directories, and one
+///   slash is dropped from the end of the result, so "a//b//" returns "a/"
+/// - Except that a starting point with a one-character name and no leading
+///   directories just loses its last slash, so "x/" returns "x", "x//"
+///   returns "x/" and ".//" returns "./", while "ab/" and "../" return "."
+///
+/// These are mostly (thankfully) documented on the find(1) man page.
+fn dirname(path: &str) -> &str {
+    let trimmed = path.trim_end_matches(is_separator);
+    if trimmed.is_empty() {
+        // Nothing but slashes
+        return &path[..path.len().saturating_sub(1)];
+    }
+    match trimmed.rfind(is_separator) {
+        Some(i) => &path[..i],
+        // The trailing slashes mean the path is longer than its name
+        None if trimmed.len() == 1 && path.len() > 1 => &path[..path.len() - 1],
+        None => ".",
+    }
+}
+
+/// How many symbolic links have to be followed from `path` to get to
+/// something that isn't one, for %{linkdepth}. A broken link counts the
+/// links up to the missing target.
+fn link_depth(path: &Path) -> io::Result<usize> {
+    // Linux's limit, after which it gives ELOOP
+    const MAX_LINKS: usize = 40;
+
+    let mut path = path.to_owned();
+    for depth in 0..=MAX_LINKS {
+        match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => {}
+            Ok(_) => return Ok(depth),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && depth > 0 => return Ok(depth),
+            Err(e) => return Err(e),
+        }
+        // Relative targets are relative to the link's directory
+        let target = fs::read_link(&path)?;
+        path = match path.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+    }
+    Err(io::Error::other("Too many levels of symbolic links"))
+}
+
+/// Reads an extended attribute, for %{xattr:NAME}, or `None` if the file
+/// doesn't have it (or the file system doesn't support them).
+#[cfg(target_os = "linux")]
+fn read_xattr(file_info: &WalkEntry, name: &str) -> io::Result<Option<Vec<u8>>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::ptr;
+
+    use uucore::libc;
+
+    let path = CString::new(file_info.path().as_os_str().as_bytes())?;
+    let name = CString::new(name)?;
+    // Like stat(), look at the link itself unless it's being followed
+    let get = if file_info.follow() {
+        libc::getxattr
+    } else {
+        libc::lgetxattr
+    };
+    let error = || {
+        let e = io::Error::last_os_error();
+        match e.raw_os_error() {
+            Some(libc::ENODATA | libc::ENOTSUP) => Ok(None),
+            _ => Err(e),
+        }
+    };
+
+    loop {
+        // SAFETY: both names are NUL-terminated, and a null buffer just asks
+        // for the size
+        let len = unsafe { get(path.as_ptr(), name.as_ptr(), ptr::null_mut(), 0) };
+        if len < 0 {
+            return error();
+        }
+        let mut value = vec![0u8; len as usize];
+        // SAFETY: as above, and the buffer is as big as we say it is
+        let len = unsafe {
+            get(
+                path.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        if len >= 0 {
+            value.truncate(len as usize);
+            return Ok(Some(value));
+        }
+        // It can grow between the two calls, in which case try again
+        if io::Error::last_os_error().raw_os_error() != Some(libc::ERANGE) {
+            return error();
+        }
+    }
+}
+
 fn format_non_link_file_type(file_type: FileType) -> char {
     match file_type {
         FileType::Regular => 'f',
@@ -386,14 +597,13 @@ fn format_directive<'entry>(
     let res: Cow<'entry, str> = match directive {
         FormatDirective::AccessTime(tf) => tf.apply(meta()?.accessed()?)?,
 
-        FormatDirective::Basename => file_info.file_name().to_string_lossy(),
+        FormatDirective::Basename => match file_info.printed_name() {
+            Cow::Borrowed(name) => name.to_string_lossy(),
+            Cow::Owned(name) => name.to_string_lossy().into_owned().into(),
+        },
 
         FormatDirective::Blocks { large_blocks } => {
-            #[cfg(unix)]
-            let blocks = meta()?.blocks();
-            #[cfg(not(unix))]
-            // Estimate using a ceiling division by the block size.
-            let blocks = (meta()?.len() + STANDARD_BLOCK_SIZE - 1) / STANDARD_BLOCK_SIZE;
+            let blocks = allocated_size(file_info)?.div_ceil(STANDARD_BLOCK_SIZE);
 
             // GNU find says it returns the number of 512-byte blocks for %b,
             // but in reality it just returns the number of blocks, *regardless
@@ -401,7 +611,7 @@ fn format_directive<'entry>(
             // even though it's arguably not 100% correct.
             if *large_blocks {
                 // Ceiling divide in half.
-                (blocks + 1) / 2
+                blocks.div_ceil(2)
             } else {
                 blocks
             }
@@ -424,23 +634,24 @@ fn format_directive<'entry>(
 
         FormatDirective::Depth => file_info.depth().to_string().into(),
 
-        #[cfg(not(unix))]
+        #[cfg(not(any(unix, windows)))]
         FormatDirective::Device => "0".into(),
+        #[cfg(windows)]
+        FormatDirective::Device => file_information(file_info)?
+            .volume_serial_number()
+            .to_string()
+            .into(),
         #[cfg(unix)]
         FormatDirective::Device => meta()?.dev().to_string().into(),
 
-        // GNU find's behavior for this is a bit...odd:
-        // - Both the root directory and the paths immediately underneath return an empty string
-        // - Any path without any slashes (i.e. relative to cwd) returns "."
-        // - "." also returns "."
-        // - ".." returns "." (???)
-        // These are all (thankfully) documented on the find(1) man page.
-        FormatDirective::Dirname => match file_info.path().parent() {
-            None => "".into(),
-            Some(p) if p == Path::new("/") => "".into(),
-            Some(p) if p == Path::new("") => ".".into(),
-            Some(parent) => parent.to_string_lossy(),
-        },
+        FormatDirective::Extension => Path::new(file_info.file_name())
+            .extension()
+            .map_or_else(String::new, |ext| ext.to_string_lossy().into_owned())
+            .into(),
+
+        FormatDirective::Dirname => dirname(&file_info.path().to_string_lossy())
+            .to_owned()
+            .into(),
 
         #[cfg(not(unix))]
         FormatDirective::Filesystem => "".into(),
@@ -469,31 +680,34 @@ fn format_directive<'entry>(
             .into()
         }
 
-        #[cfg(not(unix))]
+        #[cfg(not(any(unix, windows)))]
         FormatDirective::HardlinkCount => "0".into(),
+        #[cfg(windows)]
+        FormatDirective::HardlinkCount => file_information(file_info)?
+            .number_of_links()
+            .to_string()
+            .into(),
         #[cfg(unix)]
         FormatDirective::HardlinkCount => meta()?.nlink().to_string().into(),
 
-        #[cfg(not(unix))]
+        #[cfg(not(any(unix, windows)))]
         FormatDirective::Inode => "0".into(),
+        #[cfg(windows)]
+        FormatDirective::Inode => file_information(file_info)?.file_index().to_string().into(),
         #[cfg(unix)]
         FormatDirective::Inode => meta()?.ino().to_string().into(),
 
+        FormatDirective::LinkDepth => link_depth(file_info.path())?.to_string().into(),
+
         FormatDirective::ModificationTime(tf) => tf.apply(meta()?.modified()?)?,
 
+        // The path is printed as given, without normalizing slashes or "."
         FormatDirective::Path {
-            strip_starting_point,
-        } => file_info
-            .path()
-            .strip_prefix(if *strip_starting_point {
-                get_starting_point(file_info)
-            } else {
-                Path::new("")
-            })
-            // safe to unwrap: the prefix is derived *from* the path to begin
-            // with, so it cannot be invalid.
-            .unwrap()
-            .to_string_lossy(),
+            strip_starting_point: false,
+        } => file_info.path().to_string_lossy(),
+        FormatDirective::Path {
+            strip_starting_point: true,
+        } => strip_starting_point(file_info),
 
         FormatDirective::Permissions(PermissionsFormat::Symbolic) => {
             uucore::fs::display_permissions(meta()?, true).into()
@@ -507,20 +721,11 @@ fn format_directive<'entry>(
 
         FormatDirective::Size => meta()?.len().to_string().into(),
 
-        #[cfg(not(unix))]
-        FormatDirective::Sparseness => "1.0".into(),
-        #[cfg(unix)]
         FormatDirective::Sparseness => {
-            let meta = meta()?;
+            let len = meta()?.len();
 
-            if meta.len() > 0 {
-                format!(
-                    "{:.1}",
-                    // GNU find hardcodes a block size of 512 bytes, regardless
-                    // of the true filesystem block size.
-                    (meta.blocks() * STANDARD_BLOCK_SIZE) as f64 / (meta.len() as f64)
-                )
-                .into()
+            if len > 0 {
+                format!("{:.1}", allocated_size(file_info)? as f64 / len as f64).into()
             } else {
                 "1.0".into()
             }
@@ -541,7 +746,7 @@ fn format_directive<'entry>(
 
         FormatDirective::Type { follow_links } => if file_info.path_is_symlink() {
             if *follow_links {
-                match file_info.path().metadata().map_err(WalkError::from) {
+                match file_info.target_metadata() {
                     Ok(meta) => format_non_link_file_type(meta.file_type().into()),
                     Err(e) if e.is_not_found() => 'N',
                     Err(e) if e.is_loop() => 'L',
@@ -568,6 +773,15 @@ fn format_directive<'entry>(
             }
             .into()
         }
+
+        #[cfg(not(target_os = "linux"))]
+        FormatDirective::Xattr(_) => "".into(),
+        #[cfg(target_os = "linux")]
+        FormatDirective::Xattr(name) => read_xattr(file_info, name)?
+            .map_or_else(String::new, |value| {
+                String::from_utf8_lossy(&value).into_owned()
+            })
+            .into(),
     };
 
     Ok(res)
@@ -578,6 +792,8 @@ fn format_directive<'entry>(
 pub struct Printf {
     format: FormatString,
     output_file: Option<File>,
+    hyperlinks: Option<Rc<Hyperlinks>>,
+    relative: bool,
 }
 
 impl Printf {
@@ -585,53 +801,92 @@ impl Printf {
         Ok(Self {
             format: FormatString::parse(format)?,
             output_file,
+            hyperlinks: None,
+            relative: false,
         })
     }
 
-    fn print(&self, file_info: &WalkEntry, mut out: impl Write) {
+    /// Print %p, %P and %f as links to the file (--hyperlink).
+    #[must_use]
+    pub(crate) fn hyperlinks(mut self, hyperlinks: Option<Rc<Hyperlinks>>) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// Print %p relative to the starting point (--relative).
+    #[must_use]
+    pub(crate) fn relative(mut self, relative: bool) -> Self {
+        self.relative = relative;
+        self
+    }
+
+    /// Formats the whole record for a file, and whether it asks for the
+    /// output to be flushed, so that nothing is printed if any of it fails.
+    fn format(&self, file_info: &WalkEntry) -> Result<(String, bool), Box<dyn Error>> {
+        let mut record = String::new();
+        let mut flush = false;
         for component in &self.format.components {
             match component {
-                FormatComponent::Literal(literal) => write!(out, "{literal}").unwrap(),
-                FormatComponent::Flush => out.flush().unwrap(),
+                FormatComponent::Literal(literal) => record.push_str(literal),
+                FormatComponent::Flush => flush = true,
                 FormatComponent::Directive {
                     directive,
                     width,
                     justify,
-                } => match format_directive(file_info, directive) {
-                    Ok(content) => {
-                        if let Some(width) = width {
-                            match justify {
-                                Justify::Left => {
-                                    write!(out, "{content:<width$}").unwrap();
-                                }
-                                Justify::Right => {
-                                    write!(out, "{content:>width$}").unwrap();
-                                }
-                            }
-                        } else {
-                            write!(out, "{content}").unwrap();
+                } => {
+                    let content = match directive {
+                        FormatDirective::Path {
+                            strip_starting_point: false,
+                        } if self.relative => relative_path(file_info),
+                        _ => format_directive(file_info, directive)?,
+                    };
+                    let content = match (width, justify) {
+                        (Some(width), Justify::Left) => format!("{content:<width$}"),
+                        (Some(width), Justify::Right) => format!("{content:>width$}"),
+                        (None, _) => content.into_owned(),
+                    };
+                    match &self.hyperlinks {
+                        Some(hyperlinks) if directive.names_entry() => {
+                            record.push_str(&hyperlinks.link(file_info.path(), &content));
                         }
+                        _ => record.push_str(&content),
                     }
-                    Err(e) => {
-                        eprintln!(
-                            "Error processing '{}': {}",
-                            file_info.path().to_string_lossy(),
-                            e
-                        );
-                        break;
-                    }
-                },
+                }
             }
         }
+        Ok((record, flush))
+    }
+
+    fn print(record: &str, flush: bool, mut out: impl Write) {
+        write!(out, "{record}").unwrap();
+        if flush {
+            out.flush().unwrap();
+        }
     }
 }
 
 impl Matcher for Printf {
     fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let (record, flush) = match self.format(file_info) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                matcher_io.report_error(format_args!(
+                    "Error processing '{}': {}",
+                    file_info.path().to_string_lossy(),
+                    e
+                ));
+                return true;
+            }
+        };
+
         if let Some(file) = &self.output_file {
-            self.print(file_info, file);
+            Self::print(&record, flush, file);
         } else {
-            self.print(file_info, &mut *matcher_io.deps.get_output().borrow_mut());
+            Self::print(
+                &record,
+                flush,
+                &mut *matcher_io.deps.get_output().borrow_mut(),
+            );
         }
 
         true
@@ -691,6 +946,8 @@ mod tests {
 
         assert!(FormatString::parse("\\X").is_err());
         assert!(FormatString::parse("\\").is_err());
+        assert!(FormatString::parse("\\é").is_err());
+        assert!(FormatString::parse("\\12é").is_err());
     }
 
     #[test]
@@ -770,6 +1027,12 @@ mod tests {
 
         assert!(FormatString::parse("%").is_err());
         assert!(FormatString::parse("%A!").is_err());
+        assert!(FormatString::parse("%Aé").is_err());
+        assert_eq!(
+            FormatString::parse("%é").unwrap().components,
+            vec![FormatComponent::Literal("é".to_owned())]
+        );
+        assert!(FormatString::parse("%99999999999999999999999p").is_err());
     }
 
     #[test]
@@ -861,6 +1124,142 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_printf_relative() {
+        let deps = FakeDependencies::new();
+        let matcher = Printf::new("%p|%P\n", None).unwrap().relative(true);
+        for path in ["", "subdir/ABBBC"] {
+            let file_info = get_dir_entry_for("test_data/simple", path);
+            assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        }
+        assert_eq!(
+            fix_up_slashes(".|\nsubdir/ABBBC|subdir/ABBBC\n"),
+            deps.get_output_as_string()
+        );
+    }
+
+    #[test]
+    fn test_printf_paths_of_starting_points() {
+        let deps = FakeDependencies::new();
+        let matcher = Printf::new("%p|%h|%f|%P\n", None).unwrap();
+        for path in [
+            "test_data/simple",
+            "test_data//simple//",
+            "test_data/simple/.",
+            "test_data/simple/./",
+        ] {
+            let file_info = WalkEntry::new(path, 0, crate::find::matchers::Follow::Never);
+            assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        }
+        assert_eq!(
+            "test_data/simple|test_data|simple|\n\
+             test_data//simple//|test_data/|simple/|\n\
+             test_data/simple/.|test_data/simple|.|\n\
+             test_data/simple/./|test_data/simple|./|\n",
+            deps.get_output_as_string()
+        );
+    }
+
+    #[test]
+    fn test_dirname() {
+        for (path, expected) in [
+            ("/", ""),
+            ("//", "/"),
+            ("/tmp", ""),
+            ("/tmp//", ""),
+            ("//tmp", "/"),
+            (".", "."),
+            ("./", "."),
+            (".//", "./"),
+            ("..", "."),
+            ("../", "."),
+            ("..//", "."),
+            ("x/", "x"),
+            ("x//", "x/"),
+            ("x///", "x//"),
+            ("ab/", "."),
+            ("ab//", "."),
+            ("x/b/", "x"),
+            ("a/b", "a"),
+            ("a//b//", "a/"),
+            ("a/b/.", "a/b"),
+        ] {
+            assert_eq!(dirname(path), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_printf_file_identity() {
+        let temp_dir = Builder::new().prefix("printf_identity").tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy();
+        File::create(temp_dir.path().join("a")).unwrap();
+        fs::hard_link(temp_dir.path().join("a"), temp_dir.path().join("b")).unwrap();
+        File::create(temp_dir.path().join("c")).unwrap();
+
+        let matcher = Printf::new("%n %i %D\n", None).unwrap();
+        let deps = FakeDependencies::new();
+        for name in ["a", "b", "c"] {
+            let file_info = get_dir_entry_for(&root, name);
+            assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        }
+
+        let output = deps.get_output_as_string();
+        let lines: Vec<Vec<&str>> = output.lines().map(|l| l.split(' ').collect()).collect();
+        // Hard links share an identity, but other files on the same device
+        // don't
+        assert_eq!(lines[0], lines[1]);
+        assert_eq!(lines[0][0], "2");
+        assert_eq!(lines[2][0], "1");
+        assert_ne!(lines[0][1], lines[2][1]);
+        assert_eq!(lines[0][2], lines[2][2]);
+    }
+
+    #[test]
+    fn test_printf_sparseness() {
+        let temp_dir = Builder::new().prefix("printf_sparse").tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy();
+        File::create(temp_dir.path().join("empty")).unwrap();
+        File::create(temp_dir.path().join("sparse"))
+            .unwrap()
+            .set_len(1 << 24)
+            .unwrap();
+
+        let matcher = Printf::new("%S\n", None).unwrap();
+        let deps = FakeDependencies::new();
+        for name in ["empty", "sparse"] {
+            let file_info = get_dir_entry_for(&root, name);
+            assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        }
+
+        let output = deps.get_output_as_string();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "1.0");
+        let sparseness: f64 = lines[1].parse().unwrap();
+        // Not every filesystem makes files sparse when they're extended, but
+        // the common unix ones do
+        #[cfg(unix)]
+        assert!(sparseness < 1.0, "{sparseness}");
+        #[cfg(not(unix))]
+        assert!(sparseness <= 1.0, "{sparseness}");
+    }
+
+    #[test]
+    fn test_printf_hyperlinks() {
+        let file_info = get_dir_entry_for("test_data/simple", "abbbc");
+        let hyperlinks = Rc::new(Hyperlinks::new());
+        let deps = FakeDependencies::new();
+
+        let matcher = Printf::new("%-6f|%s", None)
+            .unwrap()
+            .hyperlinks(Some(hyperlinks.clone()));
+        assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        // Padding goes inside the link, and only names are links
+        assert_eq!(
+            format!("{}|0", hyperlinks.link(file_info.path(), "abbbc ")),
+            deps.get_output_as_string()
+        );
+    }
+
     #[test]
     fn test_printf_depth() {
         let file_info_1 = get_dir_entry_for("test_data/depth/1", "f1");
@@ -873,6 +1272,133 @@ mod tests {
         assert_eq!("1.2.", deps.get_output_as_string());
     }
 
+    #[test]
+    fn test_parse_extended_directives() {
+        assert_eq!(
+            FormatString::parse("%{ext}%-4{linkdepth}%{xattr:user.a:b}")
+                .unwrap()
+                .components,
+            vec![
+                FormatComponent::Directive {
+                    directive: FormatDirective::Extension,
+                    width: None,
+                    justify: Justify::Right,
+                },
+                FormatComponent::Directive {
+                    directive: FormatDirective::LinkDepth,
+                    width: Some(4),
+                    justify: Justify::Left,
+                },
+                FormatComponent::Directive {
+                    directive: FormatDirective::Xattr("user.a:b".to_owned()),
+                    width: None,
+                    justify: Justify::Right,
+                },
+            ]
+        );
+
+        for (format, error) in [
+            ("%{mime}", "Unknown format directive: %{mime}"),
+            ("%{}", "Unknown format directive: %{}"),
+            ("%{ext", "Unterminated format directive: %{ext"),
+            ("%{xattr}", "Missing argument: %{xattr:...}"),
+            ("%{xattr:}", "Missing argument: %{xattr:...}"),
+            ("%{ext:x}", "Unexpected argument: %{ext:x}"),
+        ] {
+            let err = FormatString::parse(format).err().unwrap();
+            assert_eq!(err.to_string(), error, "{format}");
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_printf_extended_directives() {
+        let temp_dir = Builder::new().prefix("printf_extended").tempdir().unwrap();
+        let dir = temp_dir.path();
+        File::create(dir.join("data.tar.gz")).unwrap();
+        File::create(dir.join(".hidden")).unwrap();
+        symlink("data.tar.gz", dir.join("one")).unwrap();
+        symlink(dir.join("one"), dir.join("two")).unwrap();
+        symlink("missing", dir.join("broken")).unwrap();
+        symlink("loop", dir.join("loop")).unwrap();
+
+        let dir_name = dir.to_string_lossy();
+        let deps = FakeDependencies::new();
+        let matcher = Printf::new("%f [%{ext}] %{linkdepth}\n", None).unwrap();
+        for name in ["data.tar.gz", ".hidden", "one", "two", "broken"] {
+            let file_info = get_dir_entry_for(&dir_name, name);
+            assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        }
+        assert_eq!(
+            deps.get_output_as_string(),
+            "data.tar.gz [gz] 0\n.hidden [] 0\none [] 1\ntwo [] 2\nbroken [] 1\n"
+        );
+
+        let loop_info = get_dir_entry_for(&dir_name, "loop");
+        let err = format_directive(&loop_info, &FormatDirective::LinkDepth).unwrap_err();
+        assert_eq!(err.to_string(), "Too many levels of symbolic links");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_printf_xattr() {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        use uucore::libc;
+
+        let temp_dir = Builder::new().prefix("printf_xattr").tempdir().unwrap();
+        let path = temp_dir.path().join("file");
+        File::create(&path).unwrap();
+        let file_info = get_dir_entry_for(&temp_dir.path().to_string_lossy(), "file");
+        let directive = FormatDirective::Xattr("user.comment".to_owned());
+        assert_eq!(format_directive(&file_info, &directive).unwrap(), "");
+
+        // Not every file system supports user attributes
+        let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let value = "hello";
+        // SAFETY: both names are NUL-terminated, and the value is as long as
+        // we say it is
+        let ret = unsafe {
+            libc::setxattr(
+                c_path.as_ptr(),
+                c"user.comment".as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        };
+        if ret != 0 {
+            return;
+        }
+        assert_eq!(format_directive(&file_info, &directive).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_printf_error_skips_record() {
+        let temp_dir = Builder::new().prefix("printf_error").tempdir().unwrap();
+        let path = temp_dir.path().join("gone");
+        File::create(&path).unwrap();
+        let file_info = get_dir_entry_for(&temp_dir.path().to_string_lossy(), "gone");
+        fs::remove_file(&path).unwrap();
+        let deps = FakeDependencies::new();
+
+        // Nothing is printed, not even the part before the error
+        let matcher = Printf::new("%f %s\\n", None).unwrap();
+        let mut matcher_io = deps.new_matcher_io();
+        assert!(matcher.matches(&file_info, &mut matcher_io));
+        assert_eq!(matcher_io.exit_code(), 1);
+        assert_eq!(deps.get_output_as_string(), "");
+        assert!(deps
+            .get_error_output_as_string()
+            .starts_with(&format!("Error processing '{}': ", path.display())));
+
+        // And the next record is whole
+        let file_info = get_dir_entry_for("test_data/size", "512bytes");
+        assert!(matcher.matches(&file_info, &mut deps.new_matcher_io()));
+        assert_eq!(deps.get_output_as_string(), "512bytes 512\n");
+    }
+
     #[test]
     fn test_printf_basic_types() {
         let file_info_f = get_dir_entry_for("test_data/simple", "abbbc");
@@ -4,7 +4,7 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Matcher, MatcherIO, Needs, WalkEntry};
 
 /// This matcher checks the type of the file.
 pub struct PruneMatcher;
@@ -23,6 +23,10 @@ impl Matcher for PruneMatcher {
 
         true
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Type
+    }
 }
 
 #[cfg(test)]
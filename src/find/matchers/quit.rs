@@ -4,7 +4,7 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{Matcher, MatcherIO, Needs, WalkEntry};
 
 /// This matcher quits the search immediately.
 pub struct QuitMatcher;
@@ -14,6 +14,10 @@ impl Matcher for QuitMatcher {
         matcher_io.quit();
         true
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Name
+    }
 }
 
 #[cfg(test)]
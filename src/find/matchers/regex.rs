@@ -6,9 +6,10 @@
 
 use std::{error::Error, fmt, str::FromStr};
 
-use onig::{Regex, RegexOptions, Syntax};
+use onig::{Regex, RegexOptions, Syntax, SyntaxBehavior, SyntaxOperator};
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::fold::CaseFolding;
+use super::{Matcher, MatcherIO, Needs, WalkEntry};
 
 #[derive(Debug)]
 pub struct ParseRegexTypeError(String);
@@ -30,12 +31,17 @@ impl fmt::Display for ParseRegexTypeError {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum RegexType {
+    #[default]
     Emacs,
     Grep,
     PosixBasic,
+    PosixMinimalBasic,
     PosixExtended,
+    Awk,
+    GnuAwk,
+    PosixAwk,
 }
 
 impl RegexType {
@@ -43,8 +49,20 @@ impl RegexType {
         Self::Emacs,
         Self::Grep,
         Self::PosixBasic,
+        Self::PosixMinimalBasic,
         Self::PosixExtended,
+        Self::Awk,
+        Self::GnuAwk,
+        Self::PosixAwk,
     ];
+
+    /// Whether the dialect's operators are unescaped, like POSIX extended's.
+    fn is_extended(self) -> bool {
+        matches!(
+            self,
+            Self::PosixExtended | Self::Awk | Self::GnuAwk | Self::PosixAwk
+        )
+    }
 }
 
 impl fmt::Display for RegexType {
@@ -53,7 +71,11 @@ impl fmt::Display for RegexType {
             RegexType::Emacs => write!(f, "emacs"),
             RegexType::Grep => write!(f, "grep"),
             RegexType::PosixBasic => write!(f, "posix-basic"),
+            RegexType::PosixMinimalBasic => write!(f, "posix-minimal-basic"),
             RegexType::PosixExtended => write!(f, "posix-extended"),
+            RegexType::Awk => write!(f, "awk"),
+            RegexType::GnuAwk => write!(f, "gnu-awk"),
+            RegexType::PosixAwk => write!(f, "posix-awk"),
         }
     }
 }
@@ -63,23 +85,24 @@ impl FromStr for RegexType {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "emacs" => Ok(Self::Emacs),
+            "emacs" | "findutils-default" => Ok(Self::Emacs),
             "grep" => Ok(Self::Grep),
             "posix-basic" => Ok(Self::PosixBasic),
+            "posix-minimal-basic" => Ok(Self::PosixMinimalBasic),
             "posix-extended" => Ok(Self::PosixExtended),
+            "awk" => Ok(Self::Awk),
+            "gnu-awk" => Ok(Self::GnuAwk),
+            "posix-awk" => Ok(Self::PosixAwk),
             // ed and sed are the same as posix-basic
             "ed" | "sed" => Ok(Self::PosixBasic),
+            // These only differ from posix-extended in which invalid regexes
+            // are errors
+            "egrep" | "posix-egrep" => Ok(Self::PosixExtended),
             _ => Err(ParseRegexTypeError(s.to_owned())),
         }
     }
 }
 
-impl Default for RegexType {
-    fn default() -> Self {
-        Self::Emacs
-    }
-}
-
 pub struct RegexMatcher {
     regex: Regex,
 }
@@ -90,21 +113,60 @@ impl RegexMatcher {
         pattern: &str,
         ignore_case: bool,
     ) -> Result<Self, Box<dyn Error>> {
-        let syntax = match regex_type {
+        let mut syntax = *match regex_type {
             RegexType::Emacs => Syntax::emacs(),
             RegexType::Grep => Syntax::grep(),
-            RegexType::PosixBasic => Syntax::posix_basic(),
-            RegexType::PosixExtended => Syntax::posix_extended(),
+            RegexType::PosixBasic | RegexType::PosixMinimalBasic => Syntax::posix_basic(),
+            _ => Syntax::posix_extended(),
+        };
+        // GNU regexes support \` and \' in nearly every dialect. awk and
+        // posix-awk don't, but the anchoring below needs them.
+        syntax.enable_operators(SyntaxOperator::SYNTAX_OPERATOR_ESC_GNU_BUF_ANCHOR);
+        match regex_type {
+            // GNU's Emacs regexes have no intervals
+            RegexType::Emacs => {
+                syntax.disable_operators(SyntaxOperator::SYNTAX_OPERATOR_ESC_BRACE_INTERVAL)
+            }
+            // GNU adds \| to posix-basic, but not to posix-minimal-basic
+            RegexType::PosixBasic => {
+                syntax.enable_operators(SyntaxOperator::SYNTAX_OPERATOR_ESC_VBAR_ALT)
+            }
+            // The awk dialects allow backslash escapes in brackets, and the
+            // original awk has no intervals or back-references either
+            RegexType::Awk => {
+                syntax.disable_operators(
+                    SyntaxOperator::SYNTAX_OPERATOR_BRACE_INTERVAL
+                        | SyntaxOperator::SYNTAX_OPERATOR_DECIMAL_BACKREF,
+                );
+                syntax.enable_behavior(SyntaxBehavior::SYNTAX_BEHAVIOR_BACKSLASH_ESCAPE_IN_CC);
+            }
+            RegexType::GnuAwk | RegexType::PosixAwk => {
+                syntax.enable_behavior(SyntaxBehavior::SYNTAX_BEHAVIOR_BACKSLASH_ESCAPE_IN_CC)
+            }
+            _ => {}
+        }
+
+        // The regex has to match the whole path. is_match() only accepts the
+        // first match it finds if it happens to cover everything, which
+        // misses paths that only a later alternative matches completely, so
+        // anchor the regex at the end instead. It's wrapped in a non-capturing
+        // group so that the anchor applies to every alternative, without
+        // changing the numbering of any back-references.
+        syntax.enable_operators(SyntaxOperator::SYNTAX_OPERATOR_QMARK_GROUP_EFFECT);
+        let pattern = if regex_type.is_extended() {
+            format!(r"(?:{pattern})\'")
+        } else {
+            format!(r"\(?:{pattern}\)\'")
         };
 
         let regex = Regex::with_options(
-            pattern,
+            &pattern,
             if ignore_case {
-                RegexOptions::REGEX_OPTION_IGNORECASE
+                CaseFolding::from_env().regex_options()
             } else {
                 RegexOptions::REGEX_OPTION_NONE
             },
-            syntax,
+            &syntax,
         )?;
         Ok(Self { regex })
     }
@@ -115,6 +177,10 @@ impl Matcher for RegexMatcher {
         self.regex
             .is_match(file_info.path().to_string_lossy().as_ref())
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Name
+    }
 }
 
 #[cfg(test)]
@@ -159,8 +225,8 @@ mod tests {
 
     #[test]
     fn emacs_regex() {
-        // Emacs syntax is mostly the same as POSIX extended but with escaped
-        // brace intervals.
+        // Emacs syntax is mostly the same as POSIX extended but without brace
+        // intervals, escaped or not.
         let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
 
         let matcher = RegexMatcher::new(
@@ -180,6 +246,15 @@ mod tests {
         .unwrap();
         let deps = FakeDependencies::new();
         assert!(!matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+
+        let matcher = RegexMatcher::new(
+            RegexType::Emacs,
+            &fix_up_regex_slashes(POSIX_BASIC_INTERVALS_RE),
+            true,
+        )
+        .unwrap();
+        let deps = FakeDependencies::new();
+        assert!(!matcher.matches(&abbbc, &mut deps.new_matcher_io()));
     }
 
     #[test]
@@ -227,4 +302,124 @@ mod tests {
         let deps = FakeDependencies::new();
         assert!(!matcher.matches(&abbbc, &mut deps.new_matcher_io()));
     }
+
+    #[test]
+    fn aliases() {
+        for (name, regex_type) in [
+            ("findutils-default", RegexType::Emacs),
+            ("sed", RegexType::PosixBasic),
+            ("egrep", RegexType::PosixExtended),
+            ("posix-egrep", RegexType::PosixExtended),
+        ] {
+            assert_eq!(name.parse::<RegexType>().unwrap(), regex_type, "{name}");
+        }
+        for &regex_type in RegexType::VALUES {
+            assert_eq!(
+                regex_type.to_string().parse::<RegexType>().unwrap(),
+                regex_type
+            );
+        }
+        assert!("perl".parse::<RegexType>().is_err());
+    }
+
+    #[test]
+    fn dialect_differences() {
+        let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+
+        for (regex_type, pattern, expected) in [
+            (RegexType::PosixBasic, r".*/abbbc\|x", true),
+            (RegexType::PosixMinimalBasic, r".*/abbbc\|x", false),
+            (RegexType::PosixMinimalBasic, r".*/ab\{3\}c", true),
+            (RegexType::PosixExtended, ".*/ab{3}c", true),
+            (RegexType::Awk, ".*/ab{3}c", false),
+            (RegexType::GnuAwk, ".*/ab{3}c", true),
+            (RegexType::PosixAwk, ".*/ab{3}c", true),
+            (RegexType::PosixExtended, r".*/a[\]b]bbc", false),
+            (RegexType::GnuAwk, r".*/a[\]b]bbc", true),
+        ] {
+            let matcher =
+                RegexMatcher::new(regex_type, &fix_up_regex_slashes(pattern), false).unwrap();
+            assert_eq!(
+                matcher.matches(&abbbc, &mut deps.new_matcher_io()),
+                expected,
+                "-regextype {regex_type} -regex {pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_whole_path() {
+        let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+
+        for &regex_type in RegexType::VALUES {
+            for (pattern, expected) in [
+                (".*/abbbc", true),
+                (".*bbc", true),
+                (".*/ab", false),
+                ("bbc", false),
+                ("abbbc", false),
+            ] {
+                let matcher =
+                    RegexMatcher::new(regex_type, &fix_up_regex_slashes(pattern), false).unwrap();
+                assert_eq!(
+                    matcher.matches(&abbbc, &mut deps.new_matcher_io()),
+                    expected,
+                    "-regextype {regex_type} -regex {pattern}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn alternation() {
+        let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+
+        // The first alternative only matches a prefix of the path, so this
+        // checks that the whole path has to match any alternative
+        for (regex_type, pattern, expected) in [
+            (RegexType::Emacs, r".*/a\|.*/abbbc", true),
+            (RegexType::Emacs, ".*/a|.*/abbbc", false),
+            (RegexType::Grep, r".*/a\|.*/abbbc", true),
+            (RegexType::Grep, ".*/a|.*/abbbc", false),
+            (RegexType::PosixBasic, r".*/a\|.*/abbbc", true),
+            (RegexType::PosixBasic, ".*/a|.*/abbbc", false),
+            (RegexType::PosixExtended, ".*/a|.*/abbbc", true),
+            (RegexType::PosixExtended, r".*/a\|.*/abbbc", false),
+        ] {
+            let matcher =
+                RegexMatcher::new(regex_type, &fix_up_regex_slashes(pattern), false).unwrap();
+            assert_eq!(
+                matcher.matches(&abbbc, &mut deps.new_matcher_io()),
+                expected,
+                "-regextype {regex_type} -regex {pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn back_references() {
+        let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+        let deps = FakeDependencies::new();
+
+        for (regex_type, pattern) in [
+            (RegexType::Emacs, r".*/a\(b\)\1*c"),
+            (RegexType::Grep, r".*/a\(b\)\1*c"),
+            (RegexType::PosixBasic, r".*/a\(b\)\1*c"),
+            (RegexType::PosixExtended, r".*/a(b)\1*c"),
+        ] {
+            let matcher =
+                RegexMatcher::new(regex_type, &fix_up_regex_slashes(pattern), false).unwrap();
+            assert!(
+                matcher.matches(&abbbc, &mut deps.new_matcher_io()),
+                "-regextype {regex_type} -regex {pattern}"
+            );
+        }
+
+        // There's no group for \1 to refer to in these dialects
+        assert!(RegexMatcher::new(RegexType::Emacs, r".*/a(b)\1*c", false).is_err());
+        assert!(RegexMatcher::new(RegexType::PosixExtended, r".*/a\(b\)\1*c", false).is_err());
+    }
 }
@@ -0,0 +1,104 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Telling whether the answer to -ok's question was yes, in the user's
+//! language.
+
+use std::io::{self, BufRead};
+
+use ::regex::Regex;
+
+/// What counts as yes when the locale doesn't say, which is the same as the
+/// C locale's.
+const ENGLISH: &str = "^[yY]";
+
+/// Matches affirmative answers, like rpmatch(3).
+pub struct Affirmative(Regex);
+
+impl Affirmative {
+    /// Uses the locale's `yesexpr`, e.g. `^[+1jJyY]` in German, falling back
+    /// to English if there isn't one we can use.
+    pub fn from_locale() -> Self {
+        locale_yesexpr()
+            .and_then(|expr| Self::new(&expr))
+            .unwrap_or_else(|| Self::new(ENGLISH).unwrap())
+    }
+
+    /// Uses an extended regular expression like the ones a locale's
+    /// `yesexpr` holds, if it's valid.
+    pub fn new(expr: &str) -> Option<Self> {
+        Regex::new(expr).ok().map(Self)
+    }
+
+    pub fn is_yes(&self, answer: &str) -> bool {
+        self.0.is_match(answer)
+    }
+
+    /// Reads a line and checks it, where the end of the input means no.
+    pub fn read(&self, input: &mut impl BufRead) -> io::Result<bool> {
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        Ok(self.is_yes(answer.trim_end_matches(['\n', '\r'])))
+    }
+}
+
+/// Looks up `yesexpr` for the locale in the environment. find doesn't
+/// otherwise use the locale, so it's only switched to for the lookup.
+#[cfg(all(unix, not(target_os = "android")))]
+fn locale_yesexpr() -> Option<String> {
+    use std::ffi::CStr;
+    use std::ptr;
+
+    use uucore::libc::{nl_langinfo, setlocale, LC_MESSAGES, YESEXPR};
+
+    // SAFETY: this happens while the expression is built, before anything
+    // else could be using the locale, and the strings are copied before
+    // they can be changed by the next call
+    unsafe {
+        let old = setlocale(LC_MESSAGES, ptr::null());
+        let old = (!old.is_null()).then(|| CStr::from_ptr(old).to_owned());
+        setlocale(LC_MESSAGES, c"".as_ptr());
+        let expr = nl_langinfo(YESEXPR);
+        let expr = (!expr.is_null()).then(|| CStr::from_ptr(expr).to_string_lossy().into_owned());
+        if let Some(old) = old {
+            setlocale(LC_MESSAGES, old.as_ptr());
+        }
+        expr.filter(|expr| !expr.is_empty())
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "android"))))]
+fn locale_yesexpr() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn affirmative() {
+        let english = Affirmative::new(ENGLISH).unwrap();
+        assert!(english.is_yes("y"));
+        assert!(english.is_yes("Yes please"));
+        assert!(!english.is_yes("n"));
+        assert!(!english.is_yes(""));
+        assert!(!english.is_yes(" y"));
+
+        let german = Affirmative::new("^[+1jJyY]").unwrap();
+        assert!(german.is_yes("ja"));
+        assert!(german.is_yes("1"));
+        assert!(!german.is_yes("nein"));
+
+        assert!(Affirmative::new("^[yY").is_none());
+        // Whatever the locale, there's always something
+        Affirmative::from_locale();
+
+        let mut input = "yes\nno\n".as_bytes();
+        assert!(english.read(&mut input).unwrap());
+        assert!(!english.read(&mut input).unwrap());
+        assert!(!english.read(&mut input).unwrap());
+    }
+}
@@ -3,8 +3,7 @@
 // For the full copyright and license information, please view the LICENSE
 // file that was distributed with this source code.
 
-use super::{Follow, Matcher, MatcherIO, WalkEntry, WalkError};
-use std::error::Error;
+use super::{FindError, Follow, Matcher, MatcherIO, WalkEntry, WalkError};
 use std::path::Path;
 use uucore::fs::FileInformation;
 
@@ -29,8 +28,10 @@ fn get_file_info(path: &Path, follow: bool) -> Result<FileInformation, WalkError
 }
 
 impl SameFileMatcher {
-    pub fn new(path: impl AsRef<Path>, follow: Follow) -> Result<Self, Box<dyn Error>> {
-        let info = get_file_info(path.as_ref(), follow != Follow::Never)?;
+    pub fn new(path: impl AsRef<Path>, follow: Follow) -> Result<Self, FindError> {
+        let path = path.as_ref();
+        let info = get_file_info(path, follow != Follow::Never)
+            .map_err(|e| FindError::io(&path.to_string_lossy(), e.into()))?;
         Ok(Self { info })
     }
 }
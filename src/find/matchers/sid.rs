@@ -0,0 +1,214 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Windows security identifiers, which identify users and groups the way uids
+//! and gids do on Unix.
+
+use std::ffi::OsStr;
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+use windows_sys::Win32::Foundation::{LocalFree, ERROR_SUCCESS};
+use windows_sys::Win32::Security::Authorization::{
+    ConvertStringSidToSidW, GetNamedSecurityInfoW, SE_FILE_OBJECT,
+};
+use windows_sys::Win32::Security::{
+    GetLengthSid, LookupAccountNameW, LookupAccountSidW, GROUP_SECURITY_INFORMATION,
+    OBJECT_SECURITY_INFORMATION, OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID,
+    SID_NAME_USE,
+};
+
+/// A security identifier.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sid {
+    /// The binary SID, in a buffer that's suitably aligned for the API.
+    buf: Vec<u32>,
+}
+
+fn to_wide(s: &OsStr) -> Vec<u16> {
+    s.encode_wide().chain(Some(0)).collect()
+}
+
+impl Sid {
+    /// Copies a SID that Windows gave us.
+    ///
+    /// # Safety
+    ///
+    /// `sid` must point to a valid SID.
+    unsafe fn copy_from(sid: PSID) -> Self {
+        let len = GetLengthSid(sid) as usize;
+        let mut buf = vec![0u32; len.div_ceil(4)];
+        ptr::copy_nonoverlapping(sid as *const u8, buf.as_mut_ptr().cast::<u8>(), len);
+        Self { buf }
+    }
+
+    fn as_psid(&self) -> PSID {
+        // The API takes a mutable pointer, but only reads through it
+        self.buf.as_ptr() as PSID
+    }
+
+    /// Looks up the SID for an account name, like `Administrators` or
+    /// `DOMAIN\user`.
+    pub fn from_account_name(name: &str) -> Option<Self> {
+        let name = to_wide(OsStr::new(name));
+        let mut sid_len = 0;
+        let mut domain_len = 0;
+        let mut sid_use: SID_NAME_USE = 0;
+
+        // The first call just tells us how much space to allocate
+        unsafe {
+            LookupAccountNameW(
+                ptr::null(),
+                name.as_ptr(),
+                ptr::null_mut(),
+                &mut sid_len,
+                ptr::null_mut(),
+                &mut domain_len,
+                &mut sid_use,
+            );
+        }
+        if sid_len == 0 {
+            return None;
+        }
+
+        let mut buf = vec![0u32; (sid_len as usize).div_ceil(4)];
+        let mut domain = vec![0u16; domain_len as usize];
+        let ok = unsafe {
+            LookupAccountNameW(
+                ptr::null(),
+                name.as_ptr(),
+                buf.as_mut_ptr().cast(),
+                &mut sid_len,
+                domain.as_mut_ptr(),
+                &mut domain_len,
+                &mut sid_use,
+            )
+        };
+        (ok != 0).then_some(Self { buf })
+    }
+
+    /// Parses a SID in its string form, like `S-1-5-32-544`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = to_wide(OsStr::new(s));
+        let mut sid: PSID = ptr::null_mut();
+        if unsafe { ConvertStringSidToSidW(s.as_ptr(), &mut sid) } == 0 {
+            return None;
+        }
+
+        unsafe {
+            let result = Self::copy_from(sid);
+            LocalFree(sid);
+            Some(result)
+        }
+    }
+
+    /// Gets the owner of a file.
+    pub fn file_owner(path: &Path) -> io::Result<Self> {
+        Self::of_file(path, OWNER_SECURITY_INFORMATION)
+    }
+
+    /// Gets the primary group of a file.
+    pub fn file_group(path: &Path) -> io::Result<Self> {
+        Self::of_file(path, GROUP_SECURITY_INFORMATION)
+    }
+
+    fn of_file(path: &Path, which: OBJECT_SECURITY_INFORMATION) -> io::Result<Self> {
+        let path = to_wide(path.as_os_str());
+        let mut owner: PSID = ptr::null_mut();
+        let mut group: PSID = ptr::null_mut();
+        let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+
+        let err = unsafe {
+            GetNamedSecurityInfoW(
+                path.as_ptr(),
+                SE_FILE_OBJECT,
+                which,
+                &mut owner,
+                &mut group,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut descriptor,
+            )
+        };
+        if err != ERROR_SUCCESS {
+            return Err(io::Error::from_raw_os_error(err as i32));
+        }
+
+        // The SIDs point into the descriptor, so copy before freeing it
+        let sid = if which == OWNER_SECURITY_INFORMATION {
+            owner
+        } else {
+            group
+        };
+        let result = if sid.is_null() {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the file has no owner information",
+            ))
+        } else {
+            Ok(unsafe { Self::copy_from(sid) })
+        };
+        unsafe {
+            LocalFree(descriptor);
+        }
+        result
+    }
+
+    /// Checks whether this SID belongs to an account Windows knows about, for
+    /// -nouser and -nogroup.
+    pub fn is_known(&self) -> bool {
+        let mut name_len = 0;
+        let mut domain_len = 0;
+        let mut sid_use: SID_NAME_USE = 0;
+
+        // Without buffers this always fails, but if the account exists it
+        // tells us how long the name is
+        unsafe {
+            LookupAccountSidW(
+                ptr::null(),
+                self.as_psid(),
+                ptr::null_mut(),
+                &mut name_len,
+                ptr::null_mut(),
+                &mut domain_len,
+                &mut sid_use,
+            );
+        }
+        name_len != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::Builder;
+
+    #[test]
+    fn parse_sid() {
+        // The well-known SID for the local system account
+        let system = Sid::parse("S-1-5-18").expect("S-1-5-18 should parse");
+        assert!(system.is_known());
+        assert_eq!(Sid::from_account_name("SYSTEM"), Some(system));
+
+        assert_eq!(Sid::parse("not a SID"), None);
+    }
+
+    #[test]
+    fn file_owner() {
+        let temp_dir = Builder::new().prefix("sid").tempdir().unwrap();
+        let path = temp_dir.path().join("file");
+        File::create(&path).unwrap();
+
+        let owner = Sid::file_owner(&path).unwrap();
+        assert!(owner.is_known());
+        let group = Sid::file_group(&path).unwrap();
+        assert!(group.is_known());
+
+        assert!(Sid::file_owner(&temp_dir.path().join("missing")).is_err());
+    }
+}
@@ -5,7 +5,7 @@
 // https://opensource.org/licenses/MIT.
 
 use std::error::Error;
-use std::io::{stderr, Write};
+use std::io;
 use std::str::FromStr;
 
 use super::{ComparableValue, Matcher, MatcherIO, WalkEntry};
@@ -62,6 +62,50 @@ fn byte_size_to_unit_size(unit: Unit, byte_size: u64) -> u64 {
     ((byte_size - 1) >> bits_to_shift) + 1
 }
 
+/// Gets the space actually allocated to a file, in bytes. This is less than
+/// its size if it's sparse or compressed.
+pub(crate) fn allocated_size(file_info: &WalkEntry) -> io::Result<u64> {
+    let meta = file_info.metadata()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // The block count is always in 512-byte units
+        Ok(meta.blocks() * 512)
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::Storage::FileSystem::{GetCompressedFileSizeW, INVALID_FILE_SIZE};
+
+        // Directories don't have a compressed size
+        if meta.is_dir() {
+            return Ok(meta.len());
+        }
+
+        let path: Vec<u16> = file_info
+            .path()
+            .as_os_str()
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        let mut high = 0;
+        let low = unsafe { GetCompressedFileSizeW(path.as_ptr(), &mut high) };
+        // INVALID_FILE_SIZE is also a valid low half, so check the error code
+        if low == INVALID_FILE_SIZE {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(0) {
+                return Err(err);
+            }
+        }
+        Ok((u64::from(high) << 32) | u64::from(low))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    Ok(meta.len())
+}
+
 /// Matcher that checks whether a file's size if {less than | equal to | more than}
 /// N units in size.
 pub struct SizeMatcher {
@@ -82,14 +126,61 @@ impl SizeMatcher {
 }
 
 impl Matcher for SizeMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match file_info.metadata() {
             Ok(metadata) => self
                 .value_to_match
                 .matches(byte_size_to_unit_size(self.unit, metadata.len())),
             Err(e) => {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
+                    "Error getting file size for {}: {}",
+                    file_info.path().to_string_lossy(),
+                    e
+                )
+                .unwrap();
+                false
+            }
+        }
+    }
+}
+
+/// Matcher for files that take up less space on disk than their size, i.e.
+/// whose %S sparseness is below a threshold (1.0 unless given).
+pub struct SparseMatcher {
+    threshold: f64,
+}
+
+impl SparseMatcher {
+    pub fn new(threshold: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let threshold = match threshold {
+            None => 1.0,
+            Some(arg) => match arg.parse::<f64>() {
+                Ok(value) if value.is_finite() && value > 0.0 => value,
+                _ => {
+                    return Err(From::from(format!(
+                        "Invalid argument `{arg}' to -sparse: expected a positive ratio"
+                    )));
+                }
+            },
+        };
+        Ok(Self { threshold })
+    }
+}
+
+impl Matcher for SparseMatcher {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let sizes = file_info
+            .metadata()
+            .map_err(io::Error::from)
+            .and_then(|meta| Ok((meta.len(), allocated_size(file_info)?)));
+        match sizes {
+            // Empty files have nothing to leave out
+            Ok((0, _)) => false,
+            Ok((len, allocated)) => (allocated as f64) < self.threshold * len as f64,
+            Err(e) => {
+                writeln!(
+                    matcher_io.error_output(),
                     "Error getting file size for {}: {}",
                     file_info.path().to_string_lossy(),
                     e
@@ -106,6 +197,8 @@ mod tests {
     use super::*;
     use crate::find::matchers::tests::get_dir_entry_for;
     use crate::find::tests::FakeDependencies;
+    use std::fs::File;
+    use tempfile::Builder;
 
     #[test]
     fn test_byte_size_to_unit_size() {
@@ -170,4 +263,41 @@ mod tests {
             "512-byte file should match size of 1 block"
         );
     }
+
+    #[test]
+    fn sparse_matcher_bad_ratio() {
+        for arg in ["0", "-1", "inf", "NaN"] {
+            let Err(e) = SparseMatcher::new(Some(arg)) else {
+                panic!("-sparse {arg} should fail");
+            };
+            assert!(e.to_string().contains("-sparse"), "bad description: {e}");
+        }
+    }
+
+    #[test]
+    fn sparse_matcher() {
+        let temp_dir = Builder::new().prefix("sparse_matcher").tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy();
+        File::create(temp_dir.path().join("empty")).unwrap();
+        File::create(temp_dir.path().join("sparse"))
+            .unwrap()
+            .set_len(1 << 24)
+            .unwrap();
+
+        let empty = get_dir_entry_for(&root, "empty");
+        let sparse = get_dir_entry_for(&root, "sparse");
+        let dense = get_dir_entry_for("./test_data/size", "512bytes");
+        let deps = FakeDependencies::new();
+
+        let matcher = SparseMatcher::new(None).unwrap();
+        assert!(!matcher.matches(&empty, &mut deps.new_matcher_io()));
+        assert!(!matcher.matches(&dense, &mut deps.new_matcher_io()));
+        // Not every filesystem makes files sparse when they're extended, but
+        // the common unix ones do
+        let matched = matcher.matches(&sparse, &mut deps.new_matcher_io());
+        assert!(matched || cfg!(not(unix)));
+
+        let matcher = SparseMatcher::new(Some("0.01")).unwrap();
+        assert!(!matcher.matches(&dense, &mut deps.new_matcher_io()));
+    }
 }
@@ -0,0 +1,472 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! -tar FILE, which writes matched files into a tar archive as it goes.
+
+use std::cell::RefCell;
+use std::fs::{File, Metadata};
+use std::io::{self, stderr, BufWriter, Read, Write};
+
+use super::{Matcher, MatcherIO, WalkEntry};
+
+const BLOCK_SIZE: usize = 512;
+/// Archives are padded to a multiple of this, like tar's default blocking
+/// factor of 20.
+const RECORD_SIZE: u64 = 20 * BLOCK_SIZE as u64;
+
+/// What goes in the header for one file in the archive.
+#[derive(Debug, Default)]
+struct Member {
+    /// The path, with `/` separators and no leading `/`.
+    path: Vec<u8>,
+    mode: u32,
+    uid: u64,
+    gid: u64,
+    size: u64,
+    mtime: i64,
+    uname: String,
+    gname: String,
+}
+
+impl Member {
+    fn new(path: &[u8], meta: &Metadata) -> Self {
+        // Absolute paths would be extracted over the top of whatever is there,
+        // so tar strips the leading slash too
+        let start = path.iter().position(|&b| b != b'/').unwrap_or(path.len());
+        let mut member = Self {
+            path: path[start..].to_vec(),
+            size: meta.len(),
+            ..Self::default()
+        };
+
+        #[cfg(unix)]
+        {
+            use nix::unistd::{Gid, Group, Uid, User};
+            use std::os::unix::fs::MetadataExt;
+
+            member.mode = meta.mode() & 0o7777;
+            member.uid = meta.uid().into();
+            member.gid = meta.gid().into();
+            member.mtime = meta.mtime();
+            if let Ok(Some(user)) = User::from_uid(Uid::from_raw(meta.uid())) {
+                member.uname = user.name;
+            }
+            if let Ok(Some(group)) = Group::from_gid(Gid::from_raw(meta.gid())) {
+                member.gname = group.name;
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            member.mode = if meta.permissions().readonly() {
+                0o444
+            } else {
+                0o644
+            };
+            member.mtime = meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_secs() as i64);
+        }
+        member
+    }
+
+    /// Encodes the header blocks for this member. Anything that doesn't fit
+    /// in a ustar header goes in a PAX extended header before it.
+    fn headers(&self) -> Vec<u8> {
+        let mut extended = vec![];
+        let mut header = [0u8; BLOCK_SIZE];
+
+        match split_path(&self.path) {
+            Some((prefix, name)) => {
+                header[345..345 + prefix.len()].copy_from_slice(prefix);
+                header[..name.len()].copy_from_slice(name);
+            }
+            None => {
+                pax_record(&mut extended, "path", &self.path);
+                let name = &self.path[self.path.len().saturating_sub(100)..];
+                header[..name.len()].copy_from_slice(name);
+            }
+        }
+
+        octal(&mut header[100..108], self.mode.into());
+        for (field, key, value) in [
+            (108..116, "uid", self.uid),
+            (116..124, "gid", self.gid),
+            (124..136, "size", self.size),
+        ] {
+            if !octal(&mut header[field], value) {
+                pax_record(&mut extended, key, value.to_string().as_bytes());
+            }
+        }
+        let mtime = u64::try_from(self.mtime).ok();
+        if !mtime.is_some_and(|mtime| octal(&mut header[136..148], mtime)) {
+            pax_record(&mut extended, "mtime", self.mtime.to_string().as_bytes());
+        }
+        for (field, key, value) in [
+            (265..297, "uname", &self.uname),
+            (297..329, "gname", &self.gname),
+        ] {
+            if value.len() < field.len() {
+                header[field.start..field.start + value.len()].copy_from_slice(value.as_bytes());
+            } else {
+                pax_record(&mut extended, key, value.as_bytes());
+            }
+        }
+        header[156] = b'0';
+        header[257..265].copy_from_slice(b"ustar\x0000");
+
+        let mut blocks = vec![];
+        if !extended.is_empty() {
+            let mut pax = [0u8; BLOCK_SIZE];
+            let name = b"././@PaxHeader";
+            pax[..name.len()].copy_from_slice(name);
+            octal(&mut pax[100..108], 0o644);
+            octal(&mut pax[124..136], extended.len() as u64);
+            pax[156] = b'x';
+            pax[257..265].copy_from_slice(b"ustar\x0000");
+            checksum(&mut pax);
+            blocks.extend_from_slice(&pax);
+            blocks.extend_from_slice(&extended);
+            blocks.resize(blocks.len().next_multiple_of(BLOCK_SIZE), 0);
+        }
+        checksum(&mut header);
+        blocks.extend_from_slice(&header);
+        blocks
+    }
+}
+
+/// Splits a path into the ustar prefix and name fields, if it fits.
+fn split_path(path: &[u8]) -> Option<(&[u8], &[u8])> {
+    if path.len() <= 100 {
+        return Some((&[], path));
+    }
+    // The prefix gets the longest run of whole directories that fits
+    path.iter()
+        .enumerate()
+        .filter(|&(i, &b)| b == b'/' && i <= 155)
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .rfind(|(_, name)| !name.is_empty() && name.len() <= 100)
+}
+
+/// Writes `value` into a NUL-terminated octal field, returning whether it fit.
+fn octal(field: &mut [u8], value: u64) -> bool {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    if digits.len() >= field.len() {
+        return false;
+    }
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    true
+}
+
+/// Fills in the checksum of a header, which is the sum of its bytes with the
+/// checksum field itself taken to be spaces.
+fn checksum(header: &mut [u8; BLOCK_SIZE]) {
+    header[148..156].fill(b' ');
+    let sum: u64 = header.iter().map(|&b| u64::from(b)).sum();
+    let digits = format!("{sum:06o}\0 ");
+    header[148..156].copy_from_slice(digits.as_bytes());
+}
+
+/// Appends a `LENGTH KEY=VALUE\n` record, where the length counts itself.
+fn pax_record(records: &mut Vec<u8>, key: &str, value: &[u8]) {
+    let rest = key.len() + value.len() + 3;
+    let mut len = rest + 1;
+    while len != rest + len.to_string().len() {
+        len = rest + len.to_string().len();
+    }
+    records.extend_from_slice(format!("{len} {key}=").as_bytes());
+    records.extend_from_slice(value);
+    records.push(b'\n');
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &std::path::Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &std::path::Path) -> Vec<u8> {
+    path.to_string_lossy().replace('\\', "/").into_bytes()
+}
+
+/// The archive being written, and how much of it there is so far.
+struct Archive {
+    /// Where the archive goes, once it's been opened.
+    out: Option<BufWriter<Box<dyn Write>>>,
+    /// The archive's name, where - means stdout.
+    name: String,
+    written: u64,
+    /// Whether writing to the archive has failed, after which there's no
+    /// point trying to add anything else.
+    failed: bool,
+    /// The archive file itself, so that it isn't added to itself.
+    #[cfg(unix)]
+    identity: Option<(u64, u64)>,
+}
+
+impl Archive {
+    /// Creates the archive, if it hasn't been already. This waits until
+    /// there's something to write, so that just building the expression
+    /// doesn't create or truncate anything.
+    fn open(&mut self) -> io::Result<()> {
+        if self.out.is_some() {
+            return Ok(());
+        }
+        // As for tar -f, - means stdout
+        let out: Box<dyn Write> = if self.name == "-" {
+            Box::new(io::stdout())
+        } else {
+            let file = File::create(&self.name)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let meta = file.metadata()?;
+                self.identity = Some((meta.dev(), meta.ino()));
+            }
+            Box::new(file)
+        };
+        self.out = Some(BufWriter::new(out));
+        Ok(())
+    }
+
+    /// The archive, which [Self::open] must already have opened.
+    fn out(&mut self) -> &mut BufWriter<Box<dyn Write>> {
+        self.out.as_mut().expect("the archive should be open")
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.out().write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Writes a regular file into the archive.
+    fn add(&mut self, file_info: &WalkEntry) -> Result<(), String> {
+        let path = file_info.path();
+        let mut file = File::open(path).map_err(|e| e.to_string())?;
+        let meta = file.metadata().map_err(|e| e.to_string())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            if self.identity == Some((meta.dev(), meta.ino())) {
+                return Err("file is the archive; not dumped".to_owned());
+            }
+        }
+
+        let member = Member::new(&path_bytes(path), &meta);
+        let mut written = member.size;
+        let result = self.write(&member.headers()).and_then(|()| {
+            // Exactly as much as the header says has to be written, even if
+            // the file changed size in the meantime
+            written = io::copy(&mut (&mut file).take(member.size), self.out())?;
+            self.written += written;
+            let padding = member.size.next_multiple_of(BLOCK_SIZE as u64) - written;
+            self.write(&vec![0; padding as usize])
+        });
+        if let Err(e) = result {
+            self.failed = true;
+            return Err(format!("error writing to {}: {e}", self.name));
+        }
+        if written < member.size {
+            return Err("file shrank while it was being archived".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Writes the end of the archive: two empty blocks, and then enough
+    /// padding to make up a whole record.
+    fn finish(&mut self) -> io::Result<()> {
+        // Even with nothing in it, there's still an (empty) archive
+        self.open()?;
+        let end = (self.written + 2 * BLOCK_SIZE as u64).next_multiple_of(RECORD_SIZE);
+        self.write(&vec![0; (end - self.written) as usize])?;
+        self.out().flush()
+    }
+}
+
+/// This matcher adds regular files to a tar archive (ustar, with PAX
+/// extensions for long names and large files). It's true if the file was
+/// added. The ownership, permissions and modification time of each file are
+/// kept, and the archive is finished off once the search is done.
+pub struct Tar {
+    archive: RefCell<Archive>,
+    /// Whether this is a dry run, in which case nothing is written at all.
+    dry_run: bool,
+}
+
+impl Tar {
+    /// Writes the archive to the file `name`, or to stdout if it's -. The
+    /// file isn't created until the first match, or the end of the search.
+    pub fn new(name: &str, dry_run: bool) -> Self {
+        Self {
+            archive: RefCell::new(Archive {
+                out: None,
+                name: name.to_owned(),
+                written: 0,
+                failed: false,
+                #[cfg(unix)]
+                identity: None,
+            }),
+            dry_run,
+        }
+    }
+}
+
+impl Matcher for Tar {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let path = file_info.path();
+        if !file_info.file_type().is_file() || self.archive.borrow().failed {
+            return false;
+        }
+
+        if matcher_io.is_dry_run() {
+            let mut out = matcher_io.deps.get_output().borrow_mut();
+            writeln!(out, "would archive: {}", path.to_string_lossy()).unwrap();
+            drop(out);
+            matcher_io.log_action("tar", path, "dry-run");
+            return true;
+        }
+
+        let mut archive = self.archive.borrow_mut();
+        if let Err(e) = archive.open() {
+            archive.failed = true;
+            matcher_io.log_action("tar", path, &format!("failed: {e}"));
+            matcher_io.set_exit_code(1);
+            writeln!(matcher_io.error_output(), "find: '{}': {e}", archive.name).unwrap();
+            return false;
+        }
+        match archive.add(file_info) {
+            Ok(()) => {
+                matcher_io.log_action("tar", path, "ok");
+                true
+            }
+            Err(e) => {
+                matcher_io.log_action("tar", path, &format!("failed: {e}"));
+                matcher_io.set_exit_code(1);
+                writeln!(
+                    matcher_io.error_output(),
+                    "find: {}: {e}",
+                    path.to_string_lossy()
+                )
+                .unwrap();
+                false
+            }
+        }
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+
+    fn finished(&self) {
+        let mut archive = self.archive.borrow_mut();
+        if self.dry_run {
+            return;
+        }
+        if archive.failed {
+            uucore::error::set_exit_code(1);
+            return;
+        }
+        if let Err(e) = archive.finish() {
+            writeln!(
+                &mut stderr(),
+                "find: error writing to {}: {e}",
+                archive.name
+            )
+            .unwrap();
+            uucore::error::set_exit_code(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn octal_fields() {
+        let mut field = [0u8; 8];
+        assert!(octal(&mut field, 0o644));
+        assert_eq!(&field, b"0000644\0");
+        assert!(octal(&mut field, 0o7777777));
+        assert!(!octal(&mut field, 0o10000000));
+    }
+
+    #[test]
+    fn split_paths() {
+        assert_eq!(split_path(b"a/b"), Some((&b""[..], &b"a/b"[..])));
+
+        let dirs = [b'd'; 120];
+        let long = [&dirs[..], b"/name"].concat();
+        assert_eq!(split_path(&long), Some((&dirs[..], &b"name"[..])));
+
+        // No room in the name for what comes after the last slash
+        let long = [b"d/", &[b'n'; 101][..]].concat();
+        assert_eq!(split_path(&long), None);
+    }
+
+    #[test]
+    fn pax_records() {
+        let mut records = vec![];
+        pax_record(&mut records, "path", b"a");
+        pax_record(&mut records, "path", &[b'a'; 93]);
+        assert_eq!(records[..10], *b"9 path=a\n1");
+        assert_eq!(records.len(), 9 + 103);
+    }
+
+    #[test]
+    fn headers() {
+        let member = Member {
+            path: b"a/b".to_vec(),
+            mode: 0o644,
+            uid: 1000,
+            gid: 100,
+            size: 5,
+            mtime: 1_700_000_000,
+            uname: "user".to_owned(),
+            gname: "group".to_owned(),
+        };
+        let header = member.headers();
+        assert_eq!(header.len(), BLOCK_SIZE);
+        assert_eq!(&header[..4], b"a/b\0");
+        assert_eq!(&header[100..108], b"0000644\0");
+        assert_eq!(&header[124..136], b"00000000005\0");
+        assert_eq!(&header[136..148], b"14524770400\0");
+        assert_eq!(header[156], b'0');
+        assert_eq!(&header[257..265], b"ustar\x0000");
+        assert_eq!(&header[265..270], b"user\0");
+
+        let sum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    b.into()
+                }
+            })
+            .sum();
+        assert_eq!(header[148..156], *format!("{sum:06o}\0 ").as_bytes());
+
+        // What doesn't fit goes in an extended header first
+        let member = Member {
+            path: vec![b'n'; 101],
+            mtime: -1,
+            ..member
+        };
+        let headers = member.headers();
+        assert_eq!(headers.len(), 3 * BLOCK_SIZE);
+        assert_eq!(headers[156], b'x');
+        let records = b"111 path=";
+        assert_eq!(&headers[BLOCK_SIZE..BLOCK_SIZE + records.len()], records);
+        assert!(headers[BLOCK_SIZE..2 * BLOCK_SIZE]
+            .windows(12)
+            .any(|w| w == b"12 mtime=-1\n"));
+        assert_eq!(headers[2 * BLOCK_SIZE + 156], b'0');
+    }
+}
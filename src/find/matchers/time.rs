@@ -5,8 +5,7 @@
 // https://opensource.org/licenses/MIT.
 
 use std::error::Error;
-use std::fs::{self, Metadata};
-use std::io::{stderr, Write};
+use std::fs::Metadata;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use chrono::{DateTime, Local, Timelike};
@@ -14,7 +13,7 @@ use chrono::{DateTime, Local, Timelike};
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 
-use super::{ComparableValue, Follow, Matcher, MatcherIO, WalkEntry};
+use super::{ComparableValue, FindError, Follow, Matcher, MatcherIO, WalkEntry};
 
 const SECONDS_PER_DAY: i64 = 60 * 60 * 24;
 
@@ -34,16 +33,39 @@ fn get_time(matcher_io: &mut MatcherIO, today_start: bool) -> SystemTime {
     }
 }
 
+/// Gets the metadata of the reference file for -newer and friends. Like any
+/// other path on the command line, it's only followed for -H and -L, and a
+/// broken symlink stands for itself.
+fn reference_metadata(path: &str, follow: Follow) -> Result<Metadata, FindError> {
+    follow
+        .root_metadata(path)
+        .map_err(|e| FindError::io(path, e.into()))
+}
+
+/// Gets one of the times of the reference file for -newer and friends.
+fn reference_time(
+    path: &str,
+    follow: Follow,
+    option: NewerOptionType,
+) -> Result<SystemTime, FindError> {
+    option
+        .get_file_time(&reference_metadata(path, follow)?)
+        .map_err(|e| FindError::io(path, e))
+}
+
 /// This matcher checks whether a file is newer than the file the matcher is initialized with.
 pub struct NewerMatcher {
     given_modification_time: SystemTime,
 }
 
 impl NewerMatcher {
-    pub fn new(path_to_file: &str, follow: Follow) -> Result<Self, Box<dyn Error>> {
-        let metadata = follow.root_metadata(path_to_file)?;
+    pub fn new(path_to_file: &str, follow: Follow) -> Result<Self, FindError> {
         Ok(Self {
-            given_modification_time: metadata.modified()?,
+            given_modification_time: reference_time(
+                path_to_file,
+                follow,
+                NewerOptionType::Modified,
+            )?,
         })
     }
 
@@ -63,11 +85,11 @@ impl NewerMatcher {
 }
 
 impl Matcher for NewerMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match self.matches_impl(file_info) {
             Err(e) => {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
                     "Error getting modification time for {}: {}",
                     file_info.path().to_string_lossy(),
                     e
@@ -115,12 +137,11 @@ impl NewerOptionType {
     }
 }
 
-/// This matcher checks whether the file is newer than the file time of any combination of
-/// two comparison types from the target file's `NewerOptionType`.
+/// This matcher checks whether the X time of a file is newer than the Y time of
+/// the reference file, for -newerXY (and -anewer and -cnewer).
 pub struct NewerOptionMatcher {
     x_option: NewerOptionType,
-    y_option: NewerOptionType,
-    given_modification_time: SystemTime,
+    given_time: SystemTime,
 }
 
 impl NewerOptionMatcher {
@@ -128,41 +149,31 @@ impl NewerOptionMatcher {
         x_option: String,
         y_option: String,
         path_to_file: &str,
-    ) -> Result<Self, Box<dyn Error>> {
-        let metadata = fs::metadata(path_to_file)?;
+        follow: Follow,
+    ) -> Result<Self, FindError> {
         let x_option = NewerOptionType::from_str(x_option.as_str());
         let y_option = NewerOptionType::from_str(y_option.as_str());
         Ok(Self {
             x_option,
-            y_option,
-            given_modification_time: metadata.modified()?,
+            given_time: reference_time(path_to_file, follow, y_option)?,
         })
     }
 
     fn matches_impl(&self, file_info: &WalkEntry) -> Result<bool, Box<dyn Error>> {
         let x_option_time = self.x_option.get_file_time(file_info.metadata()?)?;
-        let y_option_time = self.y_option.get_file_time(file_info.metadata()?)?;
 
-        Ok(self
-            .given_modification_time
-            .duration_since(x_option_time)
-            .is_err()
-            && self
-                .given_modification_time
-                .duration_since(y_option_time)
-                .is_err())
+        Ok(self.given_time.duration_since(x_option_time).is_err())
     }
 }
 
 impl Matcher for NewerOptionMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match self.matches_impl(file_info) {
             Err(e) => {
                 writeln!(
-                    &mut stderr(),
-                    "Error getting {:?} and {:?} time for {}: {}",
+                    matcher_io.error_output(),
+                    "Error getting {:?} time for {}: {}",
                     self.x_option,
-                    self.y_option,
                     file_info.path().to_string_lossy(),
                     e
                 )
@@ -206,11 +217,11 @@ impl NewerTimeMatcher {
 }
 
 impl Matcher for NewerTimeMatcher {
-    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
         match self.matches_impl(file_info) {
             Err(e) => {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
                     "Error getting {:?} time for {}: {}",
                     self.newer_time_type,
                     file_info.path().to_string_lossy(),
@@ -284,7 +295,7 @@ impl Matcher for FileTimeMatcher {
         match self.matches_impl(file_info, start_time) {
             Err(e) => {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
                     "Error getting {:?} time for {}: {}",
                     self.file_time_type,
                     file_info.path().to_string_lossy(),
@@ -357,7 +368,7 @@ impl Matcher for FileAgeRangeMatcher {
         match self.matches_impl(file_info, start_time) {
             Err(e) => {
                 writeln!(
-                    &mut stderr(),
+                    matcher_io.error_output(),
                     "Error getting {:?} time for {}: {}",
                     self.file_time_type,
                     file_info.path().to_string_lossy(),
@@ -405,7 +416,7 @@ mod tests {
     use chrono::NaiveTime;
     use std::fs;
     use std::fs::{File, OpenOptions};
-    use std::io::Read;
+    use std::io::{Read, Write};
     use std::thread;
     use std::time::Duration;
     use tempfile::Builder;
@@ -450,6 +461,96 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn newer_matcher_reference_types() {
+        use filetime::{set_file_mtime, set_symlink_file_times, FileTime};
+        use nix::sys::stat::Mode;
+        use nix::unistd::mkfifo;
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = Builder::new().prefix("newer").tempdir().unwrap();
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+        let path = |name: &str| temp_dir.path().join(name).to_string_lossy().into_owned();
+        let now = SystemTime::now();
+        let days_ago =
+            |days: u64| FileTime::from_system_time(now - Duration::from_secs(days * 86400));
+
+        File::create(path("target")).unwrap();
+        set_file_mtime(path("target"), days_ago(3)).unwrap();
+        File::create(path("probe")).unwrap();
+        set_file_mtime(path("probe"), days_ago(2)).unwrap();
+        symlink("target", path("link")).unwrap();
+        set_symlink_file_times(path("link"), days_ago(1), days_ago(1)).unwrap();
+        symlink("missing", path("dangling")).unwrap();
+        mkfifo(path("fifo").as_str(), Mode::from_bits_truncate(0o644)).unwrap();
+        fs::create_dir(path("dir")).unwrap();
+
+        let probe = get_dir_entry_for(&temp_dir_path, "probe");
+        let deps = FakeDependencies::new();
+        let newer = |reference: &str, follow| {
+            NewerMatcher::new(&path(reference), follow)
+                .unwrap()
+                .matches(&probe, &mut deps.new_matcher_io())
+        };
+
+        // -P compares against the link itself, -H and -L against its target
+        assert!(!newer("link", Follow::Never));
+        assert!(newer("link", Follow::Roots));
+        assert!(newer("link", Follow::Always));
+
+        // Any kind of file will do as a reference, and a broken link stands
+        // for itself whatever the mode
+        for follow in [Follow::Never, Follow::Roots, Follow::Always] {
+            assert!(!newer("dangling", follow));
+            assert!(!newer("fifo", follow));
+            assert!(!newer("dir", follow));
+        }
+
+        let missing = path("missing");
+        let err = NewerMatcher::new(&missing, Follow::Never).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("'{missing}': No such file or directory")
+        );
+    }
+
+    #[test]
+    fn newer_option_matcher_compares_reference_y_time() {
+        use filetime::{set_file_times, FileTime};
+
+        let temp_dir = Builder::new().prefix("newer").tempdir().unwrap();
+        let temp_dir_path = temp_dir.path().to_string_lossy();
+        let path = |name: &str| temp_dir.path().join(name).to_string_lossy().into_owned();
+        let now = SystemTime::now();
+        let days_ago =
+            |days: u64| FileTime::from_system_time(now - Duration::from_secs(days * 86400));
+
+        // Accessed long ago, but modified recently
+        File::create(path("reference")).unwrap();
+        set_file_times(path("reference"), days_ago(3), days_ago(1)).unwrap();
+        File::create(path("probe")).unwrap();
+        set_file_times(path("probe"), days_ago(2), days_ago(2)).unwrap();
+
+        let probe = get_dir_entry_for(&temp_dir_path, "probe");
+        let deps = FakeDependencies::new();
+        let newer = |x: &str, y: &str| {
+            NewerOptionMatcher::new(
+                x.to_owned(),
+                y.to_owned(),
+                &path("reference"),
+                Follow::Never,
+            )
+            .unwrap()
+            .matches(&probe, &mut deps.new_matcher_io())
+        };
+
+        assert!(newer("m", "a"));
+        assert!(newer("a", "a"));
+        assert!(!newer("m", "m"));
+        assert!(!newer("a", "m"));
+    }
+
     #[test]
     fn file_time_matcher() {
         // this file should already exist
@@ -744,6 +845,7 @@ mod tests {
                     x_option.to_string(),
                     y_option.to_string(),
                     &old_file.path().to_string_lossy(),
+                    Follow::Never,
                 );
 
                 assert!(
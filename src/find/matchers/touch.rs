@@ -0,0 +1,149 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! -touch [-a|-m], which updates the timestamps of matches.
+
+use filetime::{set_file_times, set_symlink_file_times, FileTime};
+
+use super::{Matcher, MatcherIO, WalkEntry};
+
+/// Which timestamps -touch updates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchTimes {
+    Both,
+    /// Just the access time (-a).
+    Access,
+    /// Just the modification time (-m).
+    Modification,
+}
+
+impl TouchTimes {
+    fn access(self) -> bool {
+        self != Self::Modification
+    }
+
+    fn modification(self) -> bool {
+        self != Self::Access
+    }
+}
+
+/// This matcher sets the access and/or modification times of each file to
+/// when find started, so every file touched by one run gets the same time.
+/// Like `touch -h`, symbolic links that aren't followed are updated
+/// themselves. It's true if the times were updated.
+pub struct TouchMatcher {
+    times: TouchTimes,
+}
+
+impl TouchMatcher {
+    pub fn new(times: TouchTimes) -> Self {
+        Self { times }
+    }
+}
+
+impl Matcher for TouchMatcher {
+    fn matches(&self, file_info: &WalkEntry, matcher_io: &mut MatcherIO) -> bool {
+        let path = file_info.path();
+        let path_str = path.to_string_lossy();
+
+        if matcher_io.is_dry_run() {
+            let mut out = matcher_io.deps.get_output().borrow_mut();
+            writeln!(out, "would touch: {path_str}").unwrap();
+            drop(out);
+            matcher_io.log_action("touch", path, "dry-run");
+            return true;
+        }
+
+        let now = FileTime::from_system_time(matcher_io.now());
+        let result = file_info.metadata().map_err(Into::into).and_then(|meta| {
+            // The one that isn't being updated has to be set to what it was
+            let atime = if self.times.access() {
+                now
+            } else {
+                FileTime::from_last_access_time(meta)
+            };
+            let mtime = if self.times.modification() {
+                now
+            } else {
+                FileTime::from_last_modification_time(meta)
+            };
+            if file_info.file_type().is_symlink() {
+                set_symlink_file_times(path, atime, mtime)
+            } else {
+                set_file_times(path, atime, mtime)
+            }
+        });
+
+        match result {
+            Ok(()) => {
+                matcher_io.log_action("touch", path, "ok");
+                true
+            }
+            Err(e) => {
+                matcher_io.log_action("touch", path, &format!("failed: {e}"));
+                matcher_io.set_exit_code(1);
+                writeln!(matcher_io.error_output(), "Failed to touch {path_str}: {e}").unwrap();
+                false
+            }
+        }
+    }
+
+    fn has_side_effects(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+    use tempfile::Builder;
+
+    use super::*;
+    use crate::find::matchers::tests::get_dir_entry_for;
+    use crate::find::tests::FakeDependencies;
+
+    #[test]
+    fn touch_matcher() {
+        let temp_dir = Builder::new().prefix("touch_matcher").tempdir().unwrap();
+        let path = temp_dir.path().join("file");
+        File::create(&path).unwrap();
+        let old = FileTime::from_unix_time(1_000_000_000, 0);
+        set_file_times(&path, old, old).unwrap();
+        let root = temp_dir.path().to_string_lossy();
+        let times = || {
+            let meta = fs::metadata(&path).unwrap();
+            (
+                FileTime::from_last_access_time(&meta),
+                FileTime::from_last_modification_time(&meta),
+            )
+        };
+
+        let mut deps = FakeDependencies::new();
+        deps.set_time(SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000));
+        let now = FileTime::from_unix_time(1_500_000_000, 0);
+
+        let mut matcher_io = deps.new_matcher_io();
+        matcher_io.set_dry_run(true);
+        let matcher = TouchMatcher::new(TouchTimes::Modification);
+        assert!(matcher.matches(&get_dir_entry_for(&root, "file"), &mut matcher_io));
+        assert_eq!(times(), (old, old));
+
+        let matcher_io = &mut deps.new_matcher_io();
+        assert!(matcher.matches(&get_dir_entry_for(&root, "file"), matcher_io));
+        assert_eq!(times(), (old, now));
+
+        set_file_times(&path, old, old).unwrap();
+        let matcher = TouchMatcher::new(TouchTimes::Access);
+        assert!(matcher.matches(&get_dir_entry_for(&root, "file"), matcher_io));
+        assert_eq!(times(), (now, old));
+
+        let matcher = TouchMatcher::new(TouchTimes::Both);
+        fs::remove_file(&path).unwrap();
+        let matcher_io = &mut deps.new_matcher_io();
+        assert!(!matcher.matches(&get_dir_entry_for(&root, "file"), matcher_io));
+        assert_eq!(matcher_io.exit_code(), 1);
+    }
+}
@@ -6,7 +6,7 @@
 
 use std::error::Error;
 
-use super::{FileType, Follow, Matcher, MatcherIO, WalkEntry};
+use super::{FileType, Matcher, MatcherIO, Needs, WalkEntry};
 
 /// This matcher checks the type of the file.
 pub struct TypeMatcher {
@@ -48,9 +48,15 @@ impl Matcher for TypeMatcher {
     fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
         file_info.file_type() == self.file_type
     }
+
+    fn needs(&self) -> Needs {
+        Needs::Type
+    }
 }
 
-/// Like [TypeMatcher], but toggles whether symlinks are followed.
+/// Like [TypeMatcher], but toggles whether symlinks are followed: links are
+/// matched by their target's type when find isn't following them, and as
+/// links when it is. Broken links are links either way.
 pub struct XtypeMatcher {
     file_type: FileType,
 }
@@ -64,21 +70,19 @@ impl XtypeMatcher {
 
 impl Matcher for XtypeMatcher {
     fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        let follow = if file_info.follow() {
-            Follow::Never
+        let meta = if file_info.follow() {
+            file_info.link_metadata()
         } else {
-            Follow::Always
+            file_info.target_metadata()
         };
 
-        let file_type = follow
-            .metadata(file_info)
-            .map(|m| m.file_type())
-            .map(FileType::from);
-
-        match file_type {
-            Ok(file_type) if file_type == self.file_type => true,
-            // Since GNU find 4.10, ELOOP will match -xtype l
-            Err(e) if self.file_type.is_symlink() && e.is_loop() => true,
+        match meta {
+            Ok(meta) => FileType::from(meta.file_type()) == self.file_type,
+            // A broken link is still a link, and since GNU find 4.10, so is
+            // one in a loop
+            Err(e) if self.file_type.is_symlink() => {
+                e.is_loop() || (e.is_not_found() && file_info.path_is_symlink())
+            }
             _ => false,
         }
     }
@@ -95,7 +99,7 @@ mod tests {
     use std::os::unix::fs::symlink;
 
     #[cfg(unix)]
-    use crate::find::matchers::tests::get_dir_entry_follow;
+    use crate::find::matchers::{tests::get_dir_entry_follow, Follow};
 
     #[cfg(windows)]
     use std::os::windows::fs::{symlink_dir, symlink_file};
@@ -3,54 +3,60 @@
 // For the full copyright and license information, please view the LICENSE
 // file that was distributed with this source code.
 
-use super::{Matcher, MatcherIO, WalkEntry};
+use super::{ComparableValue, Matcher, MatcherIO, WalkEntry};
 
+#[cfg(windows)]
+use super::sid::Sid;
 #[cfg(unix)]
 use nix::unistd::User;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 
+/// How files are identified as belonging to a user.
+#[cfg(unix)]
+type Owner = ComparableValue;
+/// Windows doesn't have numeric IDs, so we compare SIDs instead.
+#[cfg(windows)]
+type Owner = Sid;
+
 pub struct UserMatcher {
-    uid: Option<u32>,
+    uid: Option<Owner>,
 }
 
 impl UserMatcher {
+    /// Matches files owned by the user `user`. Like GNU find, a user with
+    /// that name takes precedence, but otherwise it can be a numeric ID, with
+    /// an optional + or - prefix as for -uid.
     #[cfg(unix)]
     pub fn from_user_name(user: &str) -> UserMatcher {
-        // get uid from user name
-        let Ok(user) = User::from_name(user) else {
-            return UserMatcher { uid: None };
-        };
-
-        let Some(user) = user else {
-            // This if branch is to determine whether a certain user exists in the system.
-            // If a certain user does not exist in the system,
-            // the result will need to be returned according to
-            // the flag bit of whether to invert the result.
-            return UserMatcher { uid: None };
-        };
-
-        UserMatcher {
-            uid: Some(user.uid.as_raw()),
-        }
+        let uid = super::parse_owner(user, |name| {
+            User::from_name(name)
+                .ok()
+                .flatten()
+                .map(|user| user.uid.as_raw())
+        });
+        UserMatcher { uid }
     }
 
     #[cfg(unix)]
-    pub fn from_uid(uid: u32) -> UserMatcher {
+    pub fn from_uid(uid: ComparableValue) -> UserMatcher {
         UserMatcher { uid: Some(uid) }
     }
 
+    /// Matches files owned by the user `user`, which can be an account
+    /// name or a SID in string form, like `S-1-5-32-544`.
     #[cfg(windows)]
-    pub fn from_user_name(_user: &str) -> UserMatcher {
-        UserMatcher { uid: None }
+    pub fn from_user_name(user: &str) -> UserMatcher {
+        let uid = Sid::from_account_name(user).or_else(|| Sid::parse(user));
+        UserMatcher { uid }
     }
 
     #[cfg(windows)]
-    pub fn from_uid(_uid: u32) -> UserMatcher {
+    pub fn from_uid(_uid: ComparableValue) -> UserMatcher {
         UserMatcher { uid: None }
     }
 
-    pub fn uid(&self) -> &Option<u32> {
+    pub fn uid(&self) -> &Option<Owner> {
         &self.uid
     }
 }
@@ -62,17 +68,18 @@ impl Matcher for UserMatcher {
             return false;
         };
 
-        let file_uid = metadata.uid();
-
         // When matching the -user parameter in find/matcher/mod.rs,
         // it has been judged that the user does not exist and an error is returned.
-        // So use unwarp() directly here.
-        self.uid.unwrap() == file_uid
+        // So use unwrap() directly here.
+        self.uid.as_ref().unwrap().matches(metadata.uid().into())
     }
 
     #[cfg(windows)]
-    fn matches(&self, _file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        false
+    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+        let Some(uid) = &self.uid else {
+            return false;
+        };
+        Sid::file_owner(file_info.path()).is_ok_and(|sid| sid == *uid)
     }
 }
 
@@ -103,8 +110,15 @@ impl Matcher for NoUserMatcher {
     }
 
     #[cfg(windows)]
-    fn matches(&self, _file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
-        false
+    fn matches(&self, file_info: &WalkEntry, _: &mut MatcherIO) -> bool {
+        if file_info.path().is_symlink() {
+            return false;
+        }
+
+        match Sid::file_owner(file_info.path()) {
+            Ok(sid) => !sid.is_known(),
+            Err(_) => true,
+        }
     }
 }
 
@@ -113,7 +127,9 @@ mod tests {
     #[test]
     #[cfg(unix)]
     fn test_user_matcher() {
-        use crate::find::matchers::{tests::get_dir_entry_for, user::UserMatcher, Matcher};
+        use crate::find::matchers::{
+            tests::get_dir_entry_for, user::UserMatcher, ComparableValue, Matcher,
+        };
         use crate::find::tests::FakeDependencies;
         use chrono::Local;
         use nix::unistd::{Uid, User};
@@ -141,7 +157,8 @@ mod tests {
         );
 
         // Testing a non-existent group name
-        let time_string = Local::now().format("%Y%m%d%H%M%S").to_string();
+        // (with a prefix, since numbers are accepted as IDs)
+        let time_string = Local::now().format("no%Y%m%d%H%M%S").to_string();
         let matcher = UserMatcher::from_user_name(time_string.as_str());
         assert!(
             matcher.uid().is_none(),
@@ -150,7 +167,7 @@ mod tests {
         );
 
         // Testing user id
-        let matcher = UserMatcher::from_uid(file_uid);
+        let matcher = UserMatcher::from_uid(ComparableValue::EqualTo(file_uid.into()));
         assert!(matcher.uid().is_some(), "user id {} should exist", file_uid);
         assert!(
             matcher.matches(&file_info, &mut matcher_io),
@@ -4,27 +4,127 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+//! The find utility, which can also be used as a library.
+//!
+//! # Stability
+//!
+//! The following items are the supported API, and follow semver:
+//!
+//! - [find_main], [search], [Config], [Dependencies],
+//!   [StandardDependencies] and [FindError]
+//! - [matchers::build_top_level_matcher], [matchers::Matcher],
+//!   [matchers::MatcherIO], [matchers::WalkEntry], [matchers::WalkError],
+//!   [matchers::FileType] and [matchers::Follow]
+//!
+//! Anything else that happens to be public, like the individual matchers, is
+//! an implementation detail that may change in any release.
+//!
+//! # Example
+//!
+//! ```
+//! use findutils::find::matchers::{build_top_level_matcher, Follow};
+//! use findutils::find::{search, Config, StandardDependencies};
+//!
+//! let mut config = Config::default().follow(Follow::Always).max_depth(2);
+//! let matcher = build_top_level_matcher(&["-name", "*.rs"], &mut config).unwrap();
+//! let deps = StandardDependencies::new();
+//! let exit_code = search(&["src"], &*matcher, &config, &deps);
+//! assert_eq!(exit_code, 0);
+//! ```
+
+mod error;
+mod error_summary;
+mod expr_file;
+mod gnu_errors;
 pub mod matchers;
+mod progress;
+mod sort;
+mod walk;
 
-use matchers::{Follow, WalkEntry};
+pub use error::FindError;
+
+use error_summary::{Collecting, ErrorSummary};
+use matchers::{ActionLog, Follow};
+use progress::Progress;
+use sort::SortOrder;
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::error::Error;
-use std::io::{stderr, stdout, Write};
+use std::fs::File;
+use std::io::{stderr, stdin, stdout, IsTerminal, Read, Write};
+use std::path::is_separator;
 use std::rc::Rc;
-use std::time::SystemTime;
-use walkdir::WalkDir;
+use std::time::{Duration, Instant, SystemTime};
+use uucore::error::{get_exit_code, UError};
+use walk::{Throttle, VisitedDirs, Walker};
+
+use crate::exec_common::Limits;
+use crate::words;
 
+/// The options that affect a whole search, rather than a single part of the
+/// expression. Options in the expression, like -maxdepth, are applied to this
+/// when it's built.
 pub struct Config {
     same_file_system: bool,
     depth_first: bool,
+    /// Read directories level by level (-bfs).
+    breadth_first: bool,
     min_depth: usize,
     max_depth: usize,
-    sorted_output: bool,
+    sort_order: Option<SortOrder>,
     help_requested: bool,
     version_requested: bool,
     today_start: bool,
     no_leaf_dirs: bool,
     follow: Follow,
+    dry_run: bool,
+    /// How long -exec and -execdir commands may run for (--exec-timeout).
+    exec_timeout: Option<Duration>,
+    /// How to restrict -exec and -execdir commands (--exec-sandbox).
+    exec_sandbox: Option<matchers::exec::Sandbox>,
+    /// The priority and resource limits -exec and friends' commands run
+    /// with (--exec-nice and --exec-rlimit).
+    exec_limits: Limits,
+    /// What -copy-to and -move-to do when something's in the way
+    /// (--collision).
+    collision: matchers::Collision,
+    action_log: Option<ActionLog>,
+    stats: bool,
+    progress: bool,
+    print_expression: bool,
+    /// The colors for -print to use, if --color is on.
+    colors: Option<Rc<matchers::LsColors>>,
+    /// Turns printed paths into links, if --hyperlink is on.
+    hyperlinks: Option<Rc<matchers::Hyperlinks>>,
+    /// Print paths relative to their starting points (--relative).
+    relative: bool,
+    /// Tidy up the starting points before searching them
+    /// (--normalize-paths).
+    normalize_paths: bool,
+    /// Read the starting points from this file (-files0-from), rather than
+    /// the command line.
+    files0_from: Option<String>,
+    /// Read more starting points from this file (--extra-paths-from), after
+    /// the ones on the command line.
+    extra_paths_from: Option<String>,
+    /// Whether -ok or -okdir will read answers from stdin.
+    prompts: bool,
+    /// How to keep the walk from hogging the disk (--throttle).
+    throttle: Option<Throttle>,
+    /// Print errors exactly like GNU find (--gnu-errors).
+    gnu_errors: bool,
+    /// Collect the errors into a summary in JSON at the end, rather than
+    /// printing each one (--errors=json).
+    errors_json: bool,
+    /// The -regextype until the expression gives one, which -E changes.
+    regex_type: matchers::RegexType,
+    /// Have -print and -fprint skip paths that xargs would split (-X).
+    xargs_safe: bool,
+    /// The directories searched so far, if each should only be searched once
+    /// even if several starting points lead to it (--dedup-roots).
+    visited_dirs: Option<VisitedDirs>,
+    /// What -path says the paths of the files the expression acts on start
+    /// with, so directories that can't contain any needn't be read.
+    path_prefixes: Option<Vec<String>>,
 }
 
 impl Default for Config {
@@ -32,31 +132,109 @@ impl Default for Config {
         Self {
             same_file_system: false,
             depth_first: false,
+            breadth_first: false,
             min_depth: 0,
             max_depth: usize::MAX,
-            sorted_output: false,
+            sort_order: None,
             help_requested: false,
             version_requested: false,
             today_start: false,
-            // Directory information and traversal are done by walkdir,
-            // and this configuration field will exist as
-            // a compatibility item for GNU findutils.
+            // Turns off walk::Walker's leaf optimization (-noleaf)
             no_leaf_dirs: false,
             follow: Follow::Never,
+            dry_run: false,
+            exec_timeout: None,
+            exec_sandbox: None,
+            exec_limits: Limits::default(),
+            collision: matchers::Collision::default(),
+            action_log: None,
+            stats: false,
+            progress: false,
+            print_expression: false,
+            colors: None,
+            hyperlinks: None,
+            relative: false,
+            normalize_paths: false,
+            files0_from: None,
+            extra_paths_from: None,
+            prompts: false,
+            throttle: None,
+            gnu_errors: false,
+            errors_json: false,
+            regex_type: matchers::RegexType::default(),
+            xargs_safe: false,
+            visited_dirs: None,
+            path_prefixes: None,
         }
     }
 }
 
+impl Config {
+    /// Set the symlink following mode (-H, -L or -P).
+    #[must_use]
+    pub fn follow(mut self, follow: Follow) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// Set the minimum depth of entries to match (-mindepth).
+    #[must_use]
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Set the maximum depth to descend to (-maxdepth).
+    #[must_use]
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Match the contents of a directory before the directory itself
+    /// (-depth).
+    #[must_use]
+    pub fn depth_first(mut self, depth_first: bool) -> Self {
+        self.depth_first = depth_first;
+        self
+    }
+
+    /// Don't descend into directories on other file systems (-xdev).
+    #[must_use]
+    pub fn same_file_system(mut self, same_file_system: bool) -> Self {
+        self.same_file_system = same_file_system;
+        self
+    }
+
+    /// Sort the contents of each directory by name (-sorted).
+    #[must_use]
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sort_order = sorted.then(SortOrder::default);
+        self
+    }
+
+    /// Make destructive actions report what they would do instead of doing
+    /// it (--dry-run).
+    #[must_use]
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
 /// Trait that encapsulates various dependencies (output, clocks, etc.) that we
 /// might want to fake out for unit tests.
 pub trait Dependencies {
     fn get_output(&self) -> &RefCell<dyn Write>;
+    /// Where matchers write their error messages.
+    fn get_error_output(&self) -> &RefCell<dyn Write>;
     fn now(&self) -> SystemTime;
 }
 
 /// Struct that holds the dependencies we use when run as the real executable.
 pub struct StandardDependencies {
     output: Rc<RefCell<dyn Write>>,
+    error_output: Rc<RefCell<dyn Write>>,
     now: SystemTime,
 }
 
@@ -65,6 +243,7 @@ impl StandardDependencies {
     pub fn new() -> Self {
         Self {
             output: Rc::new(RefCell::new(stdout())),
+            error_output: Rc::new(RefCell::new(stderr())),
             now: SystemTime::now(),
         }
     }
@@ -81,6 +260,10 @@ impl Dependencies for StandardDependencies {
         self.output.as_ref()
     }
 
+    fn get_error_output(&self) -> &RefCell<dyn Write> {
+        self.error_output.as_ref()
+    }
+
     fn now(&self) -> SystemTime {
         self.now
     }
@@ -89,16 +272,83 @@ impl Dependencies for StandardDependencies {
 /// The result of parsing the command-line arguments into useful forms.
 struct ParsedInfo {
     matcher: Box<dyn self::matchers::Matcher>,
+    expression: matchers::Expr,
     paths: Vec<String>,
     config: Config,
 }
 
-/// Function to generate a `ParsedInfo` from the strings supplied on the command-line.
-fn parse_args(args: &[&str]) -> Result<ParsedInfo, Box<dyn Error>> {
+/// Function to generate a `ParsedInfo` from the strings supplied on the
+/// command-line, and the `defaults` from FIND_OPTIONS. The defaults come first,
+/// so the command line can override them.
+fn parse_args(args: &[&str], defaults: &[&str]) -> Result<ParsedInfo, FindError> {
     let mut paths = vec![];
-    let mut i = 0;
     let mut config = Config::default();
+    let defaults_end = parse_leading_options(defaults, &mut config);
+    let mut i = parse_leading_options(args, &mut config);
 
+    let paths_start = i;
+    // After a --, only what the expression could really start with ends the
+    // starting points, so that other names starting with - can be searched.
+    // This doesn't look at what's on disk, so it's the same everywhere.
+    let after_double_dash = i > 0 && args[i - 1] == "--";
+    while i < args.len() {
+        let arg = args[i];
+        let starts_expression = if after_double_dash {
+            matches!(arg, "!" | "(" | "-not")
+                || matchers::is_primary(arg)
+                || parse_leading_options(&[arg], &mut Config::default()) == 1
+        } else {
+            (arg.starts_with('-') && arg != "-") || arg == "!" || arg == "("
+        };
+        if starts_expression {
+            break;
+        }
+        paths.push(arg.to_string());
+        i += 1;
+    }
+    let expression: Vec<&str> = defaults[defaults_end..]
+        .iter()
+        .chain(&args[i..])
+        .copied()
+        .collect();
+    let (matcher, expression) = matchers::build_top_level_expression(&expression, &mut config)?;
+    if config.prompts && config.files0_from.as_deref() == Some("-") {
+        return Err(From::from(
+            "option -files0-from reading from standard input cannot be combined with -ok, \
+             -okdir",
+        ));
+    }
+    if config.prompts && config.extra_paths_from.as_deref() == Some("-") {
+        return Err(From::from(
+            "option --extra-paths-from reading from standard input cannot be combined with \
+             -ok, -okdir",
+        ));
+    }
+    if config.files0_from.is_some() {
+        if config.extra_paths_from.is_some() {
+            return Err(From::from(
+                "option --extra-paths-from cannot be combined with -files0-from",
+            ));
+        }
+        if i > paths_start {
+            return Err(FindError::ExtraOperand(args[paths_start].to_owned()));
+        }
+    } else if i == paths_start && config.extra_paths_from.is_none() {
+        // With --extra-paths-from, the file's starting points replace "."
+        paths.push(".".to_string());
+    }
+    Ok(ParsedInfo {
+        matcher,
+        expression,
+        paths,
+        config,
+    })
+}
+
+/// Handles the options that come before the starting points, like -H and -L,
+/// returning the index just past them.
+fn parse_leading_options(args: &[&str], config: &mut Config) -> usize {
+    let mut i = 0;
     while i < args.len() {
         match args[i] {
             "-O0" | "-O1" | "-O2" | "-O3" => {
@@ -107,35 +357,78 @@ fn parse_args(args: &[&str]) -> Result<ParsedInfo, Box<dyn Error>> {
             "-H" => config.follow = Follow::Roots,
             "-L" => config.follow = Follow::Always,
             "-P" => config.follow = Follow::Never,
+            "--gnu-errors" => config.gnu_errors = true,
+            "-X" => config.xargs_safe = true,
+            // BSD's spellings of -sorted and -xdev
+            "-s" => config.sort_order = Some(SortOrder::default()),
+            "-x" => config.same_file_system = true,
+            "-E" => config.regex_type = matchers::RegexType::PosixExtended,
+            // Only needed by do_find
+            "--no-defaults" => {}
             "--" => {
                 // End of flags
-                i += 1;
-                break;
+                return i + 1;
             }
             _ => break,
         }
 
         i += 1;
     }
+    i
+}
 
-    let paths_start = i;
-    while i < args.len()
-        && (args[i] == "-" || !args[i].starts_with('-'))
-        && args[i] != "!"
-        && args[i] != "("
-    {
-        paths.push(args[i].to_string());
-        i += 1;
+/// Reads the default options from FIND_OPTIONS, unless they're turned off
+/// with --no-defaults.
+fn default_options(args: &[&str]) -> Result<Vec<String>, FindError> {
+    let leading = parse_leading_options(args, &mut Config::default());
+    if args[..leading].contains(&"--no-defaults") {
+        return Ok(vec![]);
     }
-    if i == paths_start {
-        paths.push(".".to_string());
+    let defaults = words::default_options("FIND_OPTIONS").map_err(FindError::Parse)?;
+    let defaults: Vec<&str> = defaults.iter().map(AsRef::as_ref).collect();
+    expr_file::expand_expr_files(&defaults)
+}
+
+/// Whether errors should look like GNU find's, which we need to know even if
+/// the rest of the command line is no good.
+fn wants_gnu_errors(args: &[&str]) -> bool {
+    let mut config = Config::default();
+    let leading = parse_leading_options(args, &mut config);
+    if !args[..leading].contains(&"--no-defaults") {
+        if let Ok(defaults) = words::default_options("FIND_OPTIONS") {
+            let defaults: Vec<&str> = defaults.iter().map(AsRef::as_ref).collect();
+            parse_leading_options(&defaults, &mut config);
+        }
+    }
+    config.gnu_errors
+}
+
+/// Resource usage counters, printed at exit with --stats, and as we go with
+/// --progress.
+#[derive(Default)]
+struct Stats {
+    entries: u64,
+    directories: u64,
+    matches: u64,
+}
+
+impl Stats {
+    fn print(&self, elapsed: Duration) {
+        eprintln!("Statistics:");
+        eprintln!("  entries scanned:     {}", self.entries);
+        eprintln!("  directories visited: {}", self.directories);
+        eprintln!("  stat calls:          {}", matchers::stat_calls());
+        eprintln!("  time taken:          {:.3}s", elapsed.as_secs_f64());
     }
-    let matcher = matchers::build_top_level_matcher(&args[i..], &mut config)?;
-    Ok(ParsedInfo {
-        matcher,
-        paths,
-        config,
-    })
+}
+
+/// What the whole search keeps track of for --stats, --progress and
+/// --errors=json.
+#[derive(Default)]
+struct Reporting {
+    stats: Stats,
+    progress: Option<Progress>,
+    errors: Option<ErrorSummary>,
 }
 
 fn process_dir(
@@ -144,33 +437,87 @@ fn process_dir(
     deps: &dyn Dependencies,
     matcher: &dyn matchers::Matcher,
     quit: &mut bool,
+    reporting: &mut Reporting,
 ) -> i32 {
-    let mut walkdir = WalkDir::new(dir)
+    let Reporting {
+        stats,
+        progress,
+        errors,
+    } = reporting;
+    let mut it = Walker::new(dir)
         .contents_first(config.depth_first)
+        .breadth_first(config.breadth_first)
         .max_depth(config.max_depth)
         .min_depth(config.min_depth)
         .same_file_system(config.same_file_system)
-        .follow_links(config.follow == Follow::Always)
-        .follow_root_links(config.follow != Follow::Never);
-    if config.sorted_output {
-        walkdir = walkdir.sort_by(|a, b| a.file_name().cmp(b.file_name()));
-    }
+        .follow(config.follow)
+        .rate_limit(match config.throttle {
+            Some(Throttle::Rate(bytes_per_sec)) => Some(bytes_per_sec),
+            _ => None,
+        })
+        .sort_order(config.sort_order.clone())
+        .skip_visited(config.visited_dirs.clone())
+        .leaf_optimization(!config.no_leaf_dirs)
+        // With -L, directories are still read to report any loops in them
+        .path_prefixes(match config.follow {
+            Follow::Always => None,
+            _ => config.path_prefixes.clone(),
+        })
+        // --stats and --progress count directories
+        .file_types(config.stats || progress.is_some() || matcher.needs() != matchers::Needs::Name);
+
+    // Whatever the matchers write as errors goes in the summary, if there is
+    // one
+    let collecting = errors.as_ref().map(|_| Collecting::new(deps));
+    let deps: &dyn Dependencies = match &collecting {
+        Some(collecting) => collecting,
+        None => deps,
+    };
 
     let mut ret = 0;
 
     // Slightly yucky loop handling here :-(. See docs for
-    // WalkDirIterator::skip_current_dir for explanation.
-    let mut it = walkdir.into_iter();
+    // Walker::skip_current_dir for explanation.
     while let Some(result) = it.next() {
-        match WalkEntry::from_walkdir(result, config.follow) {
+        match result {
             Err(err) => {
                 ret = 1;
-                writeln!(&mut stderr(), "Error: {err}").unwrap()
+                if let Some(errors) = errors.as_mut() {
+                    errors.walk_error(&err);
+                    continue;
+                }
+                if let Some(progress) = progress.as_mut() {
+                    let _ = progress.erase(&mut stderr());
+                }
+                if config.gnu_errors {
+                    gnu_errors::print(&gnu_errors::walk_error(&err));
+                } else {
+                    writeln!(&mut stderr(), "Error: {err}").unwrap();
+                }
             }
             Ok(entry) => {
+                stats.entries += 1;
+                // Checking the type may cost a stat(), so only do it if asked
+                if (config.stats || progress.is_some()) && entry.file_type().is_dir() {
+                    stats.directories += 1;
+                }
+
                 let mut matcher_io = matchers::MatcherIO::new(deps);
+                matcher_io.set_dry_run(config.dry_run);
+                matcher_io.set_action_log(config.action_log.as_ref());
 
-                matcher.matches(&entry, &mut matcher_io);
+                if let Some(progress) = progress.as_mut() {
+                    let _ = progress.before_entry(&mut stderr());
+                }
+                if matcher.matches(&entry, &mut matcher_io) {
+                    stats.matches += 1;
+                }
+                if let (Some(errors), Some(collecting)) = (errors.as_mut(), &collecting) {
+                    errors.action_errors(entry.path(), &collecting.take_errors());
+                }
+                if let Some(progress) = progress.as_mut() {
+                    let _ = progress.after_entry(stats, &mut stderr());
+                }
                 match matcher_io.exit_code() {
                     0 => {}
                     code => ret = code,
@@ -189,8 +536,12 @@ fn process_dir(
     ret
 }
 
-fn do_find(args: &[&str], deps: &dyn Dependencies) -> Result<i32, Box<dyn Error>> {
-    let paths_and_matcher = parse_args(args)?;
+fn do_find(args: &[&str], deps: &dyn Dependencies) -> Result<i32, FindError> {
+    let args = expr_file::expand_expr_files(args)?;
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+    let defaults = default_options(&args)?;
+    let defaults: Vec<&str> = defaults.iter().map(AsRef::as_ref).collect();
+    let paths_and_matcher = parse_args(&args, &defaults)?;
     if paths_and_matcher.config.help_requested {
         print_help();
         return Ok(0);
@@ -200,16 +551,206 @@ fn do_find(args: &[&str], deps: &dyn Dependencies) -> Result<i32, Box<dyn Error>
         return Ok(0);
     }
 
-    let mut ret = 0;
-    let mut quit = false;
-    for path in paths_and_matcher.paths {
-        let dir_ret = process_dir(
-            &path,
+    if paths_and_matcher.config.print_expression {
+        eprintln!("{}", paths_and_matcher.expression);
+    }
+
+    // This is for the whole process, so it's left until we're about to
+    // search, rather than done while the expression is built
+    if paths_and_matcher.config.throttle == Some(Throttle::Idle) {
+        walk::set_idle_io_priority().map_err(|source| FindError::Io {
+            context: String::from("cannot set the idle I/O priority"),
+            path: None,
+            source,
+        })?;
+    }
+
+    if let Some(file) = &paths_and_matcher.config.files0_from {
+        let paths = read_files0_from(file, paths_and_matcher.config.gnu_errors)?;
+        return Ok(search_starting_points(
+            paths
+                .iter()
+                .map(|path| path.as_deref().map_err(Clone::clone)),
+            &*paths_and_matcher.matcher,
             &paths_and_matcher.config,
             deps,
+        ));
+    }
+
+    if let Some(file) = &paths_and_matcher.config.extra_paths_from {
+        let extra_paths = read_files0_from(file, paths_and_matcher.config.gnu_errors)?;
+        return Ok(search_starting_points(
+            paths_and_matcher
+                .paths
+                .iter()
+                .map(|path| Ok(path.as_str()))
+                .chain(
+                    extra_paths
+                        .iter()
+                        .map(|path| path.as_deref().map_err(Clone::clone)),
+                ),
             &*paths_and_matcher.matcher,
-            &mut quit,
-        );
+            &paths_and_matcher.config,
+            deps,
+        ));
+    }
+
+    let paths: Vec<&str> = paths_and_matcher.paths.iter().map(AsRef::as_ref).collect();
+    Ok(search(
+        &paths,
+        &*paths_and_matcher.matcher,
+        &paths_and_matcher.config,
+        deps,
+    ))
+}
+
+/// Reads the NUL separated starting points for -files0-from and
+/// --extra-paths-from, where "-" means standard input. Zero-length names are
+/// returned as errors, so they can be reported in turn as the search reaches
+/// them, quoted like GNU find would if `gnu_errors` is set.
+fn read_files0_from(
+    file: &str,
+    gnu_errors: bool,
+) -> Result<Vec<Result<String, String>>, FindError> {
+    let mut contents = vec![];
+    let name = if file == "-" {
+        "(standard input)"
+    } else {
+        file
+    };
+    let result = if file == "-" {
+        stdin().lock().read_to_end(&mut contents)
+    } else {
+        File::open(file)
+            .map_err(|source| FindError::Io {
+                context: format!("cannot open '{file}' for reading"),
+                path: Some(file.to_owned()),
+                source,
+            })?
+            .read_to_end(&mut contents)
+    };
+    if let Err(source) = result {
+        return Err(FindError::Io {
+            context: format!("'{name}': read error"),
+            path: Some(name.to_owned()),
+            source,
+        });
+    }
+
+    let quoted = if gnu_errors {
+        gnu_errors::Quoter::from_env().quote(name)
+    } else {
+        format!("'{name}'")
+    };
+    let mut paths: Vec<&[u8]> = contents.split(|&b| b == b'\0').collect();
+    // The last name may or may not be terminated
+    if paths.last().is_some_and(|path| path.is_empty()) {
+        paths.pop();
+    }
+    Ok(paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            if path.is_empty() {
+                Err(format!("{quoted}:{}: invalid zero-length file name", i + 1))
+            } else {
+                Ok(String::from_utf8_lossy(path).into_owned())
+            }
+        })
+        .collect())
+}
+
+/// Tidies up a starting point for --normalize-paths, so that the paths of
+/// everything under it are tidy too: runs of slashes become one, and "."
+/// components are dropped unless they're the first, so "./a//b/./" becomes
+/// "./a/b/". A trailing slash is kept, since it means a symbolic link to a
+/// directory is followed.
+fn normalize_path(path: &str) -> Cow<'_, str> {
+    let separator = path.chars().find(|&c| is_separator(c)).unwrap_or('/');
+    let segments: Vec<&str> = path.split(is_separator).collect();
+    let parts: Vec<&str> = segments
+        .iter()
+        .enumerate()
+        .filter(|&(i, segment)| !segment.is_empty() && (i == 0 || *segment != "."))
+        .map(|(_, segment)| *segment)
+        .collect();
+
+    let mut normalized = String::with_capacity(path.len());
+    if path.starts_with(is_separator) {
+        normalized.push(separator);
+        // A path like \\server\share isn't the same as \server\share
+        if cfg!(windows) && path[1..].starts_with(is_separator) {
+            normalized.push(separator);
+        }
+    }
+    normalized.push_str(&parts.join(&separator.to_string()));
+    let trailing = segments.len() > 1 && segments.last().is_some_and(|s| s.is_empty() || *s == ".");
+    if trailing && !parts.is_empty() {
+        normalized.push(separator);
+    }
+
+    if normalized == path {
+        path.into()
+    } else {
+        normalized.into()
+    }
+}
+
+/// Walks each of the paths in turn, passing every entry to the matcher, and
+/// returns the exit code find would use.
+pub fn search(
+    paths: &[&str],
+    matcher: &dyn matchers::Matcher,
+    config: &Config,
+    deps: &dyn Dependencies,
+) -> i32 {
+    search_starting_points(paths.iter().map(|&path| Ok(path)), matcher, config, deps)
+}
+
+/// Like [search], but some of the starting points may be invalid, in which
+/// case the error is reported and we move on to the next one.
+fn search_starting_points<'a>(
+    paths: impl IntoIterator<Item = Result<&'a str, String>>,
+    matcher: &dyn matchers::Matcher,
+    config: &Config,
+    deps: &dyn Dependencies,
+) -> i32 {
+    let start = Instant::now();
+    let mut reporting = Reporting {
+        stats: Stats::default(),
+        // There's only any point if someone's watching
+        progress: (config.progress && stderr().is_terminal())
+            .then(|| Progress::new(stdout().is_terminal())),
+        errors: config.errors_json.then(ErrorSummary::default),
+    };
+    let mut ret = 0;
+    let mut quit = false;
+    for path in paths {
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => {
+                ret = 1;
+                if let Some(errors) = &mut reporting.errors {
+                    errors.starting_point_error(&e);
+                    continue;
+                }
+                if let Some(progress) = &mut reporting.progress {
+                    let _ = progress.erase(&mut stderr());
+                }
+                if config.gnu_errors {
+                    gnu_errors::print(&e);
+                } else {
+                    writeln!(&mut stderr(), "Error: {e}").unwrap();
+                }
+                continue;
+            }
+        };
+        let path = if config.normalize_paths {
+            normalize_path(path)
+        } else {
+            path.into()
+        };
+        let dir_ret = process_dir(&path, config, deps, matcher, &mut quit, &mut reporting);
         if dir_ret != 0 {
             ret = dir_ret;
         }
@@ -217,8 +758,19 @@ fn do_find(args: &[&str], deps: &dyn Dependencies) -> Result<i32, Box<dyn Error>
             break;
         }
     }
+    matcher.finished();
 
-    Ok(ret)
+    if let Some(progress) = &mut reporting.progress {
+        let _ = progress.erase(&mut stderr());
+    }
+    if let Some(errors) = &reporting.errors {
+        errors.write_json(&mut stderr()).unwrap();
+    }
+    if config.stats {
+        reporting.stats.print(start.elapsed());
+    }
+
+    ret
 }
 
 fn print_help() {
@@ -230,7 +782,10 @@ If no path is supplied then the current working directory is used by default.
 Early alpha implementation. Currently the only expressions supported are
  -print
  -print0
- -printf
+ -printf format
+    %{{ext}}, %{{linkdepth}} and %{{xattr:name}} in format are non-standard
+    extensions that print the file name's extension, how many symbolic
+    links lead from the path to a file, and an extended attribute on Linux.
  -name case-sensitive_filename_pattern
  -lname case-sensitive_filename_pattern
  -iname case-insensitive_filename_pattern
@@ -241,6 +796,9 @@ Early alpha implementation. Currently the only expressions supported are
  -type type_char
     currently type_char can only be f (for file) or d (for directory)
  -size [+-]N[bcwkMG]
+ -sparse [ratio]
+    a non-standard extension that matches files taking up less space on
+    disk than ratio (1 by default) times their size, like %S in -printf.
  -delete
  -prune
  -not
@@ -252,17 +810,143 @@ Early alpha implementation. Currently the only expressions supported are
  -false
  -maxdepth N
  -mindepth N
+ -files0-from file
+    read the starting points from file, separated by NUL characters, or
+    from standard input if file is -.
+ --extra-paths-from file
+    a non-standard extension that reads more starting points from file like
+    -files0-from, but searches them after the ones on the command line.
  -d[epth]
- -xdev
+ -bfs
+    a non-standard extension that searches breadth-first, so that shallower
+    files all come before deeper ones. It can't be combined with -depth or
+    -delete.
+ -xdev, -mount, --one-file-system
  -ctime [+-]N
  -atime [+-]N
  -mtime [+-]N
  -perm [-/]{{octal|u=rwx,go=w}}
  -newer path_to_file
  -exec[dir] executable [args] [{{}}] [more args] ;
- -sorted
-    a non-standard extension that sorts directory contents by name before
+ -ok[dir] executable [args] [{{}}] [more args] ;
+    like -exec[dir], but asks first, and only runs the command if the
+    answer read from stdin is yes in the current locale.
+ -sorted[=KEY[,KEY]...]
+    a non-standard extension that sorts directory contents before
     processing them. Less efficient, but allows for deterministic output.
+    KEY is name (the default), natural (file2 before file10), size or
+    mtime, with ties sorted by name.
+ --color[=WHEN]
+    a non-standard extension that colors the paths printed by -print like
+    ls does, using LS_COLORS. WHEN is always (the default), never or auto,
+    which only colors output to a terminal.
+ --hyperlink[=WHEN]
+    a non-standard extension that prints paths from -print, -print0 and
+    -printf's %p, %P and %f as links to the file, for terminals that support
+    them. WHEN is as for --color.
+ --normalize-paths
+    a non-standard extension that collapses runs of slashes and drops '.'
+    components from the starting points before searching them, so that
+    printed paths and -path see ./a/b rather than .//a/./b.
+ --relative
+    a non-standard extension that makes -print, -print0, -fprint, -fprint0
+    and -printf's %p print paths relative to their starting points, like %P,
+    with the starting points themselves printed as '.'.
+ --dry-run
+    a non-standard extension that makes -delete, -exec and -execdir (and
+    -ok and -okdir, without asking) print what they would do instead of
+    doing it.
+ --exec-timeout secs
+    a non-standard extension that kills -exec and -execdir commands that are
+    still running after secs seconds, which counts as the command failing.
+ --exec-sandbox[=dir]
+    a non-standard extension that runs -exec[dir] and -ok[dir] commands
+    with only PATH, LANG, LC_* and TZ from the environment, without being
+    able to gain privileges on Linux, and -exec and -ok commands in dir.
+ --exec-keep-env names
+    a non-standard extension that passes the comma-separated environment
+    variables on to commands as well, with --exec-sandbox.
+ --exec-nice n, --exec-rlimit name=value[,name=value]...
+    non-standard extensions that run -exec[dir] and -ok[dir] commands with
+    their niceness raised by n, like nice(1), and with limits on the CPU
+    seconds (cpu), memory (as), file size (fsize), open files (nofile) or
+    core dump size (core) they can use, where sizes can end in k, M or G.
+    These are only available on Unix.
+ --errors=format
+    a non-standard extension that, with a format of json, collects the
+    errors from the search into a summary printed to stderr at the end,
+    rather than printing each one as it happens. The default is text.
+ -log file
+    a non-standard extension that records every -delete, -exec[dir] and
+    -print action in file, with a timestamp and the result.
+ -tar file
+    a non-standard extension that adds matching regular files to a tar
+    archive written to file, or to stdout if file is -, keeping their
+    owners, permissions and modification times.
+ -copy-to dir
+ -move-to dir
+    non-standard extensions that copy or move matching files into dir, at
+    the same path relative to their starting point, creating directories as
+    needed.
+ --collision policy
+    a non-standard extension that says what -copy-to and -move-to do when
+    there's already something in the way: error (the default), skip or
+    overwrite.
+ -cap [caps]
+    a non-standard extension that matches files with Linux file
+    capabilities, or with all of caps if given, e.g. cap_net_raw,cap_setuid.
+ -chmod mode
+    a non-standard extension that changes the permissions of matching files
+    like chmod mode would, without running chmod for each of them.
+ -chown user[:group]
+    a non-standard extension that changes the owner and/or group of matching
+    files like chown -h would, without running chown for each of them.
+ -touch [-a|-m]
+    a non-standard extension that sets the access and modification times
+    (or with -a or -m, just one of them) of matching files to when find
+    started.
+ --throttle rate|idle
+    a non-standard extension that slows the search down to read
+    directories at no more than rate MB/s, or with idle, only reads when
+    nothing else is using the disk (Linux only).
+ --stats
+    a non-standard extension that prints the number of entries scanned,
+    directories visited and stat calls made, and the time taken, to stderr
+    once find has finished.
+ --dedup-roots
+    a non-standard extension that searches each directory only once, even
+    if it's reached from more than one starting point.
+ --progress
+    a non-standard extension that keeps a count of the directories and
+    entries scanned and the entries matched on stderr while searching, if
+    it's a terminal.
+ --expr-file file
+    a non-standard extension that reads more of the command line from file,
+    split into words like a shell would, with # comments.
+ --gnu-errors
+    a non-standard extension that words and quotes error messages exactly
+    like GNU find, for scripts that look for them. Like -H and -L, it must
+    come before the paths.
+ -s, -x, -E
+    BSD find's names for -sorted, -xdev and -regextype posix-extended,
+    which like -H and -L must come before the paths. A -regextype in the
+    expression still applies to the -regex and -iregex after it.
+ -X
+    a non-standard extension from BSD find that makes -print and -fprint
+    skip paths with spaces, tabs, newlines, quotes or backslashes, which
+    xargs without -0 would split up, with a warning. Like -H and -L, it
+    must come before the paths.
+ --no-defaults
+    a non-standard extension that ignores the default options in the
+    FIND_OPTIONS environment variable. Like -H and -L, it must come before
+    the paths.
+ -- path...
+    a non-standard extension that takes paths after -- that start with -,
+    like -foo, as paths, up to the first primary, (, ! or -not.
+ -print-expression
+    a non-standard extension that prints the expression as find understood
+    it to stderr, with all the operators (including any implicit -a and
+    -print) spelled out.
 "
     );
 }
@@ -276,12 +960,20 @@ fn print_version() {
 /// All main has to do is pass in the command-line args and exit the process
 /// with the exit code. Note that the first string in args is expected to be
 /// the name of the executable.
+///
+/// Problems that don't stop the search, like failing to write to a -fprint
+/// file, are recorded with [uucore::error::set_exit_code] and reflected in
+/// the result.
 pub fn find_main(args: &[&str], deps: &dyn Dependencies) -> i32 {
     match do_find(&args[1..], deps) {
-        Ok(ret) => ret,
+        Ok(ret) => ret.max(get_exit_code()),
         Err(e) => {
-            writeln!(&mut stderr(), "Error: {e}").unwrap();
-            1
+            if wants_gnu_errors(&args[1..]) {
+                gnu_errors::print(&gnu_errors::find_error(&e));
+            } else {
+                writeln!(&mut stderr(), "Error: {e}").unwrap();
+            }
+            e.code()
         }
     }
 }
@@ -321,6 +1013,7 @@ mod tests {
     /// allowing us to check output, set the time returned by clocks etc.
     pub struct FakeDependencies {
         pub output: RefCell<Cursor<Vec<u8>>>,
+        pub error_output: RefCell<Cursor<Vec<u8>>>,
         now: SystemTime,
     }
 
@@ -328,6 +1021,7 @@ mod tests {
         pub fn new() -> Self {
             Self {
                 output: RefCell::new(Cursor::new(Vec::<u8>::new())),
+                error_output: RefCell::new(Cursor::new(Vec::<u8>::new())),
                 now: SystemTime::now(),
             }
         }
@@ -341,7 +1035,15 @@ mod tests {
         }
 
         pub fn get_output_as_string(&self) -> String {
-            let mut cursor = self.output.borrow_mut();
+            Self::read_to_string(&self.output)
+        }
+
+        pub fn get_error_output_as_string(&self) -> String {
+            Self::read_to_string(&self.error_output)
+        }
+
+        fn read_to_string(output: &RefCell<Cursor<Vec<u8>>>) -> String {
+            let mut cursor = output.borrow_mut();
             cursor.set_position(0);
             let mut contents = String::new();
             cursor.read_to_string(&mut contents).unwrap();
@@ -354,6 +1056,10 @@ mod tests {
             &self.output
         }
 
+        fn get_error_output(&self) -> &RefCell<dyn Write> {
+            &self.error_output
+        }
+
         fn now(&self) -> SystemTime {
             self.now
         }
@@ -380,14 +1086,14 @@ mod tests {
     #[test]
     fn parse_args_handles_single_dash() {
         // Apparently "-" should be treated as a directory name.
-        let parsed_info = super::parse_args(&["-"]).expect("parsing should succeed");
+        let parsed_info = super::parse_args(&["-"], &[]).expect("parsing should succeed");
         assert_eq!(parsed_info.paths, ["-"]);
     }
 
     #[test]
     fn parse_args_bad_flag() {
         //
-        let result = super::parse_args(&["-asdadsafsfsadcs"]);
+        let result = super::parse_args(&["-asdadsafsfsadcs"], &[]);
         if let Err(e) = result {
             assert_eq!(e.to_string(), "Unrecognized flag: '-asdadsafsfsadcs'");
         } else {
@@ -398,40 +1104,109 @@ mod tests {
     #[test]
     fn parse_optimize_flag() {
         let parsed_info =
-            super::parse_args(&["-O0", ".", "-print"]).expect("parsing should succeed");
+            super::parse_args(&["-O0", ".", "-print"], &[]).expect("parsing should succeed");
         assert_eq!(parsed_info.paths, ["."]);
     }
 
     #[test]
     fn parse_h_flag() {
-        let parsed_info = super::parse_args(&["-H"]).expect("parsing should succeed");
+        let parsed_info = super::parse_args(&["-H"], &[]).expect("parsing should succeed");
         assert_eq!(parsed_info.config.follow, Follow::Roots);
     }
 
     #[test]
     fn parse_l_flag() {
-        let parsed_info = super::parse_args(&["-L"]).expect("parsing should succeed");
+        let parsed_info = super::parse_args(&["-L"], &[]).expect("parsing should succeed");
         assert_eq!(parsed_info.config.follow, Follow::Always);
     }
 
     #[test]
     fn parse_p_flag() {
-        let parsed_info = super::parse_args(&["-P"]).expect("parsing should succeed");
+        let parsed_info = super::parse_args(&["-P"], &[]).expect("parsing should succeed");
         assert_eq!(parsed_info.config.follow, Follow::Never);
     }
 
+    #[test]
+    fn parse_bsd_flags() {
+        let parsed_info =
+            super::parse_args(&["-s", "-x", "."], &[]).expect("parsing should succeed");
+        assert_eq!(parsed_info.config.sort_order, Some(SortOrder::default()));
+        assert!(parsed_info.config.same_file_system);
+        assert_eq!(parsed_info.paths, ["."]);
+
+        // They're only options before the starting points
+        let parsed_info = super::parse_args(&["-E"], &[]).expect("parsing should succeed");
+        assert_eq!(
+            parsed_info.config.regex_type,
+            matchers::RegexType::PosixExtended
+        );
+
+        for flag in ["-s", "-x", "-E"] {
+            let result = super::parse_args(&[".", flag], &[]);
+            let message = result.err().expect("parsing should fail").to_string();
+            assert!(message.contains("before the starting points"), "{message}");
+        }
+    }
+
+    #[test]
+    fn parse_x_flag() {
+        let parsed_info = super::parse_args(&["-X", "-L"], &[]).expect("parsing should succeed");
+        assert!(parsed_info.config.xargs_safe);
+        assert_eq!(parsed_info.config.follow, Follow::Always);
+    }
+
     #[test]
     fn parse_flag_then_double_dash() {
-        super::parse_args(&["-P", "--"]).expect("parsing should succeed");
+        super::parse_args(&["-P", "--"], &[]).expect("parsing should succeed");
     }
 
     #[test]
     fn parse_double_dash_then_flag() {
-        super::parse_args(&["--", "-P"])
+        super::parse_args(&["--", "-P"], &[])
             .err()
             .expect("parsing should fail");
     }
 
+    #[test]
+    fn parse_double_dash_then_expression() {
+        // Primaries still start the expression
+        let parsed_info =
+            super::parse_args(&["--", "-name", "x"], &[]).expect("parsing should succeed");
+        assert_eq!(parsed_info.paths, ["."]);
+        assert_eq!(parsed_info.expression.to_string(), "-name x -a -print");
+
+        // But other names starting with - are paths, whether or not they exist
+        let parsed_info =
+            super::parse_args(&["--", "-x1", "-type", "f"], &[]).expect("parsing should succeed");
+        assert_eq!(parsed_info.paths, ["-x1"]);
+        assert_eq!(parsed_info.expression.to_string(), "-type f -a -print");
+        let parsed_info = super::parse_args(&["--", "-a", "-b", "!", "-type", "d"], &[])
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.paths, ["-a", "-b"]);
+        assert_eq!(parsed_info.expression.to_string(), "! -type d -a -print");
+
+        let parsed_info = super::parse_args(&["--", "./test_data/simple", "-"], &[])
+            .expect("parsing should succeed");
+        assert_eq!(parsed_info.paths, ["./test_data/simple", "-"]);
+    }
+
+    #[test]
+    fn parse_args_with_defaults() {
+        let parsed_info = super::parse_args(
+            &["-H", "dir", "-name", "x"],
+            &["-L", "-maxdepth", "1", "--color=never"],
+        )
+        .expect("parsing should succeed");
+        // The command line overrides the defaults
+        assert_eq!(parsed_info.config.follow, Follow::Roots);
+        assert_eq!(parsed_info.config.max_depth, 1);
+        assert_eq!(parsed_info.paths, ["dir"]);
+        assert_eq!(
+            parsed_info.expression.to_string(),
+            "-maxdepth 1 -a --color=never -a -name x -a -print"
+        );
+    }
+
     #[test]
     fn find_main_not_depth_first() {
         let deps = FakeDependencies::new();
@@ -507,6 +1282,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn search_with_config_builder() {
+        let deps = FakeDependencies::new();
+        let mut config = Config::default()
+            .sorted(true)
+            .min_depth(1)
+            .max_depth(2)
+            .depth_first(true);
+        let matcher = matchers::build_top_level_matcher(&["-type", "f"], &mut config).unwrap();
+
+        let rc = search(
+            &[&fix_up_slashes("./test_data/depth")],
+            &*matcher,
+            &config,
+            &deps,
+        );
+
+        assert_eq!(rc, 0);
+        assert_eq!(
+            deps.get_output_as_string(),
+            fix_up_slashes(
+                "./test_data/depth/1/f1\n\
+                 ./test_data/depth/f0\n"
+            )
+        );
+    }
+
     #[test]
     fn find_maxdepth_depth_first() {
         let deps = FakeDependencies::new();
@@ -1136,6 +1938,41 @@ mod tests {
             &deps,
         );
         assert_eq!(rc, 0);
+        assert_eq!(
+            deps.get_output_as_string(),
+            "./test_data/simple/subdir\n./test_data/simple/subdir/ABBBC\n"
+        );
+
+        // test uid comparisons, and numeric arguments to -user
+        for (arg, value, expected) in [
+            ("-uid", format!("+{uid}"), ""),
+            (
+                "-uid",
+                format!("-{}", uid + 1),
+                "./test_data/simple/subdir\n",
+            ),
+            ("-user", uid.to_string(), "./test_data/simple/subdir\n"),
+            (
+                "-user",
+                format!("-{}", uid + 1),
+                "./test_data/simple/subdir\n",
+            ),
+        ] {
+            let deps = FakeDependencies::new();
+            let rc = find_main(
+                &[
+                    "find",
+                    "./test_data/simple/subdir",
+                    "-maxdepth",
+                    "0",
+                    arg,
+                    &value,
+                ],
+                &deps,
+            );
+            assert_eq!(rc, 0);
+            assert_eq!(deps.get_output_as_string(), expected, "{arg} {value}");
+        }
 
         // test empty uid
         let deps = FakeDependencies::new();
@@ -1207,6 +2044,33 @@ mod tests {
         );
         assert_eq!(rc, 0);
 
+        // test gid comparisons, and numeric arguments to -group
+        for (arg, value, expected) in [
+            ("-gid", format!("+{gid}"), ""),
+            (
+                "-gid",
+                format!("-{}", gid + 1),
+                "./test_data/simple/subdir\n",
+            ),
+            ("-group", gid.to_string(), "./test_data/simple/subdir\n"),
+            ("-group", format!("+{gid}"), ""),
+        ] {
+            let deps = FakeDependencies::new();
+            let rc = find_main(
+                &[
+                    "find",
+                    "./test_data/simple/subdir",
+                    "-maxdepth",
+                    "0",
+                    arg,
+                    &value,
+                ],
+                &deps,
+            );
+            assert_eq!(rc, 0);
+            assert_eq!(deps.get_output_as_string(), expected, "{arg} {value}");
+        }
+
         // test empty gid
         let deps = FakeDependencies::new();
         let rc = find_main(&["find", "./test_data/simple/subdir", "-gid", ""], &deps);
@@ -1434,6 +2298,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_normalize_path() {
+        for (path, expected) in [
+            (".", "."),
+            ("./", "./"),
+            ("./.", "./"),
+            ("a", "a"),
+            (".//a/./b/", "./a/b/"),
+            ("a//b//", "a/b/"),
+            ("a/.", "a/"),
+            ("a/./.", "a/"),
+            ("/", "/"),
+            ("/.", "/"),
+            ("/tmp//", "/tmp/"),
+            ("a/../b", "a/../b"),
+            ("", ""),
+        ] {
+            assert_eq!(normalize_path(path), expected, "{path}");
+        }
+        assert!(matches!(normalize_path("a/b"), Cow::Borrowed(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_trailing_slash_follows_link() {
+        // A trailing slash makes the link resolve to the directory, even
+        // under -P, and the starting point keeps its spelling
+        for path in ["./test_data/links/link-d/", "./test_data/links//link-d//"] {
+            let deps = FakeDependencies::new();
+            let rc = find_main(&["find", "-P", path, "-printf", "%p %y\n"], &deps);
+
+            assert_eq!(rc, 0);
+            assert_eq!(
+                deps.get_output_as_string(),
+                format!("{path} d\n{path}test f\n")
+            );
+        }
+
+        let deps = FakeDependencies::new();
+        let rc = find_main(&["find", "-P", "./test_data/links/link-f/"], &deps);
+        assert_eq!(rc, 1);
+        assert!(deps.get_output_as_string().is_empty());
+    }
+
     #[test]
     fn find_fprintf() {
         let deps = FakeDependencies::new();
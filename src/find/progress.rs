@@ -0,0 +1,108 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The live status line for --progress.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use super::Stats;
+
+/// How often the status line is redrawn.
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// Keeps a line on a terminal up to date with how far the search has got, and
+/// out of the way of anything else that's written there.
+pub(crate) struct Progress {
+    /// When the line was last drawn, if it's still on screen.
+    drawn: Option<Instant>,
+    /// Whether the results go to the same terminal, in which case the line has
+    /// to be erased before anything might be printed.
+    shared: bool,
+}
+
+impl Progress {
+    pub fn new(shared: bool) -> Self {
+        Self {
+            drawn: None,
+            shared,
+        }
+    }
+
+    /// Gets ready for an entry to be matched, which may print it.
+    pub fn before_entry(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.shared {
+            self.erase(out)?;
+        }
+        Ok(())
+    }
+
+    /// Redraws the line if it's been long enough, or if it had to be erased.
+    pub fn after_entry(&mut self, stats: &Stats, out: &mut impl Write) -> io::Result<()> {
+        if self.drawn.is_some_and(|drawn| drawn.elapsed() < INTERVAL) {
+            return Ok(());
+        }
+        write!(
+            out,
+            "\r\x1b[K{} directories, {} entries scanned, {} matched",
+            stats.directories, stats.entries, stats.matches
+        )?;
+        out.flush()?;
+        self.drawn = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Erases the line, if it's on screen, e.g. to write an error or because
+    /// the search is over.
+    pub fn erase(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.drawn.take().is_some() {
+            write!(out, "\r\x1b[K")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress() {
+        let mut stats = Stats {
+            entries: 3,
+            directories: 1,
+            matches: 2,
+        };
+        let mut out = vec![];
+
+        let mut progress = Progress::new(false);
+        progress.before_entry(&mut out).unwrap();
+        progress.after_entry(&stats, &mut out).unwrap();
+        // Too soon to draw it again
+        stats.entries += 1;
+        progress.before_entry(&mut out).unwrap();
+        progress.after_entry(&stats, &mut out).unwrap();
+        progress.erase(&mut out).unwrap();
+        progress.erase(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\r\x1b[K1 directories, 3 entries scanned, 2 matched\r\x1b[K"
+        );
+
+        // Sharing the terminal with the results means erasing it every time
+        let mut out = vec![];
+        let mut progress = Progress::new(true);
+        for _ in 0..2 {
+            progress.before_entry(&mut out).unwrap();
+            progress.after_entry(&stats, &mut out).unwrap();
+        }
+        let line = "\r\x1b[K1 directories, 4 entries scanned, 2 matched";
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{line}\r\x1b[K{line}")
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The orders that -sorted can put the contents of each directory in.
+
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::str::FromStr;
+
+/// Something to compare directory entries by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    /// The bytes of the name, like `LC_ALL=C ls`.
+    Name,
+    /// The name, with runs of digits compared by their value like `ls -v`,
+    /// so that `file2` comes before `file10`.
+    Natural,
+    /// The size, smallest first.
+    Size,
+    /// The modification time, oldest first.
+    Mtime,
+}
+
+/// What -sorted=KEY[,KEY]... sorts by: each key in turn, with any ties left
+/// at the end broken by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortOrder(Vec<SortKey>);
+
+impl Default for SortOrder {
+    /// Plain -sorted, which sorts by name.
+    fn default() -> Self {
+        Self(vec![SortKey::Name])
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|key| match key {
+                "name" => Ok(SortKey::Name),
+                "natural" => Ok(SortKey::Natural),
+                "size" => Ok(SortKey::Size),
+                "mtime" => Ok(SortKey::Mtime),
+                _ => Err(format!(
+                    "invalid sort key '{key}': expected name, natural, size or mtime"
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// The parts of an entry's metadata that can be sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SortStat {
+    pub size: u64,
+    /// Nanoseconds since the epoch.
+    pub mtime: i128,
+}
+
+impl SortOrder {
+    /// Whether entries have to be stat()ed to be sorted in this order.
+    pub fn needs_stat(&self) -> bool {
+        self.0
+            .iter()
+            .any(|key| matches!(key, SortKey::Size | SortKey::Mtime))
+    }
+
+    /// Compares two entries by name and, where it's needed and we could get
+    /// it, metadata. Entries without metadata sort first.
+    pub(crate) fn compare(
+        &self,
+        (a, a_stat): (&OsStr, Option<&SortStat>),
+        (b, b_stat): (&OsStr, Option<&SortStat>),
+    ) -> Ordering {
+        self.0
+            .iter()
+            .map(|key| match key {
+                SortKey::Name => a.cmp(b),
+                SortKey::Natural => natural_cmp(a, b),
+                SortKey::Size => a_stat.map(|s| s.size).cmp(&b_stat.map(|s| s.size)),
+                SortKey::Mtime => a_stat.map(|s| s.mtime).cmp(&b_stat.map(|s| s.mtime)),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.cmp(b))
+    }
+}
+
+/// Compares names with runs of digits compared as numbers, e.g. `a2.txt`
+/// before `a10.txt`. Equal numbers with more leading zeros come later, so
+/// that different names never compare equal.
+pub fn natural_cmp(a: &OsStr, b: &OsStr) -> Ordering {
+    let (mut a, mut b) = (a.as_encoded_bytes(), b.as_encoded_bytes());
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (a_digits, a_rest) = split_digits(a);
+                let (b_digits, b_rest) = split_digits(b);
+                let a_value = trim_zeros(a_digits);
+                let b_value = trim_zeros(b_digits);
+                // Without leading zeros, longer numbers are bigger
+                let ordering = a_value
+                    .len()
+                    .cmp(&b_value.len())
+                    .then_with(|| a_value.cmp(b_value))
+                    .then_with(|| a_digits.len().cmp(&b_digits.len()));
+                if ordering.is_ne() {
+                    return ordering;
+                }
+                (a, b) = (a_rest, b_rest);
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                (a, b) = (&a[1..], &b[1..]);
+            }
+        }
+    }
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let end = s
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let start = digits
+        .iter()
+        .position(|&b| b != b'0')
+        .unwrap_or(digits.len());
+    &digits[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted<'a>(order: &SortOrder, entries: &[(&'a str, SortStat)]) -> Vec<&'a str> {
+        let mut entries = entries.to_vec();
+        entries
+            .sort_by(|a, b| order.compare((a.0.as_ref(), Some(&a.1)), (b.0.as_ref(), Some(&b.1))));
+        entries.into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn parse_sort_order() {
+        assert_eq!("name".parse(), Ok(SortOrder::default()));
+        assert_eq!(
+            "size,natural".parse(),
+            Ok(SortOrder(vec![SortKey::Size, SortKey::Natural]))
+        );
+        assert!(!SortOrder::default().needs_stat());
+        assert!("natural,mtime".parse::<SortOrder>().unwrap().needs_stat());
+        for arg in ["", "Name", "size,", "atime"] {
+            assert!(arg.parse::<SortOrder>().is_err(), "{arg}");
+        }
+    }
+
+    #[test]
+    fn natural_order() {
+        let cmp = |a: &str, b: &str| natural_cmp(a.as_ref(), b.as_ref());
+        assert_eq!(cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(cmp("file10", "file9"), Ordering::Greater);
+        assert_eq!(cmp("file10", "file10"), Ordering::Equal);
+        assert_eq!(cmp("file1", "file01"), Ordering::Less);
+        assert_eq!(cmp("file007", "file8"), Ordering::Less);
+        assert_eq!(cmp("a2b10", "a2b9"), Ordering::Greater);
+        assert_eq!(cmp("a", "a1"), Ordering::Less);
+        assert_eq!(cmp("a1", "b"), Ordering::Less);
+        assert_eq!(
+            cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn sort_by_keys() {
+        let stat = |size, mtime| SortStat { size, mtime };
+        let entries = [
+            ("file10", stat(1, 30)),
+            ("file2", stat(2, 10)),
+            ("file1", stat(2, 20)),
+            ("b", stat(0, 20)),
+        ];
+
+        let order = |s: &str| s.parse::<SortOrder>().unwrap();
+        assert_eq!(
+            sorted(&order("name"), &entries),
+            ["b", "file1", "file10", "file2"]
+        );
+        assert_eq!(
+            sorted(&order("natural"), &entries),
+            ["b", "file1", "file2", "file10"]
+        );
+        // Ties are broken by name
+        assert_eq!(
+            sorted(&order("size"), &entries),
+            ["b", "file10", "file1", "file2"]
+        );
+        assert_eq!(
+            sorted(&order("size,mtime"), &entries),
+            ["b", "file10", "file2", "file1"]
+        );
+        assert_eq!(
+            sorted(&order("mtime"), &entries),
+            ["file2", "b", "file1", "file10"]
+        );
+
+        // Entries we couldn't stat come first
+        let order = order("size");
+        let big = stat(100, 0);
+        assert_eq!(
+            order.compare(("a".as_ref(), Some(&big)), ("b".as_ref(), None)),
+            Ordering::Greater
+        );
+    }
+}
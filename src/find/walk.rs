@@ -0,0 +1,1388 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Directory traversal.
+//!
+//! [Walker] walks a directory tree, producing a [WalkEntry] for everything it
+//! finds. It supports the traversal options find needs: pre- or post-order
+//! traversal, depth limits, staying on one file system, following symbolic
+//! links (with loop detection), sorting, and pruning the current directory.
+//! Pre-order traversal can also be breadth-first, so that shallower entries
+//! all come before deeper ones.
+//!
+//! It also implements the traditional "leaf optimization": on Unix, a
+//! directory's link count is two more than the number of subdirectories it
+//! has. Once we've seen that many subdirectories, the remaining entries can't
+//! be directories, so there's no need to stat() them on file systems that
+//! don't report file types from readdir(). -noleaf turns this off, for file
+//! systems where link counts don't work that way.
+//!
+//! The walk can also be throttled, to leave some disk bandwidth for everyone
+//! else during big scans: either by limiting how fast it reads directories,
+//! or (on Linux) by only doing I/O when the disk is otherwise idle.
+//!
+//! On Unix, directories are opened relative to their parent's file
+//! descriptor, and entries remember the directory they were found in. This
+//! lets actions like -delete and -execdir operate on the directory we actually
+//! read, even if something higher up the tree is renamed or replaced by a
+//! symlink in the meantime.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::{HashSet, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::fs::Metadata;
+use std::io;
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::vec;
+
+#[cfg(unix)]
+use nix::{dir::Dir, fcntl::OFlag, sys::stat::Mode};
+
+use super::matchers::{count_stat_call, FileType, Follow, WalkEntry, WalkError};
+use super::sort::{SortOrder, SortStat};
+
+/// The most directories we keep open at once. Deeper than this, the
+/// outermost directories are closed and their remaining contents are accessed
+/// by path instead.
+const MAX_OPEN_DIRS: usize = 128;
+
+/// How a walk is slowed down so it doesn't hog the disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Throttle {
+    /// Read directories at no more than this many bytes a second.
+    Rate(f64),
+    /// Only do I/O when nothing else wants the disk, with
+    /// [set_idle_io_priority].
+    Idle,
+}
+
+impl FromStr for Throttle {
+    type Err = String;
+
+    /// Parses `idle`, or a rate in MB/s, which may have a fractional part.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "idle" {
+            return Ok(Self::Idle);
+        }
+        match s.parse::<f64>() {
+            Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(Self::Rate(rate * 1e6)),
+            _ => Err(format!(
+                "invalid throttle '{s}': expected a rate in MB/s or 'idle'"
+            )),
+        }
+    }
+}
+
+/// Puts the whole process in the idle I/O scheduling class, so its reads
+/// only get the disk when nothing else is using it.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_idle_io_priority() -> io::Result<()> {
+    use uucore::libc::{syscall, SYS_ioprio_set};
+
+    const IOPRIO_WHO_PROCESS: i32 = 1;
+    const IOPRIO_CLASS_IDLE: i32 = 3;
+    const IOPRIO_CLASS_SHIFT: i32 = 13;
+
+    // SAFETY: ioprio_set only reads its integer arguments
+    let ret = unsafe {
+        syscall(
+            SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Puts the whole process in the idle I/O scheduling class, which only Linux
+/// has.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set_idle_io_priority() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "idle I/O priority is not supported on this platform",
+    ))
+}
+
+/// Keeps the reading of directories to an average number of bytes a second,
+/// by sleeping whenever it gets ahead.
+struct RateLimiter {
+    bytes_per_sec: f64,
+    start: Instant,
+    bytes: f64,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_sec: f64) -> Self {
+        Self {
+            bytes_per_sec,
+            start: Instant::now(),
+            bytes: 0.0,
+        }
+    }
+
+    /// Counts `bytes` as read, then waits until they're within the limit.
+    fn consume(&mut self, bytes: u64) {
+        self.bytes += bytes as f64;
+        let due = Duration::from_secs_f64(self.bytes / self.bytes_per_sec);
+        if let Some(wait) = due.checked_sub(self.start.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Roughly how many bytes reading a directory entry takes: the size of a
+/// Linux dirent64, which other systems aren't far off.
+fn dirent_size(name: &OsStr) -> u64 {
+    (19 + name.len() as u64 + 1).next_multiple_of(8)
+}
+
+/// Identifies a file uniquely, for loop detection and -xdev.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct FileId {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileId {
+    #[cfg(unix)]
+    fn new(_path: &Path, meta: &Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        Some(Self {
+            dev: meta.dev(),
+            ino: meta.ino(),
+        })
+    }
+
+    #[cfg(windows)]
+    fn new(path: &Path, _meta: &Metadata) -> Option<Self> {
+        let handle = winapi_util::Handle::from_path_any(path).ok()?;
+        let info = winapi_util::file::information(&handle).ok()?;
+        Some(Self {
+            dev: info.volume_serial_number(),
+            ino: info.file_index(),
+        })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn new(_path: &Path, _meta: &Metadata) -> Option<Self> {
+        None
+    }
+}
+
+/// The directories that have been read, which can be shared between walkers
+/// so that overlapping starting points are only searched once.
+#[derive(Clone, Debug, Default)]
+pub struct VisitedDirs(Rc<RefCell<HashSet<FileId>>>);
+
+impl VisitedDirs {
+    fn contains(&self, id: FileId) -> bool {
+        self.0.borrow().contains(&id)
+    }
+
+    fn insert(&self, id: FileId) {
+        self.0.borrow_mut().insert(id);
+    }
+}
+
+/// An open directory, which entries found in it can be accessed relative to.
+#[cfg(unix)]
+#[derive(Clone, Debug)]
+pub(crate) struct DirFd(Rc<Dir>);
+
+#[cfg(unix)]
+impl AsRawFd for DirFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+/// An open directory. Only Unix supports opening directories relative to each
+/// other, so elsewhere we always use paths.
+#[cfg(not(unix))]
+#[derive(Clone, Debug)]
+pub(crate) enum DirFd {}
+
+/// An entry read from a directory.
+struct Child {
+    name: OsString,
+    /// The type of the entry, if the directory told us.
+    file_type: Option<FileType>,
+}
+
+/// A directory above the one being read breadth-first, for detecting loops
+/// once it's no longer on the stack.
+struct Ancestor {
+    id: FileId,
+    path: PathBuf,
+    parent: Option<Rc<Ancestor>>,
+}
+
+/// A directory we are in the middle of reading.
+struct Frame {
+    /// The remaining entries in this directory.
+    children: vec::IntoIter<io::Result<Child>>,
+    /// The open directory, unless we had to close it.
+    dir: Option<DirFd>,
+    /// The path of this directory.
+    path: PathBuf,
+    /// The depth of this directory.
+    depth: usize,
+    /// The identity of this directory, if we need it to detect loops.
+    id: Option<FileId>,
+    /// For post-order traversal, the entry for the directory itself, to yield
+    /// after its contents.
+    entry: Option<WalkEntry>,
+    /// The number of subdirectories we've found so far.
+    subdirs_seen: u64,
+    /// The link count of this directory, if it tells us how many
+    /// subdirectories it has. Only fetched if the leaf optimization is needed.
+    links: OnceCell<Option<u64>>,
+    /// For breadth-first traversal, this directory and the ones above it.
+    ancestors: Option<Rc<Ancestor>>,
+}
+
+impl Frame {
+    /// Check whether we know `child` can't be a directory, because we've
+    /// already seen all of this directory's subdirectories.
+    fn is_leaf(&self, child: &io::Result<Child>) -> bool {
+        let Ok(Child {
+            file_type: None, ..
+        }) = child
+        else {
+            // We already know the type, so there's nothing to save
+            return false;
+        };
+
+        self.links
+            .get_or_init(|| self.link_count())
+            .is_some_and(|links| self.subdirs_seen + 2 >= links)
+    }
+
+    /// Get the link count of this directory, if it's meaningful.
+    #[cfg(unix)]
+    fn link_count(&self) -> Option<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        count_stat_call();
+        let links = match &self.dir {
+            Some(dir) => nix::sys::stat::fstat(dir.as_raw_fd()).ok()?.st_nlink as u64,
+            None => self.path.metadata().ok()?.nlink(),
+        };
+        // Some file systems (e.g. btrfs) always report 1 for directories
+        if links >= 2 {
+            Some(links)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn link_count(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// A directory that has been yielded (in pre-order), but not yet read.
+struct Pending {
+    path: PathBuf,
+    depth: usize,
+    id: Option<FileId>,
+    /// For breadth-first traversal, the directories above this one.
+    ancestors: Option<Rc<Ancestor>>,
+}
+
+/// An iterator over a directory tree.
+pub struct Walker {
+    root: Option<PathBuf>,
+    contents_first: bool,
+    breadth_first: bool,
+    min_depth: usize,
+    max_depth: usize,
+    same_file_system: bool,
+    follow: Follow,
+    sort: Option<SortOrder>,
+    leaf_optimization: bool,
+    file_types: bool,
+    path_prefixes: Option<Vec<String>>,
+    rate_limiter: Option<RateLimiter>,
+    visited: Option<VisitedDirs>,
+    /// The device of the root, for -xdev.
+    root_dev: Option<u64>,
+    /// The directories we're currently reading, innermost last.
+    stack: Vec<Frame>,
+    /// The last directory yielded in pre-order, which we'll descend into on
+    /// the next call to next() unless skip_current_dir() is called.
+    pending: Option<Pending>,
+    /// For breadth-first traversal, the directories still to be read once
+    /// the current one is finished.
+    queue: VecDeque<Pending>,
+}
+
+impl Walker {
+    /// Create a walker for the tree rooted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+            contents_first: false,
+            breadth_first: false,
+            min_depth: 0,
+            max_depth: usize::MAX,
+            same_file_system: false,
+            follow: Follow::Never,
+            sort: None,
+            leaf_optimization: true,
+            file_types: true,
+            path_prefixes: None,
+            rate_limiter: None,
+            visited: None,
+            root_dev: None,
+            stack: Vec::new(),
+            pending: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Yield directories after their contents, rather than before.
+    pub fn contents_first(mut self, contents_first: bool) -> Self {
+        self.contents_first = contents_first;
+        self
+    }
+
+    /// Read directories in the order they were found, so that everything at
+    /// one depth is yielded before anything deeper. This has no effect for
+    /// post-order traversal, which always goes depth-first.
+    pub fn breadth_first(mut self, breadth_first: bool) -> Self {
+        self.breadth_first = breadth_first;
+        self
+    }
+
+    /// Don't yield entries less than this deep.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Don't descend more than this deep.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Don't descend into directories on other file systems.
+    pub fn same_file_system(mut self, same_file_system: bool) -> Self {
+        self.same_file_system = same_file_system;
+        self
+    }
+
+    /// Set the symlink following mode.
+    pub fn follow(mut self, follow: Follow) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// Skip directories that have already been read, by this walker or any
+    /// other sharing the same set, along with their contents.
+    pub fn skip_visited(mut self, visited: Option<VisitedDirs>) -> Self {
+        self.visited = visited;
+        self
+    }
+
+    /// Sort the contents of each directory in the given order, if any.
+    pub fn sort_order(mut self, order: Option<SortOrder>) -> Self {
+        self.sort = order;
+        self
+    }
+
+    /// Use directory link counts to avoid stat()ing entries that can't be
+    /// directories. This is on by default, but is never used when following
+    /// all symlinks, since any entry might be a link to a directory.
+    pub fn leaf_optimization(mut self, leaf_optimization: bool) -> Self {
+        self.leaf_optimization = leaf_optimization;
+        self
+    }
+
+    /// Whether whoever's looking at the entries needs their types. If not,
+    /// entries too deep to descend into aren't stat()ed just to find out what
+    /// they are when reading the directory doesn't tell us.
+    pub fn file_types(mut self, needed: bool) -> Self {
+        self.file_types = needed;
+        self
+    }
+
+    /// Only read directories whose contents could have a path starting with
+    /// one of these prefixes.
+    pub fn path_prefixes(mut self, prefixes: Option<Vec<String>>) -> Self {
+        self.path_prefixes = prefixes;
+        self
+    }
+
+    /// Slow down to read directories at no more than this many bytes a
+    /// second, on average.
+    pub fn rate_limit(mut self, bytes_per_sec: Option<f64>) -> Self {
+        self.rate_limiter = bytes_per_sec.map(RateLimiter::new);
+        self
+    }
+
+    /// Don't descend into the most recently yielded directory. This has no
+    /// effect for post-order traversal, or if the last entry wasn't a
+    /// directory.
+    pub fn skip_current_dir(&mut self) {
+        self.pending = None;
+    }
+
+    /// Whether we need to know the identity of directories we descend into.
+    fn needs_ids(&self) -> bool {
+        self.same_file_system || self.follow != Follow::Never || self.visited.is_some()
+    }
+
+    /// Whether anything in a directory could have a path starting with one of
+    /// the prefixes we were given.
+    fn may_have_prefix_below(&self, dir: &Path) -> bool {
+        let Some(prefixes) = &self.path_prefixes else {
+            return true;
+        };
+        let mut below = dir.to_string_lossy().into_owned();
+        if !below.ends_with(std::path::is_separator) {
+            below.push(std::path::MAIN_SEPARATOR);
+        }
+        prefixes
+            .iter()
+            .any(|prefix| prefix.starts_with(&below) || below.starts_with(prefix.as_str()))
+    }
+
+    /// Whether a directory has already been read, and should be skipped.
+    fn was_visited(&self, id: Option<FileId>) -> bool {
+        match (&self.visited, id) {
+            (Some(visited), Some(id)) => visited.contains(id),
+            _ => false,
+        }
+    }
+
+    /// Handle the root path.
+    fn visit_root(&mut self, root: PathBuf) -> Option<Result<WalkEntry, WalkError>> {
+        let entry = WalkEntry::new(root, 0, self.follow);
+        let meta = match entry.metadata() {
+            Ok(meta) => meta.clone(),
+            Err(e) => return Some(Err(e.or_path(entry.path(), 0))),
+        };
+
+        let id = if self.needs_ids() {
+            FileId::new(entry.path(), &meta)
+        } else {
+            None
+        };
+        self.root_dev = id.map(|id| id.dev);
+        if meta.is_dir() && self.max_depth > 0 && self.was_visited(id) {
+            return None;
+        }
+
+        self.visit(entry, &meta, id)
+    }
+
+    /// Handle an entry read from a directory.
+    fn visit_child(
+        &mut self,
+        child: io::Result<Child>,
+        parent: &Path,
+        dir: Option<DirFd>,
+        depth: usize,
+        is_leaf: bool,
+    ) -> Option<Result<WalkEntry, WalkError>> {
+        let child = match child {
+            Ok(child) => child,
+            Err(e) => return Some(Err(WalkError::for_path(&e, parent, depth - 1))),
+        };
+
+        let path = parent.join(&child.name);
+        // Either not a directory, or too deep to descend into with nobody
+        // wanting to know its type, so leave working out what it is until
+        // someone asks. Links we're following are still stat()ed, since
+        // that's how loops and other errors are found.
+        let defer = child.file_type.is_none()
+            && (is_leaf
+                || (!self.file_types
+                    && depth >= self.max_depth
+                    && !self.follow.follow_at_depth(depth)));
+        if defer {
+            let entry = WalkEntry::new(path, depth, self.follow);
+            let entry = match dir {
+                Some(dir) => entry.with_parent(dir),
+                None => entry,
+            };
+            return self.yield_entry(entry);
+        }
+
+        let file_type = match child.file_type {
+            Some(file_type) => file_type,
+            None => match lstat_type(dir.as_ref(), &path) {
+                Ok(file_type) => file_type,
+                Err(e) => return Some(Err(WalkError::for_path(&e, path, depth))),
+            },
+        };
+        if file_type.is_dir() {
+            if let Some(frame) = self.stack.last_mut() {
+                frame.subdirs_seen += 1;
+            }
+        }
+
+        let mut meta = None;
+        let entry = if file_type.is_symlink() && self.follow.follow_at_depth(depth) {
+            count_stat_call();
+            match path.metadata() {
+                Ok(m) => {
+                    let entry =
+                        WalkEntry::child(path, depth, self.follow, m.file_type().into(), true);
+                    meta = Some(m.clone());
+                    entry.with_metadata(m)
+                }
+                Err(e) if WalkError::from(&e).is_not_found() => {
+                    WalkEntry::broken_symlink(path, depth)
+                }
+                Err(e) => return Some(Err(WalkError::for_path(&e, path, depth))),
+            }
+        } else {
+            let is_symlink = file_type.is_symlink();
+            WalkEntry::child(path, depth, self.follow, file_type, is_symlink)
+        };
+        let entry = match dir {
+            Some(dir) => entry.with_parent(dir),
+            None => entry,
+        };
+
+        if !entry.file_type().is_dir() || depth >= self.max_depth {
+            return self.yield_entry(entry);
+        }
+
+        // We're going to descend, so find out what we're descending into
+        let id = if self.needs_ids() {
+            let meta = match meta {
+                Some(meta) => meta,
+                None => match entry.metadata() {
+                    Ok(meta) => meta.clone(),
+                    Err(e) => return Some(Err(e.or_path(entry.path(), depth))),
+                },
+            };
+            FileId::new(entry.path(), &meta)
+        } else {
+            None
+        };
+
+        if let Some(id) = id {
+            if self.same_file_system && Some(id.dev) != self.root_dev {
+                return self.yield_entry(entry);
+            }
+
+            if self.follow != Follow::Never {
+                if let Some(ancestor) = self.find_ancestor(id) {
+                    return Some(Err(WalkError::file_system_loop(
+                        entry.into_path(),
+                        depth,
+                        ancestor,
+                    )));
+                }
+            }
+        }
+        if self.was_visited(id) {
+            return None;
+        }
+
+        self.descend_or_yield(entry, id)
+    }
+
+    /// Find the path of the directory being read or one above it with the
+    /// given identity, if there is one.
+    fn find_ancestor(&self, id: FileId) -> Option<PathBuf> {
+        if let Some(frame) = self.stack.iter().find(|frame| frame.id == Some(id)) {
+            return Some(frame.path.clone());
+        }
+        // Breadth-first, only the current directory is on the stack
+        let mut ancestor = self.stack.last()?.ancestors.as_deref();
+        while let Some(a) = ancestor {
+            if a.id == id {
+                return Some(a.path.clone());
+            }
+            ancestor = a.parent.as_deref();
+        }
+        None
+    }
+
+    /// Start descending into a directory, or yield it for pre-order traversal.
+    fn visit(
+        &mut self,
+        entry: WalkEntry,
+        meta: &Metadata,
+        id: Option<FileId>,
+    ) -> Option<Result<WalkEntry, WalkError>> {
+        if !meta.is_dir() || entry.depth() >= self.max_depth {
+            return self.yield_entry(entry);
+        }
+
+        self.descend_or_yield(entry, id)
+    }
+
+    fn descend_or_yield(
+        &mut self,
+        entry: WalkEntry,
+        id: Option<FileId>,
+    ) -> Option<Result<WalkEntry, WalkError>> {
+        if !self.may_have_prefix_below(entry.path()) {
+            return self.yield_entry(entry);
+        }
+
+        let path = entry.path().to_owned();
+        let depth = entry.depth();
+        if self.contents_first {
+            self.push(path, depth, id, None, Some(entry))
+        } else {
+            let ancestors = if self.breadth_first {
+                self.stack.last().and_then(|frame| frame.ancestors.clone())
+            } else {
+                None
+            };
+            self.pending = Some(Pending {
+                path,
+                depth,
+                id,
+                ancestors,
+            });
+            self.yield_entry(entry)
+        }
+    }
+
+    /// Start reading a directory.
+    fn push(
+        &mut self,
+        path: PathBuf,
+        depth: usize,
+        id: Option<FileId>,
+        ancestors: Option<Rc<Ancestor>>,
+        entry: Option<WalkEntry>,
+    ) -> Option<Result<WalkEntry, WalkError>> {
+        if let (Some(visited), Some(id)) = (&self.visited, id) {
+            visited.insert(id);
+        }
+        // The root may be a symlink we were asked to follow, but below that
+        // we must not follow a symlink that has replaced a directory.
+        let follow = depth == 0 || self.follow.follow_at_depth(depth);
+        let result = loop {
+            let parent = self.stack.last().and_then(|frame| frame.dir.as_ref());
+            match read_dir(parent, &path, follow) {
+                Err(e) if is_out_of_fds(&e) && self.close_outermost_dir() => continue,
+                result => break result,
+            }
+        };
+        if let (Some(limiter), Ok((_, children))) = (&mut self.rate_limiter, &result) {
+            let bytes = children
+                .iter()
+                .flatten()
+                .map(|child| dirent_size(&child.name))
+                .sum();
+            limiter.consume(bytes);
+        }
+        let (dir, mut children, error) = match result {
+            Ok((dir, children)) => (dir, children, None),
+            Err(e) => (None, vec![], Some(WalkError::for_path(&e, &path, depth))),
+        };
+        if let Some(order) = &self.sort {
+            let follow = self.follow.follow_at_depth(depth + 1);
+            sort_children(&mut children, order, |name| {
+                sort_stat(dir.as_ref(), &path.join(name), follow)
+            });
+        }
+
+        let ancestors = match id {
+            Some(id) if self.breadth_first && !self.contents_first => Some(Rc::new(Ancestor {
+                id,
+                path: path.clone(),
+                parent: ancestors,
+            })),
+            _ => ancestors,
+        };
+        self.stack.push(Frame {
+            children: children.into_iter(),
+            dir,
+            path,
+            depth,
+            id,
+            entry,
+            subdirs_seen: 0,
+            links: OnceCell::new(),
+            ancestors,
+        });
+        self.limit_open_dirs();
+
+        error.map(Err)
+    }
+
+    /// Close the outermost directory if we have too many open.
+    fn limit_open_dirs(&mut self) {
+        if self.stack.len() > MAX_OPEN_DIRS {
+            self.close_dir(self.stack.len() - MAX_OPEN_DIRS - 1);
+        }
+    }
+
+    /// Close the outermost directory that's still open, returning whether
+    /// there was one.
+    fn close_outermost_dir(&mut self) -> bool {
+        match self.stack.iter().position(|frame| frame.dir.is_some()) {
+            Some(i) => {
+                self.close_dir(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Close the directory for a frame, falling back to paths for its
+    /// remaining contents.
+    fn close_dir(&mut self, i: usize) {
+        self.stack[i].dir = None;
+        // The directory entry for the next frame down refers to it too
+        if let Some(entry) = self.stack.get_mut(i + 1).and_then(|f| f.entry.as_mut()) {
+            entry.clear_parent();
+        }
+    }
+
+    /// Yield an entry, unless it's too shallow.
+    fn yield_entry(&self, entry: WalkEntry) -> Option<Result<WalkEntry, WalkError>> {
+        if entry.depth() >= self.min_depth {
+            Some(Ok(entry))
+        } else {
+            None
+        }
+    }
+}
+
+/// Check whether an error means we have too many files open.
+fn is_out_of_fds(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        use nix::errno::Errno;
+        let errno = e.raw_os_error().map(Errno::from_raw);
+        matches!(errno, Some(Errno::EMFILE | Errno::ENFILE))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = e;
+        false
+    }
+}
+
+/// Open a directory, relative to its parent if possible.
+#[cfg(unix)]
+fn open_dir(parent: Option<&DirFd>, path: &Path, follow: bool) -> io::Result<DirFd> {
+    let mut flags = OFlag::O_RDONLY | OFlag::O_DIRECTORY | OFlag::O_CLOEXEC;
+    if !follow {
+        flags |= OFlag::O_NOFOLLOW;
+    }
+
+    let dir = match (parent, path.file_name()) {
+        (Some(parent), Some(name)) => {
+            Dir::openat(Some(parent.as_raw_fd()), name, flags, Mode::empty())
+        }
+        _ => Dir::open(path, flags, Mode::empty()),
+    }?;
+    Ok(DirFd(Rc::new(dir)))
+}
+
+/// Read all the entries of a directory.
+#[cfg(unix)]
+fn read_dir(
+    parent: Option<&DirFd>,
+    path: &Path,
+    follow: bool,
+) -> io::Result<(Option<DirFd>, Vec<io::Result<Child>>)> {
+    use nix::dir::Type;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut dir = open_dir(parent, path, follow)?;
+    // Nothing else can have a reference to the directory yet
+    let stream = Rc::get_mut(&mut dir.0).expect("directory should not be shared");
+
+    let mut children = Vec::new();
+    for entry in stream.iter() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                // Reading again would likely just fail again
+                children.push(Err(e.into()));
+                break;
+            }
+        };
+
+        let name = std::ffi::OsStr::from_bytes(entry.file_name().to_bytes());
+        if name == "." || name == ".." {
+            continue;
+        }
+
+        let file_type = entry.file_type().map(|t| match t {
+            Type::Fifo => FileType::Fifo,
+            Type::CharacterDevice => FileType::CharDevice,
+            Type::Directory => FileType::Directory,
+            Type::BlockDevice => FileType::BlockDevice,
+            Type::File => FileType::Regular,
+            Type::Symlink => FileType::Symlink,
+            Type::Socket => FileType::Socket,
+        });
+        children.push(Ok(Child {
+            name: name.to_owned(),
+            file_type,
+        }));
+    }
+
+    Ok((Some(dir), children))
+}
+
+/// Read all the entries of a directory.
+#[cfg(not(unix))]
+fn read_dir(
+    _parent: Option<&DirFd>,
+    path: &Path,
+    _follow: bool,
+) -> io::Result<(Option<DirFd>, Vec<io::Result<Child>>)> {
+    let children = std::fs::read_dir(path)?
+        .map(|entry| {
+            entry.map(|entry| Child {
+                name: entry.file_name(),
+                file_type: entry.file_type().ok().map(FileType::from),
+            })
+        })
+        .collect();
+    Ok((None, children))
+}
+
+/// Sort the entries of a directory, getting the metadata for them with
+/// `stat` if the order needs it.
+fn sort_children(
+    children: &mut Vec<io::Result<Child>>,
+    order: &SortOrder,
+    stat: impl Fn(&OsStr) -> Option<SortStat>,
+) {
+    let mut keyed: Vec<_> = children
+        .drain(..)
+        .map(|child| {
+            let stat = match &child {
+                Ok(child) if order.needs_stat() => stat(&child.name),
+                _ => None,
+            };
+            (child, stat)
+        })
+        .collect();
+    // Errors sort first, without any particular order
+    keyed.sort_by(|(a, a_stat), (b, b_stat)| match (a, b) {
+        (Ok(a), Ok(b)) => order.compare((&a.name, a_stat.as_ref()), (&b.name, b_stat.as_ref())),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Less,
+        (Err(_), Err(_)) => std::cmp::Ordering::Equal,
+    });
+    children.extend(keyed.into_iter().map(|(child, _)| child));
+}
+
+/// Get the size and modification time of an entry for sorting, following
+/// symlinks if asked to, unless they're broken.
+fn sort_stat(dir: Option<&DirFd>, path: &Path, follow: bool) -> Option<SortStat> {
+    count_stat_call();
+
+    #[cfg(unix)]
+    if let (Some(dir), Some(name)) = (dir, path.file_name()) {
+        use nix::fcntl::AtFlags;
+        use nix::sys::stat::fstatat;
+
+        let stat = |flags| fstatat(Some(dir.as_raw_fd()), name, flags);
+        let stat = if follow {
+            stat(AtFlags::empty()).or_else(|_| stat(AtFlags::AT_SYMLINK_NOFOLLOW))
+        } else {
+            stat(AtFlags::AT_SYMLINK_NOFOLLOW)
+        };
+        return stat.ok().map(|stat| SortStat {
+            size: stat.st_size as u64,
+            mtime: i128::from(stat.st_mtime) * 1_000_000_000 + i128::from(stat.st_mtime_nsec),
+        });
+    }
+
+    #[cfg(not(unix))]
+    let _ = dir;
+
+    let metadata = if follow {
+        path.metadata().or_else(|_| path.symlink_metadata())
+    } else {
+        path.symlink_metadata()
+    };
+    metadata.ok().map(|metadata| SortStat {
+        size: metadata.len(),
+        mtime: metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |time| time.as_nanos() as i128),
+    })
+}
+
+/// Get the type of a file without following symlinks, for when reading the
+/// directory didn't tell us.
+fn lstat_type(dir: Option<&DirFd>, path: &Path) -> io::Result<FileType> {
+    count_stat_call();
+
+    #[cfg(unix)]
+    if let (Some(dir), Some(name)) = (dir, path.file_name()) {
+        use nix::fcntl::AtFlags;
+        use nix::sys::stat::{fstatat, SFlag};
+
+        let stat = fstatat(Some(dir.as_raw_fd()), name, AtFlags::AT_SYMLINK_NOFOLLOW)?;
+        let format = SFlag::from_bits_truncate(stat.st_mode) & SFlag::S_IFMT;
+        return Ok(match format {
+            SFlag::S_IFIFO => FileType::Fifo,
+            SFlag::S_IFCHR => FileType::CharDevice,
+            SFlag::S_IFDIR => FileType::Directory,
+            SFlag::S_IFBLK => FileType::BlockDevice,
+            SFlag::S_IFREG => FileType::Regular,
+            SFlag::S_IFLNK => FileType::Symlink,
+            SFlag::S_IFSOCK => FileType::Socket,
+            _ => FileType::Unknown,
+        });
+    }
+
+    #[cfg(not(unix))]
+    let _ = dir;
+
+    Ok(path.symlink_metadata()?.file_type().into())
+}
+
+impl Iterator for Walker {
+    type Item = Result<WalkEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            if let Some(result) = self.visit_root(root) {
+                return Some(result);
+            }
+        }
+
+        loop {
+            if let Some(pending) = self.pending.take() {
+                if self.breadth_first {
+                    // Read it once everything before it has been
+                    self.queue.push_back(pending);
+                } else if let Some(result) = self.push(
+                    pending.path,
+                    pending.depth,
+                    pending.id,
+                    pending.ancestors,
+                    None,
+                ) {
+                    return Some(result);
+                }
+            }
+
+            let Some(frame) = self.stack.last_mut() else {
+                let next = self.queue.pop_front()?;
+                if let Some(result) =
+                    self.push(next.path, next.depth, next.id, next.ancestors, None)
+                {
+                    return Some(result);
+                }
+                continue;
+            };
+            match frame.children.next() {
+                Some(child) => {
+                    let is_leaf = self.leaf_optimization
+                        && self.follow != Follow::Always
+                        && frame.is_leaf(&child);
+                    let parent = frame.path.clone();
+                    let dir = frame.dir.clone();
+                    let depth = frame.depth + 1;
+                    if let Some(result) = self.visit_child(child, &parent, dir, depth, is_leaf) {
+                        return Some(result);
+                    }
+                }
+                None => {
+                    let frame = self.stack.pop()?;
+                    if let Some(result) = frame.entry.and_then(|e| self.yield_entry(e)) {
+                        return Some(result);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find::tests::fix_up_slashes;
+
+    fn walk(walker: Walker) -> Vec<String> {
+        walker
+            .map(|result| result.unwrap().path().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn pre_order() {
+        let paths = walk(
+            Walker::new(fix_up_slashes("test_data/simple")).sort_order(Some(SortOrder::default())),
+        );
+        assert_eq!(
+            paths,
+            [
+                "test_data/simple",
+                "test_data/simple/abbbc",
+                "test_data/simple/subdir",
+                "test_data/simple/subdir/ABBBC",
+            ]
+            .map(fix_up_slashes)
+        );
+    }
+
+    #[test]
+    fn post_order() {
+        let paths = walk(
+            Walker::new(fix_up_slashes("test_data/simple"))
+                .sort_order(Some(SortOrder::default()))
+                .contents_first(true),
+        );
+        assert_eq!(
+            paths,
+            [
+                "test_data/simple/abbbc",
+                "test_data/simple/subdir/ABBBC",
+                "test_data/simple/subdir",
+                "test_data/simple",
+            ]
+            .map(fix_up_slashes)
+        );
+    }
+
+    #[test]
+    fn depth_limits() {
+        let paths = walk(
+            Walker::new(fix_up_slashes("test_data/simple"))
+                .sort_order(Some(SortOrder::default()))
+                .min_depth(1)
+                .max_depth(1),
+        );
+        assert_eq!(
+            paths,
+            ["test_data/simple/abbbc", "test_data/simple/subdir"].map(fix_up_slashes)
+        );
+    }
+
+    #[test]
+    fn skip_current_dir() {
+        let mut walker =
+            Walker::new(fix_up_slashes("test_data/simple")).sort_order(Some(SortOrder::default()));
+        let mut paths = vec![];
+        while let Some(result) = walker.next() {
+            let entry = result.unwrap();
+            if entry.file_name() == "subdir" {
+                walker.skip_current_dir();
+            }
+            paths.push(entry.path().to_string_lossy().into_owned());
+        }
+        assert_eq!(
+            paths,
+            [
+                "test_data/simple",
+                "test_data/simple/abbbc",
+                "test_data/simple/subdir",
+            ]
+            .map(fix_up_slashes)
+        );
+    }
+
+    #[test]
+    fn breadth_first() {
+        let walker = || {
+            Walker::new(fix_up_slashes("test_data/depth"))
+                .sort_order(Some(SortOrder::default()))
+                .breadth_first(true)
+        };
+        assert_eq!(
+            walk(walker()),
+            [
+                "test_data/depth",
+                "test_data/depth/1",
+                "test_data/depth/f0",
+                "test_data/depth/1/2",
+                "test_data/depth/1/f1",
+                "test_data/depth/1/2/3",
+                "test_data/depth/1/2/f2",
+                "test_data/depth/1/2/3/f3",
+            ]
+            .map(fix_up_slashes)
+        );
+        assert_eq!(
+            walk(walker().min_depth(2).max_depth(2)),
+            ["test_data/depth/1/2", "test_data/depth/1/f1"].map(fix_up_slashes)
+        );
+
+        // Skipping a directory still means it's never read
+        let mut pruned = walker();
+        let mut paths = vec![];
+        while let Some(result) = pruned.next() {
+            let entry = result.unwrap();
+            if entry.file_name() == "2" {
+                pruned.skip_current_dir();
+            }
+            paths.push(entry.path().to_string_lossy().into_owned());
+        }
+        assert_eq!(
+            paths,
+            [
+                "test_data/depth",
+                "test_data/depth/1",
+                "test_data/depth/f0",
+                "test_data/depth/1/2",
+                "test_data/depth/1/f1",
+            ]
+            .map(fix_up_slashes)
+        );
+
+        // Post-order traversal is always depth-first
+        assert_eq!(
+            walk(walker().contents_first(true)),
+            walk(walker().breadth_first(false).contents_first(true))
+        );
+    }
+
+    #[test]
+    fn path_prefixes() {
+        let walker = |prefixes: &[&str]| {
+            let prefixes = prefixes.iter().map(|p| fix_up_slashes(p)).collect();
+            Walker::new(fix_up_slashes("test_data/depth"))
+                .sort_order(Some(SortOrder::default()))
+                .path_prefixes(Some(prefixes))
+        };
+        // Directories that can't contain a path with the prefix aren't read,
+        // but are still yielded themselves
+        assert_eq!(
+            walk(walker(&["test_data/depth/1/f"])),
+            [
+                "test_data/depth",
+                "test_data/depth/1",
+                "test_data/depth/1/2",
+                "test_data/depth/1/f1",
+                "test_data/depth/f0",
+            ]
+            .map(fix_up_slashes)
+        );
+        assert_eq!(walk(walker(&["test_data/dep"])).len(), 8);
+        assert_eq!(walk(walker(&[])).len(), 1);
+        assert_eq!(walk(walker(&["test_data/depth2/"])).len(), 1);
+        assert_eq!(
+            walk(walker(&["test_data/depth/1/2/3/", "test_data/depth/f"])).len(),
+            8
+        );
+        // Post-order traversal too
+        assert_eq!(walk(walker(&["x"]).contents_first(true)).len(), 1);
+    }
+
+    #[test]
+    fn missing_root() {
+        let mut walker = Walker::new(fix_up_slashes("test_data/missing"));
+        let err = walker.next().unwrap().unwrap_err();
+        assert!(err.is_not_found());
+        assert_eq!(
+            err.path(),
+            Some(Path::new(&fix_up_slashes("test_data/missing")))
+        );
+        assert!(walker.next().is_none());
+    }
+
+    #[test]
+    fn file_root() {
+        let paths = walk(Walker::new(fix_up_slashes("test_data/simple/abbbc")));
+        assert_eq!(paths, [fix_up_slashes("test_data/simple/abbbc")]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn file_system_loop() {
+        let temp_dir = tempfile::Builder::new()
+            .prefix("file_system_loop")
+            .tempdir()
+            .unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        std::os::unix::fs::symlink("../..", root.join("a/b/up")).unwrap();
+
+        let results: Vec<_> = Walker::new(root)
+            .follow(Follow::Always)
+            .sort_order(Some(SortOrder::default()))
+            .collect();
+        let paths: Vec<_> = results
+            .iter()
+            .map(|result| match result {
+                Ok(entry) => entry.path().to_owned(),
+                Err(e) => e.path().unwrap().to_owned(),
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                root.to_owned(),
+                root.join("a"),
+                root.join("a/b"),
+                root.join("a/b/up")
+            ]
+        );
+        // The loop is reported once, where it's found, and not followed
+        let err = results[3].as_ref().unwrap_err();
+        assert!(err.is_loop());
+        assert_eq!(err.ancestor(), Some(root));
+        assert_eq!(err.depth(), Some(3));
+        assert!(results[..3].iter().all(Result::is_ok));
+
+        // Breadth-first, the directories above aren't on the stack, but the
+        // loop is found all the same
+        let results: Vec<_> = Walker::new(root)
+            .follow(Follow::Always)
+            .breadth_first(true)
+            .collect();
+        assert_eq!(results.len(), 4);
+        let err = results[3].as_ref().unwrap_err();
+        assert!(err.is_loop());
+        assert_eq!(err.ancestor(), Some(root));
+
+        // Without following links, there's no loop
+        let walker = Walker::new(root).sort_order(Some(SortOrder::default()));
+        assert_eq!(walker.map(Result::unwrap).count(), 4);
+    }
+
+    #[test]
+    fn skip_visited() {
+        let visited = VisitedDirs::default();
+        let walker = |root| {
+            Walker::new(fix_up_slashes(root))
+                .sort_order(Some(SortOrder::default()))
+                .skip_visited(Some(visited.clone()))
+        };
+        assert_eq!(
+            walk(walker("test_data/simple/subdir")),
+            ["test_data/simple/subdir", "test_data/simple/subdir/ABBBC"].map(fix_up_slashes)
+        );
+        // The subdirectory has already been searched
+        assert_eq!(
+            walk(walker("test_data/simple")),
+            ["test_data/simple", "test_data/simple/abbbc"].map(fix_up_slashes)
+        );
+        assert!(walk(walker("test_data/simple/")).is_empty());
+        // But it's only directories that are skipped
+        assert_eq!(
+            walk(walker("test_data/simple/abbbc")),
+            [fix_up_slashes("test_data/simple/abbbc")]
+        );
+    }
+
+    #[test]
+    fn leaf_optimization() {
+        // Pretend we're reading a directory without file types in its
+        // entries, so we'd have to stat() a file that doesn't exist
+        let walk_missing = |leaf_optimization, links| {
+            let mut walker = Walker::new("unused").leaf_optimization(leaf_optimization);
+            walker.root = None;
+            walker.stack.push(Frame {
+                children: vec![Ok(Child {
+                    name: "missing".into(),
+                    file_type: None,
+                })]
+                .into_iter(),
+                dir: None,
+                path: PathBuf::from(fix_up_slashes("test_data/simple/subdir")),
+                depth: 0,
+                id: None,
+                entry: None,
+                subdirs_seen: 0,
+                links: OnceCell::from(links),
+                ancestors: None,
+            });
+            walker.next().unwrap()
+        };
+
+        // No subdirectories, so it can't be a directory
+        assert!(walk_missing(true, Some(2)).is_ok());
+        // There's a subdirectory we haven't seen yet
+        assert!(walk_missing(true, Some(3)).unwrap_err().is_not_found());
+        // The link count doesn't tell us anything
+        assert!(walk_missing(true, None).unwrap_err().is_not_found());
+        // -noleaf
+        assert!(walk_missing(false, Some(2)).unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn file_types() {
+        // As above, but without link counts to go on
+        let walk_missing = |file_types, max_depth, follow| {
+            let mut walker = Walker::new("unused")
+                .file_types(file_types)
+                .max_depth(max_depth)
+                .follow(follow);
+            walker.root = None;
+            walker.stack.push(Frame {
+                children: vec![Ok(Child {
+                    name: "missing".into(),
+                    file_type: None,
+                })]
+                .into_iter(),
+                dir: None,
+                path: PathBuf::from(fix_up_slashes("test_data/simple/subdir")),
+                depth: 0,
+                id: None,
+                entry: None,
+                subdirs_seen: 0,
+                links: OnceCell::from(None),
+                ancestors: None,
+            });
+            walker.next().unwrap()
+        };
+
+        // Too deep to descend into, and nobody needs the type
+        assert!(walk_missing(false, 1, Follow::Never).is_ok());
+        assert!(walk_missing(true, 1, Follow::Never)
+            .unwrap_err()
+            .is_not_found());
+        // It might be a directory to descend into
+        assert!(walk_missing(false, 2, Follow::Never)
+            .unwrap_err()
+            .is_not_found());
+        // It might be a link to follow
+        assert!(walk_missing(false, 1, Follow::Always)
+            .unwrap_err()
+            .is_not_found());
+    }
+
+    #[test]
+    fn parse_throttle() {
+        assert_eq!("idle".parse(), Ok(Throttle::Idle));
+        assert_eq!("2".parse(), Ok(Throttle::Rate(2e6)));
+        assert_eq!("0.5".parse(), Ok(Throttle::Rate(5e5)));
+        for arg in ["", "0", "-1", "inf", "NaN", "fast"] {
+            assert!(arg.parse::<Throttle>().is_err(), "{arg}");
+        }
+    }
+
+    #[test]
+    fn rate_limit() {
+        // abbbc, subdir and ABBBC take 32 bytes each, so this takes 0.1s
+        assert_eq!(dirent_size(OsStr::new("subdir")), 32);
+        let start = Instant::now();
+        let walker = Walker::new(fix_up_slashes("test_data/simple")).rate_limit(Some(960.0));
+        assert_eq!(walk(walker).len(), 4);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
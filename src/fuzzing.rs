@@ -0,0 +1,33 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Entry points for the fuzz targets in the `fuzz` directory. These are only
+//! built with the `fuzzing` feature, and exist to reach parsers that aren't
+//! otherwise public. None of them should ever panic, whatever the input.
+
+use crate::find::matchers::build_top_level_matcher;
+use crate::find::Config;
+
+/// Primaries that create files as soon as they're parsed, which we don't want
+/// the fuzzer doing.
+const CREATES_FILES: &[&str] = &["-fprint", "-fprint0", "-fprintf", "-fls", "-log"];
+
+/// Parses a find expression and builds its matchers.
+pub fn find_expression(args: &[&str]) {
+    if args.iter().any(|arg| CREATES_FILES.contains(arg)) {
+        return;
+    }
+    let _ = build_top_level_matcher(args, &mut Config::default());
+}
+
+/// Parses a -printf format string.
+pub fn printf_format(format: &str) {
+    let _ = build_top_level_matcher(&["-printf", format], &mut Config::default());
+}
+
+/// Splits xargs input into arguments, with an optional `-d` delimiter.
+pub fn xargs_input(input: &[u8], delimiter: Option<&str>) {
+    let _ = crate::xargs::read_all_args(input, delimiter);
+}
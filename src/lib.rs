@@ -4,5 +4,15 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+//! Rust implementations of find and xargs. See [find] for using find's engine
+//! as a library.
+
+mod exec_common;
 pub mod find;
+mod words;
 pub mod xargs;
+
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
@@ -25,8 +25,10 @@ enum ExitWith {
 struct Config {
     exit_with: Option<ExitWith>,
     print_stdin: bool,
+    print_env: Option<String>,
     no_print_cwd: bool,
     destination_dir: Option<String>,
+    sleep: Option<f64>,
 }
 
 fn open_file(destination_dir: &str) -> File {
@@ -62,6 +64,11 @@ fn write_content(mut f: impl Write, config: &Config, args: &[String]) {
         writeln!(f, "stdin={}", s.trim()).expect("failed to write to file");
     }
 
+    if let Some(name) = &config.print_env {
+        let value = env::var(name).unwrap_or_default();
+        writeln!(f, "env={value}").expect("failed to write to file");
+    }
+
     writeln!(f, "args=").expect("failed to write to file");
 
     // first two args are going to be the path to this executable and
@@ -105,6 +112,12 @@ fn main() {
                 "--print_stdin" => {
                     config.print_stdin = true;
                 }
+                _ if arg.starts_with("--print_env=") => {
+                    config.print_env = Some(arg["--print_env=".len()..].to_owned());
+                }
+                _ if arg.starts_with("--sleep=") => {
+                    config.sleep = arg["--sleep=".len()..].parse().ok();
+                }
                 _ => {
                     usage();
                 }
@@ -112,6 +125,10 @@ fn main() {
         }
     }
 
+    if let Some(secs) = config.sleep {
+        std::thread::sleep(std::time::Duration::from_secs_f64(secs));
+    }
+
     if let Some(destination_dir) = &config.destination_dir {
         write_content(open_file(destination_dir), &config, &args);
     } else {
@@ -0,0 +1,155 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Splitting options and expressions that don't come from the command line,
+//! like find's --expr-file and the FIND_OPTIONS and XARGS_OPTIONS environment
+//! variables, into words.
+
+use std::env::{self, VarError};
+
+/// Splits text into words, roughly as a shell would:
+///
+/// - words are separated by whitespace, including newlines
+/// - a `#` at the start of a word comments out the rest of the line
+/// - everything between single quotes is taken literally
+/// - between double quotes, `\"` and `\\` stand for `"` and `\`
+/// - elsewhere, a backslash makes the next character part of the word, and
+///   a backslash at the end of a line joins it to the next one
+///
+/// Quotes only group characters, so `'*.rs'`, `"*.rs"` and `\*.rs` are all
+/// the same as `*.rs`, and `''` is an empty word. On error, returns the line
+/// it's on and what's wrong.
+pub(crate) fn split_words(text: &str) -> Result<Vec<String>, (usize, String)> {
+    let mut words = vec![];
+    // None between words, so that '' can still start an empty one
+    let mut word: Option<String> = None;
+    let mut chars = text.chars();
+    // The line that the last character we read is on
+    let line = |chars: &std::str::Chars| {
+        let offset = text.len() - chars.as_str().len();
+        text[..offset - 1].matches('\n').count() + 1
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '#' if word.is_none() => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '\'' | '"' => {
+                let start = line(&chars);
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        None => return Err((start, format!("unterminated {c} quote"))),
+                        Some(end) if end == c => break,
+                        Some('\\') if c == '"' => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => word.push(escaped),
+                            Some(other) => {
+                                word.push('\\');
+                                word.push(other);
+                            }
+                            None => return Err((start, format!("unterminated {c} quote"))),
+                        },
+                        Some(other) => word.push(other),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // A line continuation
+                Some('\n') => {}
+                Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                None => {
+                    let line = line(&chars);
+                    return Err((line, String::from("backslash at the end")));
+                }
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Reads the default options in the environment variable `var`, which are
+/// split into words like [split_words] does. They're empty if it isn't set.
+pub(crate) fn default_options(var: &str) -> Result<Vec<String>, String> {
+    match env::var(var) {
+        Ok(value) => split_words(&value).map_err(|(_, message)| format!("{var}: {message}")),
+        Err(VarError::NotPresent) => Ok(vec![]),
+        Err(VarError::NotUnicode(_)) => Err(format!("{var} isn't valid UTF-8")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        split_words(text).unwrap()
+    }
+
+    #[test]
+    fn split_words_whitespace_and_comments() {
+        assert_eq!(words(""), Vec::<String>::new());
+        assert_eq!(
+            words("  -name\t*.rs\n\n-print  "),
+            ["-name", "*.rs", "-print"]
+        );
+        assert_eq!(
+            words("# Rust sources\n-name *.rs # but not tests\n-print"),
+            ["-name", "*.rs", "-print"]
+        );
+        // Only a # that starts a word is a comment
+        assert_eq!(words("-name a#b"), ["-name", "a#b"]);
+        assert_eq!(words("-name \\#*"), ["-name", "#*"]);
+    }
+
+    #[test]
+    fn split_words_quotes() {
+        assert_eq!(words("-name '* *'"), ["-name", "* *"]);
+        assert_eq!(words("-name \"it's\""), ["-name", "it's"]);
+        assert_eq!(words("-name 'a\"b'"), ["-name", "a\"b"]);
+        assert_eq!(words(r#"-regex "\"\\\d""#), ["-regex", r#""\\d"#]);
+        assert_eq!(words(r"-name '\*'"), ["-name", r"\*"]);
+        assert_eq!(words("-name a'b c'd"), ["-name", "ab cd"]);
+        assert_eq!(
+            words("-name '# not a comment'"),
+            ["-name", "# not a comment"]
+        );
+        assert_eq!(words("-path '' -o ''"), ["-path", "", "-o", ""]);
+        assert_eq!(words("'multi\nline'"), ["multi\nline"]);
+    }
+
+    #[test]
+    fn split_words_backslashes() {
+        assert_eq!(words(r"-name a\ b"), ["-name", "a b"]);
+        assert_eq!(words("-exec echo {} \\;"), ["-exec", "echo", "{}", ";"]);
+        assert_eq!(words("-name \\\n*.rs"), ["-name", "*.rs"]);
+        assert_eq!(words("-na\\\nme"), ["-name"]);
+    }
+
+    #[test]
+    fn split_words_errors() {
+        assert_eq!(
+            split_words("-print\n-name 'abc"),
+            Err((2, String::from("unterminated ' quote")))
+        );
+        assert_eq!(
+            split_words("# \"\n\n-name \"a\nb"),
+            Err((3, String::from("unterminated \" quote")))
+        );
+        assert_eq!(
+            split_words("-print\n\n'a\nb' \\"),
+            Err((4, String::from("backslash at the end")))
+        );
+    }
+}
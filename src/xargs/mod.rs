@@ -10,39 +10,66 @@ use std::{
     ffi::{OsStr, OsString},
     fmt::Display,
     fs,
-    io::{self, BufRead, BufReader, Read},
-    process::{Command, Stdio},
+    io::{self, BufRead, BufReader, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
 };
 
 use clap::{crate_version, error::ErrorKind, Arg, ArgAction};
+use uucore::error::UError;
+
+use crate::exec_common::{
+    describe, killed_by_signal, parse_nice, parse_rlimits, parse_timeout, spawn, timed_out,
+    wait_with_timeout, Finished, Limits, Outcome, Rlimit, SpawnError,
+};
+use crate::words;
 
 mod options {
     pub const COMMAND: &str = "COMMAND";
 
     pub const ARG_FILE: &str = "arg-file";
     pub const DELIMITER: &str = "delimiter";
+    pub const ENV_VAR: &str = "env-var";
     pub const EXIT: &str = "exit";
+    pub const FILES0_FROM: &str = "files0-from";
+    pub const GROUP_OUTPUT: &str = "group-output";
+    pub const INSERT_J: &str = "insert-J";
     pub const MAX_ARGS: &str = "max-args";
     pub const MAX_CHARS: &str = "max-chars";
     pub const MAX_LINES: &str = "max-lines";
     pub const MAX_PROCS: &str = "max-procs";
+    pub const NICE: &str = "nice";
+    pub const NO_DEFAULTS: &str = "no-defaults";
     pub const NO_RUN_IF_EMPTY: &str = "no-run-if-empty";
     pub const NULL: &str = "null";
     pub const REPLACE: &str = "replace";
     pub const REPLACE_I: &str = "replace-I";
+    pub const REPLACEMENTS_R: &str = "replacements-R";
+    pub const RLIMIT: &str = "rlimit";
+    pub const TAG: &str = "tag";
+    pub const TIMEOUT: &str = "timeout";
     pub const VERBOSE: &str = "verbose";
 }
 
 struct Options {
     arg_file: Option<String>,
-    delimiter: Option<u8>,
+    delimiter: Option<Vec<u8>>,
+    env_var: Option<OsString>,
     exit_if_pass_char_limit: bool,
+    group_output: bool,
+    insert: Option<OsString>,
     max_args: Option<usize>,
     max_chars: Option<usize>,
     max_lines: Option<usize>,
+    max_procs: usize,
     no_run_if_empty: bool,
     null: bool,
     replace: Option<String>,
+    max_replacements: Option<usize>,
+    tag: bool,
+    timeout: Option<Duration>,
     verbose: bool,
 }
 
@@ -134,11 +161,56 @@ impl Clone for LimiterCollection {
     }
 }
 
+/// Quotes a single argument following the rules the Microsoft C runtime (and
+/// `CommandLineToArgvW`) use to split a command line back into arguments.
+///
+/// Arguments without whitespace or double quotes are passed through as-is.
+/// Otherwise the argument is wrapped in double quotes, embedded quotes are
+/// escaped with a backslash, and any backslashes immediately preceding a quote
+/// (including the closing one) are doubled.
+#[cfg(any(windows, test))]
+fn quote_windows_arg(arg: &[u16]) -> Vec<u16> {
+    const BACKSLASH: u16 = b'\\' as u16;
+    const QUOTE: u16 = b'"' as u16;
+
+    let needs_quotes = arg.is_empty()
+        || arg.iter().any(|&c| {
+            c == QUOTE || c == b' ' as u16 || c == b'\t' as u16 || c == b'\n' as u16 || c == 0x0b
+        });
+    if !needs_quotes {
+        return arg.to_vec();
+    }
+
+    let mut quoted = Vec::with_capacity(arg.len() + 2);
+    quoted.push(QUOTE);
+    let mut backslashes = 0;
+    for &c in arg {
+        if c == BACKSLASH {
+            backslashes += 1;
+            continue;
+        }
+        if c == QUOTE {
+            // Escape all the preceding backslashes, and the quote itself.
+            quoted.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2 + 1));
+        } else {
+            quoted.extend(std::iter::repeat_n(BACKSLASH, backslashes));
+        }
+        backslashes = 0;
+        quoted.push(c);
+    }
+    // Backslashes before the closing quote need escaping too.
+    quoted.extend(std::iter::repeat_n(BACKSLASH, backslashes * 2));
+    quoted.push(QUOTE);
+    quoted
+}
+
 #[cfg(windows)]
 fn count_osstr_chars_for_exec(s: &OsStr) -> usize {
     use std::os::windows::ffi::OsStrExt;
-    // Include +1 for either the null terminator or trailing space.
-    s.encode_wide().count() + 1
+    // Count the argument as it will appear on the final command line, with
+    // +1 for either the null terminator or the separating space.
+    let wide: Vec<u16> = s.encode_wide().collect();
+    quote_windows_arg(&wide).len() + 1
 }
 
 #[cfg(unix)]
@@ -152,6 +224,8 @@ fn count_osstr_chars_for_exec(s: &OsStr) -> usize {
 struct MaxCharsCommandSizeLimiter {
     current_size: usize,
     max_chars: usize,
+    /// What each argument costs on top of its characters.
+    per_arg: usize,
 }
 
 impl MaxCharsCommandSizeLimiter {
@@ -159,6 +233,7 @@ impl MaxCharsCommandSizeLimiter {
         Self {
             current_size: 0,
             max_chars,
+            per_arg: 0,
         }
     }
 
@@ -166,7 +241,10 @@ impl MaxCharsCommandSizeLimiter {
     fn new_system(_env: &HashMap<OsString, OsString>) -> MaxCharsCommandSizeLimiter {
         // Taken from the CreateProcess docs.
         const MAX_CMDLINE: usize = 32767;
-        MaxCharsCommandSizeLimiter::new(MAX_CMDLINE)
+        // The program name is always wrapped in quotes when the command line is
+        // built, which count_osstr_chars_for_exec can't know about.
+        const PROGRAM_QUOTES: usize = 2;
+        MaxCharsCommandSizeLimiter::new(MAX_CMDLINE - PROGRAM_QUOTES)
     }
 
     #[cfg(unix)]
@@ -174,14 +252,22 @@ impl MaxCharsCommandSizeLimiter {
         // POSIX requires that we leave 2048 bytes of space so that the child processes
         // can have room to set their own environment variables.
         const ARG_HEADROOM: usize = 2048;
+        // Besides the strings, execve() copies the arrays of pointers to
+        // them, each ending with a null pointer
+        const POINTER: usize = std::mem::size_of::<*const uucore::libc::c_char>();
         let arg_max = unsafe { uucore::libc::sysconf(uucore::libc::_SC_ARG_MAX) } as usize;
 
         let env_size: usize = env
             .iter()
-            .map(|(var, value)| count_osstr_chars_for_exec(var) + count_osstr_chars_for_exec(value))
+            .map(|(var, value)| {
+                count_osstr_chars_for_exec(var) + count_osstr_chars_for_exec(value) + POINTER
+            })
             .sum();
 
-        Self::new(arg_max - ARG_HEADROOM - env_size)
+        Self {
+            per_arg: POINTER,
+            ..Self::new(arg_max.saturating_sub(ARG_HEADROOM + env_size + 2 * POINTER))
+        }
     }
 }
 
@@ -191,7 +277,7 @@ impl CommandSizeLimiter for MaxCharsCommandSizeLimiter {
         arg: Argument,
         cursor: LimiterCursor<'_>,
     ) -> Result<Argument, ExhaustedCommandSpace> {
-        let chars = count_osstr_chars_for_exec(&arg.arg);
+        let chars = count_osstr_chars_for_exec(&arg.arg) + self.per_arg;
         if self.current_size + chars <= self.max_chars {
             let arg = cursor.try_next(arg)?;
             self.current_size += chars;
@@ -311,7 +397,16 @@ impl CommandResult {
 enum CommandExecutionError {
     // exit code 255
     UrgentlyFailed,
-    Killed { signal: i32 },
+    Killed {
+        executable: String,
+        signal: i32,
+    },
+    /// It ran for longer than --timeout. Unlike the others, this only makes
+    /// it fail, and doesn't stop xargs.
+    TimedOut {
+        executable: String,
+        timeout: Duration,
+    },
     CannotRun(io::Error),
     NotFound,
     Unknown,
@@ -321,9 +416,14 @@ impl Display for CommandExecutionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             CommandExecutionError::UrgentlyFailed => write!(f, "Command exited with code 255"),
-            CommandExecutionError::Killed { signal } => {
-                write!(f, "Command was killed with signal {signal}")
+            // The same as find's -exec
+            CommandExecutionError::Killed { executable, signal } => {
+                write!(f, "{}", killed_by_signal(executable, *signal))
             }
+            CommandExecutionError::TimedOut {
+                executable,
+                timeout,
+            } => write!(f, "{}", timed_out(executable, *timeout)),
             CommandExecutionError::CannotRun(err) => write!(f, "Command could not be run: {err}"),
             CommandExecutionError::NotFound => write!(f, "Command not found"),
             CommandExecutionError::Unknown => write!(f, "Unknown error running command"),
@@ -333,6 +433,15 @@ impl Display for CommandExecutionError {
 
 impl Error for CommandExecutionError {}
 
+impl From<SpawnError> for CommandExecutionError {
+    fn from(e: SpawnError) -> Self {
+        match e {
+            SpawnError::NotFound(_) => Self::NotFound,
+            SpawnError::CannotRun(e) => Self::CannotRun(e),
+        }
+    }
+}
+
 enum ExecAction {
     Command(Vec<OsString>),
     Echo,
@@ -340,16 +449,32 @@ enum ExecAction {
 
 struct CommandBuilderOptions {
     action: ExecAction,
-    env: HashMap<OsString, OsString>,
     limiters: LimiterCollection,
     verbose: bool,
     close_stdin: bool,
     replace: Option<String>,
+    /// With -I, how many of the initial arguments to replace in, unless
+    /// there's no limit (-R).
+    max_replacements: Option<usize>,
+    /// Put the arguments in place of the first initial argument that's this,
+    /// rather than at the end (-J).
+    insert: Option<OsString>,
+    /// Pass the arguments in this environment variable instead of on the
+    /// command line.
+    env_var: Option<OsString>,
+    /// Kill each command that's still running after this long.
+    timeout: Option<Duration>,
+    /// The priority and resource limits each command runs with.
+    limits: Limits,
+    /// Start each line a command writes with its first argument and a tab.
+    tag: bool,
+    /// Hold back what each command writes until it exits, so it doesn't get
+    /// mixed up with what the others write.
+    group_output: bool,
 }
 impl CommandBuilderOptions {
     fn new(
         action: ExecAction,
-        env: HashMap<OsString, OsString>,
         mut limiters: LimiterCollection,
         replace: Option<String>,
     ) -> Result<Self, ExhaustedCommandSpace> {
@@ -367,11 +492,17 @@ impl CommandBuilderOptions {
 
         Ok(Self {
             action,
-            env,
             limiters,
             verbose: false,
             close_stdin: false,
             replace,
+            max_replacements: None,
+            insert: None,
+            env_var: None,
+            timeout: None,
+            limits: Limits::default(),
+            tag: false,
+            group_output: false,
         })
     }
 }
@@ -397,93 +528,383 @@ impl CommandBuilder<'_> {
         Ok(())
     }
 
-    fn execute(self) -> Result<CommandResult, CommandExecutionError> {
+    /// Starts the command. This returns `None` for the built in echo, which
+    /// has already finished.
+    fn spawn(self) -> Result<Option<Job>, CommandExecutionError> {
         let (entry_point, initial_args): (&OsStr, &[OsString]) = match &self.options.action {
             ExecAction::Command(args) => (&args[0], &args[1..]),
             ExecAction::Echo => (OsStr::new("echo"), &[]),
         };
 
         let mut command = Command::new(entry_point);
+        // The extra arguments that go on the command line
+        let mut appended_args: &[OsString] = &[];
 
         if let Some(replace_str) = &self.options.replace {
             // Replace all occurrences in initial args with the extra arg,
             // Thanks to `MaxArgsCommandSizeLimiter`, we only process a single extra arg here.
             let replacement = self.extra_args[0].to_string_lossy();
+            let mut replacements = self.options.max_replacements.unwrap_or(usize::MAX);
             let initial_args: Vec<OsString> = initial_args
                 .iter()
                 .map(|arg| {
                     let arg_str = arg.to_string_lossy();
+                    if replacements == 0 || !arg_str.contains(replace_str.as_str()) {
+                        return arg.clone();
+                    }
+                    replacements -= 1;
                     OsString::from(arg_str.replace(replace_str, &replacement))
                 })
                 .collect();
 
-            command
-                .args(&initial_args)
-                .env_clear()
-                .envs(&self.options.env);
+            add_command_args(&mut command, &initial_args);
+        } else if let Some(i) = self
+            .options
+            .insert
+            .as_ref()
+            .and_then(|insert| initial_args.iter().position(|arg| arg == insert))
+        {
+            add_command_args(&mut command, &initial_args[..i]);
+            appended_args = &self.extra_args;
+            add_command_args(&mut command, appended_args);
+            add_command_args(&mut command, &initial_args[i + 1..]);
         } else {
             // don't do any replacement
-            command
-                .args(initial_args)
-                .args(&self.extra_args)
-                .env_clear()
-                .envs(&self.options.env);
+            add_command_args(&mut command, initial_args);
+            if self.options.env_var.is_none() {
+                appended_args = &self.extra_args;
+                add_command_args(&mut command, appended_args);
+            }
         };
+        // The command inherits our environment, so that -t only shows what
+        // changes
+        if let Some(name) = &self.options.env_var {
+            command.env(name, join_args(&self.extra_args));
+        }
 
         if self.options.close_stdin {
             command.stdin(Stdio::null());
         }
+        let capture = self.options.tag || self.options.group_output;
+        if capture {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+
+        // With -I, this is the replacement, even if it's not on the command line
+        let tag = self.options.tag.then(|| {
+            self.extra_args
+                .first()
+                .map(|arg| arg.as_encoded_bytes().to_vec())
+                .unwrap_or_default()
+        });
 
         if self.options.verbose {
-            eprintln!("{command:?}");
+            eprintln!("{}", describe(&command));
         }
 
         match &self.options.action {
-            ExecAction::Command(_) => match command.status() {
-                Ok(status) => {
-                    if status.success() {
-                        Ok(CommandResult::Success)
-                    } else if let Some(err) = status.code() {
-                        if err == 255 {
-                            Err(CommandExecutionError::UrgentlyFailed)
-                        } else {
-                            Ok(CommandResult::Failure)
-                        }
-                    } else {
-                        #[cfg(unix)]
-                        {
-                            use std::os::unix::process::ExitStatusExt;
-                            if let Some(signal) = status.signal() {
-                                Err(CommandExecutionError::Killed { signal })
-                            } else {
-                                Err(CommandExecutionError::Unknown)
-                            }
-                        }
-
-                        #[cfg(not(unix))]
-                        Err(CommandExecutionError::Unknown)
+            ExecAction::Command(_) => {
+                match spawn(&mut command, self.options.timeout, &self.options.limits) {
+                    Ok(mut child) => {
+                        let output = capture.then(|| {
+                            CapturedOutput::start(&mut child, tag, self.options.group_output)
+                        });
+                        Ok(Some(Job {
+                            child,
+                            executable: entry_point.to_string_lossy().into_owned(),
+                            timeout: self.options.timeout,
+                            output,
+                        }))
                     }
+                    Err(e) => Err(e.into()),
                 }
-                Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                    Err(CommandExecutionError::NotFound)
+            }
+            ExecAction::Echo => {
+                // With -I or --env-var, there's nothing to print, just like a
+                // real echo without any arguments
+                let line = join_args(appended_args);
+                match tag {
+                    Some(tag) => println!(
+                        "{}\t{}",
+                        String::from_utf8_lossy(&tag),
+                        line.to_string_lossy()
+                    ),
+                    None => println!("{}", line.to_string_lossy()),
                 }
-                Err(e) => Err(CommandExecutionError::CannotRun(e)),
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// A command that's been started.
+struct Job {
+    child: Child,
+    /// What the command runs, to say what was killed.
+    executable: String,
+    timeout: Option<Duration>,
+    /// What the command writes, if it's not going straight to our own stdout
+    /// and stderr.
+    output: Option<CapturedOutput>,
+}
+
+impl Job {
+    /// Waits for the command to finish, and works out what its exit status
+    /// means. A command that's killed for taking longer than its timeout has
+    /// failed, but unlike one killed by a signal from elsewhere, that doesn't
+    /// stop xargs.
+    fn wait(mut self) -> Result<CommandResult, CommandExecutionError> {
+        let finished = wait_with_timeout(&mut self.child, self.timeout);
+        if let Some(output) = self.output {
+            output.finish();
+        }
+
+        match finished.map_err(CommandExecutionError::CannotRun)? {
+            Finished::Exited(status) => command_result(status, &self.executable),
+            Finished::TimedOut => {
+                let e = CommandExecutionError::TimedOut {
+                    executable: self.executable,
+                    timeout: self.timeout.unwrap_or_default(),
+                };
+                eprintln!("Error: {e}");
+                Ok(CommandResult::Failure)
+            }
+        }
+    }
+}
+
+/// Passes on what a command writes to its stdout and stderr, for --tag and
+/// --group-output.
+struct CapturedOutput {
+    stdout: JoinHandle<Vec<u8>>,
+    stderr: JoinHandle<Vec<u8>>,
+}
+
+impl CapturedOutput {
+    /// Starts reading a command's piped stdout and stderr. With `group`,
+    /// everything is held back until [CapturedOutput::finish], otherwise each
+    /// line is written as soon as it's complete. With a `tag`, each line
+    /// starts with it and a tab.
+    fn start(child: &mut Child, tag: Option<Vec<u8>>, group: bool) -> Self {
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let stderr_tag = tag.clone();
+        Self {
+            stdout: thread::spawn(move || {
+                relay(stdout, tag.as_deref(), group, || {
+                    Box::new(io::stdout().lock())
+                })
+            }),
+            stderr: thread::spawn(move || {
+                relay(stderr, stderr_tag.as_deref(), group, || {
+                    Box::new(io::stderr().lock())
+                })
+            }),
+        }
+    }
+
+    /// Waits for the command to close its pipes, and writes whatever was held
+    /// back.
+    fn finish(self) {
+        let stdout = self.stdout.join().unwrap_or_default();
+        let stderr = self.stderr.join().unwrap_or_default();
+        // Hold both locks, so nothing from another command can get in between
+        let mut out = io::stdout().lock();
+        let mut err = io::stderr().lock();
+        // Like echo, there's nowhere to report failing to write
+        let _ = out.write_all(&stdout).and_then(|()| out.flush());
+        let _ = err.write_all(&stderr).and_then(|()| err.flush());
+    }
+}
+
+/// Reads lines from `pipe`, tagging them if needed, and either writes each of
+/// them in one go to what `open` returns or, with `group`, returns them all at
+/// the end.
+fn relay(
+    pipe: Option<impl Read>,
+    tag: Option<&[u8]>,
+    group: bool,
+    open: impl Fn() -> Box<dyn Write>,
+) -> Vec<u8> {
+    let mut held = vec![];
+    let Some(pipe) = pipe else {
+        return held;
+    };
+
+    let mut reader = BufReader::new(pipe);
+    let mut line = vec![];
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if let Some(tag) = tag {
+            held.extend_from_slice(tag);
+            held.push(b'\t');
+        }
+        held.extend_from_slice(&line);
+        if !group {
+            let mut out = open();
+            let _ = out.write_all(&held).and_then(|()| out.flush());
+            held.clear();
+        }
+    }
+    held
+}
+
+fn command_result(
+    status: ExitStatus,
+    executable: &str,
+) -> Result<CommandResult, CommandExecutionError> {
+    match Outcome::new(status) {
+        Outcome::Success => Ok(CommandResult::Success),
+        Outcome::Failed(255) => Err(CommandExecutionError::UrgentlyFailed),
+        Outcome::Failed(_) | Outcome::Crashed(_) => Ok(CommandResult::Failure),
+        Outcome::Killed(signal) => Err(CommandExecutionError::Killed {
+            executable: executable.to_owned(),
+            signal,
+        }),
+        Outcome::Unknown => Err(CommandExecutionError::Unknown),
+    }
+}
+
+type JobResult = (usize, Result<CommandResult, CommandExecutionError>);
+
+/// Runs commands, up to `max_procs` (-P) at a time, and combines their
+/// results.
+///
+/// Any failing command makes the whole result a failure. An error that stops
+/// xargs (like a command exiting with 255) stops any more commands from
+/// starting, but the ones that are already running are waited for. If more
+/// than one of them hit such an error, the one that was started first wins,
+/// so the exit status doesn't depend on which finished first.
+struct Jobs {
+    max_procs: usize,
+    /// The number of commands started so far, used to order them.
+    started: usize,
+    /// The number of commands that are still running in the background.
+    running: usize,
+    sender: Sender<JobResult>,
+    receiver: Receiver<JobResult>,
+    result: CommandResult,
+    /// The error from the first started command that had one.
+    error: Option<(usize, CommandExecutionError)>,
+}
+
+impl Jobs {
+    /// Creates a new set of jobs. `max_procs` of 0 means no limit.
+    fn new(max_procs: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            max_procs: if max_procs == 0 {
+                usize::MAX
+            } else {
+                max_procs
             },
-            ExecAction::Echo => {
-                println!(
-                    "{}",
-                    self.extra_args
-                        .iter()
-                        .map(|arg| arg.to_string_lossy())
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                );
-                Ok(CommandResult::Success)
+            started: 0,
+            running: 0,
+            sender,
+            receiver,
+            result: CommandResult::Success,
+            error: None,
+        }
+    }
+
+    /// Whether an error means no more commands should be started.
+    fn stopped(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Runs a command, once there's room for it.
+    fn run(&mut self, builder: CommandBuilder<'_>) {
+        while self.running >= self.max_procs {
+            self.wait_one();
+        }
+        if self.stopped() {
+            // Something we were waiting for failed
+            return;
+        }
+
+        let id = self.started;
+        self.started += 1;
+        match builder.spawn() {
+            Ok(Some(job)) if self.max_procs == 1 => self.record(id, job.wait()),
+            Ok(Some(job)) => {
+                self.running += 1;
+                let sender = self.sender.clone();
+                thread::spawn(move || {
+                    // The receiver outlives every job, so this can't fail
+                    let _ = sender.send((id, job.wait()));
+                });
             }
+            Ok(None) => self.record(id, Ok(CommandResult::Success)),
+            Err(e) => self.record(id, Err(e)),
+        }
+    }
+
+    fn wait_one(&mut self) {
+        // safe to unwrap: we hold a sender, so this can't disconnect
+        let (id, result) = self.receiver.recv().unwrap();
+        self.running -= 1;
+        self.record(id, result);
+    }
+
+    fn record(&mut self, id: usize, result: Result<CommandResult, CommandExecutionError>) {
+        match result {
+            Ok(result) => self.result.combine(result),
+            Err(e) => {
+                if self.error.as_ref().is_none_or(|(first, _)| id < *first) {
+                    self.error = Some((id, e));
+                }
+            }
+        }
+    }
+
+    /// Waits for every command to finish, and returns the combined result.
+    fn finish(mut self) -> Result<CommandResult, CommandExecutionError> {
+        while self.running > 0 {
+            self.wait_one();
+        }
+
+        match self.error {
+            Some((_, e)) => Err(e),
+            None => Ok(self.result),
         }
     }
 }
 
+/// Joins arguments with spaces, for echo and --env-var.
+fn join_args(args: &[OsString]) -> OsString {
+    let mut joined = OsString::new();
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            joined.push(" ");
+        }
+        joined.push(arg);
+    }
+    joined
+}
+
+#[cfg(windows)]
+fn add_command_args(command: &mut Command, args: &[OsString]) {
+    use std::os::windows::{
+        ffi::{OsStrExt, OsStringExt},
+        process::CommandExt,
+    };
+    // Quote the arguments ourselves, so that the command line we hand to
+    // CreateProcess is exactly the one MaxCharsCommandSizeLimiter measured.
+    for arg in args {
+        let wide: Vec<u16> = arg.encode_wide().collect();
+        command.raw_arg(OsString::from_wide(&quote_windows_arg(&wide)));
+    }
+}
+
+#[cfg(not(windows))]
+fn add_command_args(command: &mut Command, args: &[OsString]) {
+    command.args(args);
+}
+
 trait ArgumentReader {
     fn next(&mut self) -> io::Result<Option<Argument>>;
 }
@@ -491,6 +912,8 @@ trait ArgumentReader {
 struct WhitespaceDelimitedArgumentReader<R: Read> {
     rd: R,
     pending: Vec<u8>,
+    /// Whether blanks separate arguments, or only newlines do (for -I).
+    split_on_blanks: bool,
 }
 
 impl<R> WhitespaceDelimitedArgumentReader<R>
@@ -501,6 +924,17 @@ where
         Self {
             rd,
             pending: vec![],
+            split_on_blanks: true,
+        }
+    }
+
+    /// Reads one argument per line, like -I does. Leading blanks are ignored,
+    /// but the rest of the line is kept as is, apart from quotes and
+    /// backslashes.
+    fn lines(rd: R) -> Self {
+        Self {
+            split_on_blanks: false,
+            ..Self::new(rd)
         }
     }
 }
@@ -522,6 +956,9 @@ where
         }
 
         let mut escape: Option<Escape> = None;
+        // Whether we've started an argument, which may be empty if it was
+        // quoted, e.g. ''
+        let mut started = false;
         let mut i = 0;
         loop {
             if i == pending.len() {
@@ -542,7 +979,7 @@ where
                             format!("Unterminated quote: {q}"),
                         ));
                     }
-                    if i == 0 {
+                    if !started {
                         return Ok(None);
                     }
                     pending.clear();
@@ -560,15 +997,28 @@ where
                     result.push(c);
                     escape = None;
                 }
-                (None, c @ (b'"' | b'\'')) => escape = Some(Escape::Quote(c)),
-                (None, b'\\') => escape = Some(Escape::Slash),
+                (None, c @ (b'"' | b'\'')) => {
+                    escape = Some(Escape::Quote(c));
+                    started = true;
+                }
+                (None, b'\\') => {
+                    escape = Some(Escape::Slash);
+                    started = true;
+                }
                 (None, c) if c.is_ascii_whitespace() => {
-                    if !result.is_empty() {
+                    if !started {
+                        // Skip leading whitespace and blank lines
+                    } else if self.split_on_blanks || c == b'\n' {
                         terminated_by_newline = c == b'\n';
                         break;
+                    } else {
+                        result.push(c);
                     }
                 }
-                (None, c) => result.push(c),
+                (None, c) => {
+                    result.push(c);
+                    started = true;
+                }
             }
 
             i += 1;
@@ -591,19 +1041,33 @@ where
 
 struct ByteDelimitedArgumentReader<R: Read> {
     rd: BufReader<R>,
-    delimiter: u8,
+    /// The delimiter, which may be more than one byte long.
+    delimiter: Vec<u8>,
 }
 
 impl<R> ByteDelimitedArgumentReader<R>
 where
     R: Read,
 {
-    fn new(rd: R, delimiter: u8) -> Self {
+    fn new(rd: R, delimiter: Vec<u8>) -> Self {
+        assert!(!delimiter.is_empty());
         Self {
             rd: BufReader::new(rd),
             delimiter,
         }
     }
+
+    /// Reads up to and including the next delimiter, or the end of the input.
+    fn read_until_delimiter(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        // safe to unwrap: the delimiter is never empty
+        let last = *self.delimiter.last().unwrap();
+        loop {
+            let bytes_read = self.rd.read_until(last, buf)?;
+            if bytes_read == 0 || buf.ends_with(&self.delimiter) {
+                return Ok(());
+            }
+        }
+    }
 }
 
 impl<R> ArgumentReader for ByteDelimitedArgumentReader<R>
@@ -613,19 +1077,14 @@ where
     fn next(&mut self) -> io::Result<Option<Argument>> {
         Ok(loop {
             let mut buf = vec![];
-            let bytes_read = self.rd.read_until(self.delimiter, &mut buf)?;
-            if bytes_read > 0 {
-                let need_to_trim_delimiter = buf[buf.len() - 1] == self.delimiter;
-                let bytes = if need_to_trim_delimiter {
-                    if buf.len() == 1 {
-                        // This was *only* a delimiter, so we didn't actually
-                        // read anything interesting. Try again.
-                        continue;
-                    }
-
-                    &buf[..buf.len() - 1]
-                } else {
-                    &buf[..]
+            self.read_until_delimiter(&mut buf)?;
+            if !buf.is_empty() {
+                let bytes = match buf.strip_suffix(&self.delimiter[..]) {
+                    // This was *only* a delimiter, so we didn't actually read
+                    // anything interesting. Try again.
+                    Some([]) => continue,
+                    Some(bytes) => bytes,
+                    None => &buf[..],
                 };
                 break Some(Argument {
                     arg: String::from_utf8_lossy(bytes).into_owned().into(),
@@ -658,6 +1117,18 @@ impl Display for XargsError {
 
 impl Error for XargsError {}
 
+impl UError for XargsError {
+    fn code(&self) -> i32 {
+        match self {
+            XargsError::CommandExecution(CommandExecutionError::UrgentlyFailed) => 124,
+            XargsError::CommandExecution(CommandExecutionError::Killed { .. }) => 125,
+            XargsError::CommandExecution(CommandExecutionError::CannotRun(_)) => 126,
+            XargsError::CommandExecution(CommandExecutionError::NotFound) => 127,
+            _ => 1,
+        }
+    }
+}
+
 impl From<String> for XargsError {
     fn from(s: String) -> Self {
         Self::Untyped(s)
@@ -687,6 +1158,7 @@ struct InputProcessOptions {
     max_args: Option<usize>,
     max_lines: Option<usize>,
     no_run_if_empty: bool,
+    max_procs: usize,
 }
 
 impl InputProcessOptions {
@@ -695,12 +1167,14 @@ impl InputProcessOptions {
         max_args: Option<usize>,
         max_lines: Option<usize>,
         no_run_if_empty: bool,
+        max_procs: usize,
     ) -> Self {
         InputProcessOptions {
             exit_if_pass_char_limit,
             max_args,
             max_lines,
             no_run_if_empty,
+            max_procs,
         }
     }
 }
@@ -712,7 +1186,7 @@ fn process_input(
 ) -> Result<CommandResult, XargsError> {
     let mut current_builder = CommandBuilder::new(&builder_options);
     let mut have_pending_command = false;
-    let mut result = CommandResult::Success;
+    let mut jobs = Jobs::new(options.max_procs);
 
     while let Some(arg) = args.next()? {
         if let Err(ExhaustedCommandSpace { arg, out_of_chars }) = current_builder.add_arg(arg) {
@@ -723,7 +1197,10 @@ fn process_input(
                 return Err(XargsError::ArgumentTooLarge);
             }
             if have_pending_command {
-                result.combine(current_builder.execute()?);
+                jobs.run(current_builder);
+                if jobs.stopped() {
+                    return Ok(jobs.finish()?);
+                }
             }
 
             current_builder = CommandBuilder::new(&builder_options);
@@ -736,34 +1213,87 @@ fn process_input(
     }
 
     if !options.no_run_if_empty || have_pending_command {
-        result.combine(current_builder.execute()?);
+        jobs.run(current_builder);
     }
 
-    Ok(result)
+    Ok(jobs.finish()?)
 }
 
-fn parse_delimiter(s: &str) -> Result<u8, String> {
-    match s.strip_prefix('\\') {
-        Some(hex) if hex.starts_with('x') => {
-            u8::from_str_radix(&hex[1..], 16).map_err(|e| format!("Invalid hex sequence: {e}"))
-        }
-        Some(oct) if oct.starts_with('0') => {
-            u8::from_str_radix(&oct[1..], 8).map_err(|e| format!("Invalid octal sequence: {e}"))
+/// Reads all the arguments from `input`, split by the given `-d` delimiter
+/// or by whitespace and quotes. This lets the fuzz targets exercise the
+/// argument readers without running any commands.
+#[cfg(feature = "fuzzing")]
+pub(crate) fn read_all_args(input: &[u8], delimiter: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let mut args: Box<dyn ArgumentReader> = match delimiter {
+        Some(delimiter) => Box::new(ByteDelimitedArgumentReader::new(
+            input,
+            parse_delimiter(delimiter)?,
+        )),
+        None => Box::new(WhitespaceDelimitedArgumentReader::new(input)),
+    };
+    while args.next()?.is_some() {}
+    Ok(())
+}
+
+/// Parses a `-d` delimiter. As an extension, this can be more than one byte
+/// long, made up of any mix of characters and escape sequences, e.g. `\r\n`.
+/// Hex and octal escapes take every digit that follows them.
+fn parse_delimiter(s: &str) -> Result<Vec<u8>, String> {
+    let mut delimiter = vec![];
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c != '\\' {
+            delimiter.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
         }
-        Some(special) => match special {
-            "a" => Ok(b'\x07'),
-            "b" => Ok(b'\x08'),
-            "f" => Ok(b'\x0C'),
-            "n" => Ok(b'\n'),
-            "r" => Ok(b'\r'),
-            "t" => Ok(b'\t'),
-            "v" => Ok(b'\x0B'),
-            "\\" => Ok(b'\\'),
-            "0" => Ok(b'\0'),
-            _ => Err(format!("Invalid escape sequence: \\{special}")),
-        },
-        None if s.len() == 1 => Ok(s.as_bytes()[0]),
-        None => Err("Delimiter must be one byte".to_owned()),
+
+        let Some(c) = rest.chars().next() else {
+            return Err("Invalid escape sequence: \\".to_owned());
+        };
+        rest = &rest[c.len_utf8()..];
+        let byte = match c {
+            'x' | '0' => {
+                let radix = if c == 'x' { 16 } else { 8 };
+                let end = rest
+                    .find(|c: char| !c.is_digit(radix))
+                    .unwrap_or(rest.len());
+                let (digits, after) = rest.split_at(end);
+                rest = after;
+                // Like GNU's, \0 on its own is NUL
+                if c == '0' && digits.is_empty() {
+                    delimiter.push(0);
+                    continue;
+                }
+                u8::from_str_radix(digits, radix).map_err(|e| {
+                    let kind = if c == 'x' { "hex" } else { "octal" };
+                    format!("Invalid {kind} sequence: {e}")
+                })?
+            }
+            'a' => b'\x07',
+            'b' => b'\x08',
+            'f' => b'\x0C',
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            'v' => b'\x0B',
+            '\\' => b'\\',
+            _ => return Err(format!("Invalid escape sequence: \\{c}")),
+        };
+        delimiter.push(byte);
+    }
+
+    if delimiter.is_empty() {
+        return Err("Delimiter must not be empty".to_owned());
+    }
+    Ok(delimiter)
+}
+
+fn validate_env_var_name(s: &str) -> Result<OsString, String> {
+    if s.is_empty() || s.contains('=') {
+        Err(format!("Invalid environment variable name: {s:?}"))
+    } else {
+        Ok(s.into())
     }
 }
 
@@ -775,10 +1305,19 @@ fn validate_positive_usize(s: &str) -> Result<usize, String> {
     }
 }
 
+/// Parses -R's argument, where anything negative means no limit.
+fn parse_replacements(s: &str) -> Result<Option<usize>, String> {
+    match s.parse::<i64>() {
+        Ok(v) if v < 0 => Ok(None),
+        Ok(v) => usize::try_from(v).map(Some).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 fn normalize_options<'a>(
     options: &'a Options,
     matches: &'a clap::ArgMatches,
-) -> (Option<usize>, Option<usize>, &'a Option<String>, Option<u8>) {
+) -> (Option<usize>, Option<usize>, &'a Option<String>) {
     let (max_args, max_lines, replace) =
         match (options.max_args, options.max_lines, &options.replace) {
             // These 3 options are mutually exclusive.
@@ -799,11 +1338,13 @@ fn normalize_options<'a>(
             );
                 let lines_index = matches
                     .indices_of(options::MAX_LINES)
-                    .and_then(|v| v.last());
-                let args_index = matches.indices_of(options::MAX_ARGS).and_then(|v| v.last());
+                    .and_then(|mut v| v.next_back());
+                let args_index = matches
+                    .indices_of(options::MAX_ARGS)
+                    .and_then(|mut v| v.next_back());
                 let replace_index = [options::REPLACE, options::REPLACE_I]
                     .iter()
-                    .flat_map(|o| matches.indices_of(o).and_then(|v| v.last()))
+                    .flat_map(|o| matches.indices_of(o).and_then(|mut v| v.next_back()))
                     .max();
                 if lines_index > args_index && lines_index > replace_index {
                     (None, options.max_lines, &None)
@@ -815,30 +1356,32 @@ fn normalize_options<'a>(
             }
         };
 
-    let delimiter = match (options.delimiter, options.null) {
+    (max_args, max_lines, replace)
+}
+
+fn normalize_delimiter<'a>(options: &'a Options, matches: &clap::ArgMatches) -> Option<&'a [u8]> {
+    match (&options.delimiter, options.null) {
         (Some(delimiter), true) => {
-            if matches.indices_of(options::NULL).unwrap().last()
-                > matches.indices_of(options::DELIMITER).unwrap().last()
+            if matches.indices_of(options::NULL).unwrap().next_back()
+                > matches.indices_of(options::DELIMITER).unwrap().next_back()
             {
-                Some(b'\0')
+                Some(&b"\0"[..])
             } else {
-                Some(delimiter)
+                Some(&delimiter[..])
             }
         }
-        (Some(delimiter), false) => Some(delimiter),
-        (None, true) => Some(b'\0'),
-        // If `replace` and no delimiter specified, each line of stdin turns into a line of stdout,
-        // so the input should be split at newlines only.
-        (None, false) => replace.as_ref().map(|_| b'\n'),
-    };
-
-    (max_args, max_lines, replace, delimiter)
+        (Some(delimiter), false) => Some(&delimiter[..]),
+        (None, true) => Some(&b"\0"[..]),
+        (None, false) => None,
+    }
 }
 
 fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
-    let matches = clap::Command::new("xargs")
+    let command = clap::Command::new("xargs")
         .version(crate_version!())
         .about("Run commands using arguments derived from standard input")
+        // So the command line can override XARGS_OPTIONS
+        .args_override_self(true)
         .arg(
             Arg::new(options::COMMAND)
                 .help("The command to run")
@@ -850,7 +1393,17 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
             Arg::new(options::ARG_FILE)
                 .short('a')
                 .long(options::ARG_FILE)
-                .help("Read arguments from the given file instead of stdin"),
+                .help(
+                    "Read arguments from the given file instead of stdin, which is left for \
+                     the command (- means stdin)",
+                ),
+        )
+        .arg(
+            Arg::new(options::FILES0_FROM)
+                .long(options::FILES0_FROM)
+                .value_name("FILE")
+                .help("Read arguments separated by null terminators from FILE, like -0 -a FILE")
+                .conflicts_with_all([options::ARG_FILE, options::DELIMITER]),
         )
         .arg(
             Arg::new(options::DELIMITER)
@@ -859,6 +1412,17 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 .help("Use the given delimiter to split the input")
                 .value_parser(parse_delimiter),
         )
+        .arg(
+            Arg::new(options::ENV_VAR)
+                .long(options::ENV_VAR)
+                .value_name("NAME")
+                .help(
+                    "Pass the arguments to each command in the environment variable NAME, \
+                    separated by spaces, instead of on the command line",
+                )
+                .conflicts_with_all([options::REPLACE, options::REPLACE_I])
+                .value_parser(validate_env_var_name),
+        )
         .arg(
             Arg::new(options::EXIT)
                 .short('x')
@@ -869,6 +1433,27 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 )
                 .action(ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new(options::GROUP_OUTPUT)
+                .long(options::GROUP_OUTPUT)
+                .help(
+                    "Hold back what each command writes until it exits, so that \
+                    the output of commands run in parallel isn't mixed up",
+                )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::INSERT_J)
+                .short('J')
+                .num_args(1)
+                .value_name("R")
+                .help(
+                    "Put the arguments read from stdin in place of the first initial \
+                    argument that's R, rather than after them all, like BSD xargs",
+                )
+                .conflicts_with_all([options::REPLACE, options::REPLACE_I, options::ENV_VAR])
+                .value_parser(clap::value_parser!(OsString)),
+        )
         .arg(
             Arg::new(options::MAX_ARGS)
                 .short('n')
@@ -893,9 +1478,15 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
             Arg::new(options::MAX_PROCS)
                 .short('P')
                 .long(options::MAX_PROCS)
-                .help("Run up to this many commands in parallel [NOT IMPLEMENTED]")
+                .help("Run up to this many commands in parallel, or as many as possible for 0")
                 .value_parser(clap::value_parser!(usize)),
         )
+        .arg(
+            Arg::new(options::NO_DEFAULTS)
+                .long(options::NO_DEFAULTS)
+                .help("Ignore the default options in XARGS_OPTIONS")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new(options::NO_RUN_IF_EMPTY)
                 .short('r')
@@ -920,6 +1511,43 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 )
                 .value_parser(validate_positive_usize),
         )
+        .arg(
+            Arg::new(options::TAG)
+                .long(options::TAG)
+                .help(
+                    "Start each line a command writes with its first argument \
+                    and a tab",
+                )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new(options::TIMEOUT)
+                .long(options::TIMEOUT)
+                .value_name("SECS")
+                .help(
+                    "Kill each command that's still running after SECS seconds, \
+                    which counts as it failing",
+                )
+                .value_parser(parse_timeout),
+        )
+        .arg(
+            Arg::new(options::NICE)
+                .long(options::NICE)
+                .value_name("N")
+                .help("Run each command with its niceness raised by N, like nice(1)")
+                .allow_negative_numbers(true)
+                .value_parser(parse_nice),
+        )
+        .arg(
+            Arg::new(options::RLIMIT)
+                .long(options::RLIMIT)
+                .value_name("NAME=VALUE[,NAME=VALUE]...")
+                .help(
+                    "Run each command with limits on its CPU seconds (cpu), memory (as), \
+                    file size (fsize), open files (nofile) or core dump size (core)",
+                )
+                .value_parser(parse_rlimits),
+        )
         .arg(
             Arg::new(options::VERBOSE)
                 .short('t')
@@ -950,7 +1578,36 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                 .overrides_with(options::REPLACE)
                 .value_parser(clap::value_parser!(String)),
         )
-        .try_get_matches_from(args);
+        .arg(
+            Arg::new(options::REPLACEMENTS_R)
+                .short('R')
+                .num_args(1)
+                .value_name("N")
+                .help(
+                    "With -I, only replace R in the first N initial arguments that contain \
+                    it, or in all of them if N is negative, like BSD xargs",
+                )
+                .allow_negative_numbers(true)
+                .value_parser(parse_replacements),
+        );
+
+    let mut matches = command.clone().try_get_matches_from(args);
+    if matches
+        .as_ref()
+        .is_ok_and(|matches| !matches.get_flag(options::NO_DEFAULTS))
+    {
+        // The defaults go before everything else, but after our own name
+        let defaults = words::default_options("XARGS_OPTIONS")?;
+        if !defaults.is_empty() {
+            let (name, rest) = args.split_at(args.len().min(1));
+            let args = name
+                .iter()
+                .copied()
+                .chain(defaults.iter().map(AsRef::as_ref))
+                .chain(rest.iter().copied());
+            matches = command.try_get_matches_from(args);
+        }
+    }
 
     let matches = match matches {
         Ok(m) => m,
@@ -968,14 +1625,22 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
     let options = Options {
         arg_file: matches
             .get_one::<String>(options::ARG_FILE)
+            .or_else(|| matches.get_one::<String>(options::FILES0_FROM))
             .map(std::borrow::ToOwned::to_owned),
-        delimiter: matches.get_one::<u8>(options::DELIMITER).copied(),
+        delimiter: matches.get_one::<Vec<u8>>(options::DELIMITER).cloned(),
+        env_var: matches.get_one::<OsString>(options::ENV_VAR).cloned(),
         exit_if_pass_char_limit: matches.get_flag(options::EXIT),
+        group_output: matches.get_flag(options::GROUP_OUTPUT),
+        insert: matches.get_one::<OsString>(options::INSERT_J).cloned(),
         max_args: matches.get_one::<usize>(options::MAX_ARGS).copied(),
         max_chars: matches.get_one::<usize>(options::MAX_CHARS).copied(),
         max_lines: matches.get_one::<usize>(options::MAX_LINES).copied(),
+        max_procs: matches
+            .get_one::<usize>(options::MAX_PROCS)
+            .copied()
+            .unwrap_or(1),
         no_run_if_empty: matches.get_flag(options::NO_RUN_IF_EMPTY),
-        null: matches.get_flag(options::NULL),
+        null: matches.get_flag(options::NULL) || matches.contains_id(options::FILES0_FROM),
         replace: [options::REPLACE_I, options::REPLACE]
             .iter()
             .find_map(|&option| {
@@ -985,10 +1650,23 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
                         .map_or_else(|| "{}".to_string(), std::borrow::ToOwned::to_owned)
                 })
             }),
+        max_replacements: matches
+            .get_one::<Option<usize>>(options::REPLACEMENTS_R)
+            .copied()
+            .flatten(),
+        tag: matches.get_flag(options::TAG),
+        timeout: matches
+            .get_one::<Option<Duration>>(options::TIMEOUT)
+            .copied()
+            .flatten(),
         verbose: matches.get_flag(options::VERBOSE),
     };
 
-    let (max_args, max_lines, replace, delimiter) = normalize_options(&options, &matches);
+    let (max_args, max_lines, replace) = normalize_options(&options, &matches);
+    if matches.contains_id(options::REPLACEMENTS_R) && replace.is_none() {
+        return Err("-R can only be used with -I".into());
+    }
+    let delimiter = normalize_delimiter(&options, &matches);
 
     let action = match matches.get_many::<OsString>(options::COMMAND) {
         Some(args) if args.len() > 0 => {
@@ -1009,23 +1687,56 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
         limiters.add(MaxCharsCommandSizeLimiter::new(max_chars));
     }
     limiters.add(MaxCharsCommandSizeLimiter::new_system(&env));
+    #[cfg(target_os = "linux")]
+    if let Some(name) = &options.env_var {
+        // Linux also limits the length of each string passed to a new process,
+        // including NAME=VALUE. This counts the initial arguments too, which
+        // is more cautious than it needs to be.
+        let page_size = unsafe { uucore::libc::sysconf(uucore::libc::_SC_PAGESIZE) } as usize;
+        let max_arg_strlen = 32 * page_size;
+        limiters.add(MaxCharsCommandSizeLimiter::new(
+            max_arg_strlen - count_osstr_chars_for_exec(name),
+        ));
+    }
 
-    let mut builder_options = CommandBuilderOptions::new(action, env, limiters, replace.clone())
+    let mut builder_options = CommandBuilderOptions::new(action, limiters, replace.clone())
         .map_err(|_| {
             "Base command and environment are too large to fit into one command execution"
         })?;
 
     builder_options.verbose = options.verbose;
-    builder_options.close_stdin = options.arg_file.is_none();
+    // Like GNU xargs, the commands get stdin unless the arguments come from
+    // it, which they also do with `-a -`
+    let arg_file = options.arg_file.as_deref().filter(|&path| path != "-");
+    builder_options.close_stdin = arg_file.is_none();
+    builder_options.env_var = options.env_var.clone();
+    builder_options.max_replacements = options.max_replacements;
+    builder_options.insert = options.insert.clone();
+    builder_options.timeout = options.timeout;
+    builder_options.limits = Limits::new(
+        matches.get_one::<i32>(options::NICE).copied(),
+        matches
+            .get_one::<Vec<Rlimit>>(options::RLIMIT)
+            .cloned()
+            .unwrap_or_default(),
+    )?;
+    builder_options.tag = options.tag;
+    builder_options.group_output = options.group_output;
 
-    let args_file: Box<dyn Read> = if let Some(path) = &options.arg_file {
+    let args_file: Box<dyn Read> = if let Some(path) = arg_file {
         Box::new(fs::File::open(path).map_err(|e| format!("Failed to open {path}: {e}"))?)
     } else {
         Box::new(io::stdin())
     };
 
     let args: Box<dyn ArgumentReader> = if let Some(delimiter) = delimiter {
-        Box::new(ByteDelimitedArgumentReader::new(args_file, delimiter))
+        Box::new(ByteDelimitedArgumentReader::new(
+            args_file,
+            delimiter.to_vec(),
+        ))
+    } else if replace.is_some() {
+        // With -I, each line of stdin is a single argument
+        Box::new(WhitespaceDelimitedArgumentReader::lines(args_file))
     } else {
         Box::new(WhitespaceDelimitedArgumentReader::new(args_file))
     };
@@ -1037,7 +1748,9 @@ fn do_xargs(args: &[&str]) -> Result<CommandResult, XargsError> {
             options.exit_if_pass_char_limit,
             max_args,
             max_lines,
-            options.no_run_if_empty,
+            // With -I, the command is only ever run for a line of input
+            options.no_run_if_empty || replace.is_some(),
+            options.max_procs,
         ),
     )?;
     Ok(result)
@@ -1050,17 +1763,7 @@ pub fn xargs_main(args: &[&str]) -> i32 {
         Ok(CommandResult::Failure) => 123,
         Err(e) => {
             eprintln!("Error: {e}");
-            if let XargsError::CommandExecution(cx) = e {
-                match cx {
-                    CommandExecutionError::UrgentlyFailed => 124,
-                    CommandExecutionError::Killed { .. } => 125,
-                    CommandExecutionError::CannotRun(_) => 126,
-                    CommandExecutionError::NotFound => 127,
-                    CommandExecutionError::Unknown => 1,
-                }
-            } else {
-                1
-            }
+            e.code()
         }
     }
 }
@@ -1168,6 +1871,29 @@ mod tests {
         assert!(limiter.try_arg(make_arg_hard("a"), empty_cursor()).is_ok());
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_system_chars_limiter() {
+        const POINTER: usize = std::mem::size_of::<*const u8>();
+        let empty = MaxCharsCommandSizeLimiter::new_system(&HashMap::new());
+        let env = HashMap::from([(OsString::from("A"), OsString::from("bc"))]);
+        let limiter = MaxCharsCommandSizeLimiter::new_system(&env);
+        // "A=bc", its NUL and a pointer to it
+        assert_eq!(empty.max_chars - limiter.max_chars, 5 + POINTER);
+
+        // Each argument needs a pointer too
+        let mut limiter = MaxCharsCommandSizeLimiter {
+            max_chars: 2 * (4 + POINTER),
+            ..limiter
+        };
+        for _ in 0..2 {
+            assert!(limiter
+                .try_arg(make_arg_hard("abc"), empty_cursor())
+                .is_ok());
+        }
+        assert!(limiter.try_arg(make_arg_hard(""), empty_cursor()).is_err());
+    }
+
     #[test]
     fn test_chars_limiter_asks_cursor() {
         let mut rejects: [Box<dyn CommandSizeLimiter>; 1] = [Box::new(AlwaysRejectLimiter)];
@@ -1291,6 +2017,59 @@ mod tests {
         assert_eq!(reader.next().unwrap(), None);
     }
 
+    #[test]
+    fn test_whitespace_delimited_reader_empty_args() {
+        let mut reader = WhitespaceDelimitedArgumentReader::new(&b"a '' b  \n  "[..]);
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_soft("a"));
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_soft(""));
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_soft("b"));
+        assert_eq!(reader.next().unwrap(), None);
+    }
+
+    #[test]
+    fn test_line_reader() {
+        let mut reader = WhitespaceDelimitedArgumentReader::lines(ChunkReader::new(vec![
+            Chunk::Data(b"  ab c  \n"),
+            Chunk::Data(b"\n\t\n\td 'e"),
+            Chunk::Error(io::ErrorKind::Interrupted),
+            Chunk::Data(b"  f'\\\n\n\"\"\ng"),
+        ]));
+
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_hard("ab c  "));
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_hard("d e  f\n"));
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_hard(""));
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_soft("g"));
+        assert_eq!(reader.next().unwrap(), None);
+    }
+
+    #[test]
+    fn test_jobs_combine_results() {
+        let mut jobs = Jobs::new(2);
+        jobs.record(0, Ok(CommandResult::Success));
+        jobs.record(1, Ok(CommandResult::Failure));
+        jobs.record(2, Ok(CommandResult::Success));
+        assert!(!jobs.stopped());
+        assert!(matches!(jobs.finish(), Ok(CommandResult::Failure)));
+
+        // The first command to be started wins, not the first to finish
+        let mut jobs = Jobs::new(0);
+        jobs.record(0, Ok(CommandResult::Failure));
+        jobs.record(2, Err(CommandExecutionError::UrgentlyFailed));
+        assert!(jobs.stopped());
+        jobs.record(
+            1,
+            Err(CommandExecutionError::Killed {
+                executable: "sh".to_owned(),
+                signal: 9,
+            }),
+        );
+        jobs.record(3, Err(CommandExecutionError::NotFound));
+        assert!(matches!(
+            jobs.finish(),
+            Err(CommandExecutionError::Killed { signal: 9, .. })
+        ));
+    }
+
     #[test]
     fn test_byte_delimited_reader() {
         let mut reader = ByteDelimitedArgumentReader::new(
@@ -1301,7 +2080,7 @@ mod tests {
                 Chunk::Data(b"!ef!!gh"),
                 Chunk::Data(b"!ij"),
             ]),
-            b'!',
+            b"!".to_vec(),
         );
 
         assert_eq!(reader.next().unwrap().unwrap(), make_arg_hard("abc"));
@@ -1312,18 +2091,81 @@ mod tests {
         assert_eq!(reader.next().unwrap(), None);
     }
 
+    #[test]
+    fn test_multi_byte_delimited_reader() {
+        let mut reader = ByteDelimitedArgumentReader::new(
+            ChunkReader::new(vec![
+                Chunk::Data(b"ab\nc\r"),
+                Chunk::Data(b"\nd\r\r\n"),
+                Chunk::Error(io::ErrorKind::Interrupted),
+                Chunk::Data(b"\r\ne\r"),
+            ]),
+            b"\r\n".to_vec(),
+        );
+
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_hard("ab\nc"));
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_hard("d\r"));
+        assert_eq!(reader.next().unwrap().unwrap(), make_arg_hard("e\r"));
+        assert_eq!(reader.next().unwrap(), None);
+    }
+
+    fn quote_windows(arg: &str) -> String {
+        let wide: Vec<u16> = arg.encode_utf16().collect();
+        String::from_utf16(&quote_windows_arg(&wide)).unwrap()
+    }
+
+    #[test]
+    fn test_windows_quoting() {
+        assert_eq!(quote_windows("abc"), "abc");
+        assert_eq!(quote_windows(r"C:\dir\file"), r"C:\dir\file");
+        assert_eq!(quote_windows(""), "\"\"");
+        assert_eq!(quote_windows("a b"), "\"a b\"");
+        assert_eq!(quote_windows("a\tb"), "\"a\tb\"");
+        assert_eq!(quote_windows(r#"a"b"#), r#""a\"b""#);
+        assert_eq!(quote_windows(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(quote_windows(r"a b\"), r#""a b\\""#);
+        assert_eq!(quote_windows(r"a\\b c"), r#""a\\b c""#);
+    }
+
     #[test]
     fn test_delimiter_parsing() {
-        assert_eq!(parse_delimiter("a").unwrap(), b'a');
-        assert_eq!(parse_delimiter("\\x61").unwrap(), b'a');
-        assert_eq!(parse_delimiter("\\x00061").unwrap(), b'a');
-        assert_eq!(parse_delimiter("\\0141").unwrap(), b'a');
-        assert_eq!(parse_delimiter("\\0000141").unwrap(), b'a');
-        assert_eq!(parse_delimiter("\\n").unwrap(), b'\n');
-
-        assert!(parse_delimiter("\\0").is_err());
+        assert_eq!(parse_delimiter("a").unwrap(), b"a");
+        assert_eq!(parse_delimiter("\\x61").unwrap(), b"a");
+        assert_eq!(parse_delimiter("\\x00061").unwrap(), b"a");
+        assert_eq!(parse_delimiter("\\0141").unwrap(), b"a");
+        assert_eq!(parse_delimiter("\\0000141").unwrap(), b"a");
+        assert_eq!(parse_delimiter("\\n").unwrap(), b"\n");
+
+        assert_eq!(parse_delimiter("abc").unwrap(), b"abc");
+        assert_eq!(parse_delimiter("\\r\\n").unwrap(), b"\r\n");
+        assert_eq!(parse_delimiter("\\0").unwrap(), b"\0");
+        assert_eq!(parse_delimiter("a\\0b").unwrap(), b"a\0b");
+        assert_eq!(parse_delimiter("\\x0d\\x0a").unwrap(), b"\r\n");
+        assert_eq!(parse_delimiter("a\\tb").unwrap(), b"a\tb");
+        assert_eq!(parse_delimiter("é").unwrap(), "é".as_bytes());
+
+        assert!(parse_delimiter("").is_err());
         assert!(parse_delimiter("\\x").is_err());
         assert!(parse_delimiter("\\").is_err());
-        assert!(parse_delimiter("abc").is_err());
+        assert!(parse_delimiter("a\\").is_err());
+        assert!(parse_delimiter("\\x100").is_err());
+        assert!(parse_delimiter("\\q").is_err());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let code = |e: CommandExecutionError| XargsError::from(e).code();
+        assert_eq!(code(CommandExecutionError::UrgentlyFailed), 124);
+        assert_eq!(
+            code(CommandExecutionError::Killed {
+                executable: "sh".to_owned(),
+                signal: 9
+            }),
+            125
+        );
+        assert_eq!(code(CommandExecutionError::NotFound), 127);
+        assert_eq!(code(CommandExecutionError::Unknown), 1);
+        assert_eq!(XargsError::ArgumentTooLarge.code(), 1);
+        assert_eq!(XargsError::from("bad").code(), 1);
     }
 }
@@ -18,6 +18,7 @@ use findutils::find::Dependencies;
 /// and integration tests.
 pub struct FakeDependencies {
     pub output: RefCell<Cursor<Vec<u8>>>,
+    pub error_output: RefCell<Cursor<Vec<u8>>>,
     now: SystemTime,
 }
 
@@ -25,6 +26,7 @@ impl FakeDependencies {
     pub fn new() -> Self {
         Self {
             output: RefCell::new(Cursor::new(Vec::<u8>::new())),
+            error_output: RefCell::new(Cursor::new(Vec::<u8>::new())),
             now: SystemTime::now(),
         }
     }
@@ -34,7 +36,15 @@ impl FakeDependencies {
     }
 
     pub fn get_output_as_string(&self) -> String {
-        let mut cursor = self.output.borrow_mut();
+        Self::read_to_string(&self.output)
+    }
+
+    pub fn get_error_output_as_string(&self) -> String {
+        Self::read_to_string(&self.error_output)
+    }
+
+    fn read_to_string(output: &RefCell<Cursor<Vec<u8>>>) -> String {
+        let mut cursor = output.borrow_mut();
         cursor.set_position(0);
         let mut contents = String::new();
         cursor.read_to_string(&mut contents).unwrap();
@@ -47,6 +57,10 @@ impl Dependencies for FakeDependencies {
         &self.output
     }
 
+    fn get_error_output(&self) -> &RefCell<dyn Write> {
+        &self.error_output
+    }
+
     fn now(&self) -> SystemTime {
         self.now
     }
@@ -0,0 +1,264 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Stress tests for -delete combined with -mindepth, -maxdepth and -prune.
+//!
+//! Each test generates a pseudo-random tree, runs find on it, and checks that
+//! what's left matches a simple model of what find should have done.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::path::Path;
+
+use assert_cmd::Command;
+use tempfile::Builder;
+
+/// A small xorshift generator, so that every run sees the same trees.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
+
+/// An entry in a generated tree.
+#[derive(Debug)]
+struct Node {
+    /// The path relative to the root, which is "".
+    path: String,
+    depth: usize,
+    name: String,
+    children: Vec<Node>,
+    is_dir: bool,
+}
+
+impl Node {
+    /// Generates a tree whose entries are named `dN_T` or `fN_T`, where T is a
+    /// random tag for tests like -name to pick out.
+    fn generate(rng: &mut Rng, path: String, name: String, depth: usize) -> Self {
+        let mut children = vec![];
+        let max_dirs = if depth < 6 { 3 } else { 0 };
+        for i in 0..rng.below(max_dirs + 1) {
+            let name = format!("d{i}_{}", rng.below(3));
+            let path = format!("{path}/{name}");
+            children.push(Self::generate(rng, path, name, depth + 1));
+        }
+        for i in 0..rng.below(4) {
+            let name = format!("f{i}_{}", rng.below(3));
+            children.push(Self {
+                path: format!("{path}/{name}"),
+                depth: depth + 1,
+                name,
+                children: vec![],
+                is_dir: false,
+            });
+        }
+
+        Self {
+            path,
+            depth,
+            name,
+            children,
+            is_dir: true,
+        }
+    }
+
+    fn create(&self, root: &Path) {
+        let path = root.join(self.path.trim_start_matches('/'));
+        if self.is_dir {
+            if !self.path.is_empty() {
+                fs::create_dir(&path).unwrap();
+            }
+            for child in &self.children {
+                child.create(root);
+            }
+        } else {
+            File::create(path).unwrap();
+        }
+    }
+
+    /// Works out what `find root [options] -delete` leaves behind: entries
+    /// are visited after their contents, and a directory can only be deleted
+    /// if everything in it was.
+    ///
+    /// Returns whether this entry is still there, and adds the ones that are
+    /// to `remaining`. Failures to delete are counted in `failures`.
+    fn model(&self, case: &Case, remaining: &mut BTreeSet<String>, failures: &mut usize) -> bool {
+        let visited = case.max_depth.is_none_or(|max| self.depth <= max);
+        if !visited {
+            self.keep_all(remaining);
+            return true;
+        }
+
+        let mut empty = true;
+        if case.max_depth != Some(self.depth) {
+            for child in &self.children {
+                if child.model(case, remaining, failures) {
+                    empty = false;
+                }
+            }
+        } else if !self.children.is_empty() {
+            for child in &self.children {
+                child.keep_all(remaining);
+            }
+            empty = false;
+        }
+
+        let deleted = self.depth >= case.min_depth && case.filter.deletes(self) && {
+            if self.is_dir && !empty {
+                *failures += 1;
+            }
+            !self.is_dir || empty
+        };
+        if !deleted {
+            remaining.insert(self.path.clone());
+        }
+        !deleted
+    }
+
+    fn keep_all(&self, remaining: &mut BTreeSet<String>) {
+        remaining.insert(self.path.clone());
+        for child in &self.children {
+            child.keep_all(remaining);
+        }
+    }
+}
+
+/// Which entries -delete gets to, apart from the depth limits.
+#[derive(Clone, Copy, Debug)]
+enum Filter {
+    All,
+    Name,
+    Files,
+    Dirs,
+    /// Prunes some directories, which shouldn't stop find from looking inside
+    /// them, because -delete implies -depth. find insists on an explicit
+    /// -depth for this.
+    Prune,
+}
+
+impl Filter {
+    const ALL: [Self; 5] = [Self::All, Self::Name, Self::Files, Self::Dirs, Self::Prune];
+
+    fn args(self) -> &'static [&'static str] {
+        match self {
+            Self::All => &["-delete"],
+            Self::Name => &["-name", "*_1", "-delete"],
+            Self::Files => &["-type", "f", "-delete"],
+            Self::Dirs => &["-type", "d", "-delete"],
+            Self::Prune => &["-depth", "-name", "d*_2", "-prune", "-o", "-delete"],
+        }
+    }
+
+    fn deletes(self, node: &Node) -> bool {
+        match self {
+            Self::All => true,
+            Self::Name => node.name.ends_with("_1"),
+            Self::Files => !node.is_dir,
+            Self::Dirs => node.is_dir,
+            Self::Prune => {
+                !(node.is_dir && node.name.starts_with('d') && node.name.ends_with("_2"))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Case {
+    min_depth: usize,
+    max_depth: Option<usize>,
+    filter: Filter,
+}
+
+/// Lists everything under `root`, relative to it.
+fn list_tree(root: &Path, path: &str, entries: &mut BTreeSet<String>) {
+    let full = root.join(path.trim_start_matches('/'));
+    if !full.exists() {
+        return;
+    }
+    entries.insert(path.to_owned());
+    if full.is_dir() {
+        for entry in fs::read_dir(full).unwrap() {
+            let name = entry.unwrap().file_name();
+            list_tree(root, &format!("{path}/{}", name.to_string_lossy()), entries);
+        }
+    }
+}
+
+fn check(seed: u64, case: &Case) {
+    let mut rng = Rng(seed);
+    let tree = Node::generate(&mut rng, String::new(), "root".to_owned(), 0);
+
+    let temp_dir = Builder::new().prefix("delete_stress").tempdir().unwrap();
+    let root = temp_dir.path().join("root");
+    fs::create_dir(&root).unwrap();
+    tree.create(&root);
+
+    let mut expected = BTreeSet::new();
+    let mut failures = 0;
+    tree.model(case, &mut expected, &mut failures);
+
+    let mut args = vec![root.to_string_lossy().into_owned()];
+    args.extend(["-mindepth".to_owned(), case.min_depth.to_string()]);
+    if let Some(max_depth) = case.max_depth {
+        args.extend(["-maxdepth".to_owned(), max_depth.to_string()]);
+    }
+    args.extend(case.filter.args().iter().map(|&arg| arg.to_owned()));
+
+    let output = Command::cargo_bin("find")
+        .expect("found binary")
+        .args(&args)
+        .output()
+        .unwrap();
+
+    let mut remaining = BTreeSet::new();
+    list_tree(&root, "", &mut remaining);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(remaining, expected, "seed {seed}, {case:?}\n{stderr}");
+    assert_eq!(
+        stderr.matches("Failed to delete").count(),
+        failures,
+        "seed {seed}, {case:?}\n{stderr}"
+    );
+    let code = i32::from(failures > 0);
+    assert_eq!(output.status.code(), Some(code), "seed {seed}, {case:?}");
+}
+
+#[test]
+fn delete_with_depth_limits() {
+    let mut seed = 0x5eed;
+    for filter in Filter::ALL {
+        for min_depth in 0..4 {
+            for max_depth in [None, Some(0), Some(1), Some(2), Some(4)] {
+                seed += 1;
+                let case = Case {
+                    min_depth,
+                    max_depth,
+                    filter,
+                };
+                check(seed, &case);
+            }
+        }
+    }
+}
+
+#[test]
+fn delete_deep_trees() {
+    for seed in 1..=20 {
+        for filter in Filter::ALL {
+            let case = Case {
+                min_depth: 0,
+                max_depth: None,
+                filter,
+            };
+            check(seed * 7919, &case);
+        }
+    }
+}
@@ -11,12 +11,13 @@
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::time::{Duration, Instant};
 use tempfile::Builder;
 
 use common::test_helpers::{
     fix_up_slashes, get_dir_entry_for, path_to_testing_commandline, FakeDependencies,
 };
-use findutils::find::matchers::exec::SingleExecMatcher;
+use findutils::find::matchers::exec::{Sandbox, SingleExecMatcher};
 use findutils::find::matchers::Matcher;
 
 mod common;
@@ -147,6 +148,54 @@ fn execdir_in_current_directory() {
     );
 }
 
+#[test]
+#[cfg(unix)]
+/// Like GNU find, a starting point keeps one trailing slash in {}, and
+/// "dir/." is run from inside "dir".
+fn execdir_on_starting_points() {
+    let temp_dir = Builder::new()
+        .prefix("execdir_on_starting_points")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let matcher = SingleExecMatcher::new(
+        &path_to_testing_commandline(),
+        &[temp_dir_path.as_ref(), "{}"],
+        true,
+    )
+    .expect("Failed to create matcher");
+    let deps = FakeDependencies::new();
+    let cwd = env::current_dir().unwrap();
+    for (i, (path, dir, arg)) in [
+        ("test_data/simple/", "test_data", "./simple/"),
+        ("test_data/simple//", "test_data", "./simple/"),
+        ("test_data/simple/.", "test_data/simple", "./."),
+        ("test_data/simple/./", "test_data/simple", "././"),
+        ("..", ".", "./.."),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let entry = get_dir_entry_for(path, "");
+        assert!(matcher.matches(&entry, &mut deps.new_matcher_io()));
+
+        let mut f = File::open(temp_dir.path().join(format!("{}.txt", i + 1)))
+            .expect("Failed to open output file");
+        let mut s = String::new();
+        f.read_to_string(&mut s)
+            .expect("failed to read output file");
+        assert_eq!(
+            s,
+            format!(
+                "cwd={}\nargs=\n{arg}\n",
+                cwd.join(dir).canonicalize().unwrap().to_string_lossy()
+            ),
+            "{path}"
+        );
+    }
+}
+
 #[test]
 /// Regression test for "find / -execdir whatever \;"
 fn execdir_in_root_directory() {
@@ -223,3 +272,201 @@ fn matching_fails_if_executable_fails() {
         ))
     );
 }
+
+#[test]
+#[cfg(unix)]
+fn matching_fails_if_executable_is_killed() {
+    let temp_dir = Builder::new()
+        .prefix("matching_fails_if_executable_is_killed")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let executable = path_to_testing_commandline();
+    let matcher = SingleExecMatcher::new(
+        &executable,
+        &[temp_dir_path.as_ref(), "--exit_with_signal", "{}"],
+        false,
+    )
+    .expect("Failed to create matcher");
+    let deps = FakeDependencies::new();
+    let mut matcher_io = deps.new_matcher_io();
+    assert!(!matcher.matches(&abbbc, &mut matcher_io));
+    // Like GNU find, it's only reported, and doesn't change the exit status
+    assert_eq!(matcher_io.exit_code(), 0);
+    assert_eq!(
+        deps.get_error_output_as_string(),
+        format!("find: '{executable}' terminated by signal 2\n")
+    );
+}
+
+#[test]
+fn matching_fails_if_executable_times_out() {
+    let temp_dir = Builder::new()
+        .prefix("matching_fails_if_executable_times_out")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let matcher = SingleExecMatcher::new(
+        &path_to_testing_commandline(),
+        &[temp_dir_path.as_ref(), "--sleep=10", "{}"],
+        false,
+    )
+    .expect("Failed to create matcher")
+    .timeout(Some(Duration::from_millis(200)));
+    let deps = FakeDependencies::new();
+    let mut matcher_io = deps.new_matcher_io();
+    let start = Instant::now();
+    assert!(!matcher.matches(&abbbc, &mut matcher_io));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(matcher_io.exit_code(), 1);
+    assert_eq!(
+        deps.get_error_output_as_string(),
+        format!(
+            "find: '{}' timed out after 0.2s and was killed\n",
+            path_to_testing_commandline()
+        )
+    );
+    // It was killed before it got as far as writing anything
+    assert_eq!(temp_dir.path().read_dir().unwrap().count(), 0);
+
+    // Commands that finish in time are unaffected
+    let matcher = SingleExecMatcher::new(
+        &path_to_testing_commandline(),
+        &[temp_dir_path.as_ref(), "--sleep=0", "{}"],
+        false,
+    )
+    .expect("Failed to create matcher")
+    .timeout(Some(Duration::from_secs(60)));
+    let mut matcher_io = deps.new_matcher_io();
+    assert!(matcher.matches(&abbbc, &mut matcher_io));
+    assert_eq!(matcher_io.exit_code(), 0);
+    assert!(temp_dir.path().join("1.txt").exists());
+}
+
+#[test]
+fn matching_executes_code_in_sandbox() {
+    let temp_dir = Builder::new()
+        .prefix("matching_executes_code_in_sandbox")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let sandbox_dir = Builder::new().prefix("sandbox").tempdir().unwrap();
+    let sandbox_path = sandbox_dir.path().canonicalize().unwrap();
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let deps = FakeDependencies::new();
+    // Cargo sets this for the tests, but it isn't one of the defaults
+    let name = "CARGO_MANIFEST_DIR";
+    let run = |sandbox, output| {
+        let matcher = SingleExecMatcher::new(
+            &path_to_testing_commandline(),
+            &[temp_dir_path.as_ref(), &format!("--print_env={name}"), "{}"],
+            false,
+        )
+        .expect("Failed to create matcher")
+        .sandbox(Some(sandbox));
+        assert!(matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+
+        let mut f = File::open(temp_dir.path().join(output)).expect("Failed to open output file");
+        let mut s = String::new();
+        f.read_to_string(&mut s)
+            .expect("failed to read output file");
+        s
+    };
+
+    let s = run(Sandbox::new(Some(sandbox_path.clone()), vec![]), "1.txt");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}\nenv=\nargs=\n--print_env={name}\ntest_data/simple/abbbc\n",
+            sandbox_path.to_string_lossy()
+        ))
+    );
+
+    let s = run(Sandbox::new(None, vec![name.to_owned()]), "2.txt");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}\nenv={}\nargs=\n--print_env={name}\ntest_data/simple/abbbc\n",
+            env::current_dir().unwrap().to_string_lossy(),
+            env::var(name).unwrap_or_default()
+        ))
+    );
+}
+
+#[test]
+#[cfg(windows)]
+/// Batch files should be found without their extension, like cmd.exe does.
+fn matching_executes_batch_file_without_extension() {
+    use std::io::Write;
+
+    let temp_dir = Builder::new()
+        .prefix("matching_executes_batch_file_without_extension")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let script_dir = Builder::new()
+        .prefix("matching_executes_batch_file_without_extension_script")
+        .tempdir()
+        .unwrap();
+    let mut script = File::create(script_dir.path().join("wrapper.bat")).unwrap();
+    writeln!(script, "@\"{}\" %*", path_to_testing_commandline()).unwrap();
+    drop(script);
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let matcher = SingleExecMatcher::new(
+        &script_dir.path().join("wrapper").to_string_lossy(),
+        &[temp_dir_path.as_ref(), "abc", "{}", "xyz"],
+        false,
+    )
+    .expect("Failed to create matcher");
+    let deps = FakeDependencies::new();
+    assert!(matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+
+    let mut f = File::open(temp_dir.path().join("1.txt")).expect("Failed to open output file");
+    let mut s = String::new();
+    f.read_to_string(&mut s)
+        .expect("failed to read output file");
+    assert_eq!(
+        s,
+        fix_up_slashes(&format!(
+            "cwd={}\nargs=\nabc\ntest_data/simple/abbbc\nxyz\n",
+            env::current_dir().unwrap().to_string_lossy()
+        ))
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn matching_fails_if_batch_file_fails() {
+    use std::io::Write;
+
+    let temp_dir = Builder::new()
+        .prefix("matching_fails_if_batch_file_fails")
+        .tempdir()
+        .unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+
+    let script_dir = Builder::new()
+        .prefix("matching_fails_if_batch_file_fails_script")
+        .tempdir()
+        .unwrap();
+    let mut script = File::create(script_dir.path().join("wrapper.cmd")).unwrap();
+    writeln!(script, "@\"{}\" %*", path_to_testing_commandline()).unwrap();
+    drop(script);
+
+    let abbbc = get_dir_entry_for("test_data/simple", "abbbc");
+    let matcher = SingleExecMatcher::new(
+        &script_dir.path().join("wrapper").to_string_lossy(),
+        &[temp_dir_path.as_ref(), "--exit_with_failure", "{}"],
+        false,
+    )
+    .expect("Failed to create matcher");
+    let deps = FakeDependencies::new();
+    assert!(!matcher.matches(&abbbc, &mut deps.new_matcher_io()));
+}
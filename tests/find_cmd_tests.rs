@@ -219,6 +219,35 @@ fn regex_types() {
         .success()
         .stderr(predicate::str::is_empty())
         .stdout(predicate::str::contains("teeest"));
+
+    // BSD's -E, which -regextype overrides
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            "-E",
+            &temp_dir_path,
+            "-regex",
+            &fix_up_regex_slashes(".*/te{1,3}st"),
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::contains("teeest"));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            "-E",
+            &temp_dir_path,
+            "-regextype",
+            "emacs",
+            "-regex",
+            &fix_up_regex_slashes(".*/te{1,3}st"),
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::is_empty());
 }
 
 #[test]
@@ -278,6 +307,229 @@ fn empty_files() {
         )));
 }
 
+#[test]
+fn find_double_dash_paths() {
+    let temp_dir = Builder::new()
+        .prefix("find_double_dash_paths")
+        .tempdir()
+        .unwrap();
+    for name in ["-foo", "-print", "dir"] {
+        File::create(temp_dir.path().join(name)).unwrap();
+    }
+
+    let find = |args: &[&str]| {
+        let mut command = Command::cargo_bin("find").expect("found binary");
+        command.args(args).current_dir(temp_dir.path());
+        command.assert()
+    };
+    // Names starting with - can be searched after --
+    find(&["--", "-foo", "dir", "-type", "f"])
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("-foo\ndir\n");
+    // But primaries start the expression, even if there's a file by that name
+    find(&["--", "-print"])
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::contains("./-print\n"));
+    // And it's a path even if there's no such file
+    find(&["--", "-nope"])
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("-nope"));
+}
+
+#[test]
+fn find_dedup_roots() {
+    let find = |args: &[&str]| {
+        let mut command = Command::cargo_bin("find").expect("found binary");
+        command
+            .args(["test_data/simple", "test_data/simple/subdir"])
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+    };
+    find(&["-sorted"]).stdout(fix_up_slashes(
+        "test_data/simple\n\
+         test_data/simple/abbbc\n\
+         test_data/simple/subdir\n\
+         test_data/simple/subdir/ABBBC\n\
+         test_data/simple/subdir\n\
+         test_data/simple/subdir/ABBBC\n",
+    ));
+    find(&["-sorted", "--dedup-roots"]).stdout(fix_up_slashes(
+        "test_data/simple\n\
+         test_data/simple/abbbc\n\
+         test_data/simple/subdir\n\
+         test_data/simple/subdir/ABBBC\n",
+    ));
+}
+
+#[test]
+fn find_relative() {
+    let find = |args: &[&str]| {
+        let mut command = Command::cargo_bin("find").expect("found binary");
+        command
+            .args(["test_data/simple", "test_data/simple/subdir"])
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+    };
+    find(&["-sorted", "--relative"]).stdout(fix_up_slashes(
+        ".\n\
+         abbbc\n\
+         subdir\n\
+         subdir/ABBBC\n\
+         .\n\
+         ABBBC\n",
+    ));
+    find(&["-sorted", "-type", "f", "-printf", "%p %P\\n", "--relative"]).stdout(fix_up_slashes(
+        "abbbc abbbc\n\
+             subdir/ABBBC subdir/ABBBC\n\
+             ABBBC ABBBC\n",
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn find_normalize_paths() {
+    let find = |args: &[&str]| {
+        let mut command = Command::cargo_bin("find").expect("found binary");
+        command
+            .args(["./test_data//simple/./", "-sorted"])
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+    };
+    find(&["-path", "./test_data/simple/*"]).stdout("");
+    find(&["-path", "./test_data/simple/*", "--normalize-paths"]).stdout(
+        "./test_data/simple/\n\
+         ./test_data/simple/abbbc\n\
+         ./test_data/simple/subdir\n\
+         ./test_data/simple/subdir/ABBBC\n",
+    );
+    find(&["--normalize-paths", "-maxdepth", "0"]).stdout("./test_data/simple/\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn find_dot_starting_points() {
+    let find = |args: &[&str]| {
+        let mut command = Command::cargo_bin("find").expect("found binary");
+        command
+            .current_dir("test_data/simple")
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+    };
+    find(&[
+        ".",
+        "..",
+        "/",
+        "./",
+        "subdir/..",
+        "-maxdepth",
+        "0",
+        "-printf",
+        "%p|%f|%P|%h\n",
+    ])
+    .stdout(
+        ".|.||.\n\
+         ..|..||.\n\
+         /|/||\n\
+         ./|./||.\n\
+         subdir/..|..||subdir\n",
+    );
+    find(&[".", "-name", ".", "-prune"]).stdout(".\n");
+    find(&[".", "-sorted", "!", "-name", ".", "-prune"]).stdout("./abbbc\n./subdir\n");
+    find(&["subdir/..", "-name", "..", "-prune"]).stdout("subdir/..\n");
+    find(&["/", "-maxdepth", "0", "-name", "/"]).stdout("/\n");
+}
+
+#[test]
+fn find_sorted_keys() {
+    let temp_dir = Builder::new().prefix("find_sorted_keys").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    for (name, size) in [("file10", 2), ("file2", 1), ("file1", 3), ("z", 1)] {
+        fs::write(temp_dir.path().join(name), "x".repeat(size)).unwrap();
+    }
+
+    let sorted = |order: &str, expected: &[&str]| {
+        let expected: String = expected.iter().map(|name| format!("{name}\n")).collect();
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args([&temp_dir_path, "-mindepth", "1", order, "-printf", "%f\n"])
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout(expected);
+    };
+    sorted("-sorted", &["file1", "file10", "file2", "z"]);
+    sorted("-sorted=name", &["file1", "file10", "file2", "z"]);
+    sorted("-sorted=natural", &["file1", "file2", "file10", "z"]);
+    sorted("-sorted=size", &["file2", "z", "file10", "file1"]);
+    sorted("-sorted=size,natural", &["file2", "z", "file10", "file1"]);
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&temp_dir_path, "-sorted=atime"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr("Error: invalid sort key 'atime': expected name, natural, size or mtime\n");
+}
+
+#[test]
+fn find_breadth_first() {
+    let find = |args: &[&str]| {
+        let mut command = Command::cargo_bin("find").expect("found binary");
+        command.arg("test_data/depth").args(args).assert()
+    };
+    find(&["-bfs", "-sorted"])
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes(
+            "test_data/depth\n\
+             test_data/depth/1\n\
+             test_data/depth/f0\n\
+             test_data/depth/1/2\n\
+             test_data/depth/1/f1\n\
+             test_data/depth/1/2/3\n\
+             test_data/depth/1/2/f2\n\
+             test_data/depth/1/2/3/f3\n",
+        ));
+    find(&[
+        "-bfs",
+        "-sorted",
+        "-maxdepth",
+        "2",
+        "-name",
+        "2",
+        "-prune",
+        "-o",
+        "-print",
+    ])
+    .success()
+    .stderr(predicate::str::is_empty())
+    .stdout(fix_up_slashes(
+        "test_data/depth\n\
+             test_data/depth/1\n\
+             test_data/depth/f0\n\
+             test_data/depth/1/f1\n",
+    ));
+
+    for option in ["-depth", "-delete"] {
+        find(&["-bfs", option])
+            .failure()
+            .stdout(predicate::str::is_empty())
+            .stderr("Error: -bfs can't be combined with -depth, or -delete, which turns it on\n");
+    }
+}
+
 #[serial(working_dir)]
 #[test]
 fn find_printf() {
@@ -1004,51 +1256,1483 @@ fn find_fprinter() {
 }
 
 #[test]
-#[serial(working_dir)]
-fn find_follow() {
+#[cfg(target_os = "linux")]
+fn find_fprint_write_error() {
+    for p in ["-fprint", "-fls"] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args(["test_data/simple", "-maxdepth", "0", p, "/dev/full"])
+            .assert()
+            .code(1)
+            .stdout(predicate::str::is_empty())
+            .stderr(predicate::str::contains("No space left on device"));
+    }
+}
+
+#[test]
+fn find_copy_and_move() {
+    use std::path::Path;
+
+    let temp_dir = Builder::new()
+        .prefix("find_copy_and_move")
+        .tempdir()
+        .unwrap();
+    let root = temp_dir.path().join("root");
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(root.join("a.txt"), "a").unwrap();
+    fs::write(root.join("sub/b.txt"), "b").unwrap();
+    fs::write(root.join("sub/c.log"), "c").unwrap();
+    let root = root.to_string_lossy();
+    let dest = temp_dir.path().join("dest");
+    let dest_name = dest.to_string_lossy();
+
     Command::cargo_bin("find")
         .expect("found binary")
-        .args(["test_data/links/link-f", "-follow"])
+        .args([&root, "-name", "*.txt", "-copy-to", &dest_name])
         .assert()
         .success()
-        .stdout(predicate::str::contains("test_data/links/link-f"))
+        .stdout(predicate::str::is_empty())
         .stderr(predicate::str::is_empty());
-}
+    assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "a");
+    assert_eq!(fs::read_to_string(dest.join("sub/b.txt")).unwrap(), "b");
+    assert!(!dest.join("sub/c.log").exists());
 
-#[test]
-#[serial(working_dir)]
-fn find_fprintf() {
-    let _ = fs::remove_file("test_data/find_fprintf");
+    // Copying again runs into what's already there
+    fs::write(format!("{root}/a.txt"), "new").unwrap();
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("{root}/a.txt"), "-copy-to", &dest_name])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("the destination already exists"));
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("{root}/a.txt"), "-copy-to", &dest_name])
+        .args(["--collision", "skip"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "a");
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("{root}/a.txt"), "--collision", "overwrite"])
+        .args(["-copy-to", &dest_name])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "new");
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&root, "--collision", "clobber"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must be one of"));
+
+    // A dry run doesn't touch anything
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("{root}/sub"), "-name", "*.log", "--dry-run"])
+        .args(["-move-to", &dest_name])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!(
+            "would move: {root}/sub"
+        )));
+    assert!(Path::new(&format!("{root}/sub/c.log")).exists());
 
     Command::cargo_bin("find")
         .expect("found binary")
         .args([
-            "test_data/simple",
-            "-fprintf",
-            "test_data/find_fprintf",
-            "%h %H %p %P",
+            &format!("{root}/sub"),
+            "-name",
+            "*.log",
+            "-move-to",
+            &dest_name,
         ])
         .assert()
+        .success();
+    assert!(!Path::new(&format!("{root}/sub/c.log")).exists());
+    assert_eq!(fs::read_to_string(dest.join("c.log")).unwrap(), "c");
+}
+
+#[test]
+fn find_move_starting_point() {
+    for depth in [&[][..], &["-depth"]] {
+        let temp_dir = Builder::new()
+            .prefix("find_move_starting_point")
+            .tempdir()
+            .unwrap();
+        let root = temp_dir.path().join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/a"), "a").unwrap();
+        let dest = temp_dir.path().join("dest");
+        // So that sub is merged with it rather than moved whole
+        fs::create_dir_all(dest.join("sub")).unwrap();
+
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .arg(&root)
+            .args(depth)
+            .arg("-move-to")
+            .arg(&dest)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty());
+        assert_eq!(fs::read_to_string(dest.join("sub/a")).unwrap(), "a");
+        assert!(!root.exists(), "{depth:?}");
+
+        // What's left behind keeps its directory
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/b"), "b").unwrap();
+        fs::write(root.join("c"), "c").unwrap();
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .arg(&root)
+            .args(depth)
+            .args(["(", "-type", "d", "-o", "-name", "b", ")", "-move-to"])
+            .arg(&dest)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty());
+        assert_eq!(fs::read_to_string(dest.join("sub/b")).unwrap(), "b");
+        assert!(!root.join("sub").exists(), "{depth:?}");
+        assert!(root.join("c").exists(), "{depth:?}");
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn find_chmod() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let temp_dir = Builder::new().prefix("find_chmod").tempdir().unwrap();
+    let root = temp_dir.path().to_string_lossy();
+    let file = temp_dir.path().join("file");
+    fs::write(&file, "").unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+    let mode = || fs::metadata(&file).unwrap().mode() & 0o7777;
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&root, "-type", "f", "-chmod", "u+x,go=", "-print"])
+        .assert()
         .success()
-        .stdout(predicate::str::is_empty())
+        .stdout(format!("{root}/file\n"));
+    assert_eq!(mode(), 0o700);
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&root, "-type", "f", "-chmod", "640", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(format!("would chmod: {root}/file to 0640\n"));
+    assert_eq!(mode(), 0o700);
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&root, "-chmod", "u+q"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid mode 'u+q'"));
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&root, "-chown", "no-such-user"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid user: 'no-such-user'"));
+}
+
+#[test]
+fn find_touch() {
+    use filetime::{set_file_times, FileTime};
+
+    let temp_dir = Builder::new().prefix("find_touch").tempdir().unwrap();
+    let file = temp_dir.path().join("file");
+    fs::write(&file, "").unwrap();
+    let old = FileTime::from_unix_time(1_000_000_000, 0);
+    set_file_times(&file, old, old).unwrap();
+    let file_name = file.to_string_lossy();
+    let times = || {
+        let meta = fs::metadata(&file).unwrap();
+        (
+            FileTime::from_last_access_time(&meta),
+            FileTime::from_last_modification_time(&meta),
+        )
+    };
+
+    // -a is the access time here, not an operator
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&file_name, "-touch", "-a", "-print"])
+        .assert()
+        .success()
+        .stdout(format!("{file_name}\n"));
+    let (atime, mtime) = times();
+    assert!(atime > old);
+    assert_eq!(mtime, old);
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&file_name, "-touch"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    assert!(times().1 > old);
+}
+
+#[test]
+#[cfg(unix)]
+fn find_tar() {
+    let temp_dir = Builder::new().prefix("find_tar").tempdir().unwrap();
+    let root = temp_dir.path().join("root");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("a"), "a\n").unwrap();
+    fs::write(root.join("b"), "bb\n").unwrap();
+    let root = root.to_string_lossy();
+    let archive = temp_dir.path().join("out.tar");
+    let archive_name = archive.to_string_lossy();
+
+    // Only regular files go in, and the action is false for anything else
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&root, "-sorted", "!", "-tar", &archive_name, "-print"])
+        .assert()
+        .success()
+        .stdout(format!("{root}\n"))
         .stderr(predicate::str::is_empty());
 
-    // read test_data/find_fprintf
-    let mut f = File::open("test_data/find_fprintf").unwrap();
-    let mut contents = String::new();
-    f.read_to_string(&mut contents).unwrap();
-    assert!(contents.contains("test_data/simple"));
+    let listing = Command::new("tar")
+        .arg("-tf")
+        .arg(&archive)
+        .output()
+        .unwrap();
+    assert!(listing.status.success(), "{listing:?}");
+    let root = root.trim_start_matches('/');
+    assert_eq!(
+        String::from_utf8_lossy(&listing.stdout),
+        format!("{root}/a\n{root}/b\n")
+    );
 
-    let _ = fs::remove_file("test_data/find_fprintf");
+    // The archive can go to stdout, and keeps the contents
+    let output = Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("/{root}"), "-name", "b", "-tar", "-"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(output.stdout.len() % 10240, 0);
+    Command::new("tar")
+        .args(["-xOf", "-"])
+        .write_stdin(output.stdout)
+        .assert()
+        .success()
+        .stdout("bb\n");
+
+    // A dry run doesn't touch the archive
+    fs::remove_file(&archive).unwrap();
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("/{root}/a"), "-tar", &archive_name, "--dry-run"])
+        .assert()
+        .success()
+        .stdout(format!("would archive: /{root}/a\n"));
+    assert!(!archive.exists());
+
+    // Nor does an expression that can't be built
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("/{root}/a"), "-tar", &archive_name, "-size", "x"])
+        .assert()
+        .failure();
+    assert!(!archive.exists());
+
+    // But with no matches, the archive is still there, and empty
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("/{root}"), "-name", "none", "-tar", &archive_name])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    assert_eq!(fs::read(&archive).unwrap(), vec![0; 10240]);
+
+    // An archive that can't be created is reported once
+    let missing = temp_dir.path().join("missing/out.tar");
+    let missing = missing.to_string_lossy();
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&format!("/{root}"), "-sorted", "-tar", &missing])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(
+            predicate::str::starts_with(format!("find: '{missing}': ")).and(predicate::function(
+                |stderr: &str| stderr.lines().count() == 1,
+            )),
+        );
 }
 
 #[test]
-#[serial(working_dir)]
-fn find_ls() {
+#[cfg(unix)]
+fn find_sparse() {
+    let temp_dir = Builder::new().prefix("find_sparse").tempdir().unwrap();
+    let root = temp_dir.path().to_string_lossy();
+    File::create(temp_dir.path().join("sparse"))
+        .unwrap()
+        .set_len(1 << 24)
+        .unwrap();
+    fs::write(temp_dir.path().join("dense"), "dense").unwrap();
+
+    // The ratio is optional, so -sparse is happy to be followed by -print
+    for args in [&["-sparse", "-print"][..], &["-sparse", "0.5"]] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args([&root, "-type", "f"])
+            .args(args)
+            .assert()
+            .success()
+            .stdout(format!("{root}/sparse\n"))
+            .stderr(predicate::str::is_empty());
+    }
+
     Command::cargo_bin("find")
         .expect("found binary")
-        .args(["./test_data/simple/subdir", "-ls"])
+        .args([&root, "-sparse", "0"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("expected a positive ratio"));
+}
+
+#[test]
+fn find_color() {
+    // Only -print is colored, whether it's explicit or not
+    for args in [&["--color=always"][..], &["-print", "--color"]] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .env("LS_COLORS", "di=01;34:fi=:*c=33")
+            .args(["test_data/simple", "-maxdepth", "1"])
+            .args(args)
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with(fix_up_slashes(
+                "\x1b[01;34mtest_data/simple\x1b[0m\n\x1b[33mtest_data/simple/abbbc\x1b[0m\n",
+            )))
+            .stderr(predicate::str::is_empty());
+    }
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-maxdepth", "0", "--color", "-print0"])
+        .assert()
+        .success()
+        .stdout("test_data/simple\0")
+        .stderr(predicate::str::is_empty());
+
+    // Not a terminal
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-maxdepth", "0", "--color=auto"])
         .assert()
         .success()
+        .stdout("test_data/simple\n")
         .stderr(predicate::str::is_empty());
 }
+
+#[test]
+fn find_hyperlink() {
+    let output = Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            "test_data/simple",
+            "-maxdepth",
+            "0",
+            "--hyperlink",
+            "--color=never",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    // ESC ] 8 ; ; file://HOST/.../test_data/simple ESC \ test_data/simple ESC ] 8 ; ; ESC \
+    let re = Regex::new(
+        r"^\x1b\]8;;file://[^/]*/.*/test_data/simple\x1b\\test_data/simple\x1b\]8;;\x1b\\\n$",
+    )
+    .unwrap();
+    assert!(re.is_match(&stdout), "{stdout:?}");
+
+    // Not a terminal
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-maxdepth", "0", "--hyperlink=auto"])
+        .assert()
+        .success()
+        .stdout("test_data/simple\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn find_exec_timeout() {
+    // --exec-timeout applies wherever it appears
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            "test_data/simple",
+            "-maxdepth",
+            "0",
+            "-exec",
+            "sleep",
+            "10",
+            ";",
+        ])
+        .args(["-print", "--exec-timeout", "0.2"])
+        .timeout(std::time::Duration::from_secs(5))
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr("find: 'sleep' timed out after 0.2s and was killed\n");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-maxdepth", "0", "--exec-timeout", "1m"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("invalid time interval '1m'"));
+}
+
+#[test]
+#[cfg(unix)]
+fn find_errors_json() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "test_data/nope", "-maxdepth", "0"])
+        .args(["--errors=json", "-exec", "test_data/nope", ";"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_empty())
+        .stderr(
+            "{\"errors\":2,\"kinds\":{\"action\":1,\"not_found\":1},\"items\":[\
+             {\"kind\":\"action\",\"path\":\"test_data/simple\",\
+             \"message\":\"Failed to run test_data/nope: No such file or directory (os error 2)\"},\
+             {\"kind\":\"not_found\",\"path\":\"test_data/nope\",\
+             \"message\":\"test_data/nope: No such file or directory (os error 2)\"}]}\n",
+        );
+
+    // Even with no errors, there's a summary
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-maxdepth", "0", "--errors", "json"])
+        .assert()
+        .success()
+        .stdout("test_data/simple\n")
+        .stderr("{\"errors\":0,\"kinds\":{},\"items\":[]}\n");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "--errors=xml"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("expected text or json"));
+}
+
+#[test]
+#[cfg(unix)]
+fn find_exec_limits() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-maxdepth", "0", "--exec-nice", "3"])
+        .args(["-exec", "sh", "-c", "ulimit -t; ulimit -n", ";"])
+        .args(["--exec-rlimit", "cpu=5,nofile=40"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("5\n40\n");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "--exec-nice", "lots"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("invalid niceness 'lots'"));
+}
+
+#[test]
+#[cfg(unix)]
+fn find_ok() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-sorted", "-maxdepth", "1"])
+        .args(["-ok", "echo", "yes:", "{}", ";", "-o", "-print"])
+        .env("LC_ALL", "C")
+        // The last one gets no answer at all
+        .write_stdin("y\nno\n")
+        .assert()
+        .success()
+        .stdout(
+            "yes: test_data/simple\n\
+             test_data/simple/abbbc\n\
+             test_data/simple/subdir\n",
+        )
+        .stderr(
+            "< echo ... test_data/simple > ? \
+             < echo ... test_data/simple/abbbc > ? \
+             < echo ... test_data/simple/subdir > ? ",
+        );
+
+    // The command doesn't get the answers
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/simple", "-maxdepth", "0"])
+        .args(["-okdir", "sh", "-c", "cat; echo {}", ";"])
+        .env("LC_ALL", "C")
+        .write_stdin("Y\nmore\n")
+        .assert()
+        .success()
+        .stdout("./simple\n")
+        .stderr("< sh ... test_data/simple > ? ");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", "-", "-ok", "echo", "{}", ";"])
+        .write_stdin("test_data\0")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(
+            "Error: option -files0-from reading from standard input cannot be combined \
+             with -ok, -okdir\n",
+        );
+}
+
+#[test]
+#[cfg(unix)]
+#[serial(working_dir)]
+fn find_exec_sandbox() {
+    let find = |args: &[&str]| {
+        let mut command = Command::cargo_bin("find").expect("found binary");
+        command
+            .args(["test_data/simple", "-maxdepth", "0"])
+            .args(args)
+            .args(["-exec", "sh", "-c", "echo \"$(pwd) [$FOO] [$BAR]\"", ";"])
+            .env("FOO", "foo")
+            .env("BAR", "bar")
+            .assert()
+    };
+    let cwd = env::current_dir().unwrap();
+    find(&[])
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(format!("{} [foo] [bar]\n", cwd.display()));
+    find(&["--exec-sandbox"])
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(format!("{} [] []\n", cwd.display()));
+    find(&["--exec-sandbox=/", "--exec-keep-env", "FOO"])
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("/ [foo] []\n");
+    find(&["--exec-sandbox=test_data/nope"])
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr("Error: 'test_data/nope' is not a directory\n");
+}
+
+#[test]
+#[serial(working_dir)]
+fn find_follow() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["test_data/links/link-f", "-follow"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("test_data/links/link-f"))
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+#[cfg(unix)]
+#[serial(working_dir)]
+fn find_symlink_loops() {
+    if let Err(e) = symlink("link-loop", "test_data/links/link-loop") {
+        assert!(
+            e.kind() == ErrorKind::AlreadyExists,
+            "Failed to create sym link: {e:?}"
+        );
+    }
+
+    // A link to itself can't be followed, which is an error, but the search
+    // carries on
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["--gnu-errors", "-L", "test_data/links", "-name", "abbbc"])
+        .env("LC_ALL", "C")
+        .assert()
+        .code(1)
+        .stdout("test_data/links/abbbc\n")
+        .stderr("find: 'test_data/links/link-loop': Too many levels of symbolic links\n");
+
+    // Each link back to an ancestor is reported where it's found
+    let temp_dir = Builder::new()
+        .prefix("find_symlink_loops")
+        .tempdir()
+        .unwrap();
+    let root = temp_dir.path().to_string_lossy();
+    fs::create_dir_all(temp_dir.path().join("d/e")).unwrap();
+    symlink("..", temp_dir.path().join("d/up")).unwrap();
+    symlink("../..", temp_dir.path().join("d/e/up")).unwrap();
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["--gnu-errors", "-L", &root, "-sorted"])
+        .env("LC_ALL", "C")
+        .assert()
+        .code(1)
+        .stdout(format!("{root}\n{root}/d\n{root}/d/e\n"))
+        .stderr(format!(
+            "find: File system loop detected; '{root}/d/e/up' is part of the same file \
+             system loop as '{root}'.\n\
+             find: File system loop detected; '{root}/d/up' is part of the same file \
+             system loop as '{root}'.\n"
+        ));
+
+    // Without -L, they're just links
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&root, "-type", "l", "-sorted"])
+        .assert()
+        .success()
+        .stdout(format!("{root}/d/e/up\n{root}/d/up\n"))
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+#[serial(working_dir)]
+fn find_fprintf() {
+    let _ = fs::remove_file("test_data/find_fprintf");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            "test_data/simple",
+            "-fprintf",
+            "test_data/find_fprintf",
+            "%h %H %p %P",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::is_empty());
+
+    // read test_data/find_fprintf
+    let mut f = File::open("test_data/find_fprintf").unwrap();
+    let mut contents = String::new();
+    f.read_to_string(&mut contents).unwrap();
+    assert!(contents.contains("test_data/simple"));
+
+    let _ = fs::remove_file("test_data/find_fprintf");
+}
+
+#[test]
+#[serial(working_dir)]
+fn find_ls() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple/subdir", "-ls"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn find_dry_run() {
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let test_file = temp_dir.path().join("test");
+    File::create(&test_file).expect("created test file");
+
+    // --dry-run applies to -delete even when it comes afterwards.
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&temp_dir_path, "-name", "test", "-delete", "--dry-run"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(format!("would delete: {}\n", test_file.to_string_lossy()));
+
+    assert!(test_file.exists(), "test file should not be deleted");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            &temp_dir_path,
+            "--dry-run",
+            "-name",
+            "test",
+            "-exec",
+            "rm",
+            "{}",
+            ";",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::starts_with("would run: ").and(predicate::str::contains("\"rm\"")));
+
+    assert!(test_file.exists(), "test file should not be deleted");
+}
+
+#[test]
+fn find_log() {
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let log_dir = Builder::new().prefix("find_cmd_log_").tempdir().unwrap();
+
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let test_file = temp_dir.path().join("test");
+    let log_file = log_dir.path().join("log");
+
+    // The second run adds to the records of the first
+    for _ in 0..2 {
+        File::create(&test_file).expect("created test file");
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args([
+                &temp_dir_path,
+                "-log",
+                &log_file.to_string_lossy(),
+                "-name",
+                "test",
+                "-print",
+                "-delete",
+            ])
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty());
+
+        assert!(!test_file.exists(), "test file should be deleted");
+    }
+
+    let log = fs::read_to_string(&log_file).expect("log file should exist");
+    let lines: Vec<_> = log.lines().collect();
+    assert_eq!(lines.len(), 4, "unexpected log: {log}");
+    let re =
+        Regex::new(r#"^\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d\.\d{3}Z\t(\w+)\t"(.*)"\t(.*)$"#).unwrap();
+    for (line, action) in lines.iter().zip(["print", "delete", "print", "delete"]) {
+        let captures = re.captures(line).expect("log line should be well formed");
+        assert_eq!(&captures[1], action);
+        assert_eq!(
+            &captures[2],
+            test_file.to_string_lossy().escape_debug().to_string()
+        );
+        assert_eq!(&captures[3], "ok");
+    }
+}
+
+#[test]
+#[serial(working_dir)]
+fn find_stats() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "--stats", "-name", "no-such-file"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(
+            predicate::str::contains("entries scanned:     4\n")
+                .and(predicate::str::contains("directories visited: 2\n"))
+                .and(predicate::str::is_match(r"stat calls: +\d+\n").unwrap())
+                .and(predicate::str::is_match(r"time taken: +\d+\.\d{3}s\n").unwrap()),
+        );
+}
+
+#[test]
+#[cfg(unix)]
+fn find_path_prefix_pruning() {
+    let temp_dir = Builder::new()
+        .prefix("find_path_prefix_pruning")
+        .tempdir()
+        .unwrap();
+    for dir in ["a/x/y", "a/z", "ab/x", "b/a/x", "[a]/x"] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+    }
+
+    // The same files are found, whichever directories can be skipped
+    let find = |args: &[&str], expected: &[&str], scanned: usize| {
+        let expected: String = expected.iter().map(|path| format!("{path}\n")).collect();
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .current_dir(temp_dir.path())
+            .args(["."])
+            .args(args)
+            .args(["-sorted", "--stats"])
+            .assert()
+            .success()
+            .stdout(expected)
+            .stderr(predicate::str::contains(format!(
+                "entries scanned:     {scanned}\n"
+            )));
+    };
+    find(&["-path", "./a/*"], &["./a/x", "./a/x/y", "./a/z"], 8);
+    find(&["-path", "./a*/x"], &["./a/x", "./ab/x"], 9);
+    find(&["-path", "./?/x"], &["./a/x"], 12);
+    find(&["-path", "./[a]/*"], &["./a/x", "./a/x/y", "./a/z"], 12);
+    find(&["-path", r"./\[a]/*"], &["./[a]/x"], 6);
+    find(&["-path", "./a"], &["./a"], 9);
+    find(
+        &["-path", "./b/*", "-o", "-path", "./ab*"],
+        &["./ab", "./ab/x", "./b/a", "./b/a/x"],
+        8,
+    );
+    find(&["-type", "d", "-path", "./b/a*"], &["./b/a", "./b/a/x"], 7);
+    find(&["-path", "./a/x/*", "-depth"], &["./a/x/y"], 8);
+    // Here, files that don't match -path are printed too, or might be
+    find(
+        &["-print", "-path", "./a/x", "-quit"],
+        &[".", "./[a]", "./[a]/x", "./a", "./a/x"],
+        5,
+    );
+    find(
+        &["-path", "./a/*", "-o", "-name", "x"],
+        &["./[a]/x", "./a/x", "./a/x/y", "./a/z", "./ab/x", "./b/a/x"],
+        12,
+    );
+    find(&["!", "-path", "./a/*", "-name", "y"], &[], 12);
+}
+
+#[test]
+fn find_progress_not_a_terminal() {
+    // Nothing to see unless stderr is a terminal
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "--progress", "-name", "abbbc"])
+        .assert()
+        .success()
+        .stdout(fix_up_slashes("./test_data/simple/abbbc\n"))
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn find_print_expression() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            "./test_data/simple",
+            "-print-expression",
+            "-sorted",
+            "-name",
+            "a*",
+            "-o",
+            "-not",
+            "-type",
+            "d",
+        ])
+        .assert()
+        .success()
+        .stdout(fix_up_slashes(
+            "./test_data/simple/abbbc\n./test_data/simple/subdir/ABBBC\n",
+        ))
+        .stderr("( -print-expression -a -sorted -a -name 'a*' -o ! -type d ) -a -print\n");
+}
+
+#[test]
+fn find_caseless_unicode() {
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let test_file = temp_dir.path().join("Éclair");
+    File::create(&test_file).expect("created test file");
+    let expected = format!("{}\n", test_file.to_string_lossy());
+
+    for args in [["-iname", "éCLAIR"], ["-iregex", ".*éCLAIR"]] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .env("LC_ALL", "C.UTF-8")
+            .arg(&*temp_dir_path)
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout(expected.clone());
+
+        // Only ASCII letters have a case in the C locale
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .env("LC_ALL", "C")
+            .arg(&*temp_dir_path)
+            .args(args)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout("");
+    }
+}
+
+#[test]
+fn find_files0_from() {
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let list = temp_dir.path().join("list");
+    let list_path = list.to_string_lossy();
+    fs::write(
+        &list,
+        "./test_data/simple/abbbc\0\0./test_data/simple/subdir\0",
+    )
+    .unwrap();
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", &list_path, "-sorted"])
+        .assert()
+        .code(1)
+        .stdout(fix_up_slashes(
+            "./test_data/simple/abbbc\n\
+             ./test_data/simple/subdir\n\
+             ./test_data/simple/subdir/ABBBC\n",
+        ))
+        .stderr(format!(
+            "Error: '{list_path}':2: invalid zero-length file name\n"
+        ));
+
+    // The last name doesn't need to be terminated
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", "-", "-name", "a*"])
+        .write_stdin("./test_data/simple/abbbc\0./test_data/simple/subdir")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes("./test_data/simple/abbbc\n"));
+
+    // An empty file is an empty list of starting points, not "."
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", "-"])
+        .write_stdin("")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "-files0-from", &list_path])
+        .assert()
+        .code(1)
+        .stderr(
+            "Error: extra operand './test_data/simple'\n\
+             file operands cannot be combined with -files0-from\n",
+        )
+        .stdout("");
+
+    let missing = temp_dir.path().join("missing");
+    let missing_path = missing.to_string_lossy();
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", &missing_path])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::starts_with(format!(
+            "Error: cannot open '{missing_path}' for reading: "
+        )))
+        .stdout("");
+}
+
+#[test]
+fn find_extra_paths_from() {
+    // The command line's starting points come first
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            "./test_data/simple/abbbc",
+            "--extra-paths-from",
+            "-",
+            "-sorted",
+        ])
+        .write_stdin("./test_data/simple/subdir\0\0")
+        .assert()
+        .code(1)
+        .stdout(fix_up_slashes(
+            "./test_data/simple/abbbc\n\
+             ./test_data/simple/subdir\n\
+             ./test_data/simple/subdir/ABBBC\n",
+        ))
+        .stderr("Error: '(standard input)':2: invalid zero-length file name\n");
+
+    // Without any, "." isn't searched
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["--extra-paths-from", "-"])
+        .write_stdin("./test_data/simple/abbbc")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes("./test_data/simple/abbbc\n"));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-files0-from", "-", "--extra-paths-from", "-"])
+        .assert()
+        .code(1)
+        .stderr("Error: option --extra-paths-from cannot be combined with -files0-from\n")
+        .stdout("");
+}
+
+// Other platforms don't let everyone set the sticky bit on files
+#[cfg(target_os = "linux")]
+#[test]
+fn find_symbolic_permissions() {
+    use nix::sys::stat::Mode;
+    use nix::unistd::mkfifo;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let temp_dir_path = temp_dir.path().to_string_lossy();
+    let chmod = |name: &str, mode: u32| {
+        let path = temp_dir.path().join(name);
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    };
+
+    for (name, mode) in [
+        ("f0000", 0o0000),
+        ("f1776", 0o1776),
+        ("f2644", 0o2644),
+        ("f2755", 0o2755),
+        ("f4644", 0o4644),
+        ("f4755", 0o4755),
+        ("f6000", 0o6000),
+        ("f7777", 0o7777),
+    ] {
+        File::create(temp_dir.path().join(name)).unwrap();
+        chmod(name, mode);
+    }
+    fs::create_dir(temp_dir.path().join("d1777")).unwrap();
+    chmod("d1777", 0o1777);
+    mkfifo(
+        &temp_dir.path().join("fifo"),
+        Mode::from_bits_truncate(0o644),
+    )
+    .unwrap();
+    chmod("fifo", 0o644);
+    symlink("f0000", temp_dir.path().join("link")).unwrap();
+    let _socket = UnixListener::bind(temp_dir.path().join("sock")).unwrap();
+    chmod("sock", 0o755);
+
+    // What GNU find prints for the same files
+    let expected = "\
+        drwxrwxrwt d1777\n\
+        ---------- f0000\n\
+        -rwxrwxrwT f1776\n\
+        -rw-r-Sr-- f2644\n\
+        -rwxr-sr-x f2755\n\
+        -rwSr--r-- f4644\n\
+        -rwsr-xr-x f4755\n\
+        ---S--S--- f6000\n\
+        -rwsrwsrwt f7777\n\
+        prw-r--r-- fifo\n\
+        lrwxrwxrwx link\n\
+        srwxr-xr-x sock\n";
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([
+            &*temp_dir_path,
+            "-mindepth",
+            "1",
+            "-sorted",
+            "-printf",
+            "%M %f\\n",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(expected);
+
+    let output = Command::cargo_bin("find")
+        .expect("found binary")
+        .args([&*temp_dir_path, "-mindepth", "1", "-sorted", "-ls"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let ls_permissions: String = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let name = fields.last().unwrap().rsplit('/').next().unwrap();
+            format!("{} {}\n", fields[2], name)
+        })
+        .collect();
+    assert_eq!(ls_permissions, expected);
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["/dev/null", "-printf", "%M\\n"])
+        .assert()
+        .success()
+        .stdout("crw-rw-rw-\n");
+}
+
+#[test]
+fn find_newer_missing_reference() {
+    for arg in ["-newer", "-anewer", "-newermc"] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args(["./test_data/simple", arg, "./test_data/simple/missing"])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::starts_with(fix_up_slashes(
+                "Error: './test_data/simple/missing': ",
+            )))
+            .stdout("");
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn find_type_and_xtype_with_links() {
+    let temp_dir = Builder::new().prefix("find_xtype").tempdir().unwrap();
+    let root = temp_dir.path();
+    File::create(root.join("file")).unwrap();
+    fs::create_dir(root.join("dir")).unwrap();
+    symlink("file", root.join("link-f")).unwrap();
+    symlink("dir", root.join("link-d")).unwrap();
+    symlink("missing", root.join("link-missing")).unwrap();
+    symlink("file/x", root.join("link-notdir")).unwrap();
+    let root = root.to_string_lossy();
+
+    // -xtype checks what -type doesn't: the target when not following links,
+    // and the link itself when following them. Under -L, only broken links
+    // are still links.
+    let links = "link-d link-f link-missing link-notdir";
+    let broken = "link-missing link-notdir";
+    let cases = [
+        ("-P", "-type", "l", links),
+        ("-P", "-type", "f", "file"),
+        ("-P", "-type", "d", "dir"),
+        ("-P", "-xtype", "l", broken),
+        ("-P", "-xtype", "f", "file link-f"),
+        ("-P", "-xtype", "d", "dir link-d"),
+        ("-H", "-type", "l", links),
+        ("-H", "-xtype", "l", broken),
+        ("-H", "-xtype", "f", "file link-f"),
+        ("-L", "-type", "l", broken),
+        ("-L", "-type", "f", "file link-f"),
+        ("-L", "-type", "d", "dir link-d"),
+        ("-L", "-xtype", "l", links),
+        ("-L", "-xtype", "f", "file"),
+        ("-L", "-xtype", "d", "dir"),
+    ];
+    for (follow, test, file_type, expected) in cases {
+        let output = Command::cargo_bin("find")
+            .expect("found binary")
+            .args([follow, &root, "-mindepth", "1", "-maxdepth", "1"])
+            .args([test, file_type, "-printf", "%f\\n"])
+            .output()
+            .unwrap();
+        let mut names: Vec<_> = std::str::from_utf8(&output.stdout)
+            .unwrap()
+            .lines()
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names.join(" "), expected, "{follow} {test} {file_type}");
+    }
+
+    // -H follows the starting points, but nothing below them
+    for (test, file_type) in [("-type", "d"), ("-xtype", "l")] {
+        Command::cargo_bin("find")
+            .expect("found binary")
+            .args(["-H", &format!("{root}/link-d"), "-maxdepth", "0"])
+            .args([test, file_type, "-printf", "%f\\n"])
+            .assert()
+            .success()
+            .stdout("link-d\n");
+    }
+}
+
+#[test]
+fn find_expr_file() {
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let expr = temp_dir.path().join("expr");
+    let expr_path = expr.to_string_lossy();
+    fs::write(
+        &expr,
+        "# Everything called abbbc, whatever the case\n\
+         -iname 'ab*c' # not just abbbc\n\
+         -a ! -name \"*c\\\"\" -print\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "-sorted", "--expr-file", &expr_path])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes(
+            "./test_data/simple/abbbc\n\
+             ./test_data/simple/subdir/ABBBC\n",
+        ));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "-sorted", "--expr-file=-"])
+        .write_stdin("-name 'abb bc' -o -name abbbc")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes("./test_data/simple/abbbc\n"));
+
+    fs::write(&expr, "-name 'abbbc\n").unwrap();
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "--expr-file", &expr_path])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(format!("Error: '{expr_path}':1: unterminated ' quote\n"));
+}
+
+#[test]
+fn find_default_options() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "-name", "a*"])
+        .env("FIND_OPTIONS", "-P -sorted -maxdepth '1'")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes("./test_data/simple/abbbc\n"));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["--no-defaults", "./test_data/simple", "-iname", "a*"])
+        .env("FIND_OPTIONS", "-sorted -maxdepth 0")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes(
+            "./test_data/simple/abbbc\n\
+             ./test_data/simple/subdir/ABBBC\n",
+        ));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple"])
+        .env("FIND_OPTIONS", "-name 'a")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("Error: FIND_OPTIONS: unterminated ' quote\n");
+}
+
+#[test]
+fn find_throttle() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "--throttle", "0.01", "-name", "abbbc"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(fix_up_slashes("./test_data/simple/abbbc\n"));
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "--throttle", "0"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("Error: invalid throttle '0': expected a rate in MB/s or 'idle'\n");
+
+    let idle = Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "--throttle", "idle", "-name", "abbbc"])
+        .assert();
+    if cfg!(target_os = "linux") {
+        idle.success()
+            .stderr(predicate::str::is_empty())
+            .stdout(fix_up_slashes("./test_data/simple/abbbc\n"));
+    } else {
+        idle.failure().stdout("");
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn find_cap() {
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "-cap", "-o", "-cap", "CAP_NET_RAW"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["./test_data/simple", "-cap", "cap_net_raw,cap_fly"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("Error: unknown capability 'cap_fly'\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn find_perm_conditional_execute() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = Builder::new().prefix("find_cmd_").tempdir().unwrap();
+    let dir = temp_dir.path().join("dir");
+    let file = temp_dir.path().join("file");
+    fs::create_dir(&dir).unwrap();
+    File::create(&file).unwrap();
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+
+    // Like chmod, X is x for the directory but not the file
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([temp_dir.path().to_str().unwrap(), "-mindepth", "1"])
+        .args(["-perm", "u=rwX,go=rX", "-printf", "%f\n", "-sorted"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("dir\nfile\n");
+
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args(["-perm", "u=rwxg=rx"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::starts_with(
+            "Error: invalid mode 'u=rwxg=rx': ",
+        ));
+}
+
+/// Arguments to find, and the errors they should give with --gnu-errors in
+/// the C locale and a UTF-8 one.
+#[cfg(unix)]
+const GNU_ERRORS: &[(&[&str], &str, &str)] = &[
+    (
+        &["./test_data/nope"],
+        "find: './test_data/nope': No such file or directory\n",
+        "find: ‘./test_data/nope’: No such file or directory\n",
+    ),
+    (
+        &[".", "-foo"],
+        "find: unknown predicate `-foo'\n",
+        "find: unknown predicate `-foo'\n",
+    ),
+    (
+        &["./test_data/simple", "-name", "a", "b"],
+        "find: paths must precede expression: `b'\n",
+        "find: paths must precede expression: `b'\n",
+    ),
+    (
+        &[".", "-maxdepth", "x"],
+        "find: Expected a positive decimal integer argument to -maxdepth, but got 'x'\n",
+        "find: Expected a positive decimal integer argument to -maxdepth, but got ‘x’\n",
+    ),
+    (
+        &[".", "-exec", "echo"],
+        "find: missing argument to `-exec'\n",
+        "find: missing argument to `-exec'\n",
+    ),
+    (
+        &[".", "-mtime", "x"],
+        "find: invalid argument `x' to `-mtime'\n",
+        "find: invalid argument `x' to `-mtime'\n",
+    ),
+    (
+        &[".", "-type", "f,q"],
+        "find: Unknown argument to -type: q\n",
+        "find: Unknown argument to -type: q\n",
+    ),
+    (
+        &[".", "-size", "3q"],
+        "find: invalid -size type `q'\n",
+        "find: invalid -size type `q'\n",
+    ),
+    (
+        &[".", "-perm", "u+q"],
+        "find: invalid mode 'u+q'\n",
+        "find: invalid mode ‘u+q’\n",
+    ),
+    (
+        &[".", "-user", "it's"],
+        "find: 'it\\'s' is not the name of a known user\n",
+        "find: ‘it's’ is not the name of a known user\n",
+    ),
+    (
+        &[".", "-newer", "é\n"],
+        "find: '\\303\\251\\n': No such file or directory\n",
+        "find: ‘é\\n’: No such file or directory\n",
+    ),
+    (
+        &["-files0-from", "./test_data/nope"],
+        "find: cannot open './test_data/nope' for reading: No such file or directory\n",
+        "find: cannot open ‘./test_data/nope’ for reading: No such file or directory\n",
+    ),
+    (
+        &[".", "-files0-from", "./test_data/nope"],
+        "find: extra operand '.'\nfind: file operands cannot be combined with -files0-from\n",
+        "find: extra operand ‘.’\nfind: file operands cannot be combined with -files0-from\n",
+    ),
+    (
+        &[".", "-name", "a", "-o"],
+        "find: expected an expression after '-o'\n",
+        "find: expected an expression after '-o'\n",
+    ),
+    (
+        &[".", "(", "-name", "a"],
+        "find: invalid expression; I was expecting to find a ')' somewhere but did not see one.\n",
+        "find: invalid expression; I was expecting to find a ')' somewhere but did not see one.\n",
+    ),
+];
+
+#[cfg(unix)]
+#[serial(working_dir)]
+#[test]
+fn find_gnu_errors() {
+    // If GNU find is installed, it has to agree too
+    let gnu = std::process::Command::new("find")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("GNU findutils"));
+
+    for (args, c, utf8) in GNU_ERRORS {
+        for (locale, expected) in [("C", c), ("C.UTF-8", utf8)] {
+            let output = Command::cargo_bin("find")
+                .expect("found binary")
+                .arg("--gnu-errors")
+                .args(*args)
+                .env("LC_ALL", locale)
+                .output()
+                .unwrap();
+            assert_eq!(output.status.code(), Some(1), "{args:?}");
+            let stderr = String::from_utf8(output.stderr).unwrap();
+            assert_eq!(stderr, *expected, "{args:?} in {locale}");
+
+            if gnu {
+                let output = std::process::Command::new("find")
+                    .args(*args)
+                    .env("LC_ALL", locale)
+                    .output()
+                    .unwrap();
+                assert_eq!(
+                    String::from_utf8_lossy(&output.stderr),
+                    stderr,
+                    "GNU find {args:?} in {locale}"
+                );
+            }
+        }
+    }
+
+    // The option can come from FIND_OPTIONS too
+    Command::cargo_bin("find")
+        .expect("found binary")
+        .args([".", "-foo"])
+        .env("FIND_OPTIONS", "--gnu-errors")
+        .assert()
+        .failure()
+        .stderr("find: unknown predicate `-foo'\n");
+}
@@ -0,0 +1,64 @@
+// This file is part of the uutils findutils package.
+//
+// For the full copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Tests for the multi-call binary, which is only built with the multicall
+//! feature.
+#![cfg(feature = "multicall")]
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+#[test]
+fn multicall_by_argument() {
+    Command::cargo_bin("findutils")
+        .expect("found binary")
+        .args(["find", "./test_data/simple/subdir", "-name", "ABBBC"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("./test_data/simple/subdir/ABBBC\n"));
+
+    Command::cargo_bin("findutils")
+        .expect("found binary")
+        .args(["xargs", "-n1"])
+        .write_stdin("a b")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("a\nb\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn multicall_by_name() {
+    let temp_dir = tempfile::Builder::new()
+        .prefix("multicall")
+        .tempdir()
+        .unwrap();
+    let xargs = temp_dir.path().join("xargs");
+    std::os::unix::fs::symlink(assert_cmd::cargo::cargo_bin("findutils"), &xargs).unwrap();
+
+    Command::new(&xargs)
+        .args(["echo", "x"])
+        .write_stdin("a b")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("x a b\n"));
+}
+
+#[test]
+fn multicall_unknown_utility() {
+    for args in [&[][..], &["locate"], &["--help"]] {
+        Command::cargo_bin("findutils")
+            .expect("found binary")
+            .args(args)
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains("find\n"))
+            .stdout(predicate::str::is_empty());
+    }
+}
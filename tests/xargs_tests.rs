@@ -61,7 +61,34 @@ fn xargs_delim() {
 
     Command::cargo_bin("xargs")
         .expect("found binary")
-        .args(["-dabc"])
+        .args(["-d\\r\\n", "-n1"])
+        .write_stdin("a b\r\nc\nd\r\n\r\ne")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("a b\nc\nd\ne\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-d\\0", "-n1"])
+        .write_stdin("a b\0c\n\0")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("a b\nc\n\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-dabc", "-n1"])
+        .write_stdin("1abc2ab3abc")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("1\n2ab3\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-da\\q"])
         .assert()
         .failure()
         .code(1)
@@ -239,6 +266,21 @@ fn xargs_exit_on_large() {
         .stdout(predicate::str::is_empty());
 }
 
+#[test]
+#[cfg(unix)]
+fn xargs_many_short_args() {
+    // With millions of arguments, the pointers to them take more space than
+    // the arguments themselves, and the commands fail with E2BIG unless that's
+    // accounted for
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .arg("true")
+        .write_stdin("a\n".repeat(2_000_000))
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
 #[test]
 fn xargs_exec() {
     let result = Command::cargo_bin("xargs")
@@ -301,6 +343,94 @@ fn xargs_exec_stdin_open() {
     );
 }
 
+#[test]
+fn xargs_exec_stdin_shared() {
+    let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+    write!(temp_file, "a b").unwrap();
+
+    // The first command reads all of it, leaving nothing for the next
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-n1",
+            "-a",
+            &temp_file.path().to_string_lossy(),
+            &path_to_testing_commandline(),
+            "-",
+            "--print_stdin",
+            "--no_print_cwd",
+        ])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff(
+            "stdin=test\nargs=\n--print_stdin\n--no_print_cwd\na\n\
+            stdin=\nargs=\n--print_stdin\n--no_print_cwd\nb\n",
+        ));
+}
+
+#[test]
+fn xargs_files0_from() {
+    let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+    write!(temp_file, "a b\0c\0").unwrap();
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "--files0-from",
+            &temp_file.path().to_string_lossy(),
+            &path_to_testing_commandline(),
+            "-",
+            "--print_stdin",
+            "--no_print_cwd",
+        ])
+        .write_stdin("test")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff(
+            "stdin=test\nargs=\n--print_stdin\n--no_print_cwd\na b\nc\n",
+        ));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["--files0-from=-", "echo"])
+        .write_stdin("a b\0c\0")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("a b c\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["--files0-from", "-", "-a", "-", "echo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn xargs_arg_file_stdin() {
+    // -a - reads the arguments from stdin, so the command doesn't get it
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-a",
+            "-",
+            &path_to_testing_commandline(),
+            "-",
+            "--print_stdin",
+            "--no_print_cwd",
+        ])
+        .write_stdin("a b")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff(
+            "stdin=\nargs=\n--print_stdin\n--no_print_cwd\na\nb\n",
+        ));
+}
+
 #[test]
 fn xargs_exec_failure() {
     let result = Command::cargo_bin("xargs")
@@ -376,7 +506,11 @@ fn xargs_exec_with_signal() {
     assert!(result.is_ok(), "xargs failed: {result:?}");
     let result = result.unwrap();
     assert_eq!(result.status.code(), Some(125));
-    assert!(!result.stderr.is_empty(), "stderr: {result:?}");
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(
+        stderr.contains("terminated by signal 2"),
+        "stderr: {stderr:?}"
+    );
 
     let stdout_string = String::from_utf8(result.stdout).expect("Found invalid UTF-8");
 
@@ -386,6 +520,51 @@ fn xargs_exec_with_signal() {
     );
 }
 
+#[test]
+fn xargs_parallel() {
+    for max_procs in ["-P1", "-P3", "-P0"] {
+        let result = Command::cargo_bin("xargs")
+            .expect("found binary")
+            .args([
+                "-n1",
+                max_procs,
+                &path_to_testing_commandline(),
+                "-",
+                "--no_print_cwd",
+            ])
+            .write_stdin("a --exit_with_failure b c")
+            .output()
+            .unwrap();
+
+        // One failure is enough for the whole run to fail, but everything
+        // still runs
+        assert_eq!(result.status.code(), Some(123), "{max_procs}: {result:?}");
+        assert!(result.stderr.is_empty(), "{max_procs}: {result:?}");
+        let stdout = String::from_utf8(result.stdout).unwrap();
+        assert_eq!(stdout.lines().filter(|&line| line == "args=").count(), 4);
+    }
+
+    let result = Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-n1",
+            "-P2",
+            &path_to_testing_commandline(),
+            "-",
+            "--no_print_cwd",
+        ])
+        .write_stdin(format!("--exit_with_urgent_failure{}", " a".repeat(50)))
+        .output()
+        .unwrap();
+
+    // Nothing else starts once the failure is seen, though the commands that
+    // were already running are waited for
+    assert_eq!(result.status.code(), Some(124), "{result:?}");
+    assert!(!result.stderr.is_empty(), "{result:?}");
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert!(stdout.lines().filter(|&line| line == "args=").count() < 51);
+}
+
 #[test]
 fn xargs_exec_not_found() {
     Command::cargo_bin("xargs")
@@ -410,10 +589,16 @@ fn xargs_exec_verbose() {
             "--print_stdin",
             "--no_print_cwd",
         ])
+        .env("XARGS_VERBOSE_TEST", "secret")
         .write_stdin("a b c\nd")
         .assert()
         .success()
-        .stderr(predicate::str::contains("testing-commandline"))
+        // The environment it inherits isn't shown
+        .stderr(predicate::str::diff(format!(
+            "{0:?} \"-\" \"--print_stdin\" \"--no_print_cwd\" \"a\" \"b\"\n\
+             {0:?} \"-\" \"--print_stdin\" \"--no_print_cwd\" \"c\" \"d\"\n",
+            path_to_testing_commandline()
+        )))
         .stdout(predicate::str::diff(
             "stdin=\nargs=\n--print_stdin\n--no_print_cwd\na\nb\n\
             stdin=\nargs=\n--print_stdin\n--no_print_cwd\nc\nd\n",
@@ -565,6 +750,328 @@ fn xargs_replace_multiple_lines() {
         .stdout(predicate::str::diff("\n\n\n"));
 }
 
+#[test]
+fn xargs_replace_limit() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-I", "_", "-R", "2", "echo", "_", "-", "_", "_"])
+        .write_stdin("a\nb")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("a - a _\nb - b _\n"));
+
+    // Negative means no limit
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-I", "_", "-R", "-1", "echo", "_", "_", "_"])
+        .write_stdin("a")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("a a a\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-R", "1", "echo"])
+        .write_stdin("a")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("-R can only be used with -I"));
+}
+
+#[test]
+fn xargs_insert() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-J", "%", "-n", "2", "echo", "[", "%", "]"])
+        .write_stdin("a b c")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("[ a b ]\n[ c ]\n"));
+
+    // Only the first one is replaced, and without one they go at the end
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-J", "%", "echo", "%", "%"])
+        .write_stdin("a b")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("a b %\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-J", "%", "echo", "x"])
+        .write_stdin("a b")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("x a b\n"));
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-J", "%", "-I", "%", "echo", "%"])
+        .write_stdin("a")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn xargs_replace_gnu_compat() {
+    for (args, stdin, stdout) in [
+        // The replacement can be part of a larger argument
+        (
+            &["-I{}", "echo", "a{}b", "pre-{}", "{}{}"][..],
+            "x\ny\n",
+            "axb pre-x xx\nayb pre-y yy\n",
+        ),
+        (
+            &["-IREPL", "echo", "REPLREPL", "RE", "PL"],
+            "x\n",
+            "xx RE PL\n",
+        ),
+        // Leading blanks are ignored, but trailing ones are kept
+        (
+            &["-I{}", "echo", "[{}]"],
+            "  lead\n\t\ttab\ntrail  \n",
+            "[lead]\n[tab]\n[trail  ]\n",
+        ),
+        // Blank lines are skipped
+        (&["-I{}", "echo", "[{}]"], "a\n\n  \nb", "[a]\n[b]\n"),
+        // Quotes and backslashes still work, and can make empty arguments
+        (
+            &["-I{}", "echo", "[{}]"],
+            "'a  b' \"c\"  d\\ e\n",
+            "[a  b c  d e]\n",
+        ),
+        (&["-I{}", "echo", "[{}]"], "a\\\nb\n", "[a\nb]\n"),
+        (&["-I{}", "echo", "[{}]"], "x\n''\ny\n", "[x]\n[]\n[y]\n"),
+        // But not with -0 or -d
+        (
+            &["-0", "-I{}", "echo", "[{}]"],
+            "a b\0 'c'\0",
+            "[a b]\n[ 'c']\n",
+        ),
+        (
+            &["-d:", "-I{}", "echo", "[{}]"],
+            "a:b :c",
+            "[a]\n[b ]\n[c]\n",
+        ),
+        // Nothing is run without any input
+        (&["-I{}", "echo", "[{}]"], "", ""),
+        (&["-I{}", "echo", "[{}]"], "\n  \n", ""),
+        // The input only appears where it's replaced
+        (&["-I{}", "echo"], "x\ny\n", "\n\n"),
+        (&["-I{}"], "x\ny\n", "\n\n"),
+    ] {
+        Command::cargo_bin("xargs")
+            .expect("found binary")
+            .args(args)
+            .write_stdin(stdin)
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .stdout(predicate::str::diff(stdout));
+    }
+}
+
+#[test]
+fn xargs_replace_too_long() {
+    // An input line that doesn't fit is an error, and stops processing
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-I{}", "-s30", "echo", "[{}]"])
+        .write_stdin(format!("a\n{}\nc\n", "b".repeat(30)))
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Argument too large"))
+        .stdout(predicate::str::diff("[a]\n"));
+}
+
+#[test]
+fn xargs_timeout() {
+    // A command that runs out of time fails, but doesn't stop xargs
+    let result = Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-n1",
+            "--timeout",
+            "0.2",
+            &path_to_testing_commandline(),
+            "-",
+            "--no_print_cwd",
+        ])
+        .write_stdin("--sleep=10 --sleep=0")
+        .timeout(std::time::Duration::from_secs(5))
+        .output();
+
+    assert!(result.is_ok(), "xargs failed: {result:?}");
+    let result = result.unwrap();
+    assert_eq!(result.status.code(), Some(123));
+    assert_eq!(
+        String::from_utf8_lossy(&result.stderr),
+        format!(
+            "Error: '{}' timed out after 0.2s and was killed\n",
+            path_to_testing_commandline()
+        )
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&result.stdout),
+        "args=\n--no_print_cwd\n--sleep=0\n"
+    );
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["--timeout", "soon", "echo"])
+        .write_stdin("a")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("invalid time interval 'soon'"));
+}
+
+#[test]
+#[cfg(unix)]
+fn xargs_limits() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "--rlimit",
+            "cpu=5,nofile=40",
+            "sh",
+            "-c",
+            "ulimit -t; ulimit -n",
+        ])
+        .write_stdin("")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout("5\n40\n");
+
+    // A command that uses up its CPU time is killed
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "--nice",
+            "5",
+            "--rlimit",
+            "cpu=1",
+            "sh",
+            "-c",
+            "while :; do :; done",
+        ])
+        .write_stdin("")
+        .timeout(std::time::Duration::from_secs(10))
+        .assert()
+        .code(125);
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["--rlimit", "ram=1G", "echo"])
+        .write_stdin("a")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("invalid resource limit 'ram=1G'"));
+}
+
+#[test]
+#[cfg(unix)]
+fn xargs_group_output() {
+    // b finishes first, but neither command's lines are split up
+    let script = "echo \"$1 start\"; sleep \"$2\"; echo \"$1 end\"; echo \"$1 err\" >&2";
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-n2", "-P2", "--group-output", "sh", "-c", script, "sh"])
+        .write_stdin("a 0.5 b 0.1")
+        .assert()
+        .success()
+        .stdout("b start\nb end\na start\na end\n")
+        .stderr("b err\na err\n");
+}
+
+#[test]
+#[cfg(unix)]
+fn xargs_tag() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-n1",
+            "--tag",
+            "sh",
+            "-c",
+            "echo \"$1\"; echo x; printf y >&2",
+            "sh",
+        ])
+        .write_stdin("a b")
+        .assert()
+        .success()
+        .stdout("a\ta\na\tx\nb\tb\nb\tx\n")
+        .stderr("a\tyb\ty");
+
+    // With -I, the tag is the replacement
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-I{}", "--tag", "--group-output", "echo", "[{}]"])
+        .write_stdin("a\nb c\n")
+        .assert()
+        .success()
+        .stdout("a\t[a]\nb c\t[b c]\n")
+        .stderr("");
+
+    // The built in echo is tagged too
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-n2", "--tag"])
+        .write_stdin("a b c")
+        .assert()
+        .success()
+        .stdout("a\ta b\nc\tc\n");
+}
+
+#[test]
+fn xargs_env_var() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args([
+            "-n2",
+            "--env-var",
+            "XARGS_ARGS",
+            &path_to_testing_commandline(),
+            "-",
+            "--no_print_cwd",
+            "--print_env=XARGS_ARGS",
+        ])
+        .write_stdin("a b\nc")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff(
+            "env=a b\nargs=\n--no_print_cwd\n--print_env=XARGS_ARGS\n\
+             env=c\nargs=\n--no_print_cwd\n--print_env=XARGS_ARGS\n",
+        ));
+
+    // With the default echo, there's nothing left to print
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["--env-var", "XARGS_ARGS"])
+        .write_stdin("a b")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty())
+        .stdout(predicate::str::diff("\n"));
+
+    for args in [&["--env-var", "A=B"][..], &["--env-var", "A", "-I{}"]] {
+        Command::cargo_bin("xargs")
+            .expect("found binary")
+            .args(args)
+            .write_stdin("a b")
+            .assert()
+            .failure()
+            .code(1)
+            .stdout(predicate::str::is_empty());
+    }
+}
+
 #[test]
 fn xargs_help() {
     for option_style in ["-h", "--help"] {
@@ -596,3 +1103,44 @@ fn xargs_version() {
             .stdout(predicate::str::starts_with("xargs "));
     }
 }
+
+#[test]
+fn xargs_default_options() {
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .env("XARGS_OPTIONS", "-n 2 -r")
+        .write_stdin("a b c")
+        .assert()
+        .success()
+        .stderr("")
+        .stdout("a b\nc\n");
+
+    // The command line overrides the defaults
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["-n1"])
+        .env("XARGS_OPTIONS", "-n 2")
+        .write_stdin("a b c")
+        .assert()
+        .success()
+        .stderr("")
+        .stdout("a\nb\nc\n");
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .args(["--no-defaults"])
+        .env("XARGS_OPTIONS", "-n 2")
+        .write_stdin("a b c")
+        .assert()
+        .success()
+        .stderr("")
+        .stdout("a b c\n");
+
+    Command::cargo_bin("xargs")
+        .expect("found binary")
+        .env("XARGS_OPTIONS", "--no-such-option")
+        .write_stdin("a")
+        .assert()
+        .failure()
+        .stdout("");
+}